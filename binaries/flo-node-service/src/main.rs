@@ -1,4 +1,4 @@
-use flo_node::serve;
+use flo_node::serve_with_drain_signal;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -20,7 +20,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
   tracing::info!("starting.");
 
-  serve().await?;
+  serve_with_drain_signal(drain_signal()).await?;
 
   Ok(())
 }
+
+/// Resolves on SIGTERM (Ctrl-C on Windows), the node then drains before exiting
+async fn drain_signal() {
+  #[cfg(unix)]
+  {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+      Ok(mut stream) => {
+        stream.recv().await;
+      }
+      Err(err) => {
+        tracing::error!("register signal handler: {}", err);
+        std::future::pending::<()>().await;
+      }
+    }
+  }
+  #[cfg(not(unix))]
+  {
+    if let Err(err) = tokio::signal::ctrl_c().await {
+      tracing::error!("register signal handler: {}", err);
+      std::future::pending::<()>().await;
+    }
+  }
+  tracing::info!("drain signal received.");
+}
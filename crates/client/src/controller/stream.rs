@@ -302,6 +302,7 @@ impl ControllerStream {
               if let Some(slot) = info.slots.get_mut(p.slot_index as usize) {
                 slot.player = p.player.map(PlayerInfo::unpack).transpose()?;
                 slot.settings = SlotSettings::unpack(p.slot_settings.clone())?;
+                slot.ready = p.ready;
                 Ok(())
               } else {
                 tracing::error!("PacketGamePlayerEnter: invalid slot index: {}", p.slot_index);
@@ -423,6 +424,48 @@ impl ControllerStream {
             .notify(RemoveNode { node_id: p.node_id })
             .await?;
         }
+        p: proto::PacketMatchmakingQueueUpdate => {
+          SendWs::new(
+            id,
+            OutgoingMessage::MatchmakingQueueUpdate(p)
+          ).notify(parent).await?;
+        }
+        p: proto::PacketMatchmakingMatchFound => {
+          SendWs::new(
+            id,
+            OutgoingMessage::MatchmakingMatchFound(p)
+          ).notify(parent).await?;
+        }
+        p: proto::PacketGameInvite => {
+          SendWs::new(
+            id,
+            OutgoingMessage::GameInvite(p)
+          ).notify(parent).await?;
+        }
+        p: proto::PacketInviteUpdate => {
+          SendWs::new(
+            id,
+            OutgoingMessage::InviteUpdate(p)
+          ).notify(parent).await?;
+        }
+        p: proto::PacketPartyUpdate => {
+          SendWs::new(
+            id,
+            OutgoingMessage::PartyUpdate(p)
+          ).notify(parent).await?;
+        }
+        p: proto::PacketLobbyChat => {
+          SendWs::new(
+            id,
+            OutgoingMessage::LobbyChat(p)
+          ).notify(parent).await?;
+        }
+        p: proto::PacketGameHostUpdate => {
+          SendWs::new(
+            id,
+            OutgoingMessage::GameHostUpdate(p)
+          ).notify(parent).await?;
+        }
       }
     };
     Ok(())
@@ -33,6 +33,7 @@ pub async fn run_test_lobby(
     id: 1,
     name: "Player 1".to_string(),
     source: PlayerSource::Test,
+    ratings: vec![],
   };
   let game = GameInfo {
     id: 0,
@@ -51,6 +52,7 @@ pub async fn run_test_lobby(
         ..Default::default()
       },
       client_status: SlotClientStatus::Pending,
+      ready: false,
     }],
     node: None,
     is_private: false,
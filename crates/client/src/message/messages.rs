@@ -5,9 +5,13 @@ use std::borrow::Cow;
 use std::str::FromStr;
 
 use flo_net::proto::flo_connect::{
+  PacketGameHostUpdate, PacketGameInvite, PacketGameInviteRequest, PacketGameKickRequest,
   PacketGamePlayerLeave, PacketGamePlayerPingMapSnapshot, PacketGamePlayerPingMapSnapshotRequest,
-  PacketGameSelectNode, PacketGameSelectNodeRequest, PacketGameStartReject, PacketGameStartRequest,
-  PacketGameStarting, PacketPlayerPingMapUpdate,
+  PacketGameReadyRequest, PacketGameSelectNode, PacketGameSelectNodeRequest, PacketGameStartReject,
+  PacketGameStartRequest, PacketGameStarting, PacketGameTransferHostRequest, PacketInviteResponse,
+  PacketInviteUpdate, PacketLobbyChat, PacketLobbyChatRequest, PacketMatchmakingMatchFound,
+  PacketMatchmakingQueueUpdate, PacketPartyInviteRequest, PacketPartyUpdate,
+  PacketPlayerPingMapUpdate,
 };
 
 use crate::error::{Error, Result};
@@ -39,6 +43,14 @@ pub enum IncomingMessage {
   ClearNodeAddrOverrides,
   WatchGame(WatchGame),
   WatchGameSetSpeed(WatchGameSetSpeed),
+  GameInviteRequest(PacketGameInviteRequest),
+  PartyInviteRequest(PacketPartyInviteRequest),
+  InviteResponse(PacketInviteResponse),
+  PartyLeaveRequest,
+  LobbyChatRequest(PacketLobbyChatRequest),
+  GameKickRequest(PacketGameKickRequest),
+  GameTransferHostRequest(PacketGameTransferHostRequest),
+  GameReadyRequest(PacketGameReadyRequest),
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -75,6 +87,13 @@ pub enum OutgoingMessage {
   WatchGameError(ErrorMessage),
   WatchGameSetSpeedError(ErrorMessage),
   LanGameJoined(LanGameJoined),
+  MatchmakingQueueUpdate(PacketMatchmakingQueueUpdate),
+  MatchmakingMatchFound(PacketMatchmakingMatchFound),
+  GameInvite(PacketGameInvite),
+  InviteUpdate(PacketInviteUpdate),
+  PartyUpdate(PacketPartyUpdate),
+  LobbyChat(PacketLobbyChat),
+  GameHostUpdate(PacketGameHostUpdate),
 }
 
 impl FromStr for IncomingMessage {
@@ -177,6 +196,7 @@ pub struct GameSlotUpdate {
   pub slot_index: i32,
   pub slot_settings: SlotSettings,
   pub player: Option<PlayerInfo>,
+  pub ready: bool,
 }
 
 use crate::controller::SetNodeAddrOverrides;
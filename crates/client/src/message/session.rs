@@ -15,8 +15,10 @@ use crate::platform::{
 };
 use flo_net::packet::FloPacket;
 use flo_net::proto::flo_connect::{
-  PacketGamePlayerPingMapSnapshotRequest, PacketGameSlotUpdateRequest, PacketGameStartRequest,
-  PacketListNodesRequest,
+  PacketGameInviteRequest, PacketGameKickRequest, PacketGamePlayerPingMapSnapshotRequest,
+  PacketGameReadyRequest, PacketGameSlotUpdateRequest, PacketGameStartRequest,
+  PacketGameTransferHostRequest, PacketInviteResponse, PacketListNodesRequest,
+  PacketLobbyChatRequest, PacketPartyInviteRequest, PacketPartyLeaveRequest,
 };
 use flo_platform::ClientPlatformInfo;
 use flo_state::Addr;
@@ -256,6 +258,32 @@ impl Worker {
         };
         reply_sender.send(reply).await?;
       }
+      IncomingMessage::GameInviteRequest(req) => {
+        self.send_frame::<PacketGameInviteRequest>(req).await?;
+      }
+      IncomingMessage::PartyInviteRequest(req) => {
+        self.send_frame::<PacketPartyInviteRequest>(req).await?;
+      }
+      IncomingMessage::InviteResponse(req) => {
+        self.send_frame::<PacketInviteResponse>(req).await?;
+      }
+      IncomingMessage::PartyLeaveRequest => {
+        self.send_frame(PacketPartyLeaveRequest {}).await?;
+      }
+      IncomingMessage::LobbyChatRequest(req) => {
+        self.send_frame::<PacketLobbyChatRequest>(req).await?;
+      }
+      IncomingMessage::GameKickRequest(req) => {
+        self.send_frame::<PacketGameKickRequest>(req).await?;
+      }
+      IncomingMessage::GameTransferHostRequest(req) => {
+        self
+          .send_frame::<PacketGameTransferHostRequest>(req)
+          .await?;
+      }
+      IncomingMessage::GameReadyRequest(req) => {
+        self.send_frame::<PacketGameReadyRequest>(req).await?;
+      }
     }
     Ok(())
   }
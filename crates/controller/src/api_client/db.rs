@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::api_client::{ApiClient, ApiClientKey, ApiScope};
+use crate::db::DbConn;
+use crate::error::*;
+use crate::schema::{api_client, api_client_key};
+
+pub fn list_clients(conn: &DbConn) -> Result<Vec<ApiClient>> {
+  api_client::table
+    .select(ClientRow::COLUMNS)
+    .order(api_client::id)
+    .load::<ClientRow>(conn)?
+    .into_iter()
+    .map(ClientRow::into_client)
+    .collect()
+}
+
+pub fn get_client(conn: &DbConn, id: i32) -> Result<ApiClient> {
+  api_client::table
+    .find(id)
+    .select(ClientRow::COLUMNS)
+    .first::<ClientRow>(conn)
+    .optional()?
+    .ok_or_else(|| Error::ApiClientNotFound)?
+    .into_client()
+}
+
+pub fn create_client(conn: &DbConn, name: &str, scopes: &[ApiScope]) -> Result<ApiClient> {
+  if name.is_empty() {
+    return Err(Error::ApiClientInvalid("name is empty"));
+  }
+  let scopes = serde_json::to_value(scopes)?;
+  diesel::insert_into(api_client::table)
+    .values((api_client::name.eq(name), api_client::scopes.eq(scopes)))
+    .returning(ClientRow::COLUMNS)
+    .get_result::<ClientRow>(conn)?
+    .into_client()
+}
+
+pub fn update_client_scopes(conn: &DbConn, id: i32, scopes: &[ApiScope]) -> Result<ApiClient> {
+  let scopes = serde_json::to_value(scopes)?;
+  diesel::update(api_client::table.find(id))
+    .set(api_client::scopes.eq(scopes))
+    .returning(ClientRow::COLUMNS)
+    .get_result::<ClientRow>(conn)
+    .optional()?
+    .ok_or_else(|| Error::ApiClientNotFound)?
+    .into_client()
+}
+
+pub fn list_keys(conn: &DbConn, api_client_id: i32) -> Result<Vec<ApiClientKey>> {
+  let keys = api_client_key::table
+    .filter(api_client_key::api_client_id.eq(api_client_id))
+    .order(api_client_key::id)
+    .load(conn)?;
+  Ok(keys)
+}
+
+/// Keys without `expires_at` never expire
+pub fn create_key(
+  conn: &DbConn,
+  api_client_id: i32,
+  expires_at: Option<DateTime<Utc>>,
+) -> Result<ApiClientKey> {
+  get_client(conn, api_client_id)?;
+  let key = diesel::insert_into(api_client_key::table)
+    .values((
+      api_client_key::api_client_id.eq(api_client_id),
+      api_client_key::secret_key.eq(gen_secret()),
+      api_client_key::expires_at.eq(expires_at),
+    ))
+    .get_result(conn)?;
+  Ok(key)
+}
+
+pub fn get_key(conn: &DbConn, id: i32) -> Result<ApiClientKey> {
+  api_client_key::table
+    .find(id)
+    .first(conn)
+    .optional()?
+    .ok_or_else(|| Error::ApiClientKeyNotFound)
+}
+
+/// Expires the key now, or later to give integrators time to switch to a new key
+pub fn expire_key(
+  conn: &DbConn,
+  id: i32,
+  expires_at: Option<DateTime<Utc>>,
+) -> Result<ApiClientKey> {
+  diesel::update(api_client_key::table.find(id))
+    .set(api_client_key::expires_at.eq(expires_at.unwrap_or_else(Utc::now)))
+    .get_result::<ApiClientKey>(conn)
+    .optional()?
+    .ok_or_else(|| Error::ApiClientKeyNotFound)
+}
+
+/// Keys that are not expired yet, with the scopes of their api client
+pub fn get_active_keys(conn: &DbConn) -> Result<Vec<(ApiClientKey, Vec<ApiScope>)>> {
+  let now = Utc::now();
+  let rows = api_client_key::table
+    .inner_join(api_client::table)
+    .filter(
+      api_client_key::expires_at
+        .is_null()
+        .or(api_client_key::expires_at.gt(now)),
+    )
+    .select((api_client_key::all_columns, api_client::scopes))
+    .load::<(ApiClientKey, Value)>(conn)?;
+  rows
+    .into_iter()
+    .map(|(key, scopes)| Ok((key, serde_json::from_value(scopes)?)))
+    .collect()
+}
+
+fn gen_secret() -> String {
+  use rand::distributions::Alphanumeric;
+  use rand::Rng;
+  rand::thread_rng()
+    .sample_iter(&Alphanumeric)
+    .take(48)
+    .map(char::from)
+    .collect()
+}
+
+type ClientColumns = (
+  api_client::id,
+  api_client::name,
+  api_client::scopes,
+  api_client::created_at,
+);
+
+#[derive(Debug, Queryable)]
+struct ClientRow {
+  id: i32,
+  name: String,
+  scopes: Value,
+  created_at: DateTime<Utc>,
+}
+
+impl ClientRow {
+  const COLUMNS: ClientColumns = (
+    api_client::id,
+    api_client::name,
+    api_client::scopes,
+    api_client::created_at,
+  );
+
+  fn into_client(self) -> Result<ApiClient> {
+    Ok(ApiClient {
+      id: self.id,
+      name: self.name,
+      scopes: serde_json::from_value(self.scopes)?,
+      created_at: self.created_at,
+    })
+  }
+}
@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use s2_grpc_utils::{S2ProtoEnum, S2ProtoPack, S2ProtoUnpack};
+use serde::{Deserialize, Serialize};
+
+use crate::error::*;
+
+/// Grants access to a group of gRPC methods, see `crate::config::required_scope`
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, S2ProtoEnum)]
+#[repr(i32)]
+#[s2_grpc(proto_enum_type = "flo_grpc::controller::ApiScope")]
+pub enum ApiScope {
+  /// Queries players, games, nodes and other public data
+  Read = 0,
+  /// Creates and runs games, matchmaking and tournaments
+  GameManagement = 1,
+  /// Bans players and reads audit logs
+  Moderation = 2,
+  /// Everything, including nodes, matchmaking queues and api clients
+  Admin = 3,
+}
+
+pub fn has_scope(scopes: &[ApiScope], scope: ApiScope) -> bool {
+  scopes.contains(&ApiScope::Admin) || scopes.contains(&scope)
+}
+
+pub fn is_operator(api_client_id: i32) -> bool {
+  *crate::config::OPERATOR_API_CLIENT_ID == Some(api_client_id)
+}
+
+/// Clients can only manage their own keys, the operator client manages every client
+pub fn check_client_access(api_client_id: i32, target_api_client_id: i32) -> Result<()> {
+  if api_client_id != target_api_client_id && !is_operator(api_client_id) {
+    return Err(Error::ApiClientForbidden);
+  }
+  Ok(())
+}
+
+pub fn unpack_scopes(values: Vec<i32>) -> Result<Vec<ApiScope>> {
+  let mut scopes = vec![];
+  for value in values {
+    let scope = flo_grpc::controller::ApiScope::from_i32(value)
+      .map(ApiScope::unpack_enum)
+      .ok_or_else(|| Error::ApiClientInvalid("unknown scope"))?;
+    if !scopes.contains(&scope) {
+      scopes.push(scope);
+    }
+  }
+  Ok(scopes)
+}
+
+#[derive(Debug, S2ProtoPack)]
+#[s2_grpc(message_type = "flo_grpc::controller::ApiClient")]
+pub struct ApiClient {
+  pub id: i32,
+  pub name: String,
+  #[s2_grpc(skip_pack)]
+  pub scopes: Vec<ApiScope>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl ApiClient {
+  pub fn into_proto(self) -> Result<flo_grpc::controller::ApiClient> {
+    let scopes = self
+      .scopes
+      .iter()
+      .map(|scope| scope.into_proto_enum().into())
+      .collect();
+    let mut packed: flo_grpc::controller::ApiClient = self.pack()?;
+    packed.scopes = scopes;
+    Ok(packed)
+  }
+}
+
+/// Secrets are only returned once, when the key is created
+#[derive(Debug, S2ProtoPack, Queryable)]
+#[s2_grpc(message_type = "flo_grpc::controller::ApiClientKey")]
+pub struct ApiClientKey {
+  pub id: i32,
+  pub api_client_id: i32,
+  #[s2_grpc(skip_pack)]
+  pub secret_key: String,
+  pub expires_at: Option<DateTime<Utc>>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl ApiClientKey {
+  pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+    self.expires_at.map(|t| t <= now).unwrap_or(false)
+  }
+}
+
+#[derive(Debug, S2ProtoUnpack)]
+#[s2_grpc(message_type = "flo_grpc::controller::CreateApiClientKeyRequest")]
+pub struct CreateApiClientKeyParams {
+  pub api_client_id: i32,
+  pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, S2ProtoUnpack)]
+#[s2_grpc(message_type = "flo_grpc::controller::ExpireApiClientKeyRequest")]
+pub struct ExpireApiClientKeyParams {
+  pub id: i32,
+  /// Defaults to now
+  pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[test]
+fn test_has_scope() {
+  assert!(has_scope(&[ApiScope::Admin], ApiScope::Moderation));
+  assert!(has_scope(
+    &[ApiScope::Read, ApiScope::GameManagement],
+    ApiScope::GameManagement
+  ));
+  assert!(!has_scope(&[ApiScope::Read], ApiScope::GameManagement));
+  assert!(!has_scope(&[], ApiScope::Read));
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::audit::{AuditLog, AuditTarget, ListAuditLogs, ListAuditLogsParams};
+use crate::db::DbConn;
+use crate::error::*;
+use crate::schema::audit_log;
+
+pub fn insert(
+  conn: &DbConn,
+  api_client_id: i32,
+  action: &str,
+  target: &AuditTarget,
+  params: Value,
+) -> Result<()> {
+  diesel::insert_into(audit_log::table)
+    .values(&Insert {
+      api_client_id: Some(api_client_id),
+      action,
+      target_type: target.type_name(),
+      target_id: target.id(),
+      params,
+    })
+    .execute(conn)?;
+  Ok(())
+}
+
+/// Lists calls made by the api client, newest first
+pub fn list(
+  conn: &DbConn,
+  api_client_id: i32,
+  params: &ListAuditLogsParams,
+) -> Result<ListAuditLogs> {
+  const PAGE_SIZE: i64 = 100;
+  let mut q = audit_log::table
+    .filter(audit_log::api_client_id.eq(api_client_id))
+    .order(audit_log::id.desc())
+    .limit(PAGE_SIZE + 1)
+    .into_boxed();
+
+  if let Some(ref action) = params.action {
+    q = q.filter(audit_log::action.eq(action));
+  }
+
+  if let Some(ref target_type) = params.target_type {
+    q = q.filter(audit_log::target_type.eq(target_type));
+  }
+
+  if let Some(ref target_id) = params.target_id {
+    q = q.filter(audit_log::target_id.eq(target_id));
+  }
+
+  if let Some(since) = params.since {
+    q = q.filter(audit_log::created_at.ge(since));
+  }
+
+  if let Some(until) = params.until {
+    q = q.filter(audit_log::created_at.lt(until));
+  }
+
+  if let Some(id) = params.next_id {
+    q = q.filter(audit_log::id.le(id));
+  }
+
+  let mut rows = q.load::<Row>(conn)?;
+  let next_id = if rows.len() > PAGE_SIZE as usize {
+    let id = rows.last().map(|row| row.id);
+    rows.truncate(PAGE_SIZE as usize);
+    id
+  } else {
+    None
+  };
+
+  Ok(ListAuditLogs {
+    items: rows.into_iter().map(Row::into_audit_log).collect(),
+    next_id,
+  })
+}
+
+#[derive(Debug, Queryable)]
+struct Row {
+  id: i32,
+  api_client_id: Option<i32>,
+  action: String,
+  target_type: Option<String>,
+  target_id: Option<String>,
+  params: Value,
+  created_at: DateTime<Utc>,
+}
+
+impl Row {
+  fn into_audit_log(self) -> AuditLog {
+    AuditLog {
+      id: self.id,
+      api_client_id: self.api_client_id,
+      action: self.action,
+      target_type: self.target_type,
+      target_id: self.target_id,
+      params: self.params.to_string(),
+      created_at: self.created_at,
+    }
+  }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "audit_log"]
+struct Insert<'a> {
+  api_client_id: Option<i32>,
+  action: &'a str,
+  target_type: Option<&'a str>,
+  target_id: Option<String>,
+  params: Value,
+}
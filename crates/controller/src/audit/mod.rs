@@ -0,0 +1,4 @@
+pub mod db;
+mod types;
+
+pub use types::*;
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use s2_grpc_utils::{S2ProtoPack, S2ProtoUnpack};
+
+/// The entity changed by an audited call
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditTarget {
+  None,
+  Player(i32),
+  Game(i32),
+  Node(i32),
+  PlayerBan(i32),
+  MatchmakingQueue(String),
+  MapPool(String),
+  Tournament(i32),
+  ApiClient(i32),
+  ApiClientKey(i32),
+}
+
+impl AuditTarget {
+  pub fn type_name(&self) -> Option<&'static str> {
+    match *self {
+      AuditTarget::None => None,
+      AuditTarget::Player(_) => Some("player"),
+      AuditTarget::Game(_) => Some("game"),
+      AuditTarget::Node(_) => Some("node"),
+      AuditTarget::PlayerBan(_) => Some("player_ban"),
+      AuditTarget::MatchmakingQueue(_) => Some("matchmaking_queue"),
+      AuditTarget::MapPool(_) => Some("map_pool"),
+      AuditTarget::Tournament(_) => Some("tournament"),
+      AuditTarget::ApiClient(_) => Some("api_client"),
+      AuditTarget::ApiClientKey(_) => Some("api_client_key"),
+    }
+  }
+
+  pub fn id(&self) -> Option<String> {
+    match *self {
+      AuditTarget::None => None,
+      AuditTarget::Player(id)
+      | AuditTarget::Game(id)
+      | AuditTarget::Node(id)
+      | AuditTarget::PlayerBan(id)
+      | AuditTarget::Tournament(id)
+      | AuditTarget::ApiClient(id)
+      | AuditTarget::ApiClientKey(id) => Some(id.to_string()),
+      AuditTarget::MatchmakingQueue(ref name) | AuditTarget::MapPool(ref name) => {
+        Some(name.clone())
+      }
+    }
+  }
+}
+
+#[derive(Debug, S2ProtoPack)]
+#[s2_grpc(message_type = "flo_grpc::controller::AuditLog")]
+pub struct AuditLog {
+  pub id: i32,
+  pub api_client_id: Option<i32>,
+  pub action: String,
+  pub target_type: Option<String>,
+  pub target_id: Option<String>,
+  /// JSON encoded
+  pub params: String,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, S2ProtoUnpack)]
+#[s2_grpc(message_type = "flo_grpc::controller::ListAuditLogsRequest")]
+pub struct ListAuditLogsParams {
+  pub action: Option<String>,
+  pub target_type: Option<String>,
+  pub target_id: Option<String>,
+  pub since: Option<DateTime<Utc>>,
+  pub until: Option<DateTime<Utc>>,
+  pub next_id: Option<i32>,
+}
+
+#[derive(Debug)]
+pub struct ListAuditLogs {
+  pub items: Vec<AuditLog>,
+  pub next_id: Option<i32>,
+}
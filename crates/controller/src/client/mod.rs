@@ -13,12 +13,18 @@ use crate::state::{ActorMapExt, ControllerStateRef};
 
 mod handshake;
 mod sender;
-use crate::game::messages::{ResolveGamePlayerPingBroadcastTargets, UpdateSlot};
-use crate::game::state::node::SelectNode;
+use crate::game::messages::{
+  KickPlayer, LobbyChat, RemoveGamePlayer, ResolveGamePlayerPingBroadcastTargets,
+  ScheduleReadyTimeout, SetPlayerReady, TransferHost, UpdateSlot,
+};
+use crate::game::state::node::{auto_select_node, SelectNode};
 use crate::game::state::player::GetGamePlayers;
 use crate::game::state::registry::UpdateGameNodeCache;
-use crate::game::state::start::{StartGameCheck, StartGamePlayerAck};
+use crate::game::state::start::{StartGameCheck, StartGameCheckResult, StartGamePlayerAck};
 use crate::game::SlotSettings;
+use crate::invite::state::{
+  CreateGameInvites, CreatePartyInvites, GetParty, LeaveParty, RespondInvite,
+};
 use crate::node::messages::ListNode;
 use crate::player::state::conn::{Connect, Disconnect};
 use crate::player::state::ping::{GetPlayersPingSnapshot, UpdatePing};
@@ -182,6 +188,30 @@ async fn handle_stream(
             packet: proto::flo_connect::PacketPlayerMuteRemoveRequest => {
               handle_player_mute_list_update_request(state.clone(), player_id, packet.into()).await?;
             }
+            packet: proto::flo_connect::PacketGameInviteRequest => {
+              handle_game_invite_request(state.clone(), player_id, packet).await?;
+            }
+            packet: proto::flo_connect::PacketPartyInviteRequest => {
+              handle_party_invite_request(state.clone(), player_id, packet).await?;
+            }
+            packet: proto::flo_connect::PacketInviteResponse => {
+              handle_invite_response(state.clone(), player_id, packet).await?;
+            }
+            _packet: proto::flo_connect::PacketPartyLeaveRequest => {
+              handle_party_leave_request(state.clone(), player_id).await?;
+            }
+            packet: proto::flo_connect::PacketLobbyChatRequest => {
+              handle_lobby_chat_request(state.clone(), player_id, packet).await?;
+            }
+            packet: proto::flo_connect::PacketGameKickRequest => {
+              handle_game_kick_request(state.clone(), player_id, packet).await?;
+            }
+            packet: proto::flo_connect::PacketGameTransferHostRequest => {
+              handle_game_transfer_host_request(state.clone(), player_id, packet).await?;
+            }
+            packet: proto::flo_connect::PacketGameReadyRequest => {
+              handle_game_ready_request(state.clone(), player_id, packet).await?;
+            }
           }
         }
       }
@@ -238,6 +268,10 @@ async fn send_initial_state(
 
   let mut frames = vec![frame_accept];
 
+  if let Some(party) = state.invites.send(GetParty { player_id }).await?? {
+    frames.push(connect::PacketPartyUpdate { party: Some(party) }.encode_as_frame()?);
+  }
+
   if let Some(game_id) = game_id {
     let (mut game, node_player_token) = state
       .db
@@ -424,10 +458,26 @@ async fn handle_game_start_request(
   player_id: i32,
   packet: proto::flo_connect::PacketGameStartRequest,
 ) -> Result<()> {
-  state
+  if packet.auto_select_node == Some(true) {
+    auto_select_node(&state, packet.game_id, player_id).await?;
+  }
+  let res = state
     .games
     .send_to(packet.game_id, StartGameCheck { player_id })
     .await?;
+  if let StartGameCheckResult::PlayersNotReady {
+    kick_after: Some(timeout),
+    ..
+  } = res
+  {
+    state
+      .games
+      .notify(ScheduleReadyTimeout {
+        game_id: packet.game_id,
+        timeout,
+      })
+      .await?;
+  }
   Ok(())
 }
 
@@ -476,3 +526,134 @@ async fn handle_player_mute_list_update_request(
     .await?;
   Ok(())
 }
+
+async fn handle_game_invite_request(
+  state: ControllerStateRef,
+  player_id: i32,
+  packet: proto::flo_connect::PacketGameInviteRequest,
+) -> Result<()> {
+  state
+    .invites
+    .send(CreateGameInvites {
+      player_id,
+      game_id: packet.game_id,
+      player_ids: packet.player_ids,
+    })
+    .await??;
+  Ok(())
+}
+
+async fn handle_party_invite_request(
+  state: ControllerStateRef,
+  player_id: i32,
+  packet: proto::flo_connect::PacketPartyInviteRequest,
+) -> Result<()> {
+  state
+    .invites
+    .send(CreatePartyInvites {
+      player_id,
+      player_ids: packet.player_ids,
+    })
+    .await??;
+  Ok(())
+}
+
+async fn handle_invite_response(
+  state: ControllerStateRef,
+  player_id: i32,
+  packet: proto::flo_connect::PacketInviteResponse,
+) -> Result<()> {
+  state
+    .invites
+    .send(RespondInvite {
+      player_id,
+      invite_id: packet.invite_id,
+      accept: packet.accept,
+    })
+    .await??;
+  Ok(())
+}
+
+async fn handle_party_leave_request(state: ControllerStateRef, player_id: i32) -> Result<()> {
+  state.invites.send(LeaveParty { player_id }).await??;
+  Ok(())
+}
+
+async fn handle_lobby_chat_request(
+  state: ControllerStateRef,
+  player_id: i32,
+  packet: proto::flo_connect::PacketLobbyChatRequest,
+) -> Result<()> {
+  state
+    .games
+    .send_to(
+      packet.game_id,
+      LobbyChat {
+        player_id,
+        message: packet.message,
+      },
+    )
+    .await?;
+  Ok(())
+}
+
+async fn handle_game_kick_request(
+  state: ControllerStateRef,
+  player_id: i32,
+  packet: proto::flo_connect::PacketGameKickRequest,
+) -> Result<()> {
+  state
+    .games
+    .send_to(
+      packet.game_id,
+      KickPlayer {
+        player_id,
+        target_player_id: packet.player_id,
+      },
+    )
+    .await?;
+  state
+    .games
+    .send(RemoveGamePlayer {
+      game_id: packet.game_id,
+      player_id: packet.player_id,
+    })
+    .await?;
+  Ok(())
+}
+
+async fn handle_game_transfer_host_request(
+  state: ControllerStateRef,
+  player_id: i32,
+  packet: proto::flo_connect::PacketGameTransferHostRequest,
+) -> Result<()> {
+  state
+    .games
+    .send_to(
+      packet.game_id,
+      TransferHost {
+        player_id,
+        target_player_id: packet.player_id,
+      },
+    )
+    .await?;
+  Ok(())
+}
+
+async fn handle_game_ready_request(
+  state: ControllerStateRef,
+  player_id: i32,
+  packet: proto::flo_connect::PacketGameReadyRequest,
+) -> Result<()> {
+  state
+    .games
+    .send_to(
+      packet.game_id,
+      SetPlayerReady {
+        player_id,
+        ready: packet.ready,
+      },
+    )
+    .await?;
+  Ok(())
+}
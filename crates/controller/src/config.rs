@@ -6,30 +6,56 @@ use once_cell::sync::Lazy;
 use std::collections::BTreeMap;
 use std::env;
 use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+use tonic::codegen::{http, Service as TowerService};
+use tonic::transport::NamedService;
 use tonic::{metadata::MetadataValue, service::Interceptor, Request, Status};
 
 use crate::error::*;
 
+use crate::api_client::{has_scope, ApiScope};
 use crate::player::PlayerSource;
-use crate::schema::{api_client, player};
+use crate::schema::player;
 use crate::state::{Data, Reload};
 use flo_state::{async_trait, Actor, Context, Handler, Message, RegistryRef, Service};
 
 pub static JWT_SECRET_BASE64: Lazy<String> =
   Lazy::new(|| env::var("JWT_SECRET_BASE64").expect("env `JWT_SECRET_BASE64`"));
 
-#[derive(Debug, Queryable)]
-pub struct ApiClient {
-  id: i32,
-  _name: String,
-  secret_key: String,
-  _created_at: DateTime<Utc>,
-  player_id: i32,
+/// Nodes hosting this many games are skipped by the node recommendation
+pub static NODE_MAX_GAMES: Lazy<Option<usize>> = Lazy::new(|| {
+  env::var("FLO_NODE_MAX_GAMES")
+    .ok()
+    .and_then(|v| v.parse().ok())
+});
+
+/// Lobby members that are still not ready this long after the host tried to start the game are kicked
+pub static GAME_READY_TIMEOUT: Lazy<Option<Duration>> = Lazy::new(|| {
+  env::var("FLO_GAME_READY_TIMEOUT_SECS")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .map(Duration::from_secs)
+});
+
+/// The api client allowed to manage other api clients, see `crate::api_client::check_client_access`
+pub static OPERATOR_API_CLIENT_ID: Lazy<Option<i32>> = Lazy::new(|| {
+  env::var("FLO_OPERATOR_API_CLIENT_ID")
+    .ok()
+    .and_then(|v| v.parse().ok())
+});
+
+#[derive(Debug)]
+struct ApiKeyEntry {
+  api_client_id: i32,
+  api_player_id: i32,
+  scopes: Vec<ApiScope>,
+  expires_at: Option<DateTime<Utc>>,
 }
 
 pub struct ConfigStorage {
   db: ExecutorRef,
-  api_client_map: Arc<ArcSwap<BTreeMap<Vec<u8>, ApiClient>>>,
+  api_client_map: Arc<ArcSwap<BTreeMap<Vec<u8>, ApiKeyEntry>>>,
 }
 
 impl Actor for ConfigStorage {}
@@ -81,43 +107,154 @@ impl Handler<GetInterceptor> for ConfigStorage {
 pub const REQUEST_META_SECRET: &str = "x-flo-secret";
 pub const REQUEST_META_API_CLIENT_ID: &str = "x-flo-api-client-id-bin";
 pub const REQUEST_META_API_PLAYER_ID: &str = "x-flo-api-player-id-bin";
+pub const REQUEST_META_METHOD: &str = "x-flo-grpc-method";
 
 #[derive(Clone)]
 pub struct FloGrpcInterceptor {
-  api_client_map: Arc<ArcSwap<BTreeMap<Vec<u8>, ApiClient>>>,
+  api_client_map: Arc<ArcSwap<BTreeMap<Vec<u8>, ApiKeyEntry>>>,
 }
 
 impl Interceptor for FloGrpcInterceptor {
   fn call(&mut self, mut req: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
-    let secret = req.metadata().get(REQUEST_META_SECRET);
-    match secret {
-      Some(secret) => match self.api_client_map.load().get(secret.as_bytes()) {
-        Some(client) => {
-          let meta = req.metadata_mut();
-          meta.insert_bin(
-            REQUEST_META_API_CLIENT_ID,
-            MetadataValue::from_bytes(&client.id.to_le_bytes()),
-          );
-          meta.insert_bin(
-            REQUEST_META_API_PLAYER_ID,
-            MetadataValue::from_bytes(&client.player_id.to_le_bytes()),
-          );
-          Ok(req)
-        }
-        None => Err(Status::unauthenticated("invalid secret")),
-      },
-      None => Err(Status::unauthenticated(
-        "`x-flo-secret` metadata was not found",
-      )),
+    let (api_client_id, api_player_id) = {
+      let secret = req
+        .metadata()
+        .get(REQUEST_META_SECRET)
+        .ok_or_else(|| Status::unauthenticated("`x-flo-secret` metadata was not found"))?;
+      let map = self.api_client_map.load();
+      let entry = map
+        .get(secret.as_bytes())
+        .ok_or_else(|| Status::unauthenticated("invalid secret"))?;
+      if entry.expires_at.map(|t| t <= Utc::now()).unwrap_or(false) {
+        return Err(Status::unauthenticated("secret expired"));
+      }
+
+      let method = req
+        .metadata()
+        .get(REQUEST_META_METHOD)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|path| path.rsplit('/').next())
+        .unwrap_or_default();
+      let scope = required_scope(method);
+      if !has_scope(&entry.scopes, scope) {
+        return Err(Status::permission_denied(format!(
+          "`{}` requires the `{:?}` scope",
+          method, scope
+        )));
+      }
+      (entry.api_client_id, entry.api_player_id)
+    };
+
+    let meta = req.metadata_mut();
+    meta.insert_bin(
+      REQUEST_META_API_CLIENT_ID,
+      MetadataValue::from_bytes(&api_client_id.to_le_bytes()),
+    );
+    meta.insert_bin(
+      REQUEST_META_API_PLAYER_ID,
+      MetadataValue::from_bytes(&api_player_id.to_le_bytes()),
+    );
+    Ok(req)
+  }
+}
+
+/// The scope an api key needs to call a gRPC method,
+/// methods not listed here require `Admin`
+pub fn required_scope(method: &str) -> ApiScope {
+  match method {
+    "GetPlayer"
+    | "GetPlayerByToken"
+    | "ListNodes"
+    | "ListGames"
+    | "ListGameHistory"
+    | "GetGame"
+    | "GetRecommendedGameNode"
+    | "SearchMapChecksum"
+    | "GetMapInfo"
+    | "ListMapPools"
+    | "GetPlayersBySourceIds"
+    | "ListLeaderboard"
+    | "GetPlayerPingMaps"
+    | "ListMatchmakingQueues"
+    | "GetTournament" => ApiScope::Read,
+    "UpdateAndGetPlayer"
+    | "CreateGame"
+    | "JoinGame"
+    | "CreateJoinGameToken"
+    | "JoinGameByToken"
+    | "LeaveGame"
+    | "SelectGameNode"
+    | "CancelGame"
+    | "ImportMapChecksums"
+    | "UploadMap"
+    | "CreateGameAsBot"
+    | "StartGameAsBot"
+    | "CancelGameAsBot"
+    | "EnqueueMatchmakingPlayer"
+    | "DequeueMatchmakingPlayer"
+    | "UpsertMatchmakingQueue"
+    | "RemoveMatchmakingQueue"
+    | "UpsertMapPool"
+    | "RemoveMapPool"
+    | "CreateTournament"
+    | "CancelTournament" => ApiScope::GameManagement,
+    "ListPlayerBans"
+    | "CreatePlayerBan"
+    | "RemovePlayerBan"
+    | "ListPlayerBanAudits"
+    | "ListAuditLogs" => ApiScope::Moderation,
+    // imported map infos are shared by all api clients and trusted for layout checks
+    "ImportMaps" => ApiScope::Admin,
+    _ => ApiScope::Admin,
+  }
+}
+
+/// Copies the gRPC method path into the request metadata,
+/// interceptors only see the metadata
+#[derive(Clone)]
+pub struct GrpcMethodService<S> {
+  inner: S,
+}
+
+impl<S> GrpcMethodService<S> {
+  pub fn new(inner: S) -> Self {
+    Self { inner }
+  }
+}
+
+impl<S, B> TowerService<http::Request<B>> for GrpcMethodService<S>
+where
+  S: TowerService<http::Request<B>>,
+{
+  type Response = S::Response;
+  type Error = S::Error;
+  type Future = S::Future;
+
+  fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+    self.inner.poll_ready(cx)
+  }
+
+  fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+    let value = http::HeaderValue::from_str(req.uri().path()).ok();
+    let headers = req.headers_mut();
+    // never trust the value sent by the client
+    headers.remove(REQUEST_META_METHOD);
+    if let Some(value) = value {
+      headers.insert(REQUEST_META_METHOD, value);
     }
+    self.inner.call(req)
   }
 }
 
+impl<S: NamedService> NamedService for GrpcMethodService<S> {
+  const NAME: &'static str = S::NAME;
+}
+
 impl ConfigStorage {
-  async fn load_map(db: &ExecutorRef) -> Result<BTreeMap<Vec<u8>, ApiClient>> {
+  async fn load_map(db: &ExecutorRef) -> Result<BTreeMap<Vec<u8>, ApiKeyEntry>> {
     let mut map = BTreeMap::new();
 
-    let (api_player_map, items) = db
+    let (api_player_map, keys) = db
       .exec(|conn| -> Result<_> {
         create_api_players(conn)?;
 
@@ -132,27 +269,27 @@ impl ConfigStorage {
           .into_iter()
           .collect();
 
-        let items = api_client::table
-          .select((
-            api_client::id,
-            api_client::name,
-            api_client::secret_key,
-            api_client::created_at,
-            diesel::dsl::sql::<diesel::sql_types::Integer>("0"),
-          ))
-          .load::<ApiClient>(conn)?;
-        Ok((api_player_map, items))
+        let keys = crate::api_client::db::get_active_keys(conn)?;
+        Ok((api_player_map, keys))
       })
       .await?;
 
-    for mut item in items {
-      item.player_id = if let Some(player_id) = api_player_map.get(&item.id).cloned() {
+    for (key, scopes) in keys {
+      let api_player_id = if let Some(player_id) = api_player_map.get(&key.api_client_id).cloned() {
         player_id
       } else {
-        tracing::error!(id = item.id, "api player not found");
+        tracing::error!(id = key.api_client_id, "api player not found");
         continue;
       };
-      map.insert(item.secret_key.as_bytes().to_vec(), item);
+      map.insert(
+        key.secret_key.as_bytes().to_vec(),
+        ApiKeyEntry {
+          api_client_id: key.api_client_id,
+          api_player_id,
+          scopes,
+          expires_at: key.expires_at,
+        },
+      );
     }
 
     Ok(map)
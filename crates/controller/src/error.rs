@@ -11,6 +11,12 @@ pub enum Error {
   NodeNotFound,
   #[error("Node not ready")]
   NodeNotReady,
+  #[error("Node is under maintenance")]
+  NodeDraining,
+  #[error("Node is disabled")]
+  NodeDisabled,
+  #[error("Node is used by games, disable it instead")]
+  NodeInUse,
   #[error("Node rejected connection: {addr:?}: {reason:?}")]
   NodeConnectionRejected {
     addr: std::net::SocketAddrV4,
@@ -54,12 +60,26 @@ pub enum Error {
   GameNodeNotSelected,
   #[error("Slot update denied")]
   GameSlotUpdateDenied,
+  #[error("The host can't be kicked")]
+  GameKickHost,
   #[error("Game already started")]
   GameStarted,
   #[error("Game not in starting state")]
   GameNotStarting,
   #[error("This map has no player slot")]
   MapHasNoPlayer,
+  #[error("Map not found")]
+  MapNotFound,
+  #[error("Invalid map sha1, expected 40 hex characters")]
+  MapSha1Invalid,
+  #[error("This map is not in the map pool")]
+  MapNotAllowed,
+  #[error("The map checksum or slot layout does not match the registered map")]
+  MapLayoutMismatch,
+  #[error("Map pool not found")]
+  MapPoolNotFound,
+  #[error("Invalid map pool: {0}")]
+  MapPoolInvalid(&'static str),
   #[error("Player not in game")]
   PlayerNotInGame,
   #[error("Player already in game")]
@@ -86,6 +106,40 @@ pub enum Error {
   PlayerTeamInvalid,
   #[error("Player not belongs to the current API client")]
   PlayerOwnerCheckFailed,
+  #[error("Matchmaking queue not found")]
+  MatchmakingQueueNotFound,
+  #[error("Invalid matchmaking queue: {0}")]
+  MatchmakingQueueInvalid(&'static str),
+  #[error("Player not in matchmaking queue")]
+  PlayerNotInMatchmakingQueue,
+  #[error("Invalid game history cursor")]
+  GameHistoryCursorInvalid,
+  #[error("Player banned: {0:?}")]
+  PlayerBanned(crate::player::PlayerBanType),
+  #[error("Invalid player ban: {0}")]
+  PlayerBanInvalid(&'static str),
+  #[error("Tournament not found")]
+  TournamentNotFound,
+  #[error("Invalid tournament: {0}")]
+  TournamentInvalid(&'static str),
+  #[error("Invite not found")]
+  InviteNotFound,
+  #[error("Party not found")]
+  PartyNotFound,
+  #[error("You are not the party leader")]
+  PlayerNotPartyLeader,
+  #[error("Too many pending invites")]
+  TooManyInvites,
+  #[error("The party doesn't fit into a team of the queue")]
+  PartyTooLarge,
+  #[error("Api client not found")]
+  ApiClientNotFound,
+  #[error("Api client key not found")]
+  ApiClientKeyNotFound,
+  #[error("Invalid api client: {0}")]
+  ApiClientInvalid(&'static str),
+  #[error("Api client not allowed to manage other api clients")]
+  ApiClientForbidden,
   #[error("Operation timeout: {0}")]
   Timeout(anyhow::Error),
   #[error("net: {0}")]
@@ -102,6 +156,8 @@ pub enum Error {
   Proto(#[from] s2_grpc_utils::result::Error),
   #[error("gRPC transport: {0}")]
   GrpcTransport(#[from] tonic::transport::Error),
+  #[error("map: {0}")]
+  War3Map(#[from] flo_w3map::error::Error),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -124,9 +180,35 @@ impl From<Error> for Status {
       e @ Error::GameNotFound
       | e @ Error::PlayerNotFound
       | e @ Error::MapHasNoPlayer
+      | e @ Error::MapNotFound
+      | e @ Error::MapSha1Invalid
+      | e @ Error::MapNotAllowed
+      | e @ Error::MapLayoutMismatch
+      | e @ Error::MapPoolNotFound
+      | e @ Error::MapPoolInvalid(_)
+      | e @ Error::War3Map(_)
       | e @ Error::GameFull
       | e @ Error::GameNotCancellable
-      | e @ Error::JoinTokenExpired => Status::invalid_argument(e.to_string()),
+      | e @ Error::JoinTokenExpired
+      | e @ Error::MatchmakingQueueNotFound
+      | e @ Error::MatchmakingQueueInvalid(_)
+      | e @ Error::PlayerNotInMatchmakingQueue
+      | e @ Error::PartyTooLarge
+      | e @ Error::PlayerNotPartyLeader
+      | e @ Error::GameHistoryCursorInvalid
+      | e @ Error::TournamentNotFound
+      | e @ Error::TournamentInvalid(_)
+      | e @ Error::PlayerBanInvalid(_)
+      | e @ Error::NodeNotFound
+      | e @ Error::NodeDisabled
+      | e @ Error::NodeInUse
+      | e @ Error::InvalidNodeAddress(_)
+      | e @ Error::ApiClientNotFound
+      | e @ Error::ApiClientKeyNotFound
+      | e @ Error::ApiClientInvalid(_) => Status::invalid_argument(e.to_string()),
+      e @ Error::PlayerBanned(_) | e @ Error::ApiClientForbidden => {
+        Status::permission_denied(e.to_string())
+      }
       e @ Error::PlayerTokenExpired => Status::unauthenticated(e.to_string()),
       Error::JsonWebToken(e) => Status::unauthenticated(e.to_string()),
       e => Status::internal(e.to_string()),
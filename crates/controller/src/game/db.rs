@@ -11,13 +11,14 @@ use crate::error::*;
 use crate::game::slots::{UsedSlot, UsedSlotInfo};
 use crate::game::state::GameStatusUpdate;
 use crate::game::{
-  Computer, CreateGameSlot, Game, GameEntry, GameStatus, Race, Slot, SlotClientStatus,
+  Computer, CreateGameSlot, Game, GameDesyncReport, GameEntry, GamePlayerResult, GameResult,
+  GameResultUpdate, GameStatus, ObserverChatPolicy, PlayerGameResult, Race, Slot, SlotClientStatus,
   SlotSettings, SlotStatus, Slots,
 };
 use crate::map::Map;
 use crate::node::{NodeRef, NodeRefColumns, PlayerToken};
 use crate::player::{PlayerRef, PlayerRefColumns};
-use crate::schema::{game, game_used_slot, node, player};
+use crate::schema::{game, game_desync, game_used_slot, node, player};
 use diesel::pg::expression::dsl::{all, any};
 
 pub fn get(conn: &DbConn, id: i32) -> Result<GameRowWithRelated> {
@@ -144,12 +145,193 @@ pub fn query(conn: &DbConn, params: &QueryGameParams) -> Result<QueryGame> {
   Ok(QueryGame { games, has_more })
 }
 
-pub fn cancel(conn: &DbConn, game_id: i32, created_by: Option<i32>) -> Result<()> {
+#[derive(Debug, Default, S2ProtoUnpack)]
+#[s2_grpc(message_type = "flo_grpc::controller::ListGameHistoryRequest")]
+pub struct QueryGameHistoryParams {
+  pub player_id: Option<i32>,
+  /// Players that played against `player_id`, or in the game if `player_id` is not set
+  pub opponent_ids: Vec<i32>,
+  pub map_sha1: Option<Vec<u8>>,
+  pub map_name: Option<String>,
+  pub node_id: Option<i32>,
+  pub started_after: Option<DateTime<Utc>>,
+  pub started_before: Option<DateTime<Utc>>,
+  pub ended_after: Option<DateTime<Utc>>,
+  pub ended_before: Option<DateTime<Utc>>,
+  pub min_duration_secs: Option<i32>,
+  pub max_duration_secs: Option<i32>,
+  pub take: Option<i64>,
+  pub cursor: Option<String>,
+}
+
+#[derive(Debug, S2ProtoPack)]
+#[s2_grpc(message_type = "flo_grpc::controller::ListGameHistoryReply")]
+pub struct QueryGameHistory {
+  pub games: Vec<GameEntry>,
+  pub next_cursor: Option<String>,
+}
+
+/// Lists started games, most recent first.
+/// Private games are only listed to the api client that created them.
+pub fn query_history(
+  conn: &DbConn,
+  api_client_id: i32,
+  params: &QueryGameHistoryParams,
+) -> Result<QueryGameHistory> {
+  use diesel::sql_types::{Bool, Jsonb};
+  use game::dsl;
+
+  let take = std::cmp::min(100, params.take.clone().unwrap_or(30));
+
+  let mut q = game::table
+    .left_outer_join(node::table)
+    .left_outer_join(player::table)
+    .select(GameEntry::columns())
+    .filter(dsl::started_at.is_not_null())
+    .filter(
+      dsl::is_private
+        .eq(false)
+        .or(player::dsl::api_client_id.eq(api_client_id)),
+    )
+    .order((dsl::started_at.desc(), dsl::id.desc()))
+    .limit(take + 1)
+    .into_boxed();
+
+  if let Some(player_id) = params.player_id.clone() {
+    let subq = game_used_slot::table
+      .select(game_used_slot::dsl::game_id)
+      .filter(game_used_slot::dsl::player_id.eq(player_id));
+    q = q.filter(dsl::id.eq(any(subq)));
+  }
+
+  for opponent_id in params.opponent_ids.iter().cloned() {
+    if let Some(player_id) = params.player_id.clone() {
+      // ids are integers, safe to inline
+      q = q.filter(sql::<Bool>(&format!(
+        "exists (select 1 from game_used_slot a \
+         inner join game_used_slot b on a.game_id = b.game_id and a.team <> b.team \
+         where a.game_id = game.id and a.player_id = {} and b.player_id = {})",
+        player_id, opponent_id
+      )));
+    } else {
+      let subq = game_used_slot::table
+        .select(game_used_slot::dsl::game_id)
+        .filter(game_used_slot::dsl::player_id.eq(opponent_id));
+      q = q.filter(dsl::id.eq(any(subq)));
+    }
+  }
+
+  if let Some(sha1) = params.map_sha1.clone() {
+    let sha1 = serde_json::to_value(&crate::map::MapSha1::unpack(sha1)?)?;
+    q = q.filter(sql::<Jsonb>("game.meta->'map'->'sha1'").eq(sha1));
+  }
+
+  if let Some(ref map_name) = params.map_name {
+    q = q.filter(dsl::map_name.ilike(format!("%{}%", escape_like(map_name.trim()))));
+  }
+
+  if let Some(node_id) = params.node_id.clone() {
+    q = q.filter(dsl::node_id.eq(node_id));
+  }
+
+  if let Some(v) = params.started_after.clone() {
+    q = q.filter(dsl::started_at.ge(v));
+  }
+
+  if let Some(v) = params.started_before.clone() {
+    q = q.filter(dsl::started_at.lt(v));
+  }
+
+  if let Some(v) = params.ended_after.clone() {
+    q = q.filter(dsl::ended_at.ge(v));
+  }
+
+  if let Some(v) = params.ended_before.clone() {
+    q = q.filter(dsl::ended_at.lt(v));
+  }
+
+  if let Some(secs) = params.min_duration_secs.clone() {
+    q = q.filter(sql::<Bool>(&format!(
+      "extract(epoch from game.ended_at - game.started_at) >= {}",
+      secs
+    )));
+  }
+
+  if let Some(secs) = params.max_duration_secs.clone() {
+    q = q.filter(sql::<Bool>(&format!(
+      "extract(epoch from game.ended_at - game.started_at) <= {}",
+      secs
+    )));
+  }
+
+  if let Some(ref cursor) = params.cursor {
+    let (started_at, id) = decode_history_cursor(cursor)?;
+    q = q.filter(
+      dsl::started_at
+        .lt(started_at)
+        .or(dsl::started_at.eq(started_at).and(dsl::id.lt(id))),
+    );
+  }
+
+  let mut games: Vec<GameEntry> = q.load(conn)?;
+
+  let next_cursor = if games.len() > take as usize {
+    games.truncate(take as usize);
+    games
+      .last()
+      .and_then(|game| Some(encode_history_cursor(game.started_at?, game.id)))
+  } else {
+    None
+  };
+
+  Ok(QueryGameHistory { games, next_cursor })
+}
+
+/// `<started_at in microseconds>_<game id>`
+fn encode_history_cursor(started_at: DateTime<Utc>, id: i32) -> String {
+  let micros = started_at.timestamp() * 1_000_000 + started_at.timestamp_subsec_micros() as i64;
+  format!("{}_{}", micros, id)
+}
+
+fn decode_history_cursor(value: &str) -> Result<(DateTime<Utc>, i32)> {
+  use chrono::TimeZone;
+  let mut parts = value.splitn(2, '_');
+  let micros: i64 = parts
+    .next()
+    .and_then(|v| v.parse().ok())
+    .ok_or_else(|| Error::GameHistoryCursorInvalid)?;
+  let id: i32 = parts
+    .next()
+    .and_then(|v| v.parse().ok())
+    .ok_or_else(|| Error::GameHistoryCursorInvalid)?;
+  let started_at = Utc
+    .timestamp_opt(
+      micros.div_euclid(1_000_000),
+      (micros.rem_euclid(1_000_000) * 1000) as u32,
+    )
+    .single()
+    .ok_or_else(|| Error::GameHistoryCursorInvalid)?;
+  Ok((started_at, id))
+}
+
+/// Escapes the `like` wildcards so the input is matched literally
+fn escape_like(value: &str) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for c in value.chars() {
+    if matches!(c, '\\' | '%' | '_') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+pub fn cancel(conn: &DbConn, game_id: i32, host_player_id: Option<i32>) -> Result<()> {
   use game::dsl;
 
   let mut q = game::table.find(game_id).into_boxed();
-  if let Some(created_by) = created_by {
-    q = q.filter(dsl::created_by.eq(created_by));
+  if let Some(host_player_id) = host_player_id {
+    q = q.filter(dsl::host_player_id.eq(host_player_id));
   }
   let status: GameStatus = q
     .select(dsl::status)
@@ -173,6 +355,8 @@ pub struct CreateGameParams {
   pub map: Map,
   pub is_private: bool,
   pub is_live: bool,
+  #[s2_grpc(proto_enum)]
+  pub observer_chat_policy: ObserverChatPolicy,
 }
 
 /// Creates a game, make the creator as the first player
@@ -201,13 +385,17 @@ pub fn create(conn: &DbConn, params: CreateGameParams) -> Result<Game> {
     is_live: params.is_live,
     max_players: max_players as i32,
     created_by: Some(params.player_id),
+    host_player_id: params.player_id,
     meta: meta_value,
     random_seed: rand::random(),
     locked: false,
     node_id: None,
     mask_player_names: false,
     enable_ping_equalizer: false,
+    enable_adaptive_step: false,
+    observer_chat_policy: params.observer_chat_policy,
     flo_tv_delay_override_secs: None,
+    rating_mode: None,
   };
 
   let row = conn.transaction(|| -> Result<_> {
@@ -233,7 +421,11 @@ pub struct CreateGameAsBotParams {
   pub slots: Vec<CreateGameSlot>,
   pub mask_player_names: bool,
   pub enable_ping_equalizer: bool,
+  pub enable_adaptive_step: bool,
+  #[s2_grpc(proto_enum)]
+  pub observer_chat_policy: ObserverChatPolicy,
   pub flo_tv_delay_override_secs: Option<i32>,
+  pub rating_mode: Option<String>,
 }
 
 /// Creates a full game and lock it
@@ -254,6 +446,8 @@ pub fn create_as_bot(
     return Err(Error::TooManyPlayers);
   }
 
+  crate::node::db::check_node_enabled(conn, params.node_id)?;
+
   let (player_slots, referee_slots): (Vec<_>, Vec<_>) = params
     .slots
     .iter()
@@ -275,6 +469,17 @@ pub fn create_as_bot(
     return Err(Error::GameHasNoPlayer);
   }
 
+  for slot in params.slots.iter() {
+    if let Some(player_id) = slot.player_id {
+      crate::player::db::check_game_ban(
+        conn,
+        player_id,
+        crate::player::db::GameBanCheck::for_team(slot.settings.team),
+        &params.map.sha1,
+      )?;
+    }
+  }
+
   player_ids.push(api_player_id);
   player_ids.sort();
   player_ids.dedup();
@@ -307,6 +512,8 @@ pub fn create_as_bot(
       settings: slot.settings.clone(),
       client_status: SlotClientStatus::Pending,
       player,
+      ready: false,
+      is_referee: false,
     });
   }
 
@@ -323,6 +530,8 @@ pub fn create_as_bot(
       },
       client_status: SlotClientStatus::Pending,
       player,
+      ready: false,
+      is_referee: slot.is_referee,
     });
   }
 
@@ -345,13 +554,17 @@ pub fn create_as_bot(
     is_live: params.is_live,
     max_players: max_players as i32,
     created_by: Some(api_player_id),
+    host_player_id: api_player_id,
     meta: meta_value,
     random_seed: rand::random(),
     locked: true,
     node_id: Some(params.node_id),
     mask_player_names: params.mask_player_names,
     enable_ping_equalizer: params.enable_ping_equalizer,
+    enable_adaptive_step: params.enable_adaptive_step,
+    observer_chat_policy: params.observer_chat_policy,
     flo_tv_delay_override_secs: params.flo_tv_delay_override_secs,
+    rating_mode: params.rating_mode.as_deref(),
   };
 
   let row = conn.transaction(|| -> Result<_> {
@@ -403,51 +616,179 @@ pub struct LeaveGame {
   pub game_ended: bool,
   pub removed_players: Vec<i32>,
   pub slots: Vec<Slot>,
+  /// Set if the host left and the next player became the host
+  pub new_host_player_id: Option<i32>,
 }
 
 pub fn remove_player(conn: &DbConn, game_id: i32, player_id: i32) -> Result<LeaveGame> {
-  let InspectId { status, locked } = inspect_id(conn, game_id)?;
+  check_lobby_editable(conn, game_id)?;
 
-  if locked {
-    return Err(Error::GameSlotUpdateDenied);
-  }
+  let GetSlots {
+    mut slots,
+    host_player_id,
+  } = get_slots(conn, game_id)?;
 
-  if status != GameStatus::Preparing {
-    return Err(Error::GameStarted);
-  }
+  let leave = leave_slots(&mut slots, host_player_id, player_id);
+  save_leave(conn, game_id, &slots, &leave)?;
+  Ok(leave)
+}
+
+/// Removes a player from the lobby on behalf of the host
+pub fn kick_player(
+  conn: &DbConn,
+  game_id: i32,
+  host_player_id: i32,
+  player_id: i32,
+) -> Result<LeaveGame> {
+  check_lobby_editable(conn, game_id)?;
 
   let GetSlots {
     mut slots,
-    host_player_id,
+    host_player_id: current_host_player_id,
   } = get_slots(conn, game_id)?;
 
-  // host left, kick all players
+  let leave = kick_slots(
+    &mut slots,
+    current_host_player_id,
+    host_player_id,
+    player_id,
+  )?;
+  save_leave(conn, game_id, &slots, &leave)?;
+  Ok(leave)
+}
+
+/// Releases the slot of a leaving player, the next player becomes the host if the host left
+fn leave_slots(slots: &mut Slots, host_player_id: i32, player_id: i32) -> LeaveGame {
+  let mut ended = false;
+  let mut removed_players = Vec::with_capacity(1);
+  let mut new_host_player_id = None;
+  if slots.release_player_slot(player_id) {
+    removed_players.push(player_id);
+    if slots.is_empty() {
+      ended = true;
+    } else if player_id == host_player_id {
+      // players are preferred over observers
+      new_host_player_id = slots
+        .iter()
+        .filter(|slot| slot.settings.team != 24)
+        .chain(slots.iter())
+        .find_map(|slot| slot.player.as_ref().map(|p| p.id));
+    }
+  }
+  LeaveGame {
+    game_ended: ended,
+    removed_players,
+    slots: slots.to_vec(),
+    new_host_player_id,
+  }
+}
+
+fn kick_slots(
+  slots: &mut Slots,
+  current_host_player_id: i32,
+  host_player_id: i32,
+  player_id: i32,
+) -> Result<LeaveGame> {
+  if current_host_player_id != host_player_id {
+    return Err(Error::PlayerNotHost);
+  }
   if player_id == host_player_id {
-    let removed = slots.release_all_player_slots();
-    upsert_used_slots(conn, game_id, slots.as_used())?;
+    return Err(Error::GameKickHost);
+  }
+
+  if !slots.release_player_slot(player_id) {
+    return Err(Error::PlayerNotInGame);
+  }
+
+  Ok(LeaveGame {
+    game_ended: false,
+    removed_players: vec![player_id],
+    slots: slots.to_vec(),
+    new_host_player_id: None,
+  })
+}
+
+fn save_leave(conn: &DbConn, game_id: i32, slots: &Slots, leave: &LeaveGame) -> Result<()> {
+  if leave.removed_players.is_empty() {
+    return Ok(());
+  }
+  upsert_used_slots(conn, game_id, slots.as_used())?;
+  if leave.game_ended {
     end_game(conn, game_id, GameStatus::Ended)?;
-    Ok(LeaveGame {
-      game_ended: true,
-      removed_players: removed,
-      slots: slots.into_inner(),
-    })
-  } else {
-    let mut ended = false;
-    let mut removed_players = Vec::with_capacity(1);
-    if slots.release_player_slot(player_id) {
-      removed_players.push(player_id);
-      upsert_used_slots(conn, game_id, slots.as_used())?;
-      if slots.is_empty() {
-        ended = true;
-        end_game(conn, game_id, GameStatus::Ended)?;
-      }
-    }
-    Ok(LeaveGame {
-      game_ended: ended,
-      removed_players,
-      slots: slots.into_inner(),
-    })
+  } else if let Some(id) = leave.new_host_player_id {
+    set_host(conn, game_id, id)?;
+  }
+  Ok(())
+}
+
+/// Makes another lobby member the host, returns the updated slot of the former host
+pub fn transfer_host(
+  conn: &DbConn,
+  game_id: i32,
+  host_player_id: i32,
+  player_id: i32,
+) -> Result<(i32, Slot)> {
+  check_lobby_editable(conn, game_id)?;
+
+  let GetSlots {
+    slots,
+    host_player_id: current_host_player_id,
+  } = get_slots(conn, game_id)?;
+  if current_host_player_id != host_player_id {
+    return Err(Error::PlayerNotHost);
+  }
+  if slots.find_player_slot(player_id).is_none() {
+    return Err(Error::PlayerNotInGame);
   }
+
+  set_host(conn, game_id, player_id)?;
+
+  // the host never readies, so the former host is kept ready to not block the start
+  update_slot_ready(conn, game_id, host_player_id, true)
+}
+
+pub fn get_host_player_id(conn: &DbConn, game_id: i32) -> Result<i32> {
+  use game::dsl;
+  game::table
+    .find(game_id)
+    .select(dsl::host_player_id)
+    .first(conn)
+    .optional()?
+    .ok_or_else(|| Error::GameNotFound)
+}
+
+pub fn get_map(conn: &DbConn, game_id: i32) -> Result<Map> {
+  let meta: Value = game::table
+    .find(game_id)
+    .select(game::dsl::meta)
+    .first(conn)
+    .optional()?
+    .ok_or_else(|| Error::GameNotFound)?;
+  let meta: Meta = serde_json::from_value(meta)?;
+  Ok(meta.map)
+}
+
+/// `created_by` keeps the creator of the game, only the host changes
+fn set_host(conn: &DbConn, game_id: i32, player_id: i32) -> Result<()> {
+  use game::dsl;
+  diesel::update(game::table.find(game_id))
+    .set(dsl::host_player_id.eq(player_id))
+    .execute(conn)?;
+  Ok(())
+}
+
+fn check_lobby_editable(conn: &DbConn, game_id: i32) -> Result<()> {
+  let InspectId { status, locked } = inspect_id(conn, game_id)?;
+
+  if locked {
+    return Err(Error::GameSlotUpdateDenied);
+  }
+
+  if status != GameStatus::Preparing {
+    return Err(Error::GameStarted);
+  }
+
+  Ok(())
 }
 
 #[derive(Queryable)]
@@ -510,7 +851,7 @@ pub fn get_slot_owner_info(conn: &DbConn, game_id: i32, slot_index: i32) -> Resu
   let rows: Vec<(i32, Option<i32>, Option<i32>)> = game::table
     .left_join(game_used_slot::table)
     .select((
-      g::created_by,
+      g::host_player_id,
       gus::slot_index.nullable(),
       gus::player_id.nullable(),
     ))
@@ -584,6 +925,58 @@ fn sync_slot_at(conn: &DbConn, game_id: i32, slot_index: i32, slot: &Slot) -> Re
   Ok(())
 }
 
+/// Updates the ready state of a lobby member, returns the slot index and the updated slot
+pub fn update_slot_ready(
+  conn: &DbConn,
+  game_id: i32,
+  player_id: i32,
+  ready: bool,
+) -> Result<(i32, Slot)> {
+  use game_used_slot::dsl;
+
+  let InspectId { status, .. } = inspect_id(conn, game_id)?;
+  if status != GameStatus::Preparing {
+    return Err(Error::GameStarted);
+  }
+
+  let slots = get_slots(conn, game_id)?.slots;
+  let slot_index = slots
+    .iter()
+    .position(|s| s.player.as_ref().map(|p| p.id) == Some(player_id))
+    .ok_or_else(|| Error::PlayerNotInGame)? as i32;
+
+  diesel::update(
+    game_used_slot::table.filter(dsl::game_id.eq(game_id).and(dsl::slot_index.eq(slot_index))),
+  )
+  .set(dsl::ready.eq(ready))
+  .execute(conn)?;
+
+  let mut slot = slots[slot_index as usize].clone();
+  slot.ready = ready;
+  Ok((slot_index, slot))
+}
+
+/// Returns the lobby members, except the host, who are not ready
+pub fn get_unready_player_ids(
+  conn: &DbConn,
+  game_id: i32,
+  host_player_id: i32,
+) -> Result<Vec<i32>> {
+  use game_used_slot::dsl;
+  game_used_slot::table
+    .filter(
+      dsl::game_id
+        .eq(game_id)
+        .and(dsl::player_id.is_not_null())
+        .and(dsl::player_id.ne(host_player_id))
+        .and(dsl::ready.eq(false)),
+    )
+    .select(dsl::player_id)
+    .load::<Option<i32>>(conn)
+    .map(|rows| rows.into_iter().filter_map(|id| id).collect())
+    .map_err(Into::into)
+}
+
 pub fn update_slot_client_status(
   conn: &DbConn,
   game_id: i32,
@@ -646,6 +1039,114 @@ pub fn update_status(conn: &DbConn, update: &GameStatusUpdate) -> Result<()> {
   })
 }
 
+pub fn update_result(conn: &DbConn, update: &GameResultUpdate) -> Result<()> {
+  let game_id = update.game_id;
+  conn.transaction(|| {
+    diesel::update(game::table.find(game_id))
+      .set((
+        game::dsl::result_winning_team.eq(update.winning_team),
+        game::dsl::result_reported_at.eq(sql("now()")),
+      ))
+      .execute(conn)?;
+
+    for player in &update.players {
+      diesel::update(
+        game_used_slot::table.filter(
+          game_used_slot::dsl::game_id
+            .eq(game_id)
+            .and(game_used_slot::player_id.eq(player.player_id)),
+        ),
+      )
+      .set((
+        game_used_slot::result.eq(Some(player.result)),
+        game_used_slot::leave_reason.eq(player.leave_reason),
+        game_used_slot::removed_reason.eq(player.removed_reason),
+      ))
+      .execute(conn)?;
+    }
+    Ok(())
+  })
+}
+
+pub fn insert_desync(conn: &DbConn, report: &GameDesyncReport) -> Result<()> {
+  diesel::insert_into(game_desync::table)
+    .values(GameDesyncInsert {
+      game_id: report.game_id,
+      tick: report.tick as i32,
+      time: report.time as i32,
+      game_version: report.game_version.as_deref(),
+      players: serde_json::to_value(&report.players)?,
+    })
+    .execute(conn)?;
+  Ok(())
+}
+
+pub fn get_result(conn: &DbConn, game_id: i32) -> Result<Option<GameResult>> {
+  let (winning_team, reported_at): (Option<i32>, Option<DateTime<Utc>>) = game::table
+    .find(game_id)
+    .select((
+      game::dsl::result_winning_team,
+      game::dsl::result_reported_at,
+    ))
+    .first(conn)
+    .optional()?
+    .ok_or_else(|| Error::GameNotFound)?;
+
+  let reported_at = if let Some(v) = reported_at {
+    v
+  } else {
+    return Ok(None);
+  };
+
+  let rows: Vec<(
+    Option<i32>,
+    Option<PlayerGameResult>,
+    Option<i32>,
+    Option<i32>,
+  )> = game_used_slot::table
+    .select((
+      game_used_slot::player_id,
+      game_used_slot::result,
+      game_used_slot::leave_reason,
+      game_used_slot::removed_reason,
+    ))
+    .filter(
+      game_used_slot::game_id
+        .eq(game_id)
+        .and(game_used_slot::result.is_not_null()),
+    )
+    .order(game_used_slot::slot_index)
+    .load(conn)?;
+
+  Ok(Some(GameResult {
+    winning_team,
+    players: rows
+      .into_iter()
+      .filter_map(|(player_id, result, leave_reason, removed_reason)| {
+        Some(GamePlayerResult {
+          player_id: player_id?,
+          result: result?,
+          leave_reason,
+          removed_reason,
+        })
+      })
+      .collect(),
+    reported_at,
+  }))
+}
+
+pub fn get_referee_player_ids(conn: &DbConn, game_id: i32) -> Result<Vec<i32>> {
+  let ids: Vec<Option<i32>> = game_used_slot::table
+    .select(game_used_slot::player_id)
+    .filter(
+      game_used_slot::game_id
+        .eq(game_id)
+        .and(game_used_slot::is_referee.eq(true)),
+    )
+    .load(conn)?;
+  Ok(ids.into_iter().flatten().collect())
+}
+
 fn upsert_used_slots(conn: &DbConn, game_id: i32, used_slots: Vec<UsedSlot>) -> Result<()> {
   use diesel::pg::upsert::excluded;
   use game_used_slot::dsl;
@@ -675,6 +1176,8 @@ fn upsert_used_slots(conn: &DbConn, game_id: i32, used_slots: Vec<UsedSlot>) ->
         dsl::handicap.eq(excluded(dsl::handicap)),
         dsl::race.eq(excluded(dsl::race)),
         dsl::client_status.eq(excluded(dsl::client_status)),
+        dsl::ready.eq(excluded(dsl::ready)),
+        dsl::is_referee.eq(excluded(dsl::is_referee)),
       ))
       .execute(conn)?;
     Ok(())
@@ -694,7 +1197,7 @@ fn get_slots(conn: &DbConn, game_id: i32) -> Result<GetSlots> {
     use game::dsl;
     game::table
       .find(game_id)
-      .select((dsl::created_by, dsl::max_players))
+      .select((dsl::host_player_id, dsl::max_players))
       .first(conn)
       .optional()?
       .ok_or_else(|| Error::GameNotFound)?
@@ -715,12 +1218,16 @@ fn get_slots(conn: &DbConn, game_id: i32) -> Result<GetSlots> {
 
 fn get_used_slots(conn: &DbConn, game_id: i32) -> Result<Vec<UsedSlot>> {
   use game_used_slot::dsl;
-  game_used_slot::table
+  let mut slots: Vec<UsedSlot> = game_used_slot::table
     .left_outer_join(player::table)
     .select(UsedSlot::columns())
     .filter(dsl::game_id.eq(game_id))
-    .load(conn)
-    .map_err(Into::into)
+    .load(conn)?;
+  crate::rating::db::fill_player_ratings(
+    conn,
+    slots.iter_mut().filter_map(|slot| slot.player.as_mut()),
+  )?;
+  Ok(slots)
 }
 
 #[derive(Debug, Queryable)]
@@ -758,7 +1265,9 @@ pub fn get_full(conn: &DbConn, id: i32) -> Result<Game> {
   let meta: Meta = serde_json::from_value(row.meta.clone())?;
   let used_slots = get_used_slots(conn, id)?;
   let slots: Vec<Slot> = Slots::from_used(row.max_players as usize, used_slots).into_inner();
-  Ok(row.into_game(meta, slots)?)
+  let mut game = row.into_game(meta, slots)?;
+  game.result = get_result(conn, id)?;
+  Ok(game)
 }
 
 pub fn get_full_and_node_token(
@@ -800,7 +1309,7 @@ pub struct GameStateFromDb {
   pub status: GameStatus,
   pub players: Vec<(i32, Option<Vec<u8>>)>,
   pub node_id: Option<i32>,
-  pub created_by: i32,
+  pub host_player_id: i32,
 }
 
 /// Loads game players info from database
@@ -816,7 +1325,7 @@ pub fn get_all_active_game_state(conn: &DbConn) -> Result<Vec<GameStateFromDb>>
       GameStatus::Running,
     ]))
     .order(dsl::created_at)
-    .select((dsl::id, dsl::status, dsl::node_id, dsl::created_by))
+    .select((dsl::id, dsl::status, dsl::node_id, dsl::host_player_id))
     .load(conn)?;
 
   let game_ids: Vec<_> = rows.iter().map(|(id, _, _, _)| *id).collect();
@@ -846,14 +1355,14 @@ pub fn get_all_active_game_state(conn: &DbConn) -> Result<Vec<GameStateFromDb>>
   };
 
   let mut games = Vec::with_capacity(rows.len());
-  for (id, status, node_id, created_by) in rows {
+  for (id, status, node_id, host_player_id) in rows {
     let players = game_players_map.remove(&id).unwrap_or_default();
     games.push(GameStateFromDb {
       id,
       status,
       players,
       node_id,
-      created_by,
+      host_player_id,
     });
   }
   Ok(games)
@@ -882,11 +1391,15 @@ pub fn select_node(conn: &DbConn, id: i32, player_id: i32, node_id: Option<i32>)
     return Err(Error::GameStarted);
   }
 
+  if let Some(node_id) = node_id {
+    crate::node::db::check_node_enabled(conn, node_id)?;
+  }
+
   let n: usize = diesel::update(game::table.find(id))
     .filter(
       dsl::status
         .eq(GameStatus::Preparing)
-        .and(game::created_by.eq(player_id)),
+        .and(game::host_player_id.eq(player_id)),
     )
     .set(dsl::node_id.eq(node_id))
     .execute(conn)?;
@@ -1017,6 +1530,8 @@ pub struct GameRowWithRelated {
   pub mask_player_names: bool,
   pub game_version: Option<String>,
   pub enable_ping_equalizer: bool,
+  pub enable_adaptive_step: bool,
+  pub observer_chat_policy: ObserverChatPolicy,
   pub flo_tv_delay_override_secs: Option<i32>,
 }
 
@@ -1040,6 +1555,8 @@ pub(crate) type GameRowWithRelatedColumns = (
   game::dsl::mask_player_names,
   game::dsl::game_version,
   game::dsl::enable_ping_equalizer,
+  game::dsl::enable_adaptive_step,
+  game::dsl::observer_chat_policy,
   game::dsl::flo_tv_delay_override_secs,
 );
 
@@ -1065,6 +1582,8 @@ impl GameRowWithRelated {
       game::dsl::mask_player_names,
       game::dsl::game_version,
       game::dsl::enable_ping_equalizer,
+      game::dsl::enable_adaptive_step,
+      game::dsl::observer_chat_policy,
       game::dsl::flo_tv_delay_override_secs,
     )
   }
@@ -1092,7 +1611,10 @@ impl GameRowWithRelated {
       mask_player_names: self.mask_player_names,
       game_version: self.game_version,
       enable_ping_equalizer: self.enable_ping_equalizer,
+      enable_adaptive_step: self.enable_adaptive_step,
+      observer_chat_policy: self.observer_chat_policy,
       flo_tv_delay_override_secs: self.flo_tv_delay_override_secs,
+      result: None,
     })
   }
 }
@@ -1106,13 +1628,27 @@ pub struct GameInsert<'a> {
   pub is_live: bool,
   pub max_players: i32,
   pub created_by: Option<i32>,
+  pub host_player_id: i32,
   pub meta: Value,
   pub random_seed: i32,
   pub locked: bool,
   pub node_id: Option<i32>,
   pub mask_player_names: bool,
   pub enable_ping_equalizer: bool,
+  pub enable_adaptive_step: bool,
+  pub observer_chat_policy: ObserverChatPolicy,
   pub flo_tv_delay_override_secs: Option<i32>,
+  pub rating_mode: Option<&'a str>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "game_desync"]
+struct GameDesyncInsert<'a> {
+  game_id: i32,
+  tick: i32,
+  time: i32,
+  game_version: Option<&'a str>,
+  players: Value,
 }
 
 #[derive(Debug, Insertable)]
@@ -1128,6 +1664,8 @@ pub struct UsedSlotInsert {
   status: SlotStatus,
   race: Race,
   client_status: SlotClientStatus,
+  ready: bool,
+  is_referee: bool,
 }
 
 impl UsedSlotInsert {
@@ -1143,6 +1681,8 @@ impl UsedSlotInsert {
       status: slot.settings.status,
       race: slot.settings.race,
       client_status: slot.client_status,
+      ready: slot.ready,
+      is_referee: slot.is_referee,
     }
   }
 }
@@ -1159,6 +1699,8 @@ pub struct UsedSlotUpdate {
   status: SlotStatus,
   race: Race,
   client_status: SlotClientStatus,
+  ready: bool,
+  is_referee: bool,
 }
 
 impl UsedSlotUpdate {
@@ -1172,6 +1714,97 @@ impl UsedSlotUpdate {
       status: slot.settings.status,
       race: slot.settings.race,
       client_status: slot.client_status,
+      ready: slot.ready,
+      is_referee: slot.is_referee,
     }
   }
 }
+
+#[cfg(test)]
+fn test_lobby_slots(player_ids: &[i32]) -> Slots {
+  use crate::player::{PlayerRef, PlayerSource};
+  let mut slots = Slots::new(2);
+  for id in player_ids {
+    slots.join(&PlayerRef {
+      id: *id,
+      name: format!("player{}", id),
+      source: PlayerSource::Test,
+      realm: None,
+      ratings: vec![],
+    });
+  }
+  slots
+}
+
+#[test]
+fn test_leave_slots() {
+  // 1 and 2 are players, 3 is an observer
+  let mut slots = test_lobby_slots(&[1, 2, 3]);
+  let leave = leave_slots(&mut slots, 1, 2);
+  assert_eq!(leave.removed_players, vec![2]);
+  assert!(!leave.game_ended);
+  assert_eq!(leave.new_host_player_id, None);
+
+  // the host left, the player is preferred over the observer
+  let mut slots = test_lobby_slots(&[1, 2, 3]);
+  let leave = leave_slots(&mut slots, 1, 1);
+  assert_eq!(leave.new_host_player_id, Some(2));
+  assert!(slots.find_player_slot(1).is_none());
+
+  let mut slots = test_lobby_slots(&[1, 2, 3]);
+  leave_slots(&mut slots, 1, 2);
+  let leave = leave_slots(&mut slots, 1, 1);
+  assert_eq!(leave.new_host_player_id, Some(3));
+
+  let mut slots = test_lobby_slots(&[1]);
+  let leave = leave_slots(&mut slots, 1, 1);
+  assert!(leave.game_ended);
+  assert_eq!(leave.new_host_player_id, None);
+
+  let mut slots = test_lobby_slots(&[1, 2]);
+  let leave = leave_slots(&mut slots, 1, 4);
+  assert!(leave.removed_players.is_empty());
+  assert!(!leave.game_ended);
+}
+
+#[test]
+fn test_kick_slots() {
+  let mut slots = test_lobby_slots(&[1, 2, 3]);
+  let leave = kick_slots(&mut slots, 1, 1, 3).unwrap();
+  assert_eq!(leave.removed_players, vec![3]);
+  assert!(!leave.game_ended);
+  assert_eq!(leave.new_host_player_id, None);
+  assert!(slots.find_player_slot(3).is_none());
+
+  let mut slots = test_lobby_slots(&[1, 2]);
+  assert!(matches!(
+    kick_slots(&mut slots, 1, 2, 1),
+    Err(Error::PlayerNotHost)
+  ));
+  assert!(matches!(
+    kick_slots(&mut slots, 1, 1, 1),
+    Err(Error::GameKickHost)
+  ));
+  assert!(matches!(
+    kick_slots(&mut slots, 1, 1, 4),
+    Err(Error::PlayerNotInGame)
+  ));
+  assert!(slots.find_player_slot(2).is_some());
+}
+
+#[test]
+fn test_escape_like() {
+  assert_eq!(escape_like("(2)Echo Isles"), "(2)Echo Isles");
+  assert_eq!(escape_like("100%_map\\"), "100\\%\\_map\\\\");
+}
+
+#[test]
+fn test_history_cursor() {
+  use chrono::TimeZone;
+  let started_at = Utc.timestamp_opt(1_600_000_000, 123_456_000).unwrap();
+  let cursor = encode_history_cursor(started_at, 42);
+  assert_eq!(cursor, "1600000000123456_42");
+  assert_eq!(decode_history_cursor(&cursor).unwrap(), (started_at, 42));
+  assert!(decode_history_cursor("1600000000123456").is_err());
+  assert!(decode_history_cursor("x_1").is_err());
+}
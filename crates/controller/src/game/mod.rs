@@ -6,16 +6,19 @@ mod types;
 
 pub mod messages {
   pub use super::state::cancel::CancelGame;
+  pub use super::state::chat::LobbyChat;
   pub use super::state::create::CreateGame;
+  pub use super::state::host::{KickPlayer, TransferHost};
   pub use super::state::join::PlayerJoin;
   pub use super::state::leave::PlayerLeave;
   pub use super::state::node::SelectNode;
   pub use super::state::player::GetGamePlayers;
+  pub use super::state::ready::{ScheduleReadyTimeout, SetPlayerReady};
   pub use super::state::registry::{
     AddGamePlayer, Register, Remove, RemoveGamePlayer, ResolveGamePlayerPingBroadcastTargets,
   };
   pub use super::state::slot::UpdateSlot;
-  pub use super::state::start::{StartGameCheck, StartGamePlayerAck};
+  pub use super::state::start::{StartGameCheck, StartGameCheckResult, StartGamePlayerAck};
 }
 
 pub use slots::Slots;
@@ -39,6 +39,8 @@ impl Slots {
             player: used.player,
             settings: used.settings,
             client_status: used.client_status,
+            ready: used.ready,
+            is_referee: used.is_referee,
           }
         } else {
           Self::make_unused_slot(map_players, idx)
@@ -359,6 +361,8 @@ pub struct UsedSlot {
   pub settings: SlotSettings,
   pub client_status: SlotClientStatus,
   pub player: Option<PlayerRef>,
+  pub ready: bool,
+  pub is_referee: bool,
 }
 
 impl<'a> From<(usize, &'a Slot)> for UsedSlot {
@@ -368,6 +372,8 @@ impl<'a> From<(usize, &'a Slot)> for UsedSlot {
       player: slot.player.clone(),
       settings: slot.settings.clone(),
       client_status: slot.client_status,
+      ready: slot.ready,
+      is_referee: slot.is_referee,
     }
   }
 }
@@ -377,6 +383,8 @@ pub(crate) type UsedSlotColumns = (
   SlotSettingsColumns,
   game_used_slot::dsl::client_status,
   Nullable<PlayerRefColumns>,
+  game_used_slot::dsl::ready,
+  game_used_slot::dsl::is_referee,
 );
 
 impl UsedSlot {
@@ -386,6 +394,8 @@ impl UsedSlot {
       SlotSettings::COLUMNS,
       game_used_slot::dsl::client_status,
       PlayerRef::COLUMNS.nullable(),
+      game_used_slot::dsl::ready,
+      game_used_slot::dsl::is_referee,
     )
   }
 }
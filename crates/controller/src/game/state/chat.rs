@@ -0,0 +1,86 @@
+use crate::error::*;
+use crate::game::state::GameActor;
+use crate::game::GameStatus;
+use crate::player::PlayerBanType;
+use flo_net::packet::FloPacket;
+use flo_net::proto;
+use flo_state::{async_trait, Context, Handler, Message};
+
+/// Same as the in-game chat limit of Warcraft III
+const MAX_MESSAGE_LEN: usize = 255;
+
+/// Relays a chat message to the lobby members,
+/// players who muted the sender don't receive it
+pub struct LobbyChat {
+  pub player_id: i32,
+  pub message: String,
+}
+
+impl Message for LobbyChat {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<LobbyChat> for GameActor {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    LobbyChat { player_id, message }: LobbyChat,
+  ) -> Result<()> {
+    // lobby chat is closed once the game starts, but the client stream stays open
+    if self.status != GameStatus::Preparing || self.started() {
+      return Ok(());
+    }
+
+    if !self.players.contains(&player_id) {
+      return Err(Error::PlayerNotInGame);
+    }
+
+    let message: String = message.trim().chars().take(MAX_MESSAGE_LEN).collect();
+    if message.is_empty() {
+      return Ok(());
+    }
+
+    let players = self.players.clone();
+    let (ban_list_map, mute_list_map) = self
+      .db
+      .exec(move |conn| {
+        Ok::<_, Error>((
+          crate::player::db::get_ban_list_map(conn, &[player_id])?,
+          crate::player::db::get_mute_list_map(conn, &players)?,
+        ))
+      })
+      .await?;
+
+    // dropped silently, like chat of banned players in game
+    if ban_list_map
+      .get(&player_id)
+      .map(|list| list.contains(&PlayerBanType::Chat))
+      .unwrap_or_default()
+    {
+      return Ok(());
+    }
+
+    let targets = self
+      .players
+      .iter()
+      .filter(|id| {
+        mute_list_map
+          .get(*id)
+          .map(|list| !list.contains(&player_id))
+          .unwrap_or(true)
+      })
+      .cloned()
+      .collect();
+
+    let frame = proto::flo_connect::PacketLobbyChat {
+      game_id: self.game_id,
+      player_id,
+      message,
+    }
+    .encode_as_frame()?;
+    self.player_reg.broadcast(targets, frame).await?;
+
+    Ok(())
+  }
+}
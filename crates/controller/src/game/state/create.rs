@@ -6,6 +6,7 @@ use crate::game::{Game, GameStatus};
 use flo_state::{async_trait, Context, Handler, Message};
 
 pub struct CreateGame {
+  pub api_client_id: i32,
   pub params: CreateGameParams,
 }
 
@@ -18,12 +19,24 @@ impl Handler<CreateGame> for GameRegistry {
   async fn handle(
     &mut self,
     _: &mut Context<Self>,
-    CreateGame { params }: CreateGame,
+    CreateGame {
+      api_client_id,
+      params,
+    }: CreateGame,
   ) -> <CreateGame as Message>::Result {
     let player_id = params.player_id;
     let game = self
       .db
-      .exec(move |conn| crate::game::db::create(conn, params))
+      .exec(move |conn| {
+        crate::player::db::check_game_ban(
+          conn,
+          params.player_id,
+          crate::player::db::GameBanCheck::Create,
+          &params.map.sha1,
+        )?;
+        crate::map::db::check_map_allowed(conn, api_client_id, &params.map)?;
+        crate::game::db::create(conn, params)
+      })
       .await?;
 
     self.register(Register {
@@ -67,6 +80,7 @@ impl Handler<CreateGameAsBot> for GameRegistry {
     let (mut game, player_ids, mute_list_map) = self
       .db
       .exec(move |conn| {
+        crate::map::db::check_map_allowed(conn, api_client_id, &params.map)?;
         let game = crate::game::db::create_as_bot(conn, api_client_id, api_player_id, params)?;
         let player_ids = game.get_player_ids();
         let mute_list_map = crate::player::db::get_mute_list_map(conn, &player_ids)?;
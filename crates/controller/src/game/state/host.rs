@@ -0,0 +1,137 @@
+use crate::error::*;
+use crate::game::state::GameActor;
+use crate::game::Slot;
+use flo_net::packet::FloPacket;
+use flo_net::proto;
+use flo_state::{async_trait, Context, Handler, Message};
+use s2_grpc_utils::S2ProtoPack;
+
+impl GameActor {
+  /// Updates the host and notifies the lobby members
+  pub(crate) async fn set_host(&mut self, host_player_id: i32) -> Result<()> {
+    self.host_player = host_player_id;
+    let frame = proto::flo_connect::PacketGameHostUpdate {
+      game_id: self.game_id,
+      host_player_id,
+    }
+    .encode_as_frame()?;
+    self
+      .player_reg
+      .broadcast(self.players.clone(), frame)
+      .await?;
+    Ok(())
+  }
+
+  /// Notifies the lobby members of a slot change
+  pub(crate) async fn broadcast_slot_update(&mut self, slot_index: i32, slot: Slot) -> Result<()> {
+    let settings: proto::flo_connect::SlotSettings = slot.settings.pack()?;
+    let frame = proto::flo_connect::PacketGameSlotUpdate {
+      game_id: self.game_id,
+      slot_index,
+      slot_settings: settings.into(),
+      player: slot.player.map(|p| p.pack()).transpose()?,
+      ready: slot.ready,
+    }
+    .encode_as_frame()?;
+    self
+      .player_reg
+      .broadcast(self.players.clone(), frame)
+      .await?;
+    Ok(())
+  }
+
+  /// Removes a player from the lobby and notifies the lobby members including the removed player
+  pub(crate) async fn kick(&mut self, target_player_id: i32) -> Result<()> {
+    let game_id = self.game_id;
+    let host_player_id = self.host_player;
+    let leave = self
+      .db
+      .exec(move |conn| {
+        crate::game::db::kick_player(conn, game_id, host_player_id, target_player_id)
+      })
+      .await?;
+
+    // the kicked player also receives the packet to tell why it left
+    let recipients = self.players.clone();
+    self
+      .players
+      .retain(|id| !leave.removed_players.contains(id));
+
+    let frame = proto::flo_connect::PacketGamePlayerLeave {
+      game_id,
+      player_id: target_player_id,
+      reason: proto::flo_connect::PlayerLeaveReason::Kicked.into(),
+    }
+    .encode_as_frame()?;
+    self.player_reg.broadcast(recipients, frame).await?;
+    self
+      .player_reg
+      .player_leave_game(target_player_id, game_id)
+      .await?;
+
+    Ok(())
+  }
+}
+
+/// Removes a player from the lobby, only allowed for the host
+pub struct KickPlayer {
+  pub player_id: i32,
+  pub target_player_id: i32,
+}
+
+impl Message for KickPlayer {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<KickPlayer> for GameActor {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    KickPlayer {
+      player_id,
+      target_player_id,
+    }: KickPlayer,
+  ) -> Result<()> {
+    if self.host_player != player_id {
+      return Err(Error::PlayerNotHost);
+    }
+
+    self.kick(target_player_id).await
+  }
+}
+
+/// Makes another lobby member the host, only allowed for the host
+pub struct TransferHost {
+  pub player_id: i32,
+  pub target_player_id: i32,
+}
+
+impl Message for TransferHost {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<TransferHost> for GameActor {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    TransferHost {
+      player_id,
+      target_player_id,
+    }: TransferHost,
+  ) -> Result<()> {
+    if self.host_player != player_id {
+      return Err(Error::PlayerNotHost);
+    }
+
+    let game_id = self.game_id;
+    let (slot_index, slot) = self
+      .db
+      .exec(move |conn| crate::game::db::transfer_host(conn, game_id, player_id, target_player_id))
+      .await?;
+
+    self.set_host(target_player_id).await?;
+    self.broadcast_slot_update(slot_index, slot).await
+  }
+}
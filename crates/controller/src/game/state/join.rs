@@ -30,6 +30,19 @@ impl Handler<PlayerJoin> for GameActor {
         conn.transaction(|| {
           crate::game::db::add_player(conn, game_id, player_id)?;
           let game = crate::game::db::get_full(conn, game_id)?;
+          // observe only bans still allow joining an observer slot
+          let team = game
+            .get_player_slot_info(player_id)
+            .ok_or_else(|| Error::PlayerSlotNotFound)?
+            .slot
+            .settings
+            .team;
+          crate::player::db::check_game_ban(
+            conn,
+            player_id,
+            crate::player::db::GameBanCheck::for_team(team),
+            &game.map.sha1,
+          )?;
           let mut mute_list_map =
             crate::player::db::get_mute_list_map(conn, &game.get_player_ids())?;
           Ok::<_, Error>((game, mute_list_map.remove(&player_id).unwrap_or_default()))
@@ -45,6 +58,16 @@ impl Handler<PlayerJoin> for GameActor {
       .player_replace_game(player_id, game.clone(), mute_list)
       .await?;
 
+    // the game info only has the creator of the game
+    if self.host_player != game.created_by.id {
+      let frame = proto::flo_connect::PacketGameHostUpdate {
+        game_id,
+        host_player_id: self.host_player,
+      }
+      .encode_as_frame()?;
+      self.player_reg.send(player_id, frame).await?;
+    }
+
     {
       let slot_info = game
         .get_player_slot_info(player_id)
@@ -82,6 +82,10 @@ async fn leave_game_lobby(
     .filter_map(|s| s.player.as_ref().map(|p| p.id))
     .collect();
 
+  state
+    .players
+    .retain(|id| !leave.removed_players.contains(id));
+
   broadcast(
     state,
     game_id,
@@ -92,6 +96,10 @@ async fn leave_game_lobby(
   )
   .await?;
 
+  if let Some(host_player_id) = leave.new_host_player_id {
+    state.set_host(host_player_id).await?;
+  }
+
   Ok(PlayerLeaveResult {
     game_ended: leave.game_ended,
   })
@@ -1,9 +1,12 @@
 pub mod cancel;
+pub mod chat;
 pub mod create;
+pub mod host;
 pub mod join;
 pub mod leave;
 pub mod node;
 pub mod player;
+pub mod ready;
 pub mod registry;
 pub mod slot;
 pub mod start;
@@ -81,12 +84,13 @@ impl GameRegistry {
           player_reg: player_packet_sender.clone(),
           nodes: nodes.clone(),
           status: game.status,
-          host_player: game.created_by,
+          host_player: game.host_player_id,
           players,
           selected_node_id: game.node_id,
           start_state: None,
           player_tokens,
           player_client_status_map: Default::default(),
+          ready_timeout_pending: false,
         }),
       );
     }
@@ -198,6 +202,8 @@ pub struct GameActor {
   pub start_state: Option<Owner<StartGameState>>,
   pub player_tokens: HashMap<i32, [u8; 16]>,
   pub player_client_status_map: HashMap<i32, SlotClientStatus>,
+  /// Set while unready lobby members are waiting to be kicked, see `ready::ScheduleReadyTimeout`
+  pub ready_timeout_pending: bool,
 }
 
 impl Actor for GameActor {}
@@ -1,9 +1,15 @@
 use crate::error::*;
-use crate::game::state::GameActor;
+use crate::game::state::registry::UpdateGameNodeCache;
+use crate::game::state::{GameActor, GameRegistry};
+use crate::node::messages::{GetNodeDraining, ListNode};
+use crate::node::select::{select_node, NodeSelection};
+use crate::node::NodeRegistry;
+use crate::state::{ActorMapExt, ControllerState};
 
 use flo_net::packet::FloPacket;
 use flo_net::proto;
-use flo_state::{async_trait, Context, Handler, Message};
+use flo_state::{async_trait, Addr, Context, Handler, Message};
+use std::collections::BTreeMap;
 
 pub struct SelectNode {
   pub node_id: Option<i32>,
@@ -27,6 +33,16 @@ impl Handler<SelectNode> for GameActor {
       return Err(Error::GameStarted);
     }
 
+    if let Some(node_id) = node_id {
+      match self.nodes.send_to(node_id, GetNodeDraining).await {
+        Ok(true) => return Err(Error::NodeDraining),
+        Ok(false) => {}
+        // disabled or removed nodes are rejected by `select_node`
+        Err(Error::ActorNotFound) => {}
+        Err(err) => return Err(err),
+      }
+    }
+
     self
       .db
       .exec(move |conn| crate::game::db::select_node(conn, game_id, player_id, node_id))
@@ -43,3 +59,171 @@ impl Handler<SelectNode> for GameActor {
     Ok(())
   }
 }
+
+/// The data needed to recommend a node for a game, copied out of the registry
+/// so the node and player registries are queried by the caller
+pub struct GetNodeSelectionState {
+  pub game_id: i32,
+}
+
+pub struct NodeSelectionState {
+  /// The node already selected for the game
+  pub node_id: Option<i32>,
+  pub player_ids: Vec<i32>,
+  /// Number of games on each node
+  pub node_games: BTreeMap<i32, usize>,
+}
+
+impl Message for GetNodeSelectionState {
+  type Result = Result<NodeSelectionState>;
+}
+
+#[async_trait]
+impl Handler<GetNodeSelectionState> for GameRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    GetNodeSelectionState { game_id }: GetNodeSelectionState,
+  ) -> Result<NodeSelectionState> {
+    let player_ids = self
+      .game_players_map
+      .get(&game_id)
+      .cloned()
+      .ok_or_else(|| Error::GameNotFound)?;
+
+    Ok(NodeSelectionState {
+      node_id: self.game_node_map.get(&game_id).cloned(),
+      player_ids,
+      node_games: self.node_games(),
+    })
+  }
+}
+
+/// Number of games on each node
+pub struct GetNodeGames;
+
+impl Message for GetNodeGames {
+  type Result = BTreeMap<i32, usize>;
+}
+
+#[async_trait]
+impl Handler<GetNodeGames> for GameRegistry {
+  async fn handle(&mut self, _: &mut Context<Self>, _: GetNodeGames) -> BTreeMap<i32, usize> {
+    self.node_games()
+  }
+}
+
+impl GameRegistry {
+  fn node_games(&self) -> BTreeMap<i32, usize> {
+    let mut node_games: BTreeMap<i32, usize> = BTreeMap::new();
+    for node_id in self.game_node_map.values() {
+      *node_games.entry(*node_id).or_default() += 1;
+    }
+    node_games
+  }
+}
+
+/// Recommends a node for a game from the ping maps of its players
+pub async fn get_recommended_node(
+  state: &ControllerState,
+  game_id: i32,
+) -> Result<Option<NodeSelection>> {
+  let selection_state = state
+    .games
+    .send(GetNodeSelectionState { game_id })
+    .await??;
+  recommend_node(state, selection_state).await
+}
+
+/// Selects the recommended node if the game doesn't have one.
+/// Returns the selected node id.
+pub async fn auto_select_node(
+  state: &ControllerState,
+  game_id: i32,
+  player_id: i32,
+) -> Result<Option<i32>> {
+  let selection_state = state
+    .games
+    .send(GetNodeSelectionState { game_id })
+    .await??;
+  if let Some(node_id) = selection_state.node_id {
+    return Ok(Some(node_id));
+  }
+
+  let node_id = if let Some(selection) = recommend_node(state, selection_state).await? {
+    selection.node_id
+  } else {
+    return Ok(None);
+  };
+
+  state
+    .games
+    .send_to(
+      game_id,
+      SelectNode {
+        node_id: Some(node_id),
+        player_id,
+      },
+    )
+    .await?;
+  state
+    .games
+    .notify(UpdateGameNodeCache {
+      game_id,
+      node_id: Some(node_id),
+    })
+    .await?;
+
+  tracing::debug!(game_id, node_id, "node auto selected");
+
+  Ok(Some(node_id))
+}
+
+async fn recommend_node(
+  state: &ControllerState,
+  NodeSelectionState {
+    player_ids,
+    node_games,
+    ..
+  }: NodeSelectionState,
+) -> Result<Option<NodeSelection>> {
+  let ping_maps = state
+    .player_packet_sender
+    .get_ping_snapshot(player_ids)
+    .await?
+    .map;
+
+  let node_ids = list_available_node_ids(&state.nodes, &node_games).await?;
+
+  Ok(select_node(&node_ids, &ping_maps))
+}
+
+/// Ids of the enabled nodes that are not draining and not full
+pub async fn list_available_node_ids(
+  nodes: &Addr<NodeRegistry>,
+  node_games: &BTreeMap<i32, usize>,
+) -> Result<Vec<i32>> {
+  let mut node_ids = vec![];
+  for node in nodes.send(ListNode).await? {
+    if node.disabled || is_node_full(node_games, node.id) {
+      continue;
+    }
+    if nodes
+      .send_to(node.id, GetNodeDraining)
+      .await
+      .unwrap_or(true)
+    {
+      continue;
+    }
+    node_ids.push(node.id);
+  }
+  Ok(node_ids)
+}
+
+/// Returns true if the node hosts `NODE_MAX_GAMES` games
+pub fn is_node_full(node_games: &BTreeMap<i32, usize>, node_id: i32) -> bool {
+  match crate::config::NODE_MAX_GAMES.clone() {
+    Some(max) => node_games.get(&node_id).cloned().unwrap_or_default() >= max,
+    None => false,
+  }
+}
@@ -0,0 +1,139 @@
+use crate::config::GAME_READY_TIMEOUT;
+use crate::error::*;
+use crate::game::state::registry::RemoveGamePlayer;
+use crate::game::state::{GameActor, GameRegistry};
+use crate::game::GameStatus;
+use flo_state::{async_trait, Context, Handler, Message};
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Toggles the ready state of a lobby member
+pub struct SetPlayerReady {
+  pub player_id: i32,
+  pub ready: bool,
+}
+
+impl Message for SetPlayerReady {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<SetPlayerReady> for GameActor {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    SetPlayerReady { player_id, ready }: SetPlayerReady,
+  ) -> Result<()> {
+    // a late toggle while the game is starting is ignored, the client stream stays open
+    if self.status != GameStatus::Preparing || self.started() {
+      return Ok(());
+    }
+
+    if !self.players.contains(&player_id) {
+      return Err(Error::PlayerNotInGame);
+    }
+
+    let game_id = self.game_id;
+    let (slot_index, slot) = self
+      .db
+      .exec(move |conn| crate::game::db::update_slot_ready(conn, game_id, player_id, ready))
+      .await?;
+
+    self.broadcast_slot_update(slot_index, slot).await
+  }
+}
+
+impl GameActor {
+  /// Returns the timeout if the unready players should be kicked after it,
+  /// only one timeout is pending at a time
+  pub(crate) fn take_ready_timeout(&mut self) -> Option<Duration> {
+    if self.ready_timeout_pending {
+      return None;
+    }
+    let timeout = (*GAME_READY_TIMEOUT)?;
+    self.ready_timeout_pending = true;
+    Some(timeout)
+  }
+}
+
+/// Kicks the lobby members that are still not ready, returns the kicked player ids
+struct KickUnreadyPlayers;
+
+impl Message for KickUnreadyPlayers {
+  type Result = Result<Vec<i32>>;
+}
+
+#[async_trait]
+impl Handler<KickUnreadyPlayers> for GameActor {
+  async fn handle(&mut self, _: &mut Context<Self>, _: KickUnreadyPlayers) -> Result<Vec<i32>> {
+    self.ready_timeout_pending = false;
+
+    if self.status != GameStatus::Preparing || self.started() {
+      return Ok(vec![]);
+    }
+
+    let game_id = self.game_id;
+    let host_player_id = self.host_player;
+    let player_ids = self
+      .db
+      .exec(move |conn| crate::game::db::get_unready_player_ids(conn, game_id, host_player_id))
+      .await?;
+
+    let mut kicked = Vec::with_capacity(player_ids.len());
+    for player_id in player_ids {
+      if let Err(err) = self.kick(player_id).await {
+        tracing::error!(game_id, player_id, "kick unready player: {}", err);
+      } else {
+        kicked.push(player_id);
+      }
+    }
+
+    Ok(kicked)
+  }
+}
+
+/// Kicks the unready lobby members of a game once the timeout elapses
+pub struct ScheduleReadyTimeout {
+  pub game_id: i32,
+  pub timeout: Duration,
+}
+
+impl Message for ScheduleReadyTimeout {
+  type Result = ();
+}
+
+#[async_trait]
+impl Handler<ScheduleReadyTimeout> for GameRegistry {
+  async fn handle(
+    &mut self,
+    ctx: &mut Context<Self>,
+    ScheduleReadyTimeout { game_id, timeout }: ScheduleReadyTimeout,
+  ) {
+    let game = if let Some(game) = self.map.get(&game_id).map(|v| v.addr()) {
+      game
+    } else {
+      return;
+    };
+
+    let addr = ctx.addr();
+    ctx.spawn(async move {
+      sleep(timeout).await;
+      match game.send(KickUnreadyPlayers).await {
+        Ok(Ok(player_ids)) => {
+          for player_id in player_ids {
+            addr
+              .notify(RemoveGamePlayer { game_id, player_id })
+              .await
+              .ok();
+          }
+        }
+        Ok(Err(err)) => {
+          tracing::error!(game_id, "kick unready players: {}", err);
+        }
+        Err(err) => {
+          tracing::debug!(game_id, "kick unready players: {}", err);
+        }
+      }
+    });
+  }
+}
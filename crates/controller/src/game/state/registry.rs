@@ -52,6 +52,7 @@ impl GameRegistry {
         start_state: None,
         player_tokens: Default::default(),
         player_client_status_map: Default::default(),
+        ready_timeout_pending: false,
       }),
     );
   }
@@ -2,6 +2,7 @@ use crate::error::*;
 use crate::game::db::UpdateSlotSettings;
 use crate::game::state::GameActor;
 use crate::game::{Slot, SlotSettings};
+use crate::player::db::GameBanCheck;
 use diesel::prelude::*;
 use flo_net::packet::FloPacket;
 use flo_net::proto;
@@ -42,6 +43,16 @@ impl Handler<UpdateSlot> for GameActor {
           if !info.is_slot_owner(player_id) {
             return Err(Error::GameSlotUpdateDenied);
           }
+          // moving between observer and player slots changes which bans apply
+          if let Some(slot_player_id) = info.slot_player_id {
+            let map = crate::game::db::get_map(conn, game_id)?;
+            crate::player::db::check_game_ban(
+              conn,
+              slot_player_id,
+              GameBanCheck::for_team(settings.team),
+              &map.sha1,
+            )?;
+          }
           crate::game::db::update_slot_settings(conn, game_id, slot_index, settings)
         })
       })
@@ -57,6 +68,7 @@ impl Handler<UpdateSlot> for GameActor {
         slot_index: index,
         slot_settings: settings.into(),
         player: slot.player.clone().map(|p| p.pack()).transpose()?,
+        ready: slot.ready,
       }
       .encode_as_frame()?;
       frames_slot_update.push(frame);
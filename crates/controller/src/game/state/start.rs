@@ -20,7 +20,18 @@ pub struct StartGameCheck {
 }
 
 impl Message for StartGameCheck {
-  type Result = Result<()>;
+  type Result = Result<StartGameCheckResult>;
+}
+
+#[derive(Debug)]
+pub enum StartGameCheckResult {
+  Starting,
+  /// The host has to wait for the other lobby members to get ready
+  PlayersNotReady {
+    player_ids: Vec<i32>,
+    /// Set if the unready players should be kicked after this timeout
+    kick_after: Option<Duration>,
+  },
 }
 
 #[async_trait]
@@ -29,7 +40,7 @@ impl Handler<StartGameCheck> for GameActor {
     &mut self,
     ctx: &mut Context<Self>,
     StartGameCheck { player_id }: StartGameCheck,
-  ) -> Result<()> {
+  ) -> Result<StartGameCheckResult> {
     let game_id = self.game_id;
 
     if self.host_player != player_id {
@@ -45,6 +56,28 @@ impl Handler<StartGameCheck> for GameActor {
       return Err(Error::GameStarted);
     }
 
+    let host_player_id = self.host_player;
+    let unready_player_ids = self
+      .db
+      .exec(move |conn| crate::game::db::get_unready_player_ids(conn, game_id, host_player_id))
+      .await?;
+    if !unready_player_ids.is_empty() {
+      let frame = proto::flo_connect::PacketGameStartReject {
+        game_id,
+        message: format!(
+          "Waiting for {} player(s) to get ready.",
+          unready_player_ids.len()
+        ),
+        player_client_info_map: Default::default(),
+      }
+      .encode_as_frame()?;
+      self.player_reg.broadcast(players, frame).await?;
+      return Ok(StartGameCheckResult::PlayersNotReady {
+        player_ids: unready_player_ids,
+        kick_after: self.take_ready_timeout(),
+      });
+    }
+
     self.start_state = StartGameState::new(game_id, ctx.addr(), players, None)
       .start()
       .into();
@@ -55,7 +88,7 @@ impl Handler<StartGameCheck> for GameActor {
       .broadcast(self.players.clone(), frame)
       .await?;
 
-    Ok(())
+    Ok(StartGameCheckResult::Starting)
   }
 }
 
@@ -111,15 +144,25 @@ impl GameActor {
       return Ok(Err(pkt));
     }
 
-    let (game, ban_list_map) = self
+    let (mut game, ban_list_map, referee_player_ids) = self
       .db
       .exec(move |conn| {
         let game = crate::game::db::get_full(conn, game_id)?;
+        if let Some(node) = game.node.as_ref() {
+          crate::node::db::check_node_enabled(conn, node.id)?;
+        }
         let players = game.get_player_ids();
-        Ok::<_, Error>((game, crate::player::db::get_ban_list_map(conn, &players)?))
+        Ok::<_, Error>((
+          game,
+          crate::player::db::get_ban_list_map(conn, &players)?,
+          crate::game::db::get_referee_player_ids(conn, game_id)?,
+        ))
       })
       .await?;
 
+    // not persisted until the node accepts the game
+    game.game_version = agreed_version.clone();
+
     let node_id = if let Some(id) = game.node.as_ref().map(|node| node.id) {
       id
     } else {
@@ -128,7 +171,14 @@ impl GameActor {
 
     let created = self
       .nodes
-      .send_to(node_id, NodeCreateGame { game, ban_list_map })
+      .send_to(
+        node_id,
+        NodeCreateGame {
+          game,
+          ban_list_map,
+          referee_player_ids,
+        },
+      )
       .await?
       .await
       .or_cancelled();
@@ -32,7 +32,11 @@ pub struct Game {
   pub mask_player_names: bool,
   pub game_version: Option<String>,
   pub enable_ping_equalizer: bool,
+  pub enable_adaptive_step: bool,
+  #[s2_grpc(proto_enum)]
+  pub observer_chat_policy: ObserverChatPolicy,
   pub flo_tv_delay_override_secs: Option<i32>,
+  pub result: Option<GameResult>,
 }
 
 impl S2ProtoPack<flo_net::proto::flo_connect::GameInfo> for Game {
@@ -200,11 +204,131 @@ impl From<NodeGameStatus> for GameStatus {
   }
 }
 
+#[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone)]
+#[s2_grpc(message_type(flo_grpc::game::GameResult))]
+pub struct GameResult {
+  pub winning_team: Option<i32>,
+  pub players: Vec<GamePlayerResult>,
+  pub reported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone)]
+#[s2_grpc(message_type(flo_grpc::game::GamePlayerResult))]
+pub struct GamePlayerResult {
+  pub player_id: i32,
+  #[s2_grpc(proto_enum)]
+  pub result: PlayerGameResult,
+  pub leave_reason: Option<i32>,
+  pub removed_reason: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, BSDieselEnum, S2ProtoEnum)]
+#[repr(i32)]
+#[s2_grpc(proto_enum_type(
+  flo_grpc::game::PlayerGameResult,
+  flo_net::proto::flo_node::PlayerGameResult
+))]
+pub enum PlayerGameResult {
+  Unknown = 0,
+  Win = 1,
+  Loss = 2,
+  Draw = 3,
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, BSDieselEnum, S2ProtoEnum)]
+#[repr(i32)]
+#[s2_grpc(proto_enum_type(
+  flo_grpc::game::ObserverChatPolicy,
+  flo_net::proto::flo_node::ObserverChatPolicy
+))]
+pub enum ObserverChatPolicy {
+  Open = 0,
+  Isolated = 1,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameResultUpdate {
+  pub game_id: i32,
+  pub winning_team: Option<i32>,
+  pub players: Vec<GamePlayerResult>,
+}
+
+impl From<flo_net::proto::flo_node::PacketNodeGameResult> for GameResultUpdate {
+  fn from(pkt: flo_net::proto::flo_node::PacketNodeGameResult) -> Self {
+    GameResultUpdate {
+      game_id: pkt.game_id,
+      winning_team: pkt.winning_team,
+      players: pkt
+        .players
+        .into_iter()
+        .map(|p| GamePlayerResult {
+          player_id: p.player_id,
+          result: PlayerGameResult::unpack_enum(p.result()),
+          leave_reason: p.leave_reason.map(|v| v as i32),
+          removed_reason: p.removed_reason.map(|v| v as i32),
+        })
+        .collect(),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameDesyncReport {
+  pub game_id: i32,
+  pub tick: u32,
+  pub time: u32,
+  pub game_version: Option<String>,
+  pub players: Vec<GameDesyncPlayer>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameDesyncPlayer {
+  pub player_id: i32,
+  pub checksum: Option<u32>,
+  pub desynced: bool,
+  pub recent_actions: Vec<GameDesyncAction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameDesyncAction {
+  pub tick: u32,
+  pub data: Vec<u8>,
+}
+
+impl From<flo_net::proto::flo_node::PacketNodeGameDesync> for GameDesyncReport {
+  fn from(pkt: flo_net::proto::flo_node::PacketNodeGameDesync) -> Self {
+    GameDesyncReport {
+      game_id: pkt.game_id,
+      tick: pkt.tick,
+      time: pkt.time,
+      game_version: Some(pkt.game_version).filter(|v| !v.is_empty()),
+      players: pkt
+        .players
+        .into_iter()
+        .map(|p| GameDesyncPlayer {
+          player_id: p.player_id,
+          checksum: p.checksum,
+          desynced: p.desynced,
+          recent_actions: p
+            .recent_actions
+            .into_iter()
+            .map(|a| GameDesyncAction {
+              tick: a.tick,
+              data: a.data,
+            })
+            .collect(),
+        })
+        .collect(),
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone)]
 #[s2_grpc(message_type(flo_grpc::controller::CreateGameSlot))]
 pub struct CreateGameSlot {
   pub player_id: Option<i32>,
   pub settings: SlotSettings,
+  pub is_referee: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone)]
@@ -213,6 +337,8 @@ pub struct Slot {
   pub player: Option<PlayerRef>,
   pub settings: SlotSettings,
   pub client_status: SlotClientStatus,
+  pub ready: bool,
+  pub is_referee: bool,
 }
 
 impl Slot {
@@ -227,6 +353,8 @@ impl Default for Slot {
       player: None,
       settings: Default::default(),
       client_status: SlotClientStatus::Pending,
+      ready: false,
+      is_referee: false,
     }
   }
 }
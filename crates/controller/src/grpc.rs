@@ -1,21 +1,38 @@
-use crate::config::{ApiRequestExt, GetInterceptor};
+use crate::api_client::{
+  check_client_access, is_operator, unpack_scopes, CreateApiClientKeyParams,
+  ExpireApiClientKeyParams,
+};
+use crate::audit::{AuditTarget, ListAuditLogsParams};
+use crate::config::{ApiRequestExt, GetInterceptor, GrpcMethodService};
 use crate::error::{Error, Result};
 use crate::game::db::{CreateGameAsBotParams, CreateGameParams};
 use crate::game::messages::{CreateGame, PlayerJoin, PlayerLeave};
 use crate::game::state::cancel::CancelGame;
 use crate::game::state::create::CreateGameAsBot;
-use crate::game::state::node::SelectNode;
+use crate::game::state::node::{auto_select_node, get_recommended_node, SelectNode};
 use crate::game::state::registry::{AddGamePlayer, Remove, RemoveGamePlayer, UpdateGameNodeCache};
 use crate::game::state::start::{StartGameCheckAsBot, StartGameCheckAsBotResult};
-use crate::node::messages::ListNode;
+use crate::game::Race;
+use crate::invite::state::GetPartyMembers;
+use crate::map::{MapInfo, UpsertMapPoolParams};
+use crate::matchmaking::state::{Dequeue, Enqueue, ListQueues, RemoveQueue, UpsertQueue};
+use crate::matchmaking::UpsertQueueParams;
+use crate::node::db::{CreateNodeParams, UpdateNodeParams};
+use crate::node::messages::{
+  CreateNode, DeleteNode, ListNode, RotateNodeSecret, SetNodeDisabled, UpdateNode,
+};
+use crate::player::db::CreateBan;
 use crate::player::state::ping::GetPlayersPingSnapshot;
 use crate::player::{PlayerBanType, PlayerSource, SourceState};
-use crate::state::{ActorMapExt, ControllerStateRef};
+use crate::state::{ActorMapExt, ControllerStateRef, Reload};
+use crate::tournament::state::{CancelTournament, CreateTournament};
+use crate::tournament::CreateTournamentParams;
 use bs_diesel_utils::executor::ExecutorError;
 use chrono::{DateTime, Utc};
 use flo_grpc::controller::flo_controller_server::*;
 use flo_grpc::controller::*;
 use s2_grpc_utils::{S2ProtoEnum, S2ProtoPack, S2ProtoUnpack};
+use serde_json::json;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use tonic::transport::Server;
 use tonic::{Request, Response, Status};
@@ -26,7 +43,7 @@ pub async fn serve(state: ControllerStateRef) -> Result<()> {
 
   let interceptor = state.config.send(GetInterceptor).await?;
   let server = FloControllerServer::with_interceptor(server_impl, interceptor);
-  let server = Server::builder().add_service(server);
+  let server = Server::builder().add_service(GrpcMethodService::new(server));
   server.serve(addr.into()).await?;
   Ok(())
 }
@@ -39,6 +56,48 @@ impl FloControllerService {
   pub fn new(state: ControllerStateRef) -> Self {
     FloControllerService { state }
   }
+
+  /// Records a mutating call after it has been applied,
+  /// so a failed write is only logged
+  async fn audit(
+    &self,
+    api_client_id: i32,
+    action: &'static str,
+    target: AuditTarget,
+    params: serde_json::Value,
+  ) {
+    let res = self
+      .state
+      .db
+      .exec(move |conn| crate::audit::db::insert(conn, api_client_id, action, &target, params))
+      .await;
+    if let Err(err) = res.map_err(Error::from) {
+      tracing::error!(api_client_id, action, "write audit log: {}", err);
+    }
+  }
+
+  async fn cancel_game_by_player(&self, game_id: i32, player_id: i32) -> Result<(), Status> {
+    self
+      .state
+      .games
+      .send_to(
+        game_id,
+        CancelGame {
+          player_id: Some(player_id),
+        },
+      )
+      .await?;
+
+    tracing::debug!(game_id, "shutting down: reason: CancelGame");
+    self
+      .state
+      .games
+      .send(Remove { game_id })
+      .await
+      .map_err(Error::from)?;
+
+    Ok(())
+  }
 }
 
 #[tonic::async_trait]
@@ -48,14 +107,20 @@ impl FloController for FloControllerService {
     request: Request<GetPlayerRequest>,
   ) -> Result<Response<GetPlayerReply>, Status> {
     let player_id = request.into_inner().player_id;
-    let player = self
+    let (player, ratings) = self
       .state
       .db
-      .exec(move |conn| crate::player::db::get(conn, player_id))
+      .exec(move |conn| {
+        Ok::<_, Error>((
+          crate::player::db::get(conn, player_id)?,
+          crate::rating::db::get_player_ratings(conn, player_id)?,
+        ))
+      })
       .await
       .map_err(Error::from)?;
     Ok(Response::new(GetPlayerReply {
       player: player.pack().map_err(Status::internal)?,
+      ratings: ratings.pack().map_err(Status::internal)?,
     }))
   }
 
@@ -65,14 +130,20 @@ impl FloController for FloControllerService {
   ) -> Result<Response<GetPlayerReply>, Status> {
     let token = request.into_inner().token;
     let player_id = crate::player::token::validate_player_token(&token)?.player_id;
-    let player = self
+    let (player, ratings) = self
       .state
       .db
-      .exec(move |conn| crate::player::db::get(conn, player_id))
+      .exec(move |conn| {
+        Ok::<_, Error>((
+          crate::player::db::get(conn, player_id)?,
+          crate::rating::db::get_player_ratings(conn, player_id)?,
+        ))
+      })
       .await
       .map_err(Error::from)?;
     Ok(Response::new(GetPlayerReply {
       player: player.pack().map_err(Status::internal)?,
+      ratings: ratings.pack().map_err(Status::internal)?,
     }))
   }
 
@@ -119,6 +190,141 @@ impl FloController for FloControllerService {
     }))
   }
 
+  async fn create_node(
+    &self,
+    request: Request<CreateNodeRequest>,
+  ) -> Result<Response<CreateNodeReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let params = CreateNodeParams::unpack(request.into_inner()).map_err(Error::from)?;
+    let node = self
+      .state
+      .nodes
+      .send(CreateNode { params })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "create_node",
+        AuditTarget::Node(node.id),
+        json!({
+          "name": node.name,
+          "location": node.location,
+          "ip_addr": node.ip_addr,
+          "country_id": node.country_id,
+        }),
+      )
+      .await;
+    Ok(Response::new(CreateNodeReply {
+      node: node.pack().map_err(Error::from)?,
+    }))
+  }
+
+  async fn update_node(
+    &self,
+    request: Request<UpdateNodeRequest>,
+  ) -> Result<Response<UpdateNodeReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let request = request.into_inner();
+    let node_id = request.node_id;
+    let params = UpdateNodeParams::unpack(request).map_err(Error::from)?;
+    let audit_params = json!({
+      "name": params.name,
+      "location": params.location,
+      "ip_addr": params.ip_addr,
+      "country_id": params.country_id,
+    });
+    let node = self
+      .state
+      .nodes
+      .send(UpdateNode { node_id, params })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "update_node",
+        AuditTarget::Node(node_id),
+        audit_params,
+      )
+      .await;
+    Ok(Response::new(UpdateNodeReply {
+      node: node.pack().map_err(Error::from)?,
+    }))
+  }
+
+  async fn set_node_disabled(
+    &self,
+    request: Request<SetNodeDisabledRequest>,
+  ) -> Result<Response<SetNodeDisabledReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let SetNodeDisabledRequest { node_id, disabled } = request.into_inner();
+    let node = self
+      .state
+      .nodes
+      .send(SetNodeDisabled { node_id, disabled })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "set_node_disabled",
+        AuditTarget::Node(node_id),
+        json!({ "disabled": disabled }),
+      )
+      .await;
+    Ok(Response::new(SetNodeDisabledReply {
+      node: node.pack().map_err(Error::from)?,
+    }))
+  }
+
+  async fn delete_node(&self, request: Request<DeleteNodeRequest>) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let node_id = request.into_inner().node_id;
+    self
+      .state
+      .nodes
+      .send(DeleteNode { node_id })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "delete_node",
+        AuditTarget::Node(node_id),
+        json!({}),
+      )
+      .await;
+    Ok(Response::new(()))
+  }
+
+  async fn rotate_node_secret(
+    &self,
+    request: Request<RotateNodeSecretRequest>,
+  ) -> Result<Response<RotateNodeSecretReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let node_id = request.into_inner().node_id;
+    let node = self
+      .state
+      .nodes
+      .send(RotateNodeSecret { node_id })
+      .await
+      .map_err(Error::from)??;
+    // the secret itself is never recorded
+    self
+      .audit(
+        api_client_id,
+        "rotate_node_secret",
+        AuditTarget::Node(node_id),
+        json!({}),
+      )
+      .await;
+    Ok(Response::new(RotateNodeSecretReply {
+      node_id,
+      secret: node.secret,
+    }))
+  }
+
   async fn list_games(
     &self,
     request: Request<ListGamesRequest>,
@@ -135,6 +341,23 @@ impl FloController for FloControllerService {
     Ok(Response::new(r.pack().map_err(Error::from)?))
   }
 
+  async fn list_game_history(
+    &self,
+    request: Request<ListGameHistoryRequest>,
+  ) -> Result<Response<ListGameHistoryReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let params = crate::game::db::QueryGameHistoryParams::unpack(request.into_inner())
+      .map_err(Status::internal)?;
+    let r = self
+      .state
+      .db
+      .exec(move |conn| crate::game::db::query_history(conn, api_client_id, &params))
+      .await
+      .map_err(Error::from)?;
+
+    Ok(Response::new(r.pack().map_err(Error::from)?))
+  }
+
   async fn get_game(
     &self,
     request: Request<GetGameRequest>,
@@ -158,15 +381,26 @@ impl FloController for FloControllerService {
     &self,
     request: Request<CreateGameRequest>,
   ) -> Result<Response<CreateGameReply>, Status> {
+    let api_client_id = request.get_api_client_id();
     let game = self
       .state
       .games
       .send(CreateGame {
+        api_client_id,
         params: CreateGameParams::unpack(request.into_inner()).map_err(Error::from)?,
       })
       .await
       .map_err(Error::from)??;
 
+    self
+      .audit(
+        api_client_id,
+        "create_game",
+        AuditTarget::Game(game.id),
+        json!({ "player_id": game.created_by.id, "name": &game.name, "map": &game.map.name }),
+      )
+      .await;
+
     Ok(Response::new(CreateGameReply {
       game: game.pack().map_err(Status::internal)?,
     }))
@@ -176,6 +410,7 @@ impl FloController for FloControllerService {
     &self,
     request: Request<JoinGameRequest>,
   ) -> Result<Response<JoinGameReply>, Status> {
+    let api_client_id = request.get_api_client_id();
     let params = request.into_inner();
 
     let game = self
@@ -199,6 +434,15 @@ impl FloController for FloControllerService {
       .await
       .map_err(Error::from)?;
 
+    self
+      .audit(
+        api_client_id,
+        "join_game",
+        AuditTarget::Game(params.game_id),
+        json!({ "player_id": params.player_id }),
+      )
+      .await;
+
     Ok(Response::new(JoinGameReply {
       game: game.pack().map_err(Error::from)?,
     }))
@@ -211,14 +455,14 @@ impl FloController for FloControllerService {
     let params = request.into_inner();
     let game_id = params.game_id;
 
-    let game = self
+    let host_player_id = self
       .state
       .db
-      .exec(move |conn| crate::game::db::get(conn, game_id))
+      .exec(move |conn| crate::game::db::get_host_player_id(conn, game_id))
       .await
       .map_err(Error::from)?;
 
-    if game.created_by.as_ref().map(|p| p.id) != Some(params.player_id) {
+    if host_player_id != params.player_id {
       return Err(Error::PlayerNotHost.into());
     }
 
@@ -231,6 +475,7 @@ impl FloController for FloControllerService {
     &self,
     request: Request<JoinGameByTokenRequest>,
   ) -> Result<Response<JoinGameReply>, Status> {
+    let api_client_id = request.get_api_client_id();
     let params = request.into_inner();
     let join_token = crate::game::token::validate_join_token(&params.token)?;
 
@@ -255,12 +500,22 @@ impl FloController for FloControllerService {
       .await
       .map_err(Error::from)?;
 
+    self
+      .audit(
+        api_client_id,
+        "join_game_by_token",
+        AuditTarget::Game(join_token.game_id),
+        json!({ "player_id": params.player_id }),
+      )
+      .await;
+
     Ok(Response::new(JoinGameReply {
       game: game.pack().map_err(Error::from)?,
     }))
   }
 
   async fn leave_game(&self, request: Request<LeaveGameRequest>) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
     let params = request.into_inner();
 
     let res = self
@@ -300,6 +555,15 @@ impl FloController for FloControllerService {
         .map_err(Error::from)?;
     }
 
+    self
+      .audit(
+        api_client_id,
+        "leave_game",
+        AuditTarget::Game(params.game_id),
+        json!({ "player_id": params.player_id }),
+      )
+      .await;
+
     Ok(Response::new(()))
   }
 
@@ -307,6 +571,7 @@ impl FloController for FloControllerService {
     &self,
     request: Request<SelectGameNodeRequest>,
   ) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
     let SelectGameNodeRequest {
       game_id,
       player_id,
@@ -332,40 +597,56 @@ impl FloController for FloControllerService {
       .await
       .map_err(Error::from)?;
 
+    self
+      .audit(
+        api_client_id,
+        "select_game_node",
+        AuditTarget::Game(game_id),
+        json!({ "player_id": player_id, "node_id": node_id }),
+      )
+      .await;
+
     Ok(Response::new(()))
   }
 
   async fn cancel_game(&self, request: Request<CancelGameRequest>) -> Result<Response<()>, Status> {
-    let req = request.into_inner();
-    let game_id = req.game_id;
-    let player_id = req.player_id;
+    let api_client_id = request.get_api_client_id();
+    let CancelGameRequest { game_id, player_id } = request.into_inner();
 
-    self
-      .state
-      .games
-      .send_to(
-        game_id,
-        CancelGame {
-          player_id: Some(player_id),
-        },
-      )
-      .await?;
+    self.cancel_game_by_player(game_id, player_id).await?;
 
-    tracing::debug!(game_id, "shutting down: reason: CancelGame");
     self
-      .state
-      .games
-      .send(Remove { game_id })
-      .await
-      .map_err(Error::from)?;
+      .audit(
+        api_client_id,
+        "cancel_game",
+        AuditTarget::Game(game_id),
+        json!({ "player_id": player_id }),
+      )
+      .await;
 
     Ok(Response::new(()))
   }
 
+  async fn get_recommended_game_node(
+    &self,
+    request: Request<GetRecommendedGameNodeRequest>,
+  ) -> Result<Response<GetRecommendedGameNodeReply>, Status> {
+    let selection = get_recommended_node(&self.state, request.into_inner().game_id).await?;
+    Ok(Response::new(match selection {
+      Some(selection) => GetRecommendedGameNodeReply {
+        node_id: Some(selection.node_id),
+        max_rtt: selection.max_rtt,
+        rtt_spread: selection.rtt_spread,
+      },
+      None => GetRecommendedGameNodeReply::default(),
+    }))
+  }
+
   async fn import_map_checksums(
     &self,
     request: Request<ImportMapChecksumsRequest>,
   ) -> Result<Response<ImportMapChecksumsReply>, Status> {
+    let api_client_id = request.get_api_client_id();
     let items =
       Vec::<crate::map::db::ImportItem>::unpack(request.into_inner().items).map_err(Error::from)?;
     let updated = self
@@ -374,6 +655,14 @@ impl FloController for FloControllerService {
       .exec(move |conn| crate::map::db::import(conn, items))
       .await
       .map_err(Error::from)?;
+    self
+      .audit(
+        api_client_id,
+        "import_map_checksums",
+        AuditTarget::None,
+        json!({ "updated": updated }),
+      )
+      .await;
     Ok(Response::new(ImportMapChecksumsReply {
       updated: updated as u32,
     }))
@@ -393,6 +682,153 @@ impl FloController for FloControllerService {
     Ok(Response::new(SearchMapChecksumReply { checksum }))
   }
 
+  async fn upload_map(
+    &self,
+    request: Request<UploadMapRequest>,
+  ) -> Result<Response<UploadMapReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let UploadMapRequest { path, data } = request.into_inner();
+    // parsing the map and rendering the preview are cpu bound
+    let map = tokio::task::spawn_blocking(move || {
+      let (map, checksum) = flo_w3map::W3Map::open_memory_with_checksum(&data)?;
+      Ok::<_, Error>(MapInfo::from_w3map(path, &map, &checksum))
+    })
+    .await
+    .map_err(|_| Error::TaskCancelled)??;
+    self
+      .state
+      .db
+      .exec({
+        let map = map.clone();
+        move |conn| crate::map::db::insert_infos(conn, vec![map])
+      })
+      .await
+      .map_err(Error::from)?;
+    self
+      .audit(
+        api_client_id,
+        "upload_map",
+        AuditTarget::None,
+        json!({ "sha1": &map.sha1, "name": &map.name, "path": &map.path }),
+      )
+      .await;
+    Ok(Response::new(UploadMapReply {
+      map: map.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn import_maps(
+    &self,
+    request: Request<ImportMapsRequest>,
+  ) -> Result<Response<ImportMapsReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let mut maps = Vec::<MapInfo>::unpack(request.into_inner().maps).map_err(Error::from)?;
+    for map in &mut maps {
+      map.sha1 = crate::map::normalize_sha1(&map.sha1)?;
+    }
+    let sha1s: Vec<_> = maps.iter().map(|map| map.sha1.clone()).collect();
+    let updated = self
+      .state
+      .db
+      .exec(move |conn| crate::map::db::insert_infos(conn, maps))
+      .await
+      .map_err(Error::from)?;
+    self
+      .audit(
+        api_client_id,
+        "import_maps",
+        AuditTarget::None,
+        json!({ "updated": updated, "sha1s": sha1s }),
+      )
+      .await;
+    Ok(Response::new(ImportMapsReply {
+      updated: updated as u32,
+    }))
+  }
+
+  async fn get_map_info(
+    &self,
+    request: Request<GetMapInfoRequest>,
+  ) -> Result<Response<GetMapInfoReply>, Status> {
+    let sha1 = crate::map::normalize_sha1(&request.into_inner().sha1)?;
+    let map = self
+      .state
+      .db
+      .exec(move |conn| crate::map::db::get_info(conn, &sha1))
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(GetMapInfoReply {
+      map: map.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn list_map_pools(
+    &self,
+    request: Request<()>,
+  ) -> Result<Response<ListMapPoolsReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let pools = self
+      .state
+      .db
+      .exec(move |conn| crate::map::db::list_pools(conn, api_client_id))
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(ListMapPoolsReply {
+      pools: pools.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn upsert_map_pool(
+    &self,
+    request: Request<UpsertMapPoolRequest>,
+  ) -> Result<Response<UpsertMapPoolReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let params = UpsertMapPoolParams::unpack(request.into_inner()).map_err(Error::from)?;
+    let pool = self
+      .state
+      .db
+      .exec(move |conn| crate::map::db::upsert_pool(conn, api_client_id, params))
+      .await
+      .map_err(Error::from)?;
+    self
+      .audit(
+        api_client_id,
+        "upsert_map_pool",
+        AuditTarget::MapPool(pool.name.clone()),
+        json!({ "map_sha1s": &pool.map_sha1s }),
+      )
+      .await;
+    Ok(Response::new(UpsertMapPoolReply {
+      pool: pool.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn remove_map_pool(
+    &self,
+    request: Request<RemoveMapPoolRequest>,
+  ) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let name = request.into_inner().name;
+    self
+      .state
+      .db
+      .exec({
+        let name = name.clone();
+        move |conn| crate::map::db::remove_pool(conn, api_client_id, &name)
+      })
+      .await
+      .map_err(Error::from)?;
+    self
+      .audit(
+        api_client_id,
+        "remove_map_pool",
+        AuditTarget::MapPool(name),
+        json!({}),
+      )
+      .await;
+    Ok(Response::new(()))
+  }
+
   async fn get_players_by_source_ids(
     &self,
     request: Request<GetPlayersBySourceIdsRequest>,
@@ -412,6 +848,26 @@ impl FloController for FloControllerService {
     }))
   }
 
+  async fn list_leaderboard(
+    &self,
+    request: Request<ListLeaderboardRequest>,
+  ) -> Result<Response<ListLeaderboardReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let params = request.into_inner();
+    let res = self
+      .state
+      .db
+      .exec(move |conn| {
+        crate::rating::db::list_leaderboard(conn, api_client_id, &params.mode, params.offset)
+      })
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(ListLeaderboardReply {
+      entries: res.entries.pack().map_err(Status::internal)?,
+      next_offset: res.next_offset,
+    }))
+  }
+
   async fn get_player_ping_maps(
     &self,
     request: Request<GetPlayerPingMapsRequest>,
@@ -449,17 +905,32 @@ impl FloController for FloControllerService {
     &self,
     request: Request<CreateGameAsBotRequest>,
   ) -> Result<Response<CreateGameAsBotReply>, Status> {
+    let api_client_id = request.get_api_client_id();
     let game = self
       .state
       .games
       .send(CreateGameAsBot {
-        api_client_id: request.get_api_client_id(),
+        api_client_id,
         api_player_id: request.get_api_player_id(),
         params: CreateGameAsBotParams::unpack(request.into_inner()).map_err(Error::from)?,
       })
       .await
       .map_err(Error::from)??;
 
+    self
+      .audit(
+        api_client_id,
+        "create_game_as_bot",
+        AuditTarget::Game(game.id),
+        json!({
+          "name": &game.name,
+          "map": &game.map.name,
+          "player_ids": game.get_player_ids(),
+          "node_id": game.node.as_ref().map(|node| node.id),
+        }),
+      )
+      .await;
+
     Ok(Response::new(CreateGameAsBotReply {
       game: game.pack().map_err(Status::internal)?,
     }))
@@ -490,46 +961,82 @@ impl FloController for FloControllerService {
         .collect()
     }
 
+    let api_client_id = request.get_api_client_id();
+    let api_player_id = request.get_api_player_id();
+    let params = request.into_inner();
+
+    if params.auto_select_node {
+      auto_select_node(&self.state, params.game_id, api_player_id).await?;
+    }
+
     let (tx, rx) = oneshot::channel();
     self
       .state
       .games
-      .send_to(request.into_inner().game_id, StartGameCheckAsBot { tx })
+      .send_to(params.game_id, StartGameCheckAsBot { tx })
       .await?;
-    match rx.await {
+    let reply = match rx.await {
       Ok(res) => match res {
-        StartGameCheckAsBotResult::Started(map) => Ok(Response::new(StartGameAsBotReply {
+        StartGameCheckAsBotResult::Started(map) => StartGameAsBotReply {
           succeed: true,
           player_ack_map: map.map(convert_map).unwrap_or_default(),
           ..Default::default()
-        })),
-        StartGameCheckAsBotResult::Rejected(pkt) => Ok(Response::new(StartGameAsBotReply {
+        },
+        StartGameCheckAsBotResult::Rejected(pkt) => StartGameAsBotReply {
           succeed: false,
           error_message: pkt.message,
           player_ack_map: convert_map(pkt.player_client_info_map),
-        })),
+        },
       },
-      Err(_) => Err(Status::cancelled("System is shutting down")),
-    }
+      Err(_) => return Err(Status::cancelled("System is shutting down")),
+    };
+
+    self
+      .audit(
+        api_client_id,
+        "start_game_as_bot",
+        AuditTarget::Game(params.game_id),
+        json!({
+          "auto_select_node": params.auto_select_node,
+          "succeed": reply.succeed,
+        }),
+      )
+      .await;
+
+    Ok(Response::new(reply))
   }
 
   async fn cancel_game_as_bot(
     &self,
     request: Request<CancelGameAsBotRequest>,
   ) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
     let player_id = request.get_api_player_id();
+    let game_id = request.into_inner().game_id;
+    self.cancel_game_by_player(game_id, player_id).await?;
+
     self
-      .cancel_game(Request::new(CancelGameRequest {
-        game_id: request.into_inner().game_id,
-        player_id,
-      }))
-      .await?;
+      .audit(
+        api_client_id,
+        "cancel_game_as_bot",
+        AuditTarget::Game(game_id),
+        json!({}),
+      )
+      .await;
 
     Ok(Response::new(()))
   }
 
-  async fn reload(&self, _request: Request<()>) -> Result<Response<()>, Status> {
+  async fn reload(&self, request: Request<()>) -> Result<Response<()>, Status> {
     self.state.reload().await?;
+    self
+      .audit(
+        request.get_api_client_id(),
+        "reload",
+        AuditTarget::None,
+        json!({}),
+      )
+      .await;
     Ok(Response::new(()))
   }
 
@@ -565,6 +1072,14 @@ impl FloController for FloControllerService {
       .map(|t| DateTime::<Utc>::unpack(t))
       .transpose()
       .map_err(Status::internal)?;
+    let player_id = params.player_id;
+    let ban_type = PlayerBanType::unpack_enum(params.ban_type());
+    let audit_params = json!({
+      "ban_type": ban_type,
+      "ban_expires_at": ban_expires_at,
+      "reason": &params.reason,
+      "map_sha1": &params.map_sha1,
+    });
     self
       .state
       .db
@@ -572,13 +1087,26 @@ impl FloController for FloControllerService {
         crate::player::db::check_player_api_client_id(conn, api_client_id, params.player_id)?;
         crate::player::db::create_ban(
           conn,
-          params.player_id,
-          PlayerBanType::unpack_enum(params.ban_type()),
-          ban_expires_at,
+          api_client_id,
+          CreateBan {
+            player_id: params.player_id,
+            ban_type,
+            ban_expires_at,
+            reason: Some(params.reason).filter(|v| !v.is_empty()),
+            map_sha1: Some(params.map_sha1).filter(|v| !v.is_empty()),
+          },
         )
       })
       .await
       .map_err(Error::from)?;
+    self
+      .audit(
+        api_client_id,
+        "create_player_ban",
+        AuditTarget::Player(player_id),
+        audit_params,
+      )
+      .await;
     Ok(Response::new(()))
   }
 
@@ -586,17 +1114,480 @@ impl FloController for FloControllerService {
     &self,
     request: Request<RemovePlayerBanRequest>,
   ) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let id = request.into_inner().id;
+    self
+      .state
+      .db
+      .exec(move |conn| {
+        crate::player::db::check_ban_api_client_id(conn, api_client_id, id)?;
+        crate::player::db::remove_ban(conn, api_client_id, id)
+      })
+      .await
+      .map_err(Error::from)?;
+    self
+      .audit(
+        api_client_id,
+        "remove_player_ban",
+        AuditTarget::PlayerBan(id),
+        json!({}),
+      )
+      .await;
+    Ok(Response::new(()))
+  }
+
+  async fn list_player_ban_audits(
+    &self,
+    request: Request<ListPlayerBanAuditsRequest>,
+  ) -> Result<Response<ListPlayerBanAuditsReply>, Status> {
     let api_client_id = request.get_api_client_id();
     let params = request.into_inner();
+    let res = self
+      .state
+      .db
+      .exec(move |conn| {
+        crate::player::db::list_ban_audit(conn, api_client_id, params.player_id, params.next_id)
+      })
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(ListPlayerBanAuditsReply {
+      items: res.items.pack().map_err(Status::internal)?,
+      next_id: res.next_id,
+    }))
+  }
+
+  async fn upsert_matchmaking_queue(
+    &self,
+    request: Request<UpsertMatchmakingQueueRequest>,
+  ) -> Result<Response<UpsertMatchmakingQueueReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let queue = self
+      .state
+      .matchmaking
+      .send(UpsertQueue {
+        api_client_id,
+        params: UpsertQueueParams::unpack(request.into_inner()).map_err(Error::from)?,
+      })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "upsert_matchmaking_queue",
+        AuditTarget::MatchmakingQueue(queue.name.clone()),
+        json!({
+          "mode": &queue.mode,
+          "team_size": queue.team_size,
+          "maps": queue.maps.iter().map(|map| &map.name).collect::<Vec<_>>(),
+          "enable_ping_equalizer": queue.enable_ping_equalizer,
+          "map_pool_id": queue.map_pool_id,
+        }),
+      )
+      .await;
+    Ok(Response::new(UpsertMatchmakingQueueReply {
+      queue: queue.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn remove_matchmaking_queue(
+    &self,
+    request: Request<RemoveMatchmakingQueueRequest>,
+  ) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let name = request.into_inner().name;
+    self
+      .state
+      .matchmaking
+      .send(RemoveQueue {
+        api_client_id,
+        name: name.clone(),
+      })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "remove_matchmaking_queue",
+        AuditTarget::MatchmakingQueue(name),
+        json!({}),
+      )
+      .await;
+    Ok(Response::new(()))
+  }
+
+  async fn list_matchmaking_queues(
+    &self,
+    request: Request<()>,
+  ) -> Result<Response<ListMatchmakingQueuesReply>, Status> {
+    let queues = self
+      .state
+      .matchmaking
+      .send(ListQueues {
+        api_client_id: request.get_api_client_id(),
+      })
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(ListMatchmakingQueuesReply {
+      queues: queues.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn enqueue_matchmaking_player(
+    &self,
+    request: Request<EnqueueMatchmakingPlayerRequest>,
+  ) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let api_player_id = request.get_api_player_id();
+    let params = request.into_inner();
+    let player_id = params.player_id;
     self
       .state
       .db
       .exec(move |conn| {
-        crate::player::db::check_ban_api_client_id(conn, api_client_id, params.id)?;
-        crate::player::db::remove_ban(conn, params.id)
+        crate::player::db::check_player_api_client_id(conn, api_client_id, player_id)
       })
       .await
       .map_err(Error::from)?;
+    // the leader queues the whole party
+    let party_member_ids = match self
+      .state
+      .invites
+      .send(GetPartyMembers { player_id })
+      .await
+      .map_err(Error::from)?
+    {
+      Some(party) if party.leader_id != player_id => {
+        return Err(Error::PlayerNotPartyLeader.into())
+      }
+      Some(party) => party
+        .member_ids
+        .into_iter()
+        .filter(|id| *id != player_id)
+        .collect(),
+      None => vec![],
+    };
+    self
+      .state
+      .matchmaking
+      .send(Enqueue {
+        api_client_id,
+        api_player_id,
+        race: Race::unpack_enum(params.race()),
+        queue_name: params.queue_name.clone(),
+        player_id,
+        rating: Some(params.rating).filter(|v| *v > 0.0),
+        party_member_ids,
+      })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "enqueue_matchmaking_player",
+        AuditTarget::Player(player_id),
+        json!({ "queue_name": params.queue_name }),
+      )
+      .await;
     Ok(Response::new(()))
   }
+
+  async fn dequeue_matchmaking_player(
+    &self,
+    request: Request<DequeueMatchmakingPlayerRequest>,
+  ) -> Result<Response<()>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let player_id = request.into_inner().player_id;
+    self
+      .state
+      .matchmaking
+      .send(Dequeue {
+        api_client_id,
+        player_id,
+      })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "dequeue_matchmaking_player",
+        AuditTarget::Player(player_id),
+        json!({}),
+      )
+      .await;
+    Ok(Response::new(()))
+  }
+
+  async fn create_tournament(
+    &self,
+    request: Request<CreateTournamentRequest>,
+  ) -> Result<Response<CreateTournamentReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let api_player_id = request.get_api_player_id();
+    let tournament = self
+      .state
+      .tournaments
+      .send(CreateTournament {
+        api_client_id,
+        api_player_id,
+        params: CreateTournamentParams::unpack(request.into_inner()).map_err(Error::from)?,
+      })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "create_tournament",
+        AuditTarget::Tournament(tournament.id),
+        json!({
+          "name": &tournament.name,
+          "format": tournament.format,
+          "player_ids": tournament.players.iter().map(|p| p.player.id).collect::<Vec<_>>(),
+        }),
+      )
+      .await;
+    Ok(Response::new(CreateTournamentReply {
+      tournament: tournament.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn get_tournament(
+    &self,
+    request: Request<GetTournamentRequest>,
+  ) -> Result<Response<GetTournamentReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let tournament_id = request.into_inner().tournament_id;
+    let tournament = self
+      .state
+      .db
+      .exec(move |conn| crate::tournament::db::get(conn, api_client_id, tournament_id))
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(GetTournamentReply {
+      tournament: tournament.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn cancel_tournament(
+    &self,
+    request: Request<CancelTournamentRequest>,
+  ) -> Result<Response<CancelTournamentReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let tournament = self
+      .state
+      .tournaments
+      .send(CancelTournament {
+        api_client_id,
+        tournament_id: request.into_inner().tournament_id,
+      })
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "cancel_tournament",
+        AuditTarget::Tournament(tournament.id),
+        json!({}),
+      )
+      .await;
+    Ok(Response::new(CancelTournamentReply {
+      tournament: tournament.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn list_audit_logs(
+    &self,
+    request: Request<ListAuditLogsRequest>,
+  ) -> Result<Response<ListAuditLogsReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let params = ListAuditLogsParams::unpack(request.into_inner()).map_err(Error::from)?;
+    let res = self
+      .state
+      .db
+      .exec(move |conn| crate::audit::db::list(conn, api_client_id, &params))
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(ListAuditLogsReply {
+      items: res.items.pack().map_err(Status::internal)?,
+      next_id: res.next_id,
+    }))
+  }
+
+  async fn list_api_clients(
+    &self,
+    request: Request<()>,
+  ) -> Result<Response<ListApiClientsReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let clients = self
+      .state
+      .db
+      .exec(move |conn| {
+        if is_operator(api_client_id) {
+          crate::api_client::db::list_clients(conn)
+        } else {
+          crate::api_client::db::get_client(conn, api_client_id).map(|client| vec![client])
+        }
+      })
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(ListApiClientsReply {
+      api_clients: clients
+        .into_iter()
+        .map(|client| client.into_proto())
+        .collect::<Result<_>>()?,
+    }))
+  }
+
+  async fn create_api_client(
+    &self,
+    request: Request<CreateApiClientRequest>,
+  ) -> Result<Response<CreateApiClientReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    if !is_operator(api_client_id) {
+      return Err(Error::ApiClientForbidden.into());
+    }
+    let CreateApiClientRequest { name, scopes } = request.into_inner();
+    let scopes = unpack_scopes(scopes)?;
+    let client = self
+      .state
+      .db
+      .exec(move |conn| crate::api_client::db::create_client(conn, &name, &scopes))
+      .await
+      .map_err(Error::from)?;
+    self
+      .audit(
+        api_client_id,
+        "create_api_client",
+        AuditTarget::ApiClient(client.id),
+        json!({ "name": client.name, "scopes": client.scopes }),
+      )
+      .await;
+    Ok(Response::new(CreateApiClientReply {
+      api_client: client.into_proto()?,
+    }))
+  }
+
+  async fn update_api_client_scopes(
+    &self,
+    request: Request<UpdateApiClientScopesRequest>,
+  ) -> Result<Response<UpdateApiClientScopesReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    // a client can't change its own scopes either
+    if !is_operator(api_client_id) {
+      return Err(Error::ApiClientForbidden.into());
+    }
+    let UpdateApiClientScopesRequest { id, scopes } = request.into_inner();
+    let scopes = unpack_scopes(scopes)?;
+    let client = self
+      .state
+      .db
+      .exec(move |conn| crate::api_client::db::update_client_scopes(conn, id, &scopes))
+      .await
+      .map_err(Error::from)?;
+    self
+      .state
+      .config
+      .send(Reload)
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "update_api_client_scopes",
+        AuditTarget::ApiClient(id),
+        json!({ "scopes": client.scopes }),
+      )
+      .await;
+    Ok(Response::new(UpdateApiClientScopesReply {
+      api_client: client.into_proto()?,
+    }))
+  }
+
+  async fn list_api_client_keys(
+    &self,
+    request: Request<ListApiClientKeysRequest>,
+  ) -> Result<Response<ListApiClientKeysReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let id = request.into_inner().api_client_id;
+    check_client_access(api_client_id, id)?;
+    let keys = self
+      .state
+      .db
+      .exec(move |conn| crate::api_client::db::list_keys(conn, id))
+      .await
+      .map_err(Error::from)?;
+    Ok(Response::new(ListApiClientKeysReply {
+      keys: keys.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn create_api_client_key(
+    &self,
+    request: Request<CreateApiClientKeyRequest>,
+  ) -> Result<Response<CreateApiClientKeyReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let params = CreateApiClientKeyParams::unpack(request.into_inner()).map_err(Error::from)?;
+    check_client_access(api_client_id, params.api_client_id)?;
+    let key = self
+      .state
+      .db
+      .exec(move |conn| {
+        crate::api_client::db::create_key(conn, params.api_client_id, params.expires_at)
+      })
+      .await
+      .map_err(Error::from)?;
+    self
+      .state
+      .config
+      .send(Reload)
+      .await
+      .map_err(Error::from)??;
+    // the secret itself is never recorded
+    self
+      .audit(
+        api_client_id,
+        "create_api_client_key",
+        AuditTarget::ApiClientKey(key.id),
+        json!({ "api_client_id": key.api_client_id, "expires_at": key.expires_at }),
+      )
+      .await;
+    Ok(Response::new(CreateApiClientKeyReply {
+      secret_key: key.secret_key.clone(),
+      key: key.pack().map_err(Status::internal)?,
+    }))
+  }
+
+  async fn expire_api_client_key(
+    &self,
+    request: Request<ExpireApiClientKeyRequest>,
+  ) -> Result<Response<ExpireApiClientKeyReply>, Status> {
+    let api_client_id = request.get_api_client_id();
+    let params = ExpireApiClientKeyParams::unpack(request.into_inner()).map_err(Error::from)?;
+    let key = self
+      .state
+      .db
+      .exec(move |conn| {
+        let key = crate::api_client::db::get_key(conn, params.id)?;
+        check_client_access(api_client_id, key.api_client_id)?;
+        crate::api_client::db::expire_key(conn, params.id, params.expires_at)
+      })
+      .await
+      .map_err(Error::from)?;
+    self
+      .state
+      .config
+      .send(Reload)
+      .await
+      .map_err(Error::from)??;
+    self
+      .audit(
+        api_client_id,
+        "expire_api_client_key",
+        AuditTarget::ApiClientKey(key.id),
+        json!({ "api_client_id": key.api_client_id, "expires_at": key.expires_at }),
+      )
+      .await;
+    Ok(Response::new(ExpireApiClientKeyReply {
+      key: key.pack().map_err(Status::internal)?,
+    }))
+  }
 }
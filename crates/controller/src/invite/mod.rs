@@ -0,0 +1,4 @@
+pub(crate) mod state;
+mod types;
+
+pub use types::*;
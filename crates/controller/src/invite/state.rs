@@ -0,0 +1,559 @@
+use crate::error::*;
+use crate::game::messages::PlayerJoin;
+use crate::game::state::registry::AddGamePlayer;
+use crate::game::state::GameRegistry;
+use crate::game::GameStatus;
+use crate::invite::{Invite, InviteKind, Party, INVITE_TIMEOUT, MAX_PENDING_INVITES};
+use crate::matchmaking::state::{DequeuePlayers, MatchmakingRegistry};
+use crate::player::state::sender::PlayerRegistryHandle;
+use crate::player::state::PlayerRegistry;
+use crate::state::{ActorMapExt, Data};
+use bs_diesel_utils::ExecutorRef;
+use flo_net::packet::FloPacket;
+use flo_net::proto::flo_connect as proto;
+use flo_state::{async_trait, Actor, Addr, Context, Handler, Message, RegistryRef, Service};
+use s2_grpc_utils::S2ProtoPack;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+const EXPIRE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pending game/party invites and parties, both only live in memory
+pub struct InviteRegistry {
+  db: ExecutorRef,
+  games: Addr<GameRegistry>,
+  matchmaking: Addr<MatchmakingRegistry>,
+  player_packet_sender: PlayerRegistryHandle,
+  next_invite_id: i32,
+  invites: BTreeMap<i32, Invite>,
+  /// leader id -> party
+  parties: BTreeMap<i32, Party>,
+  /// player id -> leader id
+  player_party_map: BTreeMap<i32, i32>,
+}
+
+impl InviteRegistry {
+  fn get_party(&self, player_id: i32) -> Option<&Party> {
+    self
+      .player_party_map
+      .get(&player_id)
+      .and_then(|leader_id| self.parties.get(leader_id))
+  }
+
+  async fn create_invites(
+    &mut self,
+    kind: InviteKind,
+    from_player_id: i32,
+    game_name: Option<String>,
+    mut player_ids: Vec<i32>,
+  ) -> Result<()> {
+    player_ids.sort_unstable();
+    player_ids.dedup();
+    let pending = self
+      .invites
+      .values()
+      .filter(|invite| invite.from.id == from_player_id)
+      .count();
+    if pending + player_ids.len() > MAX_PENDING_INVITES {
+      return Err(Error::TooManyInvites);
+    }
+
+    let (from, mute_list_map) = {
+      let player_ids = player_ids.clone();
+      self
+        .db
+        .exec(move |conn| {
+          Ok::<_, Error>((
+            crate::player::db::get_ref(conn, from_player_id)?,
+            crate::player::db::get_mute_list_map(conn, &player_ids)?,
+          ))
+        })
+        .await?
+    };
+
+    let expires_at = Instant::now() + INVITE_TIMEOUT;
+    for to_player_id in player_ids {
+      if to_player_id == from_player_id {
+        continue;
+      }
+      // players who muted the inviter don't receive invites
+      if mute_list_map
+        .get(&to_player_id)
+        .map(|list| list.contains(&from_player_id))
+        .unwrap_or_default()
+      {
+        continue;
+      }
+      if self.invites.values().any(|invite| {
+        invite.kind == kind
+          && invite.from.id == from_player_id
+          && invite.to_player_id == to_player_id
+      }) {
+        continue;
+      }
+
+      self.next_invite_id += 1;
+      let invite = Invite {
+        id: self.next_invite_id,
+        kind,
+        from: from.clone(),
+        to_player_id,
+        game_name: game_name.clone(),
+        expires_at,
+      };
+      let frame = proto::PacketGameInvite {
+        invite: Some(invite.pack()?),
+      }
+      .encode_as_frame()?;
+      self.player_packet_sender.send(to_player_id, frame).await?;
+      self.invites.insert(invite.id, invite);
+    }
+    Ok(())
+  }
+
+  async fn send_invite_update(&self, invite: &Invite, status: proto::InviteStatus) -> Result<()> {
+    let frame = proto::PacketInviteUpdate {
+      invite_id: invite.id,
+      player_id: invite.to_player_id,
+      status: status.into(),
+    }
+    .encode_as_frame()?;
+    self
+      .player_packet_sender
+      .broadcast(vec![invite.from.id, invite.to_player_id], frame)
+      .await?;
+    Ok(())
+  }
+
+  async fn accept_game_invite(
+    &mut self,
+    game_id: i32,
+    game_name: Option<String>,
+    player_id: i32,
+  ) -> proto::InviteStatus {
+    if let Err(err) = self.join_game(game_id, player_id).await {
+      tracing::debug!(game_id, player_id, "accept game invite: {}", err);
+      return proto::InviteStatus::Rejected;
+    }
+
+    // party members get their own invite from the leader
+    let member_ids: Vec<i32> = match self.get_party(player_id) {
+      Some(party) if party.leader_id == player_id => party
+        .member_ids
+        .iter()
+        .filter(|id| **id != player_id)
+        .cloned()
+        .collect(),
+      _ => vec![],
+    };
+    if !member_ids.is_empty() {
+      if let Err(err) = self
+        .create_invites(
+          InviteKind::Game { game_id },
+          player_id,
+          game_name,
+          member_ids,
+        )
+        .await
+      {
+        tracing::error!(game_id, player_id, "invite party members: {}", err);
+      }
+    }
+
+    proto::InviteStatus::Accepted
+  }
+
+  async fn join_game(&self, game_id: i32, player_id: i32) -> Result<()> {
+    self
+      .games
+      .send_to(game_id, PlayerJoin { player_id })
+      .await?;
+    self
+      .games
+      .send(AddGamePlayer { game_id, player_id })
+      .await?;
+    Ok(())
+  }
+
+  async fn accept_party_invite(
+    &mut self,
+    leader_id: i32,
+    player_id: i32,
+  ) -> Result<proto::InviteStatus> {
+    if self.player_party_map.contains_key(&player_id) {
+      return Ok(proto::InviteStatus::Rejected);
+    }
+    match self.player_party_map.get(&leader_id) {
+      // the inviter has joined another party
+      Some(id) if *id != leader_id => return Ok(proto::InviteStatus::Rejected),
+      Some(_) => {}
+      None => {
+        self.parties.insert(leader_id, Party::new(leader_id));
+        self.player_party_map.insert(leader_id, leader_id);
+      }
+    }
+
+    let party = self
+      .parties
+      .get_mut(&leader_id)
+      .ok_or_else(|| Error::PartyNotFound)?;
+    if party.is_full() {
+      return Ok(proto::InviteStatus::Rejected);
+    }
+    party.member_ids.push(player_id);
+    self.player_party_map.insert(player_id, leader_id);
+
+    let party = party.clone();
+    // the party is queued again by the leader
+    self
+      .matchmaking
+      .notify(DequeuePlayers {
+        player_ids: party.member_ids.clone(),
+      })
+      .await?;
+    self.send_party_update(&party).await?;
+    Ok(proto::InviteStatus::Accepted)
+  }
+
+  async fn pack_party(&self, party: &Party) -> Result<proto::Party> {
+    let member_ids = party.member_ids.clone();
+    let players = self
+      .db
+      .exec(move |conn| crate::player::db::get_refs_by_ids(conn, &member_ids))
+      .await?;
+    let mut members = Vec::with_capacity(players.len());
+    for id in &party.member_ids {
+      if let Some(player) = players.iter().find(|p| p.id == *id) {
+        members.push(player.clone().pack()?);
+      }
+    }
+    Ok(proto::Party {
+      leader_id: party.leader_id,
+      members,
+    })
+  }
+
+  async fn send_party_update(&self, party: &Party) -> Result<()> {
+    let frame = proto::PacketPartyUpdate {
+      party: Some(self.pack_party(party).await?),
+    }
+    .encode_as_frame()?;
+    self
+      .player_packet_sender
+      .broadcast(party.member_ids.clone(), frame)
+      .await?;
+    Ok(())
+  }
+
+  async fn send_party_removed(&self, player_ids: Vec<i32>) -> Result<()> {
+    let frame = proto::PacketPartyUpdate { party: None }.encode_as_frame()?;
+    self
+      .player_packet_sender
+      .broadcast(player_ids, frame)
+      .await?;
+    Ok(())
+  }
+
+  async fn remove_expired_invites(&mut self) -> Result<()> {
+    let now = Instant::now();
+    let ids: Vec<i32> = self
+      .invites
+      .values()
+      .filter(|invite| invite.is_expired(now))
+      .map(|invite| invite.id)
+      .collect();
+    for id in ids {
+      if let Some(invite) = self.invites.remove(&id) {
+        self
+          .send_invite_update(&invite, proto::InviteStatus::Expired)
+          .await?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl Actor for InviteRegistry {
+  async fn started(&mut self, ctx: &mut Context<Self>) {
+    let addr = ctx.addr();
+    ctx.spawn(async move {
+      loop {
+        sleep(EXPIRE_CHECK_INTERVAL).await;
+        if addr.notify(RemoveExpiredInvites).await.is_err() {
+          break;
+        }
+      }
+    });
+  }
+}
+
+#[async_trait]
+impl Service<Data> for InviteRegistry {
+  type Error = Error;
+
+  async fn create(registry: &mut RegistryRef<Data>) -> Result<Self, Self::Error> {
+    let db = registry.data().db.clone();
+    let games = registry.resolve::<GameRegistry>().await?;
+    let players = registry.resolve::<PlayerRegistry>().await?;
+    let matchmaking = registry.resolve::<MatchmakingRegistry>().await?;
+    Ok(Self {
+      db,
+      games,
+      matchmaking,
+      player_packet_sender: players.into(),
+      next_invite_id: 0,
+      invites: BTreeMap::new(),
+      parties: BTreeMap::new(),
+      player_party_map: BTreeMap::new(),
+    })
+  }
+}
+
+struct RemoveExpiredInvites;
+
+impl Message for RemoveExpiredInvites {
+  type Result = ();
+}
+
+#[async_trait]
+impl Handler<RemoveExpiredInvites> for InviteRegistry {
+  async fn handle(&mut self, _: &mut Context<Self>, _: RemoveExpiredInvites) {
+    if let Err(err) = self.remove_expired_invites().await {
+      tracing::error!("remove expired invites: {}", err);
+    }
+  }
+}
+
+/// Sent by the host to invite players into the lobby
+pub struct CreateGameInvites {
+  pub player_id: i32,
+  pub game_id: i32,
+  pub player_ids: Vec<i32>,
+}
+
+impl Message for CreateGameInvites {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<CreateGameInvites> for InviteRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    CreateGameInvites {
+      player_id,
+      game_id,
+      mut player_ids,
+    }: CreateGameInvites,
+  ) -> Result<()> {
+    let (game, host_player_id) = self
+      .db
+      .exec(move |conn| {
+        Ok::<_, Error>((
+          crate::game::db::get_full(conn, game_id)?,
+          crate::game::db::get_host_player_id(conn, game_id)?,
+        ))
+      })
+      .await?;
+    if host_player_id != player_id {
+      return Err(Error::PlayerNotHost);
+    }
+    if game.status != GameStatus::Preparing {
+      return Err(Error::GameStarted);
+    }
+
+    let joined = game.get_player_ids();
+    player_ids.retain(|id| !joined.contains(id));
+
+    self
+      .create_invites(
+        InviteKind::Game { game_id },
+        player_id,
+        Some(game.name),
+        player_ids,
+      )
+      .await
+  }
+}
+
+/// Invites players into the party of the player,
+/// a new party is created once the first invite is accepted
+pub struct CreatePartyInvites {
+  pub player_id: i32,
+  pub player_ids: Vec<i32>,
+}
+
+impl Message for CreatePartyInvites {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<CreatePartyInvites> for InviteRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    CreatePartyInvites {
+      player_id,
+      mut player_ids,
+    }: CreatePartyInvites,
+  ) -> Result<()> {
+    if let Some(party) = self.get_party(player_id) {
+      if party.leader_id != player_id {
+        return Err(Error::PlayerNotPartyLeader);
+      }
+      player_ids.retain(|id| !party.member_ids.contains(id));
+    }
+
+    self
+      .create_invites(InviteKind::Party, player_id, None, player_ids)
+      .await
+  }
+}
+
+pub struct RespondInvite {
+  pub player_id: i32,
+  pub invite_id: i32,
+  pub accept: bool,
+}
+
+impl Message for RespondInvite {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<RespondInvite> for InviteRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    RespondInvite {
+      player_id,
+      invite_id,
+      accept,
+    }: RespondInvite,
+  ) -> Result<()> {
+    let valid = self
+      .invites
+      .get(&invite_id)
+      .map(|invite| invite.to_player_id == player_id && !invite.is_expired(Instant::now()))
+      .unwrap_or_default();
+    if !valid {
+      tracing::debug!(player_id, invite_id, "invite not found or expired");
+      return Ok(());
+    }
+    let invite = self
+      .invites
+      .remove(&invite_id)
+      .ok_or_else(|| Error::InviteNotFound)?;
+
+    let status = if accept {
+      match invite.kind {
+        InviteKind::Game { game_id } => {
+          self
+            .accept_game_invite(game_id, invite.game_name.clone(), player_id)
+            .await
+        }
+        InviteKind::Party => self.accept_party_invite(invite.from.id, player_id).await?,
+      }
+    } else {
+      proto::InviteStatus::Declined
+    };
+
+    self.send_invite_update(&invite, status).await
+  }
+}
+
+pub struct LeaveParty {
+  pub player_id: i32,
+}
+
+impl Message for LeaveParty {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<LeaveParty> for InviteRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    LeaveParty { player_id }: LeaveParty,
+  ) -> Result<()> {
+    let leader_id = match self.player_party_map.remove(&player_id) {
+      Some(id) => id,
+      None => return Ok(()),
+    };
+    let mut party = self
+      .parties
+      .remove(&leader_id)
+      .ok_or_else(|| Error::PartyNotFound)?;
+
+    // the party is queued again by the leader
+    self
+      .matchmaking
+      .notify(DequeuePlayers {
+        player_ids: party.member_ids.clone(),
+      })
+      .await?;
+
+    let mut removed = vec![player_id];
+    if party.remove_member(player_id) {
+      for id in &party.member_ids {
+        self.player_party_map.insert(*id, party.leader_id);
+      }
+      self.send_party_update(&party).await?;
+      self.parties.insert(party.leader_id, party);
+    } else {
+      for id in party.member_ids {
+        self.player_party_map.remove(&id);
+        removed.push(id);
+      }
+    }
+
+    self.send_party_removed(removed).await
+  }
+}
+
+/// Returns the party of the player, sent to the player on connect
+pub struct GetParty {
+  pub player_id: i32,
+}
+
+impl Message for GetParty {
+  type Result = Result<Option<proto::Party>>;
+}
+
+#[async_trait]
+impl Handler<GetParty> for InviteRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    GetParty { player_id }: GetParty,
+  ) -> Result<Option<proto::Party>> {
+    if let Some(party) = self.get_party(player_id) {
+      Ok(Some(self.pack_party(party).await?))
+    } else {
+      Ok(None)
+    }
+  }
+}
+
+/// Returns the party of the player
+pub struct GetPartyMembers {
+  pub player_id: i32,
+}
+
+impl Message for GetPartyMembers {
+  type Result = Option<Party>;
+}
+
+#[async_trait]
+impl Handler<GetPartyMembers> for InviteRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    GetPartyMembers { player_id }: GetPartyMembers,
+  ) -> Option<Party> {
+    self.get_party(player_id).cloned()
+  }
+}
@@ -0,0 +1,100 @@
+use crate::error::Result;
+use crate::player::PlayerRef;
+use flo_net::proto::flo_connect as proto;
+use s2_grpc_utils::S2ProtoPack;
+use std::time::{Duration, Instant};
+
+pub const INVITE_TIMEOUT: Duration = Duration::from_secs(60);
+/// A full team in 4v4
+pub const MAX_PARTY_SIZE: usize = 4;
+/// Pending invites a player can have sent at the same time, a full lobby
+pub const MAX_PENDING_INVITES: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InviteKind {
+  Game { game_id: i32 },
+  Party,
+}
+
+#[derive(Debug)]
+pub struct Invite {
+  pub id: i32,
+  pub kind: InviteKind,
+  pub from: PlayerRef,
+  pub to_player_id: i32,
+  pub game_name: Option<String>,
+  pub expires_at: Instant,
+}
+
+impl Invite {
+  pub fn is_expired(&self, now: Instant) -> bool {
+    self.expires_at <= now
+  }
+
+  pub fn pack(&self) -> Result<proto::Invite> {
+    let (kind, game_id) = match self.kind {
+      InviteKind::Game { game_id } => (proto::InviteKind::Game, Some(game_id)),
+      InviteKind::Party => (proto::InviteKind::Party, None),
+    };
+    Ok(proto::Invite {
+      id: self.id,
+      kind: kind.into(),
+      from: Some(self.from.clone().pack()?),
+      game_id,
+      game_name: self.game_name.clone().unwrap_or_default(),
+      expires_in_secs: self
+        .expires_at
+        .saturating_duration_since(Instant::now())
+        .as_secs() as i32,
+    })
+  }
+}
+
+/// A group of players that joins lobbies together, led by the player who created it.
+/// Members are invited into the lobby the leader joins,
+/// the leader queues the party for matchmaking as one entry and its members play in the same team.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Party {
+  pub leader_id: i32,
+  /// In join order, the leader included
+  pub member_ids: Vec<i32>,
+}
+
+impl Party {
+  pub fn new(leader_id: i32) -> Self {
+    Self {
+      leader_id,
+      member_ids: vec![leader_id],
+    }
+  }
+
+  pub fn is_full(&self) -> bool {
+    self.member_ids.len() >= MAX_PARTY_SIZE
+  }
+
+  /// Removes the member, the next member becomes the leader if the leader left.
+  /// Returns false if less than 2 members are left and the party should be dissolved.
+  pub fn remove_member(&mut self, player_id: i32) -> bool {
+    self.member_ids.retain(|id| *id != player_id);
+    if self.leader_id == player_id {
+      if let Some(id) = self.member_ids.first() {
+        self.leader_id = *id;
+      }
+    }
+    self.member_ids.len() > 1
+  }
+}
+
+#[test]
+fn test_party_remove_member() {
+  let mut party = Party::new(1);
+  party.member_ids.extend(&[2, 3]);
+
+  assert!(party.remove_member(1));
+  assert_eq!(party.leader_id, 2);
+  assert_eq!(party.member_ids, vec![2, 3]);
+
+  assert!(!party.remove_member(3));
+  assert_eq!(party.leader_id, 2);
+  assert_eq!(party.member_ids, vec![2]);
+}
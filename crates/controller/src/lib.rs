@@ -14,16 +14,22 @@ extern crate diesel;
 mod db;
 mod schema;
 
+pub mod api_client;
+pub mod audit;
 mod client;
 mod config;
 pub mod error;
 pub mod game;
 mod grpc;
 pub mod host;
+pub mod invite;
 pub mod map;
+pub mod matchmaking;
 pub mod node;
 pub mod player;
+pub mod rating;
 mod state;
+pub mod tournament;
 
 pub use client::serve as serve_socket;
 pub use grpc::serve as serve_grpc;
@@ -1,10 +1,14 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl::{any, exists};
 use diesel::prelude::*;
 use s2_grpc_utils::S2ProtoUnpack;
 use serde::Deserialize;
+use serde_json::Value;
 
 use crate::db::DbConn;
 use crate::error::*;
-use crate::schema::map_checksum;
+use crate::map::{Map, MapInfo, MapPool, UpsertMapPoolParams};
+use crate::schema::{map_checksum, map_info, map_pool, map_pool_map};
 
 pub fn search_checksum(conn: &DbConn, sha1: String) -> Result<Option<u32>> {
   use map_checksum::dsl;
@@ -62,3 +66,343 @@ struct Insert<'a> {
   sha1: &'a str,
   checksum: Vec<u8>,
 }
+
+/// Registers the maps that are not registered yet, their checksums are also imported.
+/// Registered maps are shared by all api clients, so existing rows are never overwritten.
+pub fn insert_infos(conn: &DbConn, mut items: Vec<MapInfo>) -> Result<usize> {
+  items.sort_by_cached_key(|i| i.sha1.clone());
+  items.dedup_by(|a, b| a.sha1 == b.sha1);
+
+  let inserts = items
+    .iter()
+    .map(InfoInsert::from_info)
+    .collect::<Result<Vec<_>>>()?;
+  let checksums: Vec<_> = items
+    .iter()
+    .map(|item| Insert {
+      sha1: item.sha1.as_ref(),
+      checksum: item.checksum.to_le_bytes().to_vec(),
+    })
+    .collect();
+
+  conn.transaction(|| {
+    let inserted = diesel::insert_into(map_info::table)
+      .values(&inserts)
+      .on_conflict(map_info::sha1)
+      .do_nothing()
+      .execute(conn)?;
+
+    diesel::insert_into(map_checksum::table)
+      .values(&checksums)
+      .on_conflict(map_checksum::sha1)
+      .do_nothing()
+      .execute(conn)?;
+
+    Ok(inserted)
+  })
+}
+
+pub fn get_info(conn: &DbConn, sha1: &str) -> Result<Option<MapInfo>> {
+  get_info_row(conn, sha1)?
+    .map(InfoRow::into_info)
+    .transpose()
+}
+
+fn get_info_row(conn: &DbConn, sha1: &str) -> Result<Option<InfoRow>> {
+  use map_info::dsl;
+  map_info::table
+    .filter(dsl::sha1.eq(sha1))
+    .select((
+      dsl::id,
+      dsl::sha1,
+      dsl::checksum,
+      dsl::name,
+      dsl::description,
+      dsl::author,
+      dsl::path,
+      dsl::width,
+      dsl::height,
+      dsl::suggested_players,
+      dsl::players,
+      dsl::forces,
+      dsl::preview_jpeg,
+    ))
+    .first::<InfoRow>(conn)
+    .optional()
+    .map_err(Into::into)
+}
+
+/// Checks a map used to create a game by the api client:
+/// a registered map must have the same checksum and slot layout,
+/// and if the api client has map pools, the map must be in one of them
+pub fn check_map_allowed(conn: &DbConn, api_client_id: i32, map: &Map) -> Result<()> {
+  let pool_ids: Vec<i32> = map_pool::table
+    .filter(map_pool::api_client_id.eq(api_client_id))
+    .select(map_pool::id)
+    .load(conn)?;
+  check_map_in_pools(conn, &pool_ids, map)
+}
+
+/// Checks maps of a matchmaking queue against the queue's map pool
+pub fn check_maps_in_pool(
+  conn: &DbConn,
+  api_client_id: i32,
+  map_pool_id: i32,
+  maps: &[Map],
+) -> Result<()> {
+  let pool_id = get_pool_id(conn, api_client_id, map_pool_id)?;
+  for map in maps {
+    check_map_in_pools(conn, &[pool_id], map)?;
+  }
+  Ok(())
+}
+
+fn check_map_in_pools(conn: &DbConn, pool_ids: &[i32], map: &Map) -> Result<()> {
+  let row = get_info_row(conn, &map.sha1.to_hex())?;
+  let id = match row {
+    Some(row) => {
+      let id = row.id;
+      if !row.into_info()?.matches(map) {
+        return Err(Error::MapLayoutMismatch);
+      }
+      Some(id)
+    }
+    None => None,
+  };
+
+  if pool_ids.is_empty() {
+    return Ok(());
+  }
+
+  let id = id.ok_or_else(|| Error::MapNotAllowed)?;
+  let allowed = diesel::select(exists(
+    map_pool_map::table.filter(
+      map_pool_map::map_pool_id
+        .eq(any(pool_ids))
+        .and(map_pool_map::map_info_id.eq(id)),
+    ),
+  ))
+  .get_result::<bool>(conn)?;
+  if !allowed {
+    return Err(Error::MapNotAllowed);
+  }
+  Ok(())
+}
+
+fn get_pool_id(conn: &DbConn, api_client_id: i32, map_pool_id: i32) -> Result<i32> {
+  map_pool::table
+    .filter(
+      map_pool::id
+        .eq(map_pool_id)
+        .and(map_pool::api_client_id.eq(api_client_id)),
+    )
+    .select(map_pool::id)
+    .first(conn)
+    .optional()?
+    .ok_or_else(|| Error::MapPoolNotFound)
+}
+
+pub fn list_pools(conn: &DbConn, api_client_id: i32) -> Result<Vec<MapPool>> {
+  let rows: Vec<PoolRow> = map_pool::table
+    .filter(map_pool::api_client_id.eq(api_client_id))
+    .order(map_pool::id)
+    .load(conn)?;
+  let pool_ids: Vec<i32> = rows.iter().map(|row| row.id).collect();
+  let maps: Vec<(i32, String)> = map_pool_map::table
+    .inner_join(map_info::table)
+    .filter(map_pool_map::map_pool_id.eq(any(pool_ids)))
+    .select((map_pool_map::map_pool_id, map_info::sha1))
+    .order(map_info::sha1)
+    .load(conn)?;
+  Ok(
+    rows
+      .into_iter()
+      .map(|row| {
+        let map_sha1s = maps
+          .iter()
+          .filter(|(id, _)| *id == row.id)
+          .map(|(_, sha1)| sha1.clone())
+          .collect();
+        row.into_pool(map_sha1s)
+      })
+      .collect(),
+  )
+}
+
+/// Creates or updates the pool with the same name, all maps must be registered
+pub fn upsert_pool(
+  conn: &DbConn,
+  api_client_id: i32,
+  mut params: UpsertMapPoolParams,
+) -> Result<MapPool> {
+  use map_pool::dsl;
+
+  if params.name.is_empty() {
+    return Err(Error::MapPoolInvalid("name is empty"));
+  }
+
+  params.map_sha1s = params
+    .map_sha1s
+    .iter()
+    .map(|sha1| crate::map::normalize_sha1(sha1))
+    .collect::<Result<_>>()?;
+  params.map_sha1s.sort();
+  params.map_sha1s.dedup();
+
+  if params.map_sha1s.is_empty() {
+    return Err(Error::MapPoolInvalid("map pool is empty"));
+  }
+
+  conn.transaction(|| {
+    let map_ids: Vec<i32> = map_info::table
+      .filter(map_info::sha1.eq(any(&params.map_sha1s)))
+      .select(map_info::id)
+      .load(conn)?;
+    if map_ids.len() != params.map_sha1s.len() {
+      return Err(Error::MapNotFound);
+    }
+
+    let row: PoolRow = diesel::insert_into(map_pool::table)
+      .values(&PoolInsert {
+        api_client_id,
+        name: &params.name,
+      })
+      .on_conflict((dsl::api_client_id, dsl::name))
+      .do_update()
+      .set(dsl::updated_at.eq(diesel::dsl::now))
+      .get_result(conn)?;
+
+    diesel::delete(map_pool_map::table.filter(map_pool_map::map_pool_id.eq(row.id)))
+      .execute(conn)?;
+    let inserts: Vec<_> = map_ids
+      .into_iter()
+      .map(|map_info_id| {
+        (
+          map_pool_map::map_pool_id.eq(row.id),
+          map_pool_map::map_info_id.eq(map_info_id),
+        )
+      })
+      .collect();
+    diesel::insert_into(map_pool_map::table)
+      .values(&inserts)
+      .execute(conn)?;
+
+    Ok(row.into_pool(params.map_sha1s.clone()))
+  })
+}
+
+pub fn remove_pool(conn: &DbConn, api_client_id: i32, name: &str) -> Result<i32> {
+  use map_pool::dsl;
+  diesel::delete(
+    map_pool::table.filter(dsl::api_client_id.eq(api_client_id).and(dsl::name.eq(name))),
+  )
+  .returning(dsl::id)
+  .get_result(conn)
+  .optional()?
+  .ok_or_else(|| Error::MapPoolNotFound)
+}
+
+#[derive(Debug, Queryable)]
+struct InfoRow {
+  id: i32,
+  sha1: String,
+  checksum: i64,
+  name: String,
+  description: String,
+  author: String,
+  path: String,
+  width: i32,
+  height: i32,
+  suggested_players: String,
+  players: Value,
+  forces: Value,
+  preview_jpeg: Option<Vec<u8>>,
+}
+
+impl InfoRow {
+  fn into_info(self) -> Result<MapInfo> {
+    Ok(MapInfo {
+      sha1: self.sha1,
+      checksum: self.checksum as u32,
+      name: self.name,
+      description: self.description,
+      author: self.author,
+      path: self.path,
+      width: self.width as u32,
+      height: self.height as u32,
+      suggested_players: self.suggested_players,
+      players: serde_json::from_value(self.players)?,
+      forces: serde_json::from_value(self.forces)?,
+      preview_jpeg: self.preview_jpeg.unwrap_or_default(),
+    })
+  }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "map_info"]
+struct InfoInsert<'a> {
+  sha1: &'a str,
+  checksum: i64,
+  name: &'a str,
+  description: &'a str,
+  author: &'a str,
+  path: &'a str,
+  width: i32,
+  height: i32,
+  suggested_players: &'a str,
+  players: Value,
+  forces: Value,
+  preview_jpeg: Option<&'a [u8]>,
+}
+
+impl<'a> InfoInsert<'a> {
+  fn from_info(info: &'a MapInfo) -> Result<Self> {
+    Ok(InfoInsert {
+      sha1: &info.sha1,
+      checksum: info.checksum as i64,
+      name: &info.name,
+      description: &info.description,
+      author: &info.author,
+      path: &info.path,
+      width: info.width as i32,
+      height: info.height as i32,
+      suggested_players: &info.suggested_players,
+      players: serde_json::to_value(&info.players)?,
+      forces: serde_json::to_value(&info.forces)?,
+      preview_jpeg: if info.preview_jpeg.is_empty() {
+        None
+      } else {
+        Some(info.preview_jpeg.as_slice())
+      },
+    })
+  }
+}
+
+#[derive(Debug, Queryable)]
+struct PoolRow {
+  id: i32,
+  api_client_id: i32,
+  name: String,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+}
+
+impl PoolRow {
+  fn into_pool(self, map_sha1s: Vec<String>) -> MapPool {
+    MapPool {
+      id: self.id,
+      api_client_id: self.api_client_id,
+      name: self.name,
+      map_sha1s,
+      created_at: self.created_at,
+      updated_at: self.updated_at,
+    }
+  }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "map_pool"]
+struct PoolInsert<'a> {
+  api_client_id: i32,
+  name: &'a str,
+}
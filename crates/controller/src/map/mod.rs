@@ -1,5 +1,8 @@
 pub mod db;
 
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use flo_w3map::{MapChecksum, W3Map};
 use s2_grpc_utils::result::Error as ProtoError;
 use s2_grpc_utils::{S2ProtoPack, S2ProtoUnpack};
 use serde::{Deserialize, Serialize};
@@ -27,6 +30,11 @@ impl MapSha1 {
   pub fn to_vec(&self) -> Vec<u8> {
     self.0.to_vec()
   }
+
+  /// Lowercase hex, the format used by map checksum imports and map bans
+  pub fn to_hex(&self) -> String {
+    self.0.iter().map(|b| format!("{:02x}", b)).collect()
+  }
 }
 
 impl S2ProtoUnpack<Vec<u8>> for MapSha1 {
@@ -63,3 +71,170 @@ pub struct MapForce {
   pub flags: u32,
   pub player_set: u32,
 }
+
+/// Map metadata registered in the controller, parsed from an uploaded map file or imported
+#[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone)]
+#[s2_grpc(message_type = "flo_grpc::controller::MapInfo")]
+pub struct MapInfo {
+  /// Lowercase hex
+  pub sha1: String,
+  pub checksum: u32,
+  pub name: String,
+  pub description: String,
+  pub author: String,
+  pub path: String,
+  pub width: u32,
+  pub height: u32,
+  pub suggested_players: String,
+  pub players: Vec<MapPlayer>,
+  pub forces: Vec<MapForce>,
+  /// Empty if the map has no preview
+  pub preview_jpeg: Vec<u8>,
+}
+
+impl MapInfo {
+  pub fn from_w3map(path: String, map: &W3Map, checksum: &MapChecksum) -> Self {
+    let (width, height) = map.dimension();
+    MapInfo {
+      sha1: checksum.get_sha1_hex_string(),
+      checksum: checksum.xoro,
+      name: map.name().to_string(),
+      description: map.description().to_string(),
+      author: map.author().to_string(),
+      path,
+      width,
+      height,
+      suggested_players: map.suggested_players().to_string(),
+      players: map
+        .get_players()
+        .into_iter()
+        .map(|p| MapPlayer {
+          name: p.name.to_string(),
+          r#type: p.r#type,
+          race: p.race,
+          flags: p.flags,
+        })
+        .collect(),
+      forces: map
+        .get_forces()
+        .into_iter()
+        .map(|f| MapForce {
+          name: f.name.to_string(),
+          flags: f.flags,
+          player_set: f.player_set,
+        })
+        .collect(),
+      preview_jpeg: map.render_preview_jpeg(),
+    }
+  }
+
+  /// Checks the checksum and the player/force layout of a map sent by a client,
+  /// names are skipped because they can be localized
+  pub fn matches(&self, map: &Map) -> bool {
+    self.checksum == map.checksum
+      && self.players.len() == map.players.len()
+      && self.forces.len() == map.forces.len()
+      && self
+        .players
+        .iter()
+        .zip(&map.players)
+        .all(|(a, b)| a.r#type == b.r#type && a.race == b.race && a.flags == b.flags)
+      && self
+        .forces
+        .iter()
+        .zip(&map.forces)
+        .all(|(a, b)| a.flags == b.flags && a.player_set == b.player_set)
+  }
+}
+
+/// Validates a map sha1 sent by an api client and converts it to lowercase hex
+pub fn normalize_sha1(sha1: &str) -> Result<String> {
+  let sha1 = sha1.trim();
+  if sha1.len() != 40 || !sha1.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(Error::MapSha1Invalid);
+  }
+  Ok(sha1.to_ascii_lowercase())
+}
+
+/// A curated list of maps, games created by an api client with map pools must use one of their maps
+#[derive(Debug, S2ProtoPack, Clone)]
+#[s2_grpc(message_type = "flo_grpc::controller::MapPool")]
+pub struct MapPool {
+  pub id: i32,
+  #[s2_grpc(skip_pack)]
+  pub api_client_id: i32,
+  pub name: String,
+  pub map_sha1s: Vec<String>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, S2ProtoUnpack)]
+#[s2_grpc(message_type = "flo_grpc::controller::UpsertMapPoolRequest")]
+pub struct UpsertMapPoolParams {
+  pub name: String,
+  pub map_sha1s: Vec<String>,
+}
+
+#[test]
+fn test_normalize_sha1() {
+  let sha1 = "0123456789ABCDEFabcdef0123456789abcdef01";
+  assert_eq!(
+    normalize_sha1(sha1).unwrap(),
+    "0123456789abcdefabcdef0123456789abcdef01"
+  );
+  assert_eq!(normalize_sha1(&format!(" {} ", sha1)).unwrap().len(), 40);
+  assert!(normalize_sha1("0123").is_err());
+  assert!(normalize_sha1(&"g".repeat(40)).is_err());
+}
+
+#[test]
+fn test_map_info_matches() {
+  let player = |r#type| MapPlayer {
+    name: "Player".to_string(),
+    r#type,
+    race: 0,
+    flags: 0,
+  };
+  let info = MapInfo {
+    sha1: "00".repeat(20),
+    checksum: 1,
+    name: "(2)Map".to_string(),
+    description: String::new(),
+    author: String::new(),
+    path: "Maps\\(2)Map.w3x".to_string(),
+    width: 96,
+    height: 96,
+    suggested_players: String::new(),
+    players: vec![player(1), player(1)],
+    forces: vec![MapForce {
+      name: "Force 1".to_string(),
+      flags: 0,
+      player_set: 3,
+    }],
+    preview_jpeg: vec![],
+  };
+  let mut map = Map {
+    sha1: MapSha1([0; 20]),
+    checksum: 1,
+    name: info.name.clone(),
+    description: String::new(),
+    author: String::new(),
+    path: info.path.clone(),
+    width: 96,
+    height: 96,
+    players: vec![player(1), player(1)],
+    forces: vec![MapForce {
+      name: "Localized".to_string(),
+      flags: 0,
+      player_set: 3,
+    }],
+  };
+  assert!(info.matches(&map));
+
+  map.players[1].r#type = 2;
+  assert!(!info.matches(&map));
+
+  map.players.pop();
+  assert!(!info.matches(&map));
+}
@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::db::DbConn;
+use crate::error::*;
+use crate::game::ObserverChatPolicy;
+use crate::matchmaking::{MatchmakingQueue, UpsertQueueParams};
+use crate::schema::matchmaking_queue;
+
+pub fn get_all_queues(conn: &DbConn) -> Result<Vec<MatchmakingQueue>> {
+  matchmaking_queue::table
+    .order(matchmaking_queue::id)
+    .load::<Row>(conn)?
+    .into_iter()
+    .map(Row::into_queue)
+    .collect()
+}
+
+/// Creates or updates the queue with the same name
+pub fn upsert_queue(
+  conn: &DbConn,
+  api_client_id: i32,
+  params: UpsertQueueParams,
+) -> Result<MatchmakingQueue> {
+  use diesel::pg::upsert::excluded;
+  use matchmaking_queue::dsl;
+
+  if params.name.is_empty() {
+    return Err(Error::MatchmakingQueueInvalid("name is empty"));
+  }
+
+  if params.team_size < 1 || params.team_size > 12 {
+    return Err(Error::MatchmakingQueueInvalid("team size out of range"));
+  }
+
+  if params.maps.is_empty() {
+    return Err(Error::MatchmakingQueueInvalid("map pool is empty"));
+  }
+
+  if params
+    .maps
+    .iter()
+    .any(|map| map.players.len() < (params.team_size as usize) * 2)
+  {
+    return Err(Error::MatchmakingQueueInvalid(
+      "map does not have enough player slots",
+    ));
+  }
+
+  if let Some(map_pool_id) = params.map_pool_id {
+    crate::map::db::check_maps_in_pool(conn, api_client_id, map_pool_id, &params.maps)?;
+  }
+
+  let insert = Insert {
+    api_client_id,
+    name: &params.name,
+    mode: &params.mode,
+    team_size: params.team_size,
+    maps: serde_json::to_value(&params.maps)?,
+    enable_ping_equalizer: params.enable_ping_equalizer,
+    map_pool_id: params.map_pool_id,
+    observer_chat_policy: params.observer_chat_policy,
+  };
+
+  diesel::insert_into(matchmaking_queue::table)
+    .values(&insert)
+    .on_conflict((dsl::api_client_id, dsl::name))
+    .do_update()
+    .set((
+      dsl::mode.eq(excluded(dsl::mode)),
+      dsl::team_size.eq(excluded(dsl::team_size)),
+      dsl::maps.eq(excluded(dsl::maps)),
+      dsl::enable_ping_equalizer.eq(excluded(dsl::enable_ping_equalizer)),
+      dsl::map_pool_id.eq(excluded(dsl::map_pool_id)),
+      dsl::observer_chat_policy.eq(excluded(dsl::observer_chat_policy)),
+      dsl::updated_at.eq(diesel::dsl::now),
+    ))
+    .get_result::<Row>(conn)?
+    .into_queue()
+}
+
+pub fn remove_queue(conn: &DbConn, api_client_id: i32, name: &str) -> Result<i32> {
+  use matchmaking_queue::dsl;
+  diesel::delete(
+    matchmaking_queue::table.filter(dsl::api_client_id.eq(api_client_id).and(dsl::name.eq(name))),
+  )
+  .returning(dsl::id)
+  .get_result(conn)
+  .optional()?
+  .ok_or_else(|| Error::MatchmakingQueueNotFound)
+}
+
+#[derive(Debug, Queryable)]
+struct Row {
+  id: i32,
+  api_client_id: i32,
+  name: String,
+  mode: String,
+  team_size: i32,
+  maps: Value,
+  enable_ping_equalizer: bool,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+  map_pool_id: Option<i32>,
+  observer_chat_policy: ObserverChatPolicy,
+}
+
+impl Row {
+  fn into_queue(self) -> Result<MatchmakingQueue> {
+    Ok(MatchmakingQueue {
+      id: self.id,
+      api_client_id: self.api_client_id,
+      name: self.name,
+      mode: self.mode,
+      team_size: self.team_size,
+      maps: serde_json::from_value(self.maps)?,
+      enable_ping_equalizer: self.enable_ping_equalizer,
+      map_pool_id: self.map_pool_id,
+      observer_chat_policy: self.observer_chat_policy,
+      created_at: self.created_at,
+      updated_at: self.updated_at,
+    })
+  }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "matchmaking_queue"]
+struct Insert<'a> {
+  api_client_id: i32,
+  name: &'a str,
+  mode: &'a str,
+  team_size: i32,
+  maps: Value,
+  enable_ping_equalizer: bool,
+  map_pool_id: Option<i32>,
+  observer_chat_policy: ObserverChatPolicy,
+}
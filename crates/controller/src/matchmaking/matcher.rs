@@ -0,0 +1,296 @@
+//! Picks players that should play against each other from a queue.
+//!
+//! The longest waiting player is matched first. The accepted rating difference
+//! and RTT grow with the waiting time, so players with rare ratings or bad
+//! connections will eventually find a match.
+//! A party is one candidate and is always matched into the same team.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const RATING_WINDOW_BASE: f64 = 100.0;
+const RATING_WINDOW_PER_SEC: f64 = 5.0;
+const RATING_WINDOW_MAX: f64 = 800.0;
+
+const MAX_RTT_BASE: u32 = 120;
+const MAX_RTT_PER_SEC: u32 = 2;
+const MAX_RTT_MAX: u32 = 300;
+
+#[derive(Debug)]
+pub struct Candidate {
+  /// The player who queued, the leader of a party
+  pub player_id: i32,
+  /// Players in the same team, the player who queued included
+  pub player_ids: Vec<i32>,
+  /// Average rating of the players
+  pub rating: f64,
+  pub wait: Duration,
+  /// node id -> RTT in ms, the highest RTT of the players
+  pub rtt: BTreeMap<i32, u32>,
+}
+
+impl Candidate {
+  fn size(&self) -> usize {
+    self.player_ids.len()
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MatchResult {
+  pub node_id: i32,
+  pub teams: [Vec<i32>; 2],
+}
+
+/// Finds a match of `team_size` vs `team_size` players, hosted by one of `node_ids`
+pub fn find_match(
+  team_size: usize,
+  candidates: &[Candidate],
+  node_ids: &[i32],
+) -> Option<MatchResult> {
+  let match_size = team_size * 2;
+  let candidates: Vec<&Candidate> = candidates
+    .iter()
+    .filter(|c| c.size() > 0 && c.size() <= team_size)
+    .collect();
+  if match_size == 0 || candidates.iter().map(|c| c.size()).sum::<usize>() < match_size {
+    return None;
+  }
+
+  let mut anchors = candidates.clone();
+  anchors.sort_by(|a, b| b.wait.cmp(&a.wait));
+
+  for anchor in anchors {
+    let wait_secs = anchor.wait.as_secs();
+    let rating_window =
+      (RATING_WINDOW_BASE + RATING_WINDOW_PER_SEC * wait_secs as f64).min(RATING_WINDOW_MAX);
+    let max_rtt = std::cmp::min(
+      MAX_RTT_BASE.saturating_add(MAX_RTT_PER_SEC.saturating_mul(wait_secs as u32)),
+      MAX_RTT_MAX,
+    );
+
+    let mut nodes: Vec<i32> = node_ids
+      .iter()
+      .cloned()
+      .filter(|id| anchor.rtt.get(id).map(|v| *v <= max_rtt) == Some(true))
+      .collect();
+    if nodes.is_empty() {
+      continue;
+    }
+
+    let mut pool: Vec<&Candidate> = candidates
+      .iter()
+      .cloned()
+      .filter(|c| {
+        c.player_id != anchor.player_id && (c.rating - anchor.rating).abs() <= rating_window
+      })
+      .collect();
+    pool.sort_by(|a, b| {
+      let da = (a.rating - anchor.rating).abs();
+      let db = (b.rating - anchor.rating).abs();
+      da.partial_cmp(&db)
+        .unwrap_or(std::cmp::Ordering::Equal)
+        .then_with(|| b.wait.cmp(&a.wait))
+    });
+
+    let mut selected = vec![anchor];
+    let mut size = anchor.size();
+    for candidate in pool {
+      if size == match_size {
+        break;
+      }
+      if size + candidate.size() > match_size {
+        continue;
+      }
+      let candidate_nodes: Vec<i32> = nodes
+        .iter()
+        .cloned()
+        .filter(|id| candidate.rtt.get(id).map(|v| *v <= max_rtt) == Some(true))
+        .collect();
+      if candidate_nodes.is_empty() {
+        continue;
+      }
+      selected.push(candidate);
+      // the parties must still fit into the teams
+      if candidate.size() > 1 && split_teams(&selected, team_size).is_none() {
+        selected.pop();
+        continue;
+      }
+      nodes = candidate_nodes;
+      size += candidate.size();
+    }
+
+    if size < match_size {
+      continue;
+    }
+
+    let node_id = nodes.into_iter().min_by_key(|id| {
+      selected
+        .iter()
+        .filter_map(|c| c.rtt.get(id).cloned())
+        .max()
+        .unwrap_or_default()
+    })?;
+
+    return Some(MatchResult {
+      node_id,
+      teams: split_teams(&selected, team_size)?,
+    });
+  }
+
+  None
+}
+
+/// Tries every split of the parties that fits into the teams, the remaining players
+/// are added by rating to the team with the lower total rating.
+/// Keeps the split with the lowest rating difference,
+/// returns `None` if the parties don't fit into 2 teams of `team_size`.
+fn split_teams(selected: &[&Candidate], team_size: usize) -> Option<[Vec<i32>; 2]> {
+  let parties: Vec<&Candidate> = selected.iter().cloned().filter(|c| c.size() > 1).collect();
+  let mut players: Vec<&Candidate> = selected.iter().cloned().filter(|c| c.size() == 1).collect();
+  players.sort_by(|a, b| {
+    b.rating
+      .partial_cmp(&a.rating)
+      .unwrap_or(std::cmp::Ordering::Equal)
+  });
+
+  let mut best: Option<(f64, [Vec<i32>; 2])> = None;
+  // bit i set: the i-th party plays in the first team
+  for mask in 0..(1u32 << parties.len()) {
+    let mut teams = [vec![], vec![]];
+    let mut ratings = [0.0, 0.0];
+    for (i, party) in parties.iter().enumerate() {
+      let team = if mask & (1 << i) != 0 { 0 } else { 1 };
+      teams[team].extend(party.player_ids.iter().cloned());
+      ratings[team] += party.rating * party.size() as f64;
+    }
+    if teams.iter().any(|team| team.len() > team_size) {
+      continue;
+    }
+    for player in &players {
+      let team = if teams[1].len() >= team_size
+        || (teams[0].len() < team_size && ratings[0] <= ratings[1])
+      {
+        0
+      } else {
+        1
+      };
+      teams[team].push(player.player_id);
+      ratings[team] += player.rating;
+    }
+    let diff = (ratings[0] - ratings[1]).abs();
+    if best.as_ref().map(|(v, _)| diff < *v).unwrap_or(true) {
+      best = Some((diff, teams));
+    }
+  }
+  best.map(|(_, teams)| teams)
+}
+
+#[test]
+fn test_find_match() {
+  fn candidate(player_id: i32, rating: f64, wait_secs: u64, rtt: &[(i32, u32)]) -> Candidate {
+    Candidate {
+      player_id,
+      player_ids: vec![player_id],
+      rating,
+      wait: Duration::from_secs(wait_secs),
+      rtt: rtt.iter().cloned().collect(),
+    }
+  }
+
+  // not enough players
+  assert_eq!(
+    find_match(1, &[candidate(1, 1500.0, 0, &[(1, 50)])], &[1]),
+    None
+  );
+
+  // rating too far apart
+  let candidates = [
+    candidate(1, 1500.0, 0, &[(1, 50)]),
+    candidate(2, 2000.0, 0, &[(1, 50)]),
+  ];
+  assert_eq!(find_match(1, &candidates, &[1]), None);
+
+  // the rating window grows with the waiting time
+  let candidates = [
+    candidate(1, 1500.0, 120, &[(1, 50)]),
+    candidate(2, 2000.0, 0, &[(1, 50)]),
+  ];
+  assert_eq!(
+    find_match(1, &candidates, &[1]),
+    Some(MatchResult {
+      node_id: 1,
+      teams: [vec![2], vec![1]]
+    })
+  );
+
+  // picks the node with the lowest max rtt, skipping nodes unknown to a player
+  let candidates = [
+    candidate(1, 1500.0, 0, &[(1, 30), (2, 80), (3, 10)]),
+    candidate(2, 1550.0, 0, &[(1, 100), (2, 60)]),
+  ];
+  assert_eq!(find_match(1, &candidates, &[1, 2, 3]).unwrap().node_id, 2);
+
+  // 2v2 teams are balanced
+  let candidates = [
+    candidate(1, 1600.0, 10, &[(1, 50)]),
+    candidate(2, 1500.0, 5, &[(1, 50)]),
+    candidate(3, 1450.0, 0, &[(1, 50)]),
+    candidate(4, 1550.0, 0, &[(1, 50)]),
+    candidate(5, 1550.0, 0, &[(1, 500)]),
+  ];
+  assert_eq!(
+    find_match(2, &candidates, &[1]),
+    Some(MatchResult {
+      node_id: 1,
+      teams: [vec![1, 3], vec![4, 2]]
+    })
+  );
+
+  fn party(player_ids: &[i32], wait_secs: u64) -> Candidate {
+    Candidate {
+      player_id: player_ids[0],
+      player_ids: player_ids.to_vec(),
+      rating: 1500.0,
+      wait: Duration::from_secs(wait_secs),
+      rtt: vec![(1, 50)].into_iter().collect(),
+    }
+  }
+
+  // a party is matched into the same team
+  let candidates = [
+    party(&[1, 2], 10),
+    candidate(3, 1600.0, 0, &[(1, 50)]),
+    candidate(4, 1400.0, 0, &[(1, 50)]),
+  ];
+  assert_eq!(
+    find_match(2, &candidates, &[1]),
+    Some(MatchResult {
+      node_id: 1,
+      teams: [vec![3, 4], vec![1, 2]]
+    })
+  );
+
+  // a party larger than a team is never matched
+  let candidates = [
+    party(&[1, 2, 3], 10),
+    candidate(4, 1500.0, 0, &[(1, 50)]),
+    candidate(5, 1500.0, 0, &[(1, 50)]),
+  ];
+  assert_eq!(find_match(2, &candidates, &[1]), None);
+
+  // parties that can't be split into the teams are skipped
+  let candidates = [
+    party(&[1, 2], 10),
+    party(&[3, 4], 5),
+    party(&[5, 6], 4),
+    candidate(7, 1500.0, 3, &[(1, 50)]),
+    candidate(8, 1500.0, 2, &[(1, 50)]),
+  ];
+  assert_eq!(
+    find_match(3, &candidates, &[1]),
+    Some(MatchResult {
+      node_id: 1,
+      teams: [vec![1, 2, 7], vec![3, 4, 8]]
+    })
+  );
+}
@@ -0,0 +1,6 @@
+pub mod db;
+mod matcher;
+pub(crate) mod state;
+mod types;
+
+pub use types::*;
@@ -0,0 +1,828 @@
+use crate::error::*;
+use crate::game::db::CreateGameAsBotParams;
+use crate::game::state::cancel::CancelGame;
+use crate::game::state::create::CreateGameAsBot;
+use crate::game::state::node::{is_node_full, list_available_node_ids, GetNodeGames};
+use crate::game::state::registry::Remove;
+use crate::game::state::start::{StartGameCheckAsBot, StartGameCheckAsBotResult};
+use crate::game::state::GameRegistry;
+use crate::game::{Computer, CreateGameSlot, Race, SlotSettings, SlotStatus};
+use crate::matchmaking::matcher::{find_match, Candidate, MatchResult};
+use crate::matchmaking::{MatchmakingQueue, QueueEntry, QueueMember, UpsertQueueParams};
+use crate::node::NodeRegistry;
+use crate::player::db::GameBanCheck;
+use crate::player::state::ping::GetPlayersPingSnapshot;
+use crate::player::state::sender::PlayerRegistryHandle;
+use crate::player::state::PlayerRegistry;
+use crate::state::{ActorMapExt, Data};
+use bs_diesel_utils::ExecutorRef;
+use flo_net::packet::FloPacket;
+use flo_net::proto::flo_connect::{PacketMatchmakingMatchFound, PacketMatchmakingQueueUpdate};
+use flo_state::{async_trait, Actor, Addr, Context, Handler, Message, RegistryRef, Service};
+use flo_types::ping::PingStats;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tokio::time::sleep;
+
+const MATCH_INTERVAL: Duration = Duration::from_secs(3);
+
+pub struct MatchmakingRegistry {
+  db: ExecutorRef,
+  games: Addr<GameRegistry>,
+  players: Addr<PlayerRegistry>,
+  player_packet_sender: PlayerRegistryHandle,
+  nodes: Addr<NodeRegistry>,
+  queues: BTreeMap<i32, QueueState>,
+  /// player id -> queue id
+  player_queue_map: BTreeMap<i32, i32>,
+}
+
+struct QueueState {
+  queue: MatchmakingQueue,
+  /// The api player of the queue owner, used as the host of the created games
+  api_player_id: Option<i32>,
+  entries: Vec<QueueEntry>,
+}
+
+impl MatchmakingRegistry {
+  fn find_queue_id(&self, api_client_id: i32, name: &str) -> Option<i32> {
+    self
+      .queues
+      .values()
+      .find(|q| q.queue.api_client_id == api_client_id && q.queue.name == name)
+      .map(|q| q.queue.id)
+  }
+
+  /// Removes the entry the player is queued with, the whole party if the player is in one
+  fn remove_entry(&mut self, player_id: i32) -> Option<(String, QueueEntry)> {
+    let queue_id = self.player_queue_map.remove(&player_id)?;
+    let state = self.queues.get_mut(&queue_id)?;
+    let idx = state.entries.iter().position(|e| e.contains(player_id))?;
+    let entry = state.entries.remove(idx);
+    for id in entry.player_ids() {
+      self.player_queue_map.remove(&id);
+    }
+    Some((state.queue.name.clone(), entry))
+  }
+
+  async fn send_queue_update(&self, player_ids: Vec<i32>, queue_name: &str, queued: bool) {
+    if player_ids.is_empty() {
+      return;
+    }
+    let frame = match (PacketMatchmakingQueueUpdate {
+      queue_name: queue_name.to_string(),
+      queued,
+    })
+    .encode_as_frame()
+    {
+      Ok(frame) => frame,
+      Err(err) => {
+        tracing::error!("encode queue update: {}", err);
+        return;
+      }
+    };
+    self
+      .player_packet_sender
+      .broadcast(player_ids, frame)
+      .await
+      .ok();
+  }
+
+  async fn find_matches(&mut self, ctx: &mut Context<Self>) -> Result<()> {
+    let player_ids: Vec<i32> = self.player_queue_map.keys().cloned().collect();
+    if player_ids.is_empty() {
+      return Ok(());
+    }
+
+    let ping_map = self
+      .players
+      .send(GetPlayersPingSnapshot {
+        players: player_ids,
+      })
+      .await?
+      .map;
+    let mut node_games = self.games.send(GetNodeGames).await?;
+    let mut node_ids = list_available_node_ids(&self.nodes, &node_games).await?;
+
+    // players disconnected from the controller can't receive the game
+    let disconnected: Vec<i32> = self
+      .player_queue_map
+      .keys()
+      .filter(|id| !ping_map.contains_key(*id))
+      .cloned()
+      .collect();
+    for player_id in disconnected {
+      if let Some((queue_name, entry)) = self.remove_entry(player_id) {
+        tracing::debug!(
+          player_id,
+          "removed disconnected player from queue {}",
+          queue_name
+        );
+        // the rest of the party
+        let player_ids = entry.player_ids().filter(|id| *id != player_id).collect();
+        self.send_queue_update(player_ids, &queue_name, false).await;
+      }
+    }
+
+    let now = Instant::now();
+    let mut matches = vec![];
+    for state in self.queues.values_mut() {
+      let api_player_id = if let Some(id) = state.api_player_id {
+        id
+      } else {
+        continue;
+      };
+      if state.entries.len() < state.queue.match_size() {
+        continue;
+      }
+      loop {
+        let candidates: Vec<Candidate> = state
+          .entries
+          .iter()
+          .filter(|entry| entry.is_ready(now))
+          .map(|entry| Candidate {
+            player_id: entry.player_id,
+            player_ids: entry.player_ids().collect(),
+            rating: entry.rating(),
+            wait: now.saturating_duration_since(entry.enqueued_at),
+            rtt: entry_rtt(&ping_map, entry),
+          })
+          .collect();
+        let result = if let Some(result) =
+          find_match(state.queue.team_size as usize, &candidates, &node_ids)
+        {
+          result
+        } else {
+          break;
+        };
+        // games created in this pass are not in the registry yet
+        *node_games.entry(result.node_id).or_default() += 1;
+        if is_node_full(&node_games, result.node_id) {
+          node_ids.retain(|id| *id != result.node_id);
+        }
+        let mut entries = vec![];
+        for player_id in result.teams.iter().flatten() {
+          if let Some(idx) = state.entries.iter().position(|e| e.contains(*player_id)) {
+            entries.push(state.entries.remove(idx));
+          }
+          self.player_queue_map.remove(player_id);
+        }
+        matches.push(PendingMatch {
+          queue: state.queue.clone(),
+          api_player_id,
+          entries,
+          result,
+        });
+      }
+    }
+
+    for item in matches {
+      tracing::info!(
+        queue_id = item.queue.id,
+        node_id = item.result.node_id,
+        "match found: {:?}",
+        item.result.teams
+      );
+      let start_ctx = StartMatchContext {
+        games: self.games.clone(),
+        player_packet_sender: self.player_packet_sender.clone(),
+        addr: ctx.addr(),
+      };
+      spawn_start_match(start_ctx, item);
+    }
+
+    Ok(())
+  }
+}
+
+/// node id -> the highest RTT of the players in the entry,
+/// nodes without a RTT for every player are left out
+fn entry_rtt(
+  ping_map: &BTreeMap<i32, BTreeMap<i32, PingStats>>,
+  entry: &QueueEntry,
+) -> BTreeMap<i32, u32> {
+  let mut rtt: Option<BTreeMap<i32, u32>> = None;
+  for player_id in entry.player_ids() {
+    let player_rtt: BTreeMap<i32, u32> = ping_map
+      .get(&player_id)
+      .map(|map| {
+        map
+          .iter()
+          .filter_map(|(node_id, stats)| Some((*node_id, stats.avg.or(stats.current)?)))
+          .collect()
+      })
+      .unwrap_or_default();
+    rtt = Some(match rtt {
+      None => player_rtt,
+      Some(rtt) => rtt
+        .into_iter()
+        .filter_map(|(node_id, v)| Some((node_id, std::cmp::max(v, *player_rtt.get(&node_id)?))))
+        .collect(),
+    });
+  }
+  rtt.unwrap_or_default()
+}
+
+fn spawn_start_match(ctx: StartMatchContext, item: PendingMatch) {
+  tokio::spawn(async move {
+    let queue_id = item.queue.id;
+    let entries = item.entries.clone();
+    match ctx.start(item).await {
+      Ok(requeue) => {
+        if !requeue.is_empty() {
+          // a party is only requeued if all of its members responded
+          let (entries, dropped): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .filter(|e| e.player_ids().any(|id| requeue.contains(&id)))
+            .partition(|e| e.player_ids().all(|id| requeue.contains(&id)));
+          ctx
+            .addr
+            .notify(Requeue {
+              queue_id,
+              entries,
+              dropped: dropped
+                .iter()
+                .flat_map(|e| e.player_ids())
+                .filter(|id| requeue.contains(id))
+                .collect(),
+              failed: false,
+            })
+            .await
+            .ok();
+        }
+      }
+      Err(err) => {
+        tracing::error!(queue_id, "start match: {}", err);
+        // the failed player isn't known, requeue everyone with a backoff
+        // so a failure that repeats doesn't retry the same match forever
+        ctx
+          .addr
+          .notify(Requeue {
+            queue_id,
+            entries,
+            dropped: vec![],
+            failed: true,
+          })
+          .await
+          .ok();
+      }
+    }
+  });
+}
+
+struct PendingMatch {
+  queue: MatchmakingQueue,
+  api_player_id: i32,
+  entries: Vec<QueueEntry>,
+  result: MatchResult,
+}
+
+struct StartMatchContext {
+  games: Addr<GameRegistry>,
+  player_packet_sender: PlayerRegistryHandle,
+  addr: Addr<MatchmakingRegistry>,
+}
+
+impl StartMatchContext {
+  /// Creates and starts the game, returns the players that should be put back into the queue
+  async fn start(
+    &self,
+    PendingMatch {
+      queue,
+      api_player_id,
+      entries,
+      result,
+    }: PendingMatch,
+  ) -> Result<BTreeSet<i32>> {
+    let map = queue.maps[rand::random::<usize>() % queue.maps.len()].clone();
+    let races: BTreeMap<i32, Race> = entries
+      .iter()
+      .flat_map(|e| e.members.iter())
+      .map(|m| (m.player_id, m.race))
+      .collect();
+    let mut slots = vec![];
+    for (team, player_ids) in result.teams.iter().enumerate() {
+      for player_id in player_ids {
+        let color = slots.len() as i32;
+        slots.push(CreateGameSlot {
+          player_id: Some(*player_id),
+          settings: SlotSettings {
+            team: team as i32,
+            color,
+            computer: Computer::Easy,
+            handicap: 100,
+            status: SlotStatus::Occupied,
+            race: races.get(player_id).cloned().unwrap_or(Race::Random),
+          },
+          is_referee: false,
+        });
+      }
+    }
+
+    let game = self
+      .games
+      .send(CreateGameAsBot {
+        api_client_id: queue.api_client_id,
+        api_player_id,
+        params: CreateGameAsBotParams {
+          name: format!("{} #{}", queue.name, rand::random::<u16>()),
+          map,
+          is_private: true,
+          is_live: false,
+          node_id: result.node_id,
+          slots,
+          mask_player_names: false,
+          enable_ping_equalizer: queue.enable_ping_equalizer,
+          enable_adaptive_step: false,
+          observer_chat_policy: queue.observer_chat_policy,
+          flo_tv_delay_override_secs: None,
+          rating_mode: Some(queue.mode.clone()),
+        },
+      })
+      .await??;
+    let game_id = game.id;
+
+    match self
+      .start_game(&queue, api_player_id, game_id, &entries, result.node_id)
+      .await
+    {
+      Ok(requeue) => Ok(requeue),
+      Err(err) => {
+        // the players are requeued by the caller, the game must not be left behind
+        if let Err(cancel_err) = self.cancel_game(api_player_id, game_id).await {
+          tracing::error!(game_id, queue_id = queue.id, "cancel match: {}", cancel_err);
+        }
+        Err(err)
+      }
+    }
+  }
+
+  async fn start_game(
+    &self,
+    queue: &MatchmakingQueue,
+    api_player_id: i32,
+    game_id: i32,
+    entries: &[QueueEntry],
+    node_id: i32,
+  ) -> Result<BTreeSet<i32>> {
+    let player_ids: Vec<i32> = entries.iter().flat_map(|e| e.player_ids()).collect();
+
+    self
+      .player_packet_sender
+      .broadcast(
+        player_ids.clone(),
+        PacketMatchmakingMatchFound {
+          queue_name: queue.name.clone(),
+          game_id,
+          node_id,
+        }
+        .encode_as_frame()?,
+      )
+      .await?;
+
+    let (tx, rx) = oneshot::channel();
+    self
+      .games
+      .send_to(game_id, StartGameCheckAsBot { tx })
+      .await?;
+
+    match rx.await.map_err(|_| Error::TaskCancelled)? {
+      StartGameCheckAsBotResult::Started(_) => {
+        tracing::info!(game_id, queue_id = queue.id, "match started");
+        Ok(BTreeSet::new())
+      }
+      StartGameCheckAsBotResult::Rejected(pkt) => {
+        tracing::warn!(
+          game_id,
+          queue_id = queue.id,
+          "match start rejected: {}",
+          pkt.message
+        );
+        self.cancel_game(api_player_id, game_id).await?;
+        // players failed to respond are removed from the queue
+        Ok(
+          player_ids
+            .into_iter()
+            .filter(|id| pkt.player_client_info_map.contains_key(id))
+            .collect(),
+        )
+      }
+    }
+  }
+
+  async fn cancel_game(&self, api_player_id: i32, game_id: i32) -> Result<()> {
+    self
+      .games
+      .send_to(
+        game_id,
+        CancelGame {
+          player_id: Some(api_player_id),
+        },
+      )
+      .await?;
+    self.games.send(Remove { game_id }).await?;
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl Actor for MatchmakingRegistry {
+  async fn started(&mut self, ctx: &mut Context<Self>) {
+    let addr = ctx.addr();
+    ctx.spawn(async move {
+      loop {
+        sleep(MATCH_INTERVAL).await;
+        if addr.notify(FindMatches).await.is_err() {
+          break;
+        }
+      }
+    });
+  }
+}
+
+#[async_trait]
+impl Service<Data> for MatchmakingRegistry {
+  type Error = Error;
+
+  async fn create(registry: &mut RegistryRef<Data>) -> Result<Self, Self::Error> {
+    let db = registry.data().db.clone();
+    let games = registry.resolve::<GameRegistry>().await?;
+    let players = registry.resolve::<PlayerRegistry>().await?;
+    let nodes = registry.resolve::<NodeRegistry>().await?;
+    let queues = db
+      .exec(|conn| crate::matchmaking::db::get_all_queues(conn))
+      .await?;
+    Ok(Self {
+      db,
+      games,
+      player_packet_sender: players.clone().into(),
+      players,
+      nodes,
+      queues: queues
+        .into_iter()
+        .map(|queue| {
+          (
+            queue.id,
+            QueueState {
+              queue,
+              api_player_id: None,
+              entries: vec![],
+            },
+          )
+        })
+        .collect(),
+      player_queue_map: BTreeMap::new(),
+    })
+  }
+}
+
+struct FindMatches;
+
+impl Message for FindMatches {
+  type Result = ();
+}
+
+#[async_trait]
+impl Handler<FindMatches> for MatchmakingRegistry {
+  async fn handle(&mut self, ctx: &mut Context<Self>, _: FindMatches) {
+    if let Err(err) = self.find_matches(ctx).await {
+      tracing::error!("find matches: {}", err);
+    }
+  }
+}
+
+struct Requeue {
+  queue_id: i32,
+  entries: Vec<QueueEntry>,
+  /// Players removed from the queue with a party member who failed to respond
+  dropped: Vec<i32>,
+  /// The match failed to start
+  failed: bool,
+}
+
+impl Message for Requeue {
+  type Result = ();
+}
+
+#[async_trait]
+impl Handler<Requeue> for MatchmakingRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    Requeue {
+      queue_id,
+      entries,
+      dropped,
+      failed,
+    }: Requeue,
+  ) {
+    let queue_name = self.queues.get(&queue_id).map(|q| q.queue.name.clone());
+    let now = Instant::now();
+    let mut requeued = vec![];
+    let mut removed = dropped;
+    for mut entry in entries {
+      if failed && !entry.record_start_failure(now, MATCH_INTERVAL) {
+        tracing::warn!(
+          queue_id,
+          player_id = entry.player_id,
+          "removed from queue after {} failed match starts",
+          entry.start_failures
+        );
+        removed.extend(entry.player_ids());
+        continue;
+      }
+      let queued = entry
+        .player_ids()
+        .any(|id| self.player_queue_map.contains_key(&id));
+      let state = match self.queues.get_mut(&queue_id) {
+        Some(state) if !queued => state,
+        _ => {
+          removed.extend(entry.player_ids());
+          continue;
+        }
+      };
+      // keep the original enqueue time, so they will be matched first
+      for player_id in entry.player_ids() {
+        self.player_queue_map.insert(player_id, queue_id);
+        requeued.push(player_id);
+      }
+      state.entries.push(entry);
+    }
+    if let Some(name) = queue_name {
+      self.send_queue_update(requeued, &name, true).await;
+      self.send_queue_update(removed, &name, false).await;
+    }
+  }
+}
+
+pub struct UpsertQueue {
+  pub api_client_id: i32,
+  pub params: UpsertQueueParams,
+}
+
+impl Message for UpsertQueue {
+  type Result = Result<MatchmakingQueue>;
+}
+
+#[async_trait]
+impl Handler<UpsertQueue> for MatchmakingRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    UpsertQueue {
+      api_client_id,
+      params,
+    }: UpsertQueue,
+  ) -> Result<MatchmakingQueue> {
+    let queue = self
+      .db
+      .exec(move |conn| crate::matchmaking::db::upsert_queue(conn, api_client_id, params))
+      .await?;
+    match self.queues.get_mut(&queue.id) {
+      Some(state) => {
+        state.queue = queue.clone();
+      }
+      None => {
+        self.queues.insert(
+          queue.id,
+          QueueState {
+            queue: queue.clone(),
+            api_player_id: None,
+            entries: vec![],
+          },
+        );
+      }
+    }
+    Ok(queue)
+  }
+}
+
+pub struct RemoveQueue {
+  pub api_client_id: i32,
+  pub name: String,
+}
+
+impl Message for RemoveQueue {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<RemoveQueue> for MatchmakingRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    RemoveQueue {
+      api_client_id,
+      name,
+    }: RemoveQueue,
+  ) -> Result<()> {
+    let queue_id = self
+      .db
+      .exec(move |conn| crate::matchmaking::db::remove_queue(conn, api_client_id, &name))
+      .await?;
+    if let Some(state) = self.queues.remove(&queue_id) {
+      let player_ids: Vec<i32> = state.entries.iter().flat_map(|e| e.player_ids()).collect();
+      for player_id in &player_ids {
+        self.player_queue_map.remove(player_id);
+      }
+      self
+        .send_queue_update(player_ids, &state.queue.name, false)
+        .await;
+    }
+    Ok(())
+  }
+}
+
+pub struct ListQueues {
+  pub api_client_id: i32,
+}
+
+impl Message for ListQueues {
+  type Result = Vec<MatchmakingQueue>;
+}
+
+#[async_trait]
+impl Handler<ListQueues> for MatchmakingRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    ListQueues { api_client_id }: ListQueues,
+  ) -> Vec<MatchmakingQueue> {
+    self
+      .queues
+      .values()
+      .filter(|q| q.queue.api_client_id == api_client_id)
+      .map(|q| q.queue.clone())
+      .collect()
+  }
+}
+
+pub struct Enqueue {
+  pub api_client_id: i32,
+  pub api_player_id: i32,
+  pub queue_name: String,
+  pub player_id: i32,
+  /// Uses the stored rating of the queue mode if not provided
+  pub rating: Option<f64>,
+  pub race: Race,
+  /// The other members of the player's party, queued with the stored rating and a random race
+  pub party_member_ids: Vec<i32>,
+}
+
+impl Message for Enqueue {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<Enqueue> for MatchmakingRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    Enqueue {
+      api_client_id,
+      api_player_id,
+      queue_name,
+      player_id,
+      rating,
+      race,
+      party_member_ids,
+    }: Enqueue,
+  ) -> Result<()> {
+    let queue_id = self
+      .find_queue_id(api_client_id, &queue_name)
+      .ok_or_else(|| Error::MatchmakingQueueNotFound)?;
+
+    let (mode, maps, team_size) = self
+      .queues
+      .get(&queue_id)
+      .map(|q| {
+        (
+          q.queue.mode.clone(),
+          q.queue.maps.clone(),
+          q.queue.team_size as usize,
+        )
+      })
+      .unwrap_or_default();
+    // the party plays in the same team
+    if party_member_ids.len() + 1 > team_size {
+      return Err(Error::PartyTooLarge);
+    }
+
+    let player_ids: Vec<i32> = std::iter::once(player_id)
+      .chain(party_member_ids.iter().cloned())
+      .collect();
+    let members = {
+      let player_ids = player_ids.clone();
+      self
+        .db
+        .exec(move |conn| -> Result<_> {
+          let mut members = Vec::with_capacity(player_ids.len());
+          for id in player_ids {
+            // any map of the queue can be picked for the match
+            for map in &maps {
+              crate::player::db::check_game_ban(conn, id, GameBanCheck::Play, &map.sha1)?;
+            }
+            let rating = match rating {
+              Some(rating) if id == player_id => rating,
+              _ => crate::rating::db::get_rating(conn, id, &mode)?,
+            };
+            let race = if id == player_id { race } else { Race::Random };
+            members.push(QueueMember {
+              player_id: id,
+              rating,
+              race,
+            });
+          }
+          Ok(members)
+        })
+        .await?
+    };
+
+    // a player can only wait in one queue
+    for id in &player_ids {
+      if let Some((prev_queue_name, entry)) = self.remove_entry(*id) {
+        let notify: Vec<i32> = entry
+          .player_ids()
+          .filter(|member_id| prev_queue_name != queue_name || !player_ids.contains(member_id))
+          .collect();
+        self
+          .send_queue_update(notify, &prev_queue_name, false)
+          .await;
+      }
+    }
+
+    if let Some(state) = self.queues.get_mut(&queue_id) {
+      state.api_player_id = Some(api_player_id);
+      state.entries.push(QueueEntry::with_members(members));
+      for id in &player_ids {
+        self.player_queue_map.insert(*id, queue_id);
+      }
+    }
+
+    self.send_queue_update(player_ids, &queue_name, true).await;
+
+    Ok(())
+  }
+}
+
+pub struct Dequeue {
+  pub api_client_id: i32,
+  pub player_id: i32,
+}
+
+impl Message for Dequeue {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<Dequeue> for MatchmakingRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    Dequeue {
+      api_client_id,
+      player_id,
+    }: Dequeue,
+  ) -> Result<()> {
+    let owned = self
+      .player_queue_map
+      .get(&player_id)
+      .and_then(|id| self.queues.get(id))
+      .map(|q| q.queue.api_client_id == api_client_id)
+      .unwrap_or_default();
+    if !owned {
+      return Err(Error::PlayerNotInMatchmakingQueue);
+    }
+
+    if let Some((queue_name, entry)) = self.remove_entry(player_id) {
+      self
+        .send_queue_update(entry.player_ids().collect(), &queue_name, false)
+        .await;
+    }
+
+    Ok(())
+  }
+}
+
+/// Removes the entries of the players from the queues, sent when a party changes
+pub struct DequeuePlayers {
+  pub player_ids: Vec<i32>,
+}
+
+impl Message for DequeuePlayers {
+  type Result = ();
+}
+
+#[async_trait]
+impl Handler<DequeuePlayers> for MatchmakingRegistry {
+  async fn handle(&mut self, _: &mut Context<Self>, DequeuePlayers { player_ids }: DequeuePlayers) {
+    for player_id in player_ids {
+      if let Some((queue_name, entry)) = self.remove_entry(player_id) {
+        self
+          .send_queue_update(entry.player_ids().collect(), &queue_name, false)
+          .await;
+      }
+    }
+  }
+}
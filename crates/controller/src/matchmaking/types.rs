@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use s2_grpc_utils::{S2ProtoPack, S2ProtoUnpack};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::game::{ObserverChatPolicy, Race};
+use crate::map::Map;
+
+#[derive(Debug, Serialize, Deserialize, S2ProtoPack, Clone)]
+#[s2_grpc(message_type = "flo_grpc::controller::MatchmakingQueue")]
+pub struct MatchmakingQueue {
+  pub id: i32,
+  #[s2_grpc(skip_pack)]
+  pub api_client_id: i32,
+  pub name: String,
+  pub mode: String,
+  pub team_size: i32,
+  pub maps: Vec<Map>,
+  pub enable_ping_equalizer: bool,
+  /// Maps of the queue must be in this pool
+  pub map_pool_id: Option<i32>,
+  #[s2_grpc(proto_enum)]
+  pub observer_chat_policy: ObserverChatPolicy,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+impl MatchmakingQueue {
+  /// Number of players in a match, the queue always matches 2 teams
+  pub fn match_size(&self) -> usize {
+    (self.team_size as usize) * 2
+  }
+}
+
+#[derive(Debug, S2ProtoUnpack)]
+#[s2_grpc(message_type = "flo_grpc::controller::UpsertMatchmakingQueueRequest")]
+pub struct UpsertQueueParams {
+  pub name: String,
+  pub mode: String,
+  pub team_size: i32,
+  pub maps: Vec<Map>,
+  pub enable_ping_equalizer: bool,
+  pub map_pool_id: Option<i32>,
+  #[s2_grpc(proto_enum)]
+  pub observer_chat_policy: ObserverChatPolicy,
+}
+
+/// Entries are removed from the queue after this many failed match starts
+const MAX_START_FAILURES: u32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct QueueMember {
+  pub player_id: i32,
+  pub rating: f64,
+  pub race: Race,
+}
+
+/// A player or a party waiting in a queue, a party is queued by its leader
+/// and its members are matched into the same team
+#[derive(Debug, Clone)]
+pub struct QueueEntry {
+  /// The player who queued
+  pub player_id: i32,
+  /// All players of the entry, the player who queued first
+  pub members: Vec<QueueMember>,
+  pub enqueued_at: Instant,
+  pub start_failures: u32,
+  /// The entry is not matched before this time after a failed match start
+  pub retry_at: Option<Instant>,
+}
+
+impl QueueEntry {
+  pub fn new(player_id: i32, rating: f64, race: Race) -> Self {
+    Self::with_members(vec![QueueMember {
+      player_id,
+      rating,
+      race,
+    }])
+  }
+
+  /// `members` must not be empty, the first member is the player who queued
+  pub fn with_members(members: Vec<QueueMember>) -> Self {
+    Self {
+      player_id: members[0].player_id,
+      members,
+      enqueued_at: Instant::now(),
+      start_failures: 0,
+      retry_at: None,
+    }
+  }
+
+  pub fn player_ids(&self) -> impl Iterator<Item = i32> + '_ {
+    self.members.iter().map(|m| m.player_id)
+  }
+
+  pub fn contains(&self, player_id: i32) -> bool {
+    self.members.iter().any(|m| m.player_id == player_id)
+  }
+
+  /// Average rating of the members
+  pub fn rating(&self) -> f64 {
+    self.members.iter().map(|m| m.rating).sum::<f64>() / self.members.len() as f64
+  }
+
+  pub fn is_ready(&self, now: Instant) -> bool {
+    self.retry_at.map(|t| t <= now).unwrap_or(true)
+  }
+
+  /// Records a failed match start and backs off exponentially,
+  /// returns false if the entry should be removed from the queue
+  pub fn record_start_failure(&mut self, now: Instant, interval: Duration) -> bool {
+    self.start_failures += 1;
+    if self.start_failures >= MAX_START_FAILURES {
+      return false;
+    }
+    self.retry_at = Some(now + interval * 2u32.pow(self.start_failures));
+    true
+  }
+}
+
+#[test]
+fn test_queue_entry_start_failure() {
+  let interval = Duration::from_secs(3);
+  let now = Instant::now();
+  let mut entry = QueueEntry::new(1, 1500.0, Race::Random);
+  assert!(entry.is_ready(now));
+
+  assert!(entry.record_start_failure(now, interval));
+  assert!(!entry.is_ready(now + Duration::from_secs(5)));
+  assert!(entry.is_ready(now + Duration::from_secs(6)));
+
+  assert!(entry.record_start_failure(now, interval));
+  assert!(!entry.is_ready(now + Duration::from_secs(11)));
+  assert!(entry.is_ready(now + Duration::from_secs(12)));
+
+  assert!(!entry.record_start_failure(now, interval));
+}
+
+#[test]
+fn test_queue_entry_party() {
+  let entry = QueueEntry::with_members(vec![
+    QueueMember {
+      player_id: 1,
+      rating: 1600.0,
+      race: Race::Human,
+    },
+    QueueMember {
+      player_id: 2,
+      rating: 1400.0,
+      race: Race::Random,
+    },
+  ]);
+  assert_eq!(entry.player_id, 1);
+  assert_eq!(entry.player_ids().collect::<Vec<_>>(), vec![1, 2]);
+  assert!(entry.contains(2));
+  assert!(!entry.contains(3));
+  assert_eq!(entry.rating(), 1500.0);
+}
@@ -1,4 +1,5 @@
 use diesel::prelude::*;
+use s2_grpc_utils::S2ProtoUnpack;
 
 use crate::db::DbConn;
 use crate::error::*;
@@ -22,3 +23,128 @@ pub fn get_node(conn: &DbConn, node_id: i32) -> Result<Node> {
     .ok_or_else(|| Error::NodeNotFound)
     .map_err(Into::into)
 }
+
+/// Fails if the node can't host new games
+pub fn check_node_enabled(conn: &DbConn, node_id: i32) -> Result<()> {
+  if get_node(conn, node_id)?.disabled {
+    return Err(Error::NodeDisabled);
+  }
+  Ok(())
+}
+
+#[derive(Debug, S2ProtoUnpack)]
+#[s2_grpc(message_type = "flo_grpc::controller::CreateNodeRequest")]
+pub struct CreateNodeParams {
+  pub name: String,
+  pub location: String,
+  pub ip_addr: String,
+  pub country_id: String,
+  /// Generated if not set
+  pub secret: Option<String>,
+}
+
+pub fn create_node(conn: &DbConn, params: CreateNodeParams) -> Result<Node> {
+  validate_ip_addr(&params.ip_addr)?;
+  let secret = params.secret.unwrap_or_else(gen_secret);
+  let node = diesel::insert_into(node::table)
+    .values(&NodeInsert {
+      name: &params.name,
+      location: &params.location,
+      secret: &secret,
+      ip_addr: &params.ip_addr,
+      country_id: &params.country_id,
+    })
+    .get_result(conn)?;
+  Ok(node)
+}
+
+#[derive(Debug, S2ProtoUnpack, AsChangeset)]
+#[s2_grpc(message_type = "flo_grpc::controller::UpdateNodeRequest")]
+#[table_name = "node"]
+pub struct UpdateNodeParams {
+  pub name: Option<String>,
+  pub location: Option<String>,
+  pub ip_addr: Option<String>,
+  pub country_id: Option<String>,
+}
+
+pub fn update_node(conn: &DbConn, node_id: i32, params: UpdateNodeParams) -> Result<Node> {
+  if let Some(ip_addr) = params.ip_addr.as_ref() {
+    validate_ip_addr(ip_addr)?;
+  }
+  if params.name.is_none()
+    && params.location.is_none()
+    && params.ip_addr.is_none()
+    && params.country_id.is_none()
+  {
+    return get_node(conn, node_id);
+  }
+  diesel::update(node::table.find(node_id))
+    .set(&params)
+    .get_result::<Node>(conn)
+    .optional()?
+    .ok_or_else(|| Error::NodeNotFound)
+}
+
+pub fn set_node_disabled(conn: &DbConn, node_id: i32, disabled: bool) -> Result<Node> {
+  diesel::update(node::table.find(node_id))
+    .set(node::disabled.eq(disabled))
+    .get_result::<Node>(conn)
+    .optional()?
+    .ok_or_else(|| Error::NodeNotFound)
+}
+
+/// Returns the node with the new secret,
+/// the node has to be restarted with it before the next reconnect
+pub fn rotate_node_secret(conn: &DbConn, node_id: i32) -> Result<Node> {
+  diesel::update(node::table.find(node_id))
+    .set(node::secret.eq(gen_secret()))
+    .get_result::<Node>(conn)
+    .optional()?
+    .ok_or_else(|| Error::NodeNotFound)
+}
+
+/// Only nodes that never hosted a game can be deleted
+pub fn delete_node(conn: &DbConn, node_id: i32) -> Result<()> {
+  use crate::schema::game;
+
+  conn.transaction(|| {
+    let used: i64 = game::table
+      .filter(game::node_id.eq(node_id))
+      .count()
+      .get_result(conn)?;
+    if used > 0 {
+      return Err(Error::NodeInUse);
+    }
+
+    let n = diesel::delete(node::table.find(node_id)).execute(conn)?;
+    if n != 1 {
+      return Err(Error::NodeNotFound);
+    }
+    Ok(())
+  })
+}
+
+fn validate_ip_addr(ip_addr: &str) -> Result<()> {
+  crate::node::state::conn::parse_addr(ip_addr).map(|_| ())
+}
+
+fn gen_secret() -> String {
+  use rand::distributions::Alphanumeric;
+  use rand::Rng;
+  rand::thread_rng()
+    .sample_iter(&Alphanumeric)
+    .take(32)
+    .map(char::from)
+    .collect()
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "node"]
+struct NodeInsert<'a> {
+  name: &'a str,
+  location: &'a str,
+  secret: &'a str,
+  ip_addr: &'a str,
+  country_id: &'a str,
+}
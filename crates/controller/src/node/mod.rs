@@ -1,4 +1,5 @@
 pub mod db;
+pub mod select;
 mod state;
 mod types;
 
@@ -7,6 +8,8 @@ pub use state::request::PlayerLeaveResponse;
 pub use state::NodeRegistry;
 pub use types::*;
 pub mod messages {
-  pub use crate::node::state::conn::{NodeCreateGame, NodePlayerLeave};
-  pub use crate::node::state::ListNode;
+  pub use crate::node::state::conn::{GetNodeDraining, NodeCreateGame, NodePlayerLeave};
+  pub use crate::node::state::{
+    CreateNode, DeleteNode, ListNode, RotateNodeSecret, SetNodeDisabled, UpdateNode,
+  };
 }
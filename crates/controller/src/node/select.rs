@@ -0,0 +1,107 @@
+//! Recommends a node for a group of players from their ping maps.
+//!
+//! The node with the lowest max RTT wins. Nodes within `MAX_RTT_TOLERANCE`
+//! of the best one are considered equally good, and among them the node with
+//! the smallest RTT difference between the players is picked, so one player
+//! doesn't get a much better connection than the opponents.
+
+use flo_types::ping::PingStats;
+use std::collections::BTreeMap;
+
+const MAX_RTT_TOLERANCE: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSelection {
+  pub node_id: i32,
+  pub max_rtt: u32,
+  pub rtt_spread: u32,
+}
+
+/// Returns `None` if no node in `node_ids` is reachable by all players
+pub fn select_node(
+  node_ids: &[i32],
+  ping_maps: &BTreeMap<i32, BTreeMap<i32, PingStats>>,
+) -> Option<NodeSelection> {
+  if ping_maps.is_empty() {
+    return None;
+  }
+
+  let mut candidates = vec![];
+  'nodes: for node_id in node_ids {
+    let mut min_rtt = u32::MAX;
+    let mut max_rtt = 0;
+    for ping_map in ping_maps.values() {
+      let rtt = match ping_map
+        .get(node_id)
+        .and_then(|stats| stats.avg.or(stats.current))
+      {
+        Some(rtt) => rtt,
+        None => continue 'nodes,
+      };
+      min_rtt = std::cmp::min(min_rtt, rtt);
+      max_rtt = std::cmp::max(max_rtt, rtt);
+    }
+    candidates.push(NodeSelection {
+      node_id: *node_id,
+      max_rtt,
+      rtt_spread: max_rtt - min_rtt,
+    });
+  }
+
+  let best_max_rtt = candidates.iter().map(|c| c.max_rtt).min()?;
+  candidates
+    .into_iter()
+    .filter(|c| c.max_rtt <= best_max_rtt.saturating_add(MAX_RTT_TOLERANCE))
+    .min_by_key(|c| (c.rtt_spread, c.max_rtt, c.node_id))
+}
+
+#[test]
+fn test_select_node() {
+  fn ping_map(items: &[(i32, u32)]) -> BTreeMap<i32, PingStats> {
+    items
+      .iter()
+      .map(|(node_id, rtt)| {
+        (
+          *node_id,
+          PingStats {
+            avg: Some(*rtt),
+            ..Default::default()
+          },
+        )
+      })
+      .collect()
+  }
+
+  assert_eq!(select_node(&[1], &BTreeMap::new()), None);
+
+  // node 3 is unknown to player 2
+  let ping_maps = vec![
+    (1, ping_map(&[(1, 30), (2, 80), (3, 5)])),
+    (2, ping_map(&[(1, 150), (2, 90)])),
+  ]
+  .into_iter()
+  .collect();
+  assert_eq!(
+    select_node(&[1, 2, 3], &ping_maps),
+    Some(NodeSelection {
+      node_id: 2,
+      max_rtt: 90,
+      rtt_spread: 10,
+    })
+  );
+
+  // prefers the fairer node when max RTTs are close
+  let ping_maps = vec![
+    (1, ping_map(&[(1, 10), (2, 55)])),
+    (2, ping_map(&[(1, 50), (2, 55)])),
+  ]
+  .into_iter()
+  .collect();
+  assert_eq!(select_node(&[1, 2], &ping_maps).unwrap().node_id, 2);
+
+  // no node reachable by all players
+  let ping_maps = vec![(1, ping_map(&[(1, 10)])), (2, ping_map(&[(2, 10)]))]
+    .into_iter()
+    .collect();
+  assert_eq!(select_node(&[1, 2], &ping_maps), None);
+}
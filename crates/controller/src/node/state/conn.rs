@@ -1,7 +1,8 @@
+use crate::db::ExecutorRef;
 use crate::error::*;
 use crate::game::state::GameRegistry;
 use crate::game::state::{GameSlotClientStatusUpdate, GameStatusUpdate};
-use crate::game::{Game, GameStatus};
+use crate::game::{Game, GameDesyncReport, GameResultUpdate, GameStatus};
 use crate::node::state::request::{CreatedGameInfo, NodeRequestActor, NodeRequestExt};
 use crate::node::{NodeConnConfig, PlayerLeaveResponse};
 use crate::state::ActorMapExt;
@@ -33,16 +34,20 @@ pub struct NodeConnActor {
   status: NodeConnStatus,
   request_actor: Option<Owner<NodeRequestActor>>,
   game_reg_addr: Addr<GameRegistry>,
+  db: ExecutorRef,
+  draining: bool,
 }
 
 impl NodeConnActor {
-  pub fn new(config: NodeConnConfig, game_reg_addr: Addr<GameRegistry>) -> Self {
+  pub fn new(config: NodeConnConfig, game_reg_addr: Addr<GameRegistry>, db: ExecutorRef) -> Self {
     Self {
       config,
       status: NodeConnStatus::Connecting,
       reconnect_backoff: None,
       request_actor: None,
       game_reg_addr,
+      db,
+      draining: false,
     }
   }
 
@@ -103,7 +108,7 @@ impl NodeConnActor {
     ip: Ipv4Addr,
     port: u16,
     secret: &str,
-  ) -> Result<FloStream, NodeConnectError> {
+  ) -> Result<(FloStream, bool), NodeConnectError> {
     let addr = SocketAddrV4::new(ip, port);
     let mut stream = FloStream::connect(addr).await?;
 
@@ -116,10 +121,16 @@ impl NodeConnActor {
 
     let res = stream.recv_frame().await?;
 
-    flo_net::try_flo_packet! {
+    let draining = flo_net::try_flo_packet! {
       res => {
         packet: PacketControllerConnectAccept => {
-          tracing::info!(node_id, "node connected: version = {:?}", packet.version);
+          tracing::info!(
+            node_id,
+            "node connected: version = {:?}, draining = {}",
+            packet.version,
+            packet.draining
+          );
+          packet.draining
         }
         packet: PacketControllerConnectReject => {
           tracing::error!(node_id, "node connect rejected: reason = {:?}", packet.reason());
@@ -131,7 +142,7 @@ impl NodeConnActor {
       }
     };
 
-    Ok(stream)
+    Ok((stream, draining))
   }
 
   async fn stream_worker(addr: Addr<Self>, mut rx: mpsc::Receiver<Frame>, mut stream: FloStream) {
@@ -220,7 +231,10 @@ impl Handler<Connect> for NodeConnActor {
     let node_id = self.config.id;
     let secret = self.config.secret.clone();
     let stream = match Self::connect(node_id, ip, port, &secret).await {
-      Ok(stream) => stream,
+      Ok((stream, draining)) => {
+        self.draining = draining;
+        stream
+      }
       Err(NodeConnectError::Retry(err)) => {
         tracing::error!(node_id, "error: {}", err);
         self.schedule_reconnect(ctx);
@@ -274,6 +288,9 @@ impl Handler<IncomingFrame> for NodeConnActor {
       Response(RequestDone),
       GameSlotClientStatusUpdate(GameSlotClientStatusUpdate),
       GameStatusUpdate(Vec<GameStatusUpdate>),
+      GameResult(GameResultUpdate),
+      GameDesync(GameDesyncReport),
+      DrainStatus(bool),
     }
 
     let parsed = flo_net::try_flo_packet! {
@@ -331,6 +348,15 @@ impl Handler<IncomingFrame> for NodeConnActor {
         packet: PacketNodeGameStatusUpdateBulk => {
           Parsed::GameStatusUpdate(packet.games.into_iter().map(Into::into).collect())
         }
+        packet: PacketNodeGameResult => {
+          Parsed::GameResult(GameResultUpdate::from(packet))
+        }
+        packet: PacketNodeGameDesync => {
+          Parsed::GameDesync(GameDesyncReport::from(packet))
+        }
+        packet: PacketNodeDrainStatus => {
+          Parsed::DrainStatus(packet.draining)
+        }
       }
     };
 
@@ -375,6 +401,39 @@ impl Handler<IncomingFrame> for NodeConnActor {
           }
         });
       }
+      Parsed::GameResult(update) => {
+        let db = self.db.clone();
+        ctx.spawn(async move {
+          let game_id = update.game_id;
+          tracing::debug!(game_id, "game result: {:?}", update);
+          if let Err(err) = db
+            .exec(move |conn| {
+              crate::game::db::update_result(conn, &update)?;
+              crate::rating::db::update_game_ratings(conn, &update)
+            })
+            .await
+          {
+            tracing::error!(game_id, "update game result: {}", err);
+          }
+        });
+      }
+      Parsed::GameDesync(report) => {
+        let db = self.db.clone();
+        ctx.spawn(async move {
+          let game_id = report.game_id;
+          tracing::warn!(game_id, tick = report.tick, "game desync reported");
+          if let Err(err) = db
+            .exec(move |conn| crate::game::db::insert_desync(conn, &report))
+            .await
+          {
+            tracing::error!(game_id, "insert game desync: {}", err);
+          }
+        });
+      }
+      Parsed::DrainStatus(draining) => {
+        tracing::info!(node_id = self.config.id, "node draining: {}", draining);
+        self.draining = draining;
+      }
     }
 
     Ok(())
@@ -384,6 +443,7 @@ impl Handler<IncomingFrame> for NodeConnActor {
 pub struct NodeCreateGame {
   pub game: Game,
   pub ban_list_map: BTreeMap<i32, Vec<PlayerBanType>>,
+  pub referee_player_ids: Vec<i32>,
 }
 
 impl Message for NodeCreateGame {
@@ -395,7 +455,11 @@ impl Handler<NodeCreateGame> for NodeConnActor {
   async fn handle(
     &mut self,
     ctx: &mut Context<Self>,
-    NodeCreateGame { game, ban_list_map }: NodeCreateGame,
+    NodeCreateGame {
+      game,
+      ban_list_map,
+      referee_player_ids,
+    }: NodeCreateGame,
   ) -> Result<FutureReply<Result<CreatedGameInfo>>> {
     let addr = self
       .request_actor
@@ -404,12 +468,56 @@ impl Handler<NodeCreateGame> for NodeConnActor {
       .ok_or_else(|| Error::NodeNotReady)?;
     let (tx, rx) = FutureReply::channel();
     ctx.spawn(async move {
-      tx.send(addr.create_game(game, ban_list_map).await).ok();
+      tx.send(
+        addr
+          .create_game(game, ban_list_map, referee_player_ids)
+          .await,
+      )
+      .ok();
     });
     Ok(rx)
   }
 }
 
+/// Draining nodes are finishing their running games and reject new games
+pub struct GetNodeDraining;
+
+impl Message for GetNodeDraining {
+  type Result = Result<bool>;
+}
+
+#[async_trait]
+impl Handler<GetNodeDraining> for NodeConnActor {
+  async fn handle(&mut self, _: &mut Context<Self>, _: GetNodeDraining) -> Result<bool> {
+    Ok(self.draining)
+  }
+}
+
+/// Used by the next connect, the current connection is kept
+pub struct UpdateNodeSecret {
+  pub secret: String,
+}
+
+impl Message for UpdateNodeSecret {
+  type Result = ();
+}
+
+#[async_trait]
+impl Handler<UpdateNodeSecret> for NodeConnActor {
+  async fn handle(
+    &mut self,
+    ctx: &mut Context<Self>,
+    UpdateNodeSecret { secret }: UpdateNodeSecret,
+  ) {
+    self.config.secret = secret;
+    // a rejected node is retried with the new secret
+    if self.status == NodeConnStatus::Error {
+      self.status = NodeConnStatus::Connecting;
+      self.handle(ctx, Connect).await
+    }
+  }
+}
+
 pub struct NodePlayerLeave {
   pub game_id: i32,
   pub player_id: i32,
@@ -447,7 +555,7 @@ enum NodeConnStatus {
   Error,
 }
 
-fn parse_addr(addr: &str) -> Result<(Ipv4Addr, u16)> {
+pub(crate) fn parse_addr(addr: &str) -> Result<(Ipv4Addr, u16)> {
   let (ip, port) = if addr.contains(":") {
     let addr = if let Some(addr) = addr.parse::<SocketAddrV4>().ok() {
       addr
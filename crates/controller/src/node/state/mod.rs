@@ -4,11 +4,12 @@ pub mod request;
 use crate::db::ExecutorRef;
 use crate::error::*;
 use crate::game::state::GameRegistry;
+use crate::node::db::{CreateNodeParams, UpdateNodeParams};
 use crate::node::{Node, NodeConnConfig};
 use crate::player::state::sender::PlayerRegistryHandle;
 use crate::state::{Data, GetActorEntry, Reload};
 use arc_swap::ArcSwap;
-use conn::NodeConnActor;
+use conn::{NodeConnActor, UpdateNodeSecret};
 use flo_state::{
   async_trait, Actor, Addr, Context, Deferred, Handler, Message, Owner, RegistryRef, Service,
 };
@@ -58,7 +59,7 @@ impl NodeRegistry {
       tracing::debug!(node_id = node.id, "added");
       self.map.insert(
         node.id,
-        NodeConnActor::new(node.into(), game_reg_addr.clone()).start(),
+        NodeConnActor::new(node.into(), game_reg_addr.clone(), self.db.clone()).start(),
       );
     }
 
@@ -90,11 +91,20 @@ impl Handler<GetActorEntry<NodeConnActor>> for NodeRegistry {
 #[async_trait]
 impl Handler<Reload> for NodeRegistry {
   async fn handle(&mut self, _: &mut Context<Self>, _: Reload) -> Result<()> {
+    self.sync().await
+  }
+}
+
+impl NodeRegistry {
+  /// Applies node changes in the database to connections and connected clients
+  async fn sync(&mut self) -> Result<()> {
     use flo_net::packet::FloPacket;
     use flo_net::proto::flo_connect::{PacketAddNode, PacketRemoveNode};
     use s2_grpc_utils::S2ProtoPack;
 
     let nodes = self.load_snapshot().await?;
+    let prev_nodes = self.nodes_snapshot.load_full();
+    let prev_map: BTreeMap<i32, &Node> = prev_nodes.iter().map(|node| (node.id, node)).collect();
 
     let mut broadcast_frames = vec![];
 
@@ -110,11 +120,12 @@ impl Handler<Reload> for NodeRegistry {
     }
     for node in &nodes {
       let config = NodeConnConfig::from(node);
+      let prev = prev_map.get(&config.id).cloned();
       if !self.map.contains_key(&config.id) {
         tracing::info!(id = config.id, "node added: {}", config.addr);
         self.map.insert(
           config.id,
-          NodeConnActor::new(config, self.game_reg_addr.resolve().await?).start(),
+          NodeConnActor::new(config, self.game_reg_addr.resolve().await?, self.db.clone()).start(),
         );
         broadcast_frames.push(
           PacketAddNode {
@@ -122,6 +133,45 @@ impl Handler<Reload> for NodeRegistry {
           }
           .encode_as_frame()?,
         );
+        continue;
+      }
+
+      let prev = if let Some(prev) = prev {
+        prev
+      } else {
+        continue;
+      };
+
+      if prev.ip_addr != node.ip_addr {
+        tracing::info!(id = config.id, "node address updated: {}", config.addr);
+        // replaces the connection
+        self.map.insert(
+          config.id,
+          NodeConnActor::new(config, self.game_reg_addr.resolve().await?, self.db.clone()).start(),
+        );
+      } else if prev.secret != node.secret {
+        tracing::info!(id = config.id, "node secret updated");
+        if let Some(actor) = self.map.get(&config.id) {
+          actor
+            .send(UpdateNodeSecret {
+              secret: config.secret,
+            })
+            .await?;
+        }
+      }
+
+      if prev.name != node.name
+        || prev.location != node.location
+        || prev.ip_addr != node.ip_addr
+        || prev.country_id != node.country_id
+      {
+        broadcast_frames.push(PacketRemoveNode { node_id: node.id }.encode_as_frame()?);
+        broadcast_frames.push(
+          PacketAddNode {
+            node: node.clone().pack()?,
+          }
+          .encode_as_frame()?,
+        );
       }
     }
 
@@ -150,3 +200,126 @@ impl Handler<ListNode> for NodeRegistry {
     Vec::<_>::clone(&self.nodes_snapshot.load())
   }
 }
+
+pub struct CreateNode {
+  pub params: CreateNodeParams,
+}
+
+impl Message for CreateNode {
+  type Result = Result<Node>;
+}
+
+#[async_trait]
+impl Handler<CreateNode> for NodeRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    CreateNode { params }: CreateNode,
+  ) -> Result<Node> {
+    let node = self
+      .db
+      .exec(move |conn| crate::node::db::create_node(conn, params))
+      .await?;
+    self.sync().await?;
+    Ok(node)
+  }
+}
+
+pub struct UpdateNode {
+  pub node_id: i32,
+  pub params: UpdateNodeParams,
+}
+
+impl Message for UpdateNode {
+  type Result = Result<Node>;
+}
+
+#[async_trait]
+impl Handler<UpdateNode> for NodeRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    UpdateNode { node_id, params }: UpdateNode,
+  ) -> Result<Node> {
+    let node = self
+      .db
+      .exec(move |conn| crate::node::db::update_node(conn, node_id, params))
+      .await?;
+    self.sync().await?;
+    Ok(node)
+  }
+}
+
+/// Disabled nodes are disconnected and removed from clients,
+/// games can't select them until they are enabled again
+pub struct SetNodeDisabled {
+  pub node_id: i32,
+  pub disabled: bool,
+}
+
+impl Message for SetNodeDisabled {
+  type Result = Result<Node>;
+}
+
+#[async_trait]
+impl Handler<SetNodeDisabled> for NodeRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    SetNodeDisabled { node_id, disabled }: SetNodeDisabled,
+  ) -> Result<Node> {
+    let node = self
+      .db
+      .exec(move |conn| crate::node::db::set_node_disabled(conn, node_id, disabled))
+      .await?;
+    self.sync().await?;
+    Ok(node)
+  }
+}
+
+pub struct DeleteNode {
+  pub node_id: i32,
+}
+
+impl Message for DeleteNode {
+  type Result = Result<()>;
+}
+
+#[async_trait]
+impl Handler<DeleteNode> for NodeRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    DeleteNode { node_id }: DeleteNode,
+  ) -> Result<()> {
+    self
+      .db
+      .exec(move |conn| crate::node::db::delete_node(conn, node_id))
+      .await?;
+    self.sync().await
+  }
+}
+
+pub struct RotateNodeSecret {
+  pub node_id: i32,
+}
+
+impl Message for RotateNodeSecret {
+  type Result = Result<Node>;
+}
+
+#[async_trait]
+impl Handler<RotateNodeSecret> for NodeRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    RotateNodeSecret { node_id }: RotateNodeSecret,
+  ) -> Result<Node> {
+    let node = self
+      .db
+      .exec(move |conn| crate::node::db::rotate_node_secret(conn, node_id))
+      .await?;
+    self.sync().await?;
+    Ok(node)
+  }
+}
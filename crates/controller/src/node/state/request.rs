@@ -231,6 +231,7 @@ pub trait NodeRequestExt {
     &self,
     game: Game,
     ban_list_map: BTreeMap<i32, Vec<PlayerBanType>>,
+    referee_player_ids: Vec<i32>,
   ) -> Result<CreatedGameInfo>;
   async fn player_force_leave(&self, game_id: i32, player_id: i32) -> Result<PlayerLeaveResponse>;
 }
@@ -241,6 +242,7 @@ impl NodeRequestExt for Addr<NodeRequestActor> {
     &self,
     game: Game,
     mut ban_list_map: BTreeMap<i32, Vec<PlayerBanType>>,
+    referee_player_ids: Vec<i32>,
   ) -> Result<CreatedGameInfo> {
     let game_id = game.id;
 
@@ -262,6 +264,7 @@ impl NodeRequestExt for Addr<NodeRequestActor> {
               .remove(&player.id)
               .map(|items| items.into_iter().map(|v| v as i32).collect())
               .unwrap_or_default(),
+            is_referee: referee_player_ids.contains(&player.id),
           }),
           settings: Some(slot.settings.clone().pack()?),
           client_status: Default::default(),
@@ -280,6 +283,9 @@ impl NodeRequestExt for Addr<NodeRequestActor> {
         slots,
         status: Default::default(),
         enable_ping_equalizer: game.enable_ping_equalizer,
+        enable_adaptive_step: game.enable_adaptive_step,
+        observer_chat_policy: game.observer_chat_policy as i32,
+        game_version: game.game_version.clone().unwrap_or_default(),
       }),
     };
 
@@ -1,7 +1,11 @@
 use crate::db::DbConn;
 use crate::error::*;
-use crate::player::{Player, PlayerBan, PlayerBanType, PlayerRef, PlayerSource, SourceState};
-use crate::schema::{player, player_ban, player_mute};
+use crate::map::MapSha1;
+use crate::player::{
+  Player, PlayerBan, PlayerBanAudit, PlayerBanAuditAction, PlayerBanType, PlayerRef, PlayerSource,
+  SourceState,
+};
+use crate::schema::{player, player_ban, player_ban_audit, player_mute};
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde_json::Value;
@@ -209,32 +213,86 @@ pub fn get_ban(conn: &DbConn, id: i32) -> Result<PlayerBan> {
     .map_err(Into::into)
 }
 
-pub fn create_ban(
-  conn: &DbConn,
-  player_id: i32,
-  ban_type: PlayerBanType,
-  ban_expires_at: Option<DateTime<Utc>>,
-) -> Result<()> {
+pub struct CreateBan {
+  pub player_id: i32,
+  pub ban_type: PlayerBanType,
+  pub ban_expires_at: Option<DateTime<Utc>>,
+  pub reason: Option<String>,
+  /// Hex encoded, required for map bans
+  pub map_sha1: Option<String>,
+}
+
+/// Creates a ban or updates the existing ban of the same type and map
+pub fn create_ban(conn: &DbConn, api_client_id: i32, params: CreateBan) -> Result<()> {
   #[derive(Insertable)]
   #[table_name = "player_ban"]
-  struct Insert {
+  struct Insert<'a> {
     player_id: i32,
     ban_type: PlayerBanType,
     ban_expires_at: Option<DateTime<Utc>>,
+    reason: Option<&'a str>,
+    map_sha1: &'a str,
+    api_client_id: Option<i32>,
   }
 
-  diesel::insert_into(player_ban::table)
-    .values(&Insert {
-      player_id,
-      ban_type,
-      ban_expires_at,
-    })
-    .on_conflict((player_ban::player_id, player_ban::ban_type))
-    .do_update()
-    .set(player_ban::ban_expires_at.eq(ban_expires_at))
-    .execute(conn)?;
+  let map_sha1 = params
+    .map_sha1
+    .as_deref()
+    .unwrap_or_default()
+    .to_ascii_lowercase();
+  match params.ban_type {
+    PlayerBanType::Map => {
+      if map_sha1.len() != 40 || !map_sha1.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::PlayerBanInvalid("invalid map sha1"));
+      }
+    }
+    _ => {
+      if !map_sha1.is_empty() {
+        return Err(Error::PlayerBanInvalid(
+          "map sha1 is only allowed for map bans",
+        ));
+      }
+    }
+  }
 
-  Ok(())
+  let insert = Insert {
+    player_id: params.player_id,
+    ban_type: params.ban_type,
+    ban_expires_at: params.ban_expires_at,
+    reason: params.reason.as_deref(),
+    map_sha1: &map_sha1,
+    api_client_id: Some(api_client_id),
+  };
+
+  conn.transaction(|| -> Result<()> {
+    diesel::insert_into(player_ban::table)
+      .values(&insert)
+      .on_conflict((
+        player_ban::player_id,
+        player_ban::ban_type,
+        player_ban::map_sha1,
+      ))
+      .do_update()
+      .set((
+        player_ban::ban_expires_at.eq(insert.ban_expires_at),
+        player_ban::reason.eq(insert.reason),
+        player_ban::api_client_id.eq(insert.api_client_id),
+      ))
+      .execute(conn)?;
+
+    insert_ban_audit(
+      conn,
+      BanAuditInsert {
+        player_id: insert.player_id,
+        action: PlayerBanAuditAction::Create,
+        ban_type: insert.ban_type,
+        map_sha1: insert.map_sha1,
+        ban_expires_at: insert.ban_expires_at,
+        reason: insert.reason,
+        api_client_id: insert.api_client_id,
+      },
+    )
+  })
 }
 
 pub fn remove_ban_by_type(conn: &DbConn, player_id: i32, ban_type: PlayerBanType) -> Result<()> {
@@ -250,8 +308,167 @@ pub fn remove_ban_by_type(conn: &DbConn, player_id: i32, ban_type: PlayerBanType
   Ok(())
 }
 
-pub fn remove_ban(conn: &DbConn, id: i32) -> Result<()> {
-  diesel::delete(player_ban::table.find(id)).execute(conn)?;
+pub fn remove_ban(conn: &DbConn, api_client_id: i32, id: i32) -> Result<()> {
+  conn.transaction(|| -> Result<()> {
+    let removed: Option<(
+      i32,
+      PlayerBanType,
+      String,
+      Option<DateTime<Utc>>,
+      Option<String>,
+    )> = diesel::delete(player_ban::table.find(id))
+      .returning((
+        player_ban::player_id,
+        player_ban::ban_type,
+        player_ban::map_sha1,
+        player_ban::ban_expires_at,
+        player_ban::reason,
+      ))
+      .get_result(conn)
+      .optional()?;
+
+    if let Some((player_id, ban_type, map_sha1, ban_expires_at, reason)) = removed {
+      insert_ban_audit(
+        conn,
+        BanAuditInsert {
+          player_id,
+          action: PlayerBanAuditAction::Remove,
+          ban_type,
+          map_sha1: &map_sha1,
+          ban_expires_at,
+          reason: reason.as_deref(),
+          api_client_id: Some(api_client_id),
+        },
+      )?;
+    }
+
+    Ok(())
+  })
+}
+
+pub struct ListPlayerBanAudit {
+  pub items: Vec<PlayerBanAudit>,
+  pub next_id: Option<i32>,
+}
+
+/// Lists ban changes of players of the api client, newest first
+pub fn list_ban_audit(
+  conn: &DbConn,
+  api_client_id: i32,
+  player_id: Option<i32>,
+  next_id: Option<i32>,
+) -> Result<ListPlayerBanAudit> {
+  const PAGE_SIZE: i64 = 200;
+  let mut q = player_ban_audit::table
+    .inner_join(player::table)
+    .select((
+      player_ban_audit::id,
+      player_ban_audit::player_id,
+      player_ban_audit::action,
+      player_ban_audit::ban_type,
+      player_ban_audit::map_sha1,
+      player_ban_audit::ban_expires_at,
+      player_ban_audit::reason,
+      player_ban_audit::api_client_id,
+      player_ban_audit::created_at,
+    ))
+    .filter(player::api_client_id.eq(api_client_id))
+    .order(player_ban_audit::id.desc())
+    .limit(PAGE_SIZE + 1)
+    .into_boxed();
+
+  if let Some(id) = player_id {
+    q = q.filter(player_ban_audit::player_id.eq(id));
+  }
+
+  if let Some(id) = next_id {
+    q = q.filter(player_ban_audit::id.le(id));
+  }
+
+  let mut items = q.load::<PlayerBanAudit>(conn)?;
+  let next_id = if items.len() > PAGE_SIZE as usize {
+    let id = items.last().map(|row| row.id);
+    items.truncate(PAGE_SIZE as usize);
+    id
+  } else {
+    None
+  };
+
+  Ok(ListPlayerBanAudit { items, next_id })
+}
+
+#[derive(Insertable)]
+#[table_name = "player_ban_audit"]
+struct BanAuditInsert<'a> {
+  player_id: i32,
+  action: PlayerBanAuditAction,
+  ban_type: PlayerBanType,
+  map_sha1: &'a str,
+  ban_expires_at: Option<DateTime<Utc>>,
+  reason: Option<&'a str>,
+  api_client_id: Option<i32>,
+}
+
+fn insert_ban_audit(conn: &DbConn, insert: BanAuditInsert) -> Result<()> {
+  diesel::insert_into(player_ban_audit::table)
+    .values(&insert)
+    .execute(conn)?;
+  Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameBanCheck {
+  /// Player slots
+  Play,
+  Create,
+  /// Observer slots
+  Observe,
+}
+
+impl GameBanCheck {
+  pub fn for_team(team: i32) -> Self {
+    if team == 24 {
+      GameBanCheck::Observe
+    } else {
+      GameBanCheck::Play
+    }
+  }
+}
+
+/// Returns `Error::PlayerBanned` if an active ban prevents the player
+/// from playing, creating or observing a game on the map
+pub fn check_game_ban(
+  conn: &DbConn,
+  player_id: i32,
+  check: GameBanCheck,
+  map_sha1: &MapSha1,
+) -> Result<()> {
+  use diesel::dsl::sql;
+  let bans: Vec<(PlayerBanType, String)> = player_ban::table
+    .select((player_ban::ban_type, player_ban::map_sha1))
+    .filter(
+      player_ban::player_id.eq(player_id).and(
+        player_ban::ban_expires_at
+          .gt(sql("now()"))
+          .or(player_ban::ban_expires_at.is_null()),
+      ),
+    )
+    .order(player_ban::id)
+    .load(conn)?;
+
+  let map_sha1 = map_sha1.to_hex();
+  for (ban_type, ban_map_sha1) in bans {
+    let banned = match ban_type {
+      PlayerBanType::Chat => false,
+      PlayerBanType::JoinGame => true,
+      PlayerBanType::ObserveOnly => check != GameBanCheck::Observe,
+      PlayerBanType::CreateGame => check == GameBanCheck::Create,
+      PlayerBanType::Map => check != GameBanCheck::Observe && ban_map_sha1 == map_sha1,
+    };
+    if banned {
+      return Err(Error::PlayerBanned(ban_type));
+    }
+  }
   Ok(())
 }
 
@@ -271,6 +488,7 @@ pub fn check_ban_api_client_id(conn: &DbConn, api_client_id: i32, id: i32) -> Re
   Ok(())
 }
 
+/// Active bans enforced by the node
 pub fn get_ban_list_map(
   conn: &DbConn,
   player_ids: &[i32],
@@ -289,6 +507,9 @@ pub fn get_ban_list_map(
     .load(conn)?;
   let mut map = BTreeMap::new();
   for (player_id, ban_type) in pairs {
+    if !ban_type.is_node_enforced() {
+      continue;
+    }
     map
       .entry(player_id)
       .or_insert_with(|| vec![])
@@ -370,6 +591,7 @@ impl From<Row> for PlayerRef {
       name: p.name,
       source: p.source,
       realm: p.realm,
+      ratings: vec![],
     }
   }
 }
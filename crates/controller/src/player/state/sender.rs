@@ -1,3 +1,4 @@
+use super::ping::{GetPlayersPingSnapshot, NodePlayersPingSnapshot};
 use super::{PlayerRegistry, PlayerState};
 use crate::error::*;
 use crate::game::Game;
@@ -338,6 +339,10 @@ impl PlayerRegistryHandle {
     Ok(())
   }
 
+  pub async fn get_ping_snapshot(&self, players: Vec<i32>) -> Result<NodePlayersPingSnapshot> {
+    Ok(self.0.send(GetPlayersPingSnapshot { players }).await?)
+  }
+
   pub async fn broadcast_to_all<T>(&self, frames: T) -> Result<()>
   where
     T: Into<PlayerFrames>,
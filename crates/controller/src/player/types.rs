@@ -1,9 +1,12 @@
 use bs_diesel_utils::BSDieselEnum;
 use chrono::{DateTime, Utc};
+use diesel::backend::Backend;
+use diesel::deserialize::Queryable;
 use s2_grpc_utils::result::Error as ProtoError;
 use s2_grpc_utils::{S2ProtoEnum, S2ProtoPack, S2ProtoUnpack};
 use serde::{Deserialize, Serialize};
 
+use crate::rating::PlayerRating;
 use crate::schema::{player, player_ban};
 
 #[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack)]
@@ -32,7 +35,7 @@ pub enum PlayerSource {
   Api = 2,
 }
 
-#[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone, Queryable)]
+#[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone)]
 #[s2_grpc(message_type(flo_grpc::player::PlayerRef, flo_net::proto::flo_connect::PlayerInfo))]
 pub struct PlayerRef {
   pub id: i32,
@@ -40,6 +43,28 @@ pub struct PlayerRef {
   #[s2_grpc(proto_enum)]
   pub source: PlayerSource,
   pub realm: Option<String>,
+  /// Not loaded by `PlayerRef::COLUMNS`, see `crate::rating::db::fill_player_ratings`
+  #[serde(default)]
+  pub ratings: Vec<PlayerRating>,
+}
+
+impl<DB, ST> Queryable<ST, DB> for PlayerRef
+where
+  DB: Backend,
+  (i32, String, PlayerSource, Option<String>): Queryable<ST, DB>,
+{
+  type Row = <(i32, String, PlayerSource, Option<String>) as Queryable<ST, DB>>::Row;
+
+  fn build(row: Self::Row) -> Self {
+    let (id, name, source, realm) = Queryable::build(row);
+    PlayerRef {
+      id,
+      name,
+      source,
+      realm,
+      ratings: vec![],
+    }
+  }
 }
 
 pub(crate) type PlayerRefColumns = (
@@ -104,6 +129,28 @@ pub struct BNetState {
 ))]
 pub enum PlayerBanType {
   Chat = 0,
+  /// Can't join or create games, including as an observer
+  JoinGame = 1,
+  CreateGame = 2,
+  /// Can only observe games
+  ObserveOnly = 3,
+  /// Can't join or create games on the banned map
+  Map = 4,
+}
+
+impl PlayerBanType {
+  /// Only chat bans are sent to the node, others are enforced by the controller
+  pub fn is_node_enforced(&self) -> bool {
+    *self == PlayerBanType::Chat
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, BSDieselEnum, S2ProtoEnum)]
+#[repr(i32)]
+#[s2_grpc(proto_enum_type = "flo_grpc::player::PlayerBanAuditAction")]
+pub enum PlayerBanAuditAction {
+  Create = 0,
+  Remove = 1,
 }
 
 #[derive(Debug, Queryable, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack)]
@@ -115,6 +162,10 @@ pub struct PlayerBan {
   pub ban_type: PlayerBanType,
   pub ban_expires_at: Option<DateTime<Utc>>,
   pub created_at: DateTime<Utc>,
+  pub reason: Option<String>,
+  /// Hex encoded, only set for map bans
+  pub map_sha1: String,
+  pub api_client_id: Option<i32>,
 }
 
 pub(crate) type PlayerBanColumns = (
@@ -123,6 +174,9 @@ pub(crate) type PlayerBanColumns = (
   player_ban::ban_type,
   player_ban::ban_expires_at,
   player_ban::created_at,
+  player_ban::reason,
+  player_ban::map_sha1,
+  player_ban::api_client_id,
 );
 
 impl PlayerBan {
@@ -132,5 +186,24 @@ impl PlayerBan {
     player_ban::ban_type,
     player_ban::ban_expires_at,
     player_ban::created_at,
+    player_ban::reason,
+    player_ban::map_sha1,
+    player_ban::api_client_id,
   );
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Queryable, S2ProtoPack)]
+#[s2_grpc(message_type = "flo_grpc::player::PlayerBanAudit")]
+pub struct PlayerBanAudit {
+  pub id: i32,
+  pub player_id: i32,
+  #[s2_grpc(proto_enum)]
+  pub action: PlayerBanAuditAction,
+  #[s2_grpc(proto_enum)]
+  pub ban_type: PlayerBanType,
+  pub map_sha1: String,
+  pub ban_expires_at: Option<DateTime<Utc>>,
+  pub reason: Option<String>,
+  pub api_client_id: Option<i32>,
+  pub created_at: DateTime<Utc>,
+}
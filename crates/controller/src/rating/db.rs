@@ -0,0 +1,261 @@
+use diesel::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::db::DbConn;
+use crate::error::*;
+use crate::game::{GameResultUpdate, PlayerGameResult};
+use crate::player::PlayerRef;
+use crate::rating::elo::{update_ratings, EloPlayer, EloTeam};
+use crate::rating::{Leaderboard, LeaderboardEntry, PlayerRating, DEFAULT_RATING};
+use crate::schema::{game, game_used_slot, player, player_rating, player_rating_change};
+
+pub fn get_player_ratings(conn: &DbConn, player_id: i32) -> Result<Vec<PlayerRating>> {
+  player_rating::table
+    .select(PlayerRating::COLUMNS)
+    .filter(player_rating::player_id.eq(player_id))
+    .order(player_rating::mode)
+    .load(conn)
+    .map_err(Into::into)
+}
+
+/// Returns the rating of the player in `mode`, or the default rating if the player has no rated game
+pub fn get_rating(conn: &DbConn, player_id: i32, mode: &str) -> Result<f64> {
+  let rating: Option<f64> = player_rating::table
+    .select(player_rating::rating)
+    .filter(
+      player_rating::player_id
+        .eq(player_id)
+        .and(player_rating::mode.eq(mode)),
+    )
+    .first(conn)
+    .optional()?;
+  Ok(rating.unwrap_or(DEFAULT_RATING))
+}
+
+/// Loads ratings into player refs, used to show ratings in lobbies
+pub fn fill_player_ratings<'a, I>(conn: &DbConn, players: I) -> Result<()>
+where
+  I: IntoIterator<Item = &'a mut PlayerRef>,
+{
+  let mut players: Vec<&mut PlayerRef> = players.into_iter().collect();
+  if players.is_empty() {
+    return Ok(());
+  }
+
+  let ids: Vec<i32> = players.iter().map(|p| p.id).collect();
+  let rows: Vec<(i32, PlayerRating)> = player_rating::table
+    .select((player_rating::player_id, PlayerRating::COLUMNS))
+    .filter(player_rating::player_id.eq_any(&ids))
+    .order((player_rating::player_id, player_rating::mode))
+    .load(conn)?;
+
+  let mut map: BTreeMap<i32, Vec<PlayerRating>> = BTreeMap::new();
+  for (player_id, rating) in rows {
+    map.entry(player_id).or_default().push(rating);
+  }
+
+  for player in players.iter_mut() {
+    player.ratings = map.remove(&player.id).unwrap_or_default();
+  }
+
+  Ok(())
+}
+
+pub fn list_leaderboard(
+  conn: &DbConn,
+  api_client_id: i32,
+  mode: &str,
+  offset: Option<i32>,
+) -> Result<Leaderboard> {
+  const PAGE_SIZE: i64 = 100;
+  let offset = offset.unwrap_or_default().max(0);
+  let mut rows: Vec<(PlayerRef, PlayerRating)> = player_rating::table
+    .inner_join(player::table)
+    .select((PlayerRef::COLUMNS, PlayerRating::COLUMNS))
+    .filter(
+      player::api_client_id
+        .eq(api_client_id)
+        .and(player_rating::mode.eq(mode)),
+    )
+    .order((player_rating::rating.desc(), player_rating::player_id))
+    .offset(offset as i64)
+    .limit(PAGE_SIZE + 1)
+    .load(conn)?;
+
+  let next_offset = if rows.len() > PAGE_SIZE as usize {
+    rows.truncate(PAGE_SIZE as usize);
+    Some(offset + PAGE_SIZE as i32)
+  } else {
+    None
+  };
+
+  Ok(Leaderboard {
+    entries: rows
+      .into_iter()
+      .enumerate()
+      .map(|(i, (player, rating))| LeaderboardEntry {
+        rank: offset + i as i32 + 1,
+        player,
+        rating,
+      })
+      .collect(),
+    next_offset,
+  })
+}
+
+/// Updates ratings of the game players if the game is rated and the result is known.
+/// Each game is rated at most once.
+pub fn update_game_ratings(conn: &DbConn, update: &GameResultUpdate) -> Result<()> {
+  use diesel::pg::upsert::excluded;
+  use player_rating::dsl;
+
+  let game_id = update.game_id;
+  let mode: Option<String> = game::table
+    .find(game_id)
+    .select(game::rating_mode)
+    .first::<Option<String>>(conn)
+    .optional()?
+    .flatten();
+  let mode = if let Some(mode) = mode {
+    mode
+  } else {
+    return Ok(());
+  };
+
+  if update.players.is_empty()
+    || update
+      .players
+      .iter()
+      .any(|p| p.result == PlayerGameResult::Unknown)
+  {
+    return Ok(());
+  }
+
+  conn.transaction(|| -> Result<()> {
+    let rated: i64 = player_rating_change::table
+      .filter(player_rating_change::game_id.eq(game_id))
+      .count()
+      .get_result(conn)?;
+    if rated > 0 {
+      return Ok(());
+    }
+
+    let player_ids: Vec<i32> = update.players.iter().map(|p| p.player_id).collect();
+    let team_map: BTreeMap<i32, i32> = game_used_slot::table
+      .select((game_used_slot::player_id, game_used_slot::team))
+      .filter(
+        game_used_slot::game_id
+          .eq(game_id)
+          .and(game_used_slot::player_id.eq_any(&player_ids)),
+      )
+      .load::<(Option<i32>, i32)>(conn)?
+      .into_iter()
+      .filter_map(|(player_id, team)| Some((player_id?, team)))
+      .collect();
+    let current: BTreeMap<i32, (f64, i32)> = player_rating::table
+      .select((dsl::player_id, dsl::rating, dsl::games))
+      .filter(dsl::mode.eq(&mode).and(dsl::player_id.eq_any(&player_ids)))
+      .load::<(i32, f64, i32)>(conn)?
+      .into_iter()
+      .map(|(player_id, rating, games)| (player_id, (rating, games)))
+      .collect();
+
+    let mut teams: BTreeMap<i32, EloTeam> = BTreeMap::new();
+    let mut results = BTreeMap::new();
+    for player in &update.players {
+      let team = if let Some(team) = team_map.get(&player.player_id) {
+        *team
+      } else {
+        continue;
+      };
+      let (rating, games) = current
+        .get(&player.player_id)
+        .cloned()
+        .unwrap_or((DEFAULT_RATING, 0));
+      let score = match player.result {
+        PlayerGameResult::Win => 1.0,
+        PlayerGameResult::Draw => 0.5,
+        _ => 0.0,
+      };
+      let team = teams.entry(team).or_insert_with(|| EloTeam {
+        players: vec![],
+        score: 0.0,
+      });
+      team.score = team.score.max(score);
+      team.players.push(EloPlayer {
+        player_id: player.player_id,
+        rating,
+        games,
+      });
+      results.insert(player.player_id, player.result);
+    }
+
+    let teams: Vec<EloTeam> = teams.into_iter().map(|(_, team)| team).collect();
+    for (player_id, rating) in update_ratings(&teams) {
+      let result = results
+        .get(&player_id)
+        .cloned()
+        .unwrap_or(PlayerGameResult::Unknown);
+      let count = |v: PlayerGameResult| if result == v { 1 } else { 0 };
+
+      diesel::insert_into(player_rating::table)
+        .values(&RatingInsert {
+          player_id,
+          mode: &mode,
+          rating,
+          games: 1,
+          wins: count(PlayerGameResult::Win),
+          losses: count(PlayerGameResult::Loss),
+          draws: count(PlayerGameResult::Draw),
+        })
+        .on_conflict((dsl::player_id, dsl::mode))
+        .do_update()
+        .set((
+          dsl::rating.eq(excluded(dsl::rating)),
+          dsl::games.eq(dsl::games + 1),
+          dsl::wins.eq(dsl::wins + excluded(dsl::wins)),
+          dsl::losses.eq(dsl::losses + excluded(dsl::losses)),
+          dsl::draws.eq(dsl::draws + excluded(dsl::draws)),
+          dsl::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+      diesel::insert_into(player_rating_change::table)
+        .values(&RatingChangeInsert {
+          game_id,
+          player_id,
+          mode: &mode,
+          rating_before: current
+            .get(&player_id)
+            .map(|v| v.0)
+            .unwrap_or(DEFAULT_RATING),
+          rating_after: rating,
+        })
+        .execute(conn)?;
+    }
+
+    Ok(())
+  })
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "player_rating"]
+struct RatingInsert<'a> {
+  player_id: i32,
+  mode: &'a str,
+  rating: f64,
+  games: i32,
+  wins: i32,
+  losses: i32,
+  draws: i32,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "player_rating_change"]
+struct RatingChangeInsert<'a> {
+  game_id: i32,
+  player_id: i32,
+  mode: &'a str,
+  rating_before: f64,
+  rating_after: f64,
+}
@@ -0,0 +1,105 @@
+//! Team Elo.
+//!
+//! Every team plays against every other team, using the average rating of
+//! its players. The rating change of a team is applied to all of its players,
+//! scaled by the player's K factor, so new players move faster.
+
+const K_PROVISIONAL: f64 = 40.0;
+const K: f64 = 20.0;
+const PROVISIONAL_GAMES: i32 = 20;
+
+#[derive(Debug)]
+pub struct EloPlayer {
+  pub player_id: i32,
+  pub rating: f64,
+  pub games: i32,
+}
+
+#[derive(Debug)]
+pub struct EloTeam {
+  pub players: Vec<EloPlayer>,
+  /// 1 for a win, 0.5 for a draw, 0 for a loss
+  pub score: f64,
+}
+
+/// Returns the new rating of each player
+pub fn update_ratings(teams: &[EloTeam]) -> Vec<(i32, f64)> {
+  let teams: Vec<_> = teams.iter().filter(|t| !t.players.is_empty()).collect();
+  if teams.len() < 2 {
+    return vec![];
+  }
+
+  let averages: Vec<f64> = teams
+    .iter()
+    .map(|t| t.players.iter().map(|p| p.rating).sum::<f64>() / t.players.len() as f64)
+    .collect();
+
+  let mut updated = vec![];
+  for (i, team) in teams.iter().enumerate() {
+    let mut delta = 0.0;
+    for (j, other) in teams.iter().enumerate() {
+      if i == j {
+        continue;
+      }
+      let actual = if team.score > other.score {
+        1.0
+      } else if team.score < other.score {
+        0.0
+      } else {
+        0.5
+      };
+      let expected = 1.0 / (1.0 + 10_f64.powf((averages[j] - averages[i]) / 400.0));
+      delta += actual - expected;
+    }
+    delta /= (teams.len() - 1) as f64;
+
+    for player in &team.players {
+      let k = if player.games < PROVISIONAL_GAMES {
+        K_PROVISIONAL
+      } else {
+        K
+      };
+      updated.push((player.player_id, player.rating + k * delta));
+    }
+  }
+  updated
+}
+
+#[test]
+fn test_update_ratings() {
+  fn team(players: &[(i32, f64, i32)], score: f64) -> EloTeam {
+    EloTeam {
+      players: players
+        .iter()
+        .map(|(player_id, rating, games)| EloPlayer {
+          player_id: *player_id,
+          rating: *rating,
+          games: *games,
+        })
+        .collect(),
+      score,
+    }
+  }
+
+  assert!(update_ratings(&[team(&[(1, 1500.0, 0)], 1.0)]).is_empty());
+
+  // equal ratings, provisional winner and established loser
+  assert_eq!(
+    update_ratings(&[team(&[(1, 1500.0, 0)], 1.0), team(&[(2, 1500.0, 100)], 0.0)]),
+    vec![(1, 1520.0), (2, 1490.0)]
+  );
+
+  // draw between equal teams changes nothing
+  assert_eq!(
+    update_ratings(&[
+      team(&[(1, 1500.0, 30), (2, 1600.0, 30)], 0.5),
+      team(&[(3, 1550.0, 30), (4, 1550.0, 30)], 0.5)
+    ]),
+    vec![(1, 1500.0), (2, 1600.0), (3, 1550.0), (4, 1550.0)]
+  );
+
+  // an upset moves more than an expected win
+  let upset = update_ratings(&[team(&[(1, 1300.0, 30)], 1.0), team(&[(2, 1700.0, 30)], 0.0)]);
+  let expected = update_ratings(&[team(&[(1, 1700.0, 30)], 1.0), team(&[(2, 1300.0, 30)], 0.0)]);
+  assert!(upset[0].1 - 1300.0 > expected[0].1 - 1700.0);
+}
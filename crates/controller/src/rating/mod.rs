@@ -0,0 +1,5 @@
+pub mod db;
+mod elo;
+mod types;
+
+pub use types::*;
@@ -0,0 +1,56 @@
+use s2_grpc_utils::{S2ProtoPack, S2ProtoUnpack};
+use serde::{Deserialize, Serialize};
+
+use crate::player::PlayerRef;
+use crate::schema::player_rating;
+
+/// Rating of players without rated games
+pub const DEFAULT_RATING: f64 = 1500.0;
+
+#[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone, Queryable)]
+#[s2_grpc(message_type(
+  flo_grpc::player::PlayerRating,
+  flo_net::proto::flo_connect::PlayerRating
+))]
+pub struct PlayerRating {
+  pub mode: String,
+  pub rating: f64,
+  pub games: i32,
+  pub wins: i32,
+  pub losses: i32,
+  pub draws: i32,
+}
+
+pub(crate) type PlayerRatingColumns = (
+  player_rating::dsl::mode,
+  player_rating::dsl::rating,
+  player_rating::dsl::games,
+  player_rating::dsl::wins,
+  player_rating::dsl::losses,
+  player_rating::dsl::draws,
+);
+
+impl PlayerRating {
+  pub(crate) const COLUMNS: PlayerRatingColumns = (
+    player_rating::dsl::mode,
+    player_rating::dsl::rating,
+    player_rating::dsl::games,
+    player_rating::dsl::wins,
+    player_rating::dsl::losses,
+    player_rating::dsl::draws,
+  );
+}
+
+#[derive(Debug, S2ProtoPack)]
+#[s2_grpc(message_type = "flo_grpc::player::LeaderboardEntry")]
+pub struct LeaderboardEntry {
+  pub rank: i32,
+  pub player: PlayerRef,
+  pub rating: PlayerRating,
+}
+
+#[derive(Debug)]
+pub struct Leaderboard {
+  pub entries: Vec<LeaderboardEntry>,
+  pub next_offset: Option<i32>,
+}
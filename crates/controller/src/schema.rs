@@ -4,7 +4,29 @@ diesel::table! {
     api_client (id) {
         id -> Int4,
         name -> Text,
+        created_at -> Timestamptz,
+        scopes -> Jsonb,
+    }
+}
+
+diesel::table! {
+    api_client_key (id) {
+        id -> Int4,
+        api_client_id -> Int4,
         secret_key -> Text,
+        expires_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Int4,
+        api_client_id -> Nullable<Int4>,
+        action -> Text,
+        target_type -> Nullable<Text>,
+        target_id -> Nullable<Text>,
+        params -> Jsonb,
         created_at -> Timestamptz,
     }
 }
@@ -32,6 +54,24 @@ diesel::table! {
         game_version -> Nullable<Text>,
         enable_ping_equalizer -> Bool,
         flo_tv_delay_override_secs -> Nullable<Int4>,
+        result_winning_team -> Nullable<Int4>,
+        result_reported_at -> Nullable<Timestamptz>,
+        enable_adaptive_step -> Bool,
+        observer_chat_policy -> Int4,
+        rating_mode -> Nullable<Text>,
+        host_player_id -> Int4,
+    }
+}
+
+diesel::table! {
+    game_desync (id) {
+        id -> Int4,
+        game_id -> Int4,
+        tick -> Int4,
+        time -> Int4,
+        game_version -> Nullable<Text>,
+        players -> Jsonb,
+        created_at -> Timestamptz,
     }
 }
 
@@ -52,6 +92,11 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         client_status_synced_node_conn_id -> Nullable<Int8>,
+        result -> Nullable<Int4>,
+        leave_reason -> Nullable<Int4>,
+        removed_reason -> Nullable<Int4>,
+        is_referee -> Bool,
+        ready -> Bool,
     }
 }
 
@@ -63,6 +108,59 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    map_info (id) {
+        id -> Int4,
+        sha1 -> Text,
+        checksum -> Int8,
+        name -> Text,
+        description -> Text,
+        author -> Text,
+        path -> Text,
+        width -> Int4,
+        height -> Int4,
+        suggested_players -> Text,
+        players -> Jsonb,
+        forces -> Jsonb,
+        preview_jpeg -> Nullable<Bytea>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    map_pool (id) {
+        id -> Int4,
+        api_client_id -> Int4,
+        name -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    map_pool_map (map_pool_id, map_info_id) {
+        map_pool_id -> Int4,
+        map_info_id -> Int4,
+    }
+}
+
+diesel::table! {
+    matchmaking_queue (id) {
+        id -> Int4,
+        api_client_id -> Int4,
+        name -> Text,
+        mode -> Text,
+        team_size -> Int4,
+        maps -> Jsonb,
+        enable_ping_equalizer -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        map_pool_id -> Nullable<Int4>,
+        observer_chat_policy -> Int4,
+    }
+}
+
 diesel::table! {
     node (id) {
         id -> Int4,
@@ -98,6 +196,23 @@ diesel::table! {
         ban_type -> Int4,
         ban_expires_at -> Nullable<Timestamptz>,
         created_at -> Timestamptz,
+        reason -> Nullable<Text>,
+        map_sha1 -> Text,
+        api_client_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    player_ban_audit (id) {
+        id -> Int4,
+        player_id -> Int4,
+        action -> Int4,
+        ban_type -> Int4,
+        map_sha1 -> Text,
+        ban_expires_at -> Nullable<Timestamptz>,
+        reason -> Nullable<Text>,
+        api_client_id -> Nullable<Int4>,
+        created_at -> Timestamptz,
     }
 }
 
@@ -110,20 +225,118 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    player_rating (player_id, mode) {
+        player_id -> Int4,
+        mode -> Text,
+        rating -> Float8,
+        games -> Int4,
+        wins -> Int4,
+        losses -> Int4,
+        draws -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    player_rating_change (id) {
+        id -> Int4,
+        game_id -> Int4,
+        player_id -> Int4,
+        mode -> Text,
+        rating_before -> Float8,
+        rating_after -> Float8,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    tournament (id) {
+        id -> Int4,
+        api_client_id -> Int4,
+        api_player_id -> Int4,
+        name -> Text,
+        format -> Int4,
+        status -> Int4,
+        game_settings -> Jsonb,
+        swiss_rounds -> Nullable<Int4>,
+        winner_player_id -> Nullable<Int4>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    tournament_match (id) {
+        id -> Int4,
+        tournament_id -> Int4,
+        match_no -> Int4,
+        bracket -> Int4,
+        round -> Int4,
+        sources -> Jsonb,
+        game_id -> Nullable<Int4>,
+        ended -> Bool,
+        winner_player_id -> Nullable<Int4>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    tournament_player (tournament_id, player_id) {
+        tournament_id -> Int4,
+        player_id -> Int4,
+        seed -> Int4,
+    }
+}
+
+diesel::joinable!(api_client_key -> api_client (api_client_id));
+diesel::joinable!(audit_log -> api_client (api_client_id));
 diesel::joinable!(game -> node (node_id));
 diesel::joinable!(game -> player (created_by));
+diesel::joinable!(game_desync -> game (game_id));
 diesel::joinable!(game_used_slot -> game (game_id));
 diesel::joinable!(game_used_slot -> player (player_id));
+diesel::joinable!(map_pool -> api_client (api_client_id));
+diesel::joinable!(map_pool_map -> map_info (map_info_id));
+diesel::joinable!(map_pool_map -> map_pool (map_pool_id));
+diesel::joinable!(matchmaking_queue -> api_client (api_client_id));
+diesel::joinable!(matchmaking_queue -> map_pool (map_pool_id));
 diesel::joinable!(player -> api_client (api_client_id));
+diesel::joinable!(player_ban -> api_client (api_client_id));
 diesel::joinable!(player_ban -> player (player_id));
+diesel::joinable!(player_ban_audit -> api_client (api_client_id));
+diesel::joinable!(player_ban_audit -> player (player_id));
+diesel::joinable!(player_rating -> player (player_id));
+diesel::joinable!(player_rating_change -> game (game_id));
+diesel::joinable!(player_rating_change -> player (player_id));
+diesel::joinable!(tournament -> api_client (api_client_id));
+diesel::joinable!(tournament_match -> game (game_id));
+diesel::joinable!(tournament_match -> tournament (tournament_id));
+diesel::joinable!(tournament_player -> player (player_id));
+diesel::joinable!(tournament_player -> tournament (tournament_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     api_client,
+    api_client_key,
+    audit_log,
     game,
+    game_desync,
     game_used_slot,
     map_checksum,
+    map_info,
+    map_pool,
+    map_pool_map,
+    matchmaking_queue,
     node,
     player,
     player_ban,
+    player_ban_audit,
     player_mute,
+    player_rating,
+    player_rating_change,
+    tournament,
+    tournament_match,
+    tournament_player,
 );
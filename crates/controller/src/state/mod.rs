@@ -7,9 +7,12 @@ use std::sync::Arc;
 
 use crate::error::*;
 use crate::game::state::GameRegistry;
+use crate::invite::state::InviteRegistry;
 
+use crate::matchmaking::state::MatchmakingRegistry;
 use crate::node::NodeRegistry;
 use crate::player::state::PlayerRegistry;
+use crate::tournament::state::TournamentRegistry;
 
 use crate::config::ConfigStorage;
 use crate::player::state::sender::PlayerRegistryHandle;
@@ -28,6 +31,9 @@ pub struct ControllerState {
   pub players: Addr<PlayerRegistry>,
   pub player_packet_sender: PlayerRegistryHandle,
   pub config: Addr<ConfigStorage>,
+  pub matchmaking: Addr<MatchmakingRegistry>,
+  pub tournaments: Addr<TournamentRegistry>,
+  pub invites: Addr<InviteRegistry>,
 }
 
 pub type ControllerStateRef = Arc<ControllerState>;
@@ -47,6 +53,9 @@ impl ControllerState {
     let games = registry.resolve().await?;
     let players = registry.resolve().await?;
     let config = registry.resolve().await?;
+    let matchmaking = registry.resolve().await?;
+    let tournaments = registry.resolve().await?;
+    let invites = registry.resolve().await?;
 
     Ok(ControllerState {
       db,
@@ -56,6 +65,9 @@ impl ControllerState {
       players: players.clone(),
       player_packet_sender: PlayerRegistryHandle::from(players),
       config,
+      matchmaking,
+      tournaments,
+      invites,
     })
   }
 
@@ -0,0 +1,497 @@
+//! Bracket generation and resolution.
+//!
+//! A bracket is a list of matches, each participant of a match comes from a
+//! `MatchSource`: a seed, or the winner/loser of an earlier match. Only match
+//! results are stored, the participants are resolved from the sources, so
+//! byes and advancing winners don't need to be written anywhere.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::tournament::TournamentBracket;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum MatchSource {
+  /// Index into the seeded player list, seeds out of range are byes
+  Seed(usize),
+  WinnerOf(i32),
+  LoserOf(i32),
+  Empty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BracketMatch {
+  pub match_no: i32,
+  pub bracket: TournamentBracket,
+  pub round: i32,
+  pub sources: [MatchSource; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchState {
+  /// Waiting for earlier matches
+  Waiting,
+  /// Both players are known
+  Ready,
+  /// `winner` is `None` for draws or if both sides are byes
+  Decided {
+    winner: Option<i32>,
+    loser: Option<i32>,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedMatch {
+  pub match_no: i32,
+  pub players: [Option<i32>; 2],
+  pub state: MatchState,
+}
+
+struct Builder {
+  next_match_no: i32,
+  matches: Vec<BracketMatch>,
+}
+
+impl Builder {
+  fn new(first_match_no: i32) -> Self {
+    Self {
+      next_match_no: first_match_no,
+      matches: vec![],
+    }
+  }
+
+  fn push(&mut self, bracket: TournamentBracket, round: i32, sources: [MatchSource; 2]) -> i32 {
+    let match_no = self.next_match_no;
+    self.next_match_no += 1;
+    self.matches.push(BracketMatch {
+      match_no,
+      bracket,
+      round,
+      sources,
+    });
+    match_no
+  }
+}
+
+/// Seed order of the first round, so the top seeds meet as late as possible
+fn seed_positions(size: usize) -> Vec<usize> {
+  let mut positions = vec![0];
+  while positions.len() < size {
+    let len = positions.len() * 2;
+    positions = positions
+      .into_iter()
+      .flat_map(|s| vec![s, len - 1 - s])
+      .collect();
+  }
+  positions
+}
+
+/// Returns match numbers of each round
+fn build_main_bracket(builder: &mut Builder, players: usize) -> Vec<Vec<i32>> {
+  let size = std::cmp::max(players, 2).next_power_of_two();
+  let positions = seed_positions(size);
+  let mut rounds: Vec<Vec<i32>> = vec![];
+
+  let first = positions
+    .chunks(2)
+    .map(|pair| {
+      builder.push(
+        TournamentBracket::Main,
+        1,
+        [MatchSource::Seed(pair[0]), MatchSource::Seed(pair[1])],
+      )
+    })
+    .collect();
+  rounds.push(first);
+
+  while rounds.last().map(|r| r.len()).unwrap_or_default() > 1 {
+    let round = rounds.len() as i32 + 1;
+    let next = rounds
+      .last()
+      .unwrap()
+      .chunks(2)
+      .map(|pair| {
+        builder.push(
+          TournamentBracket::Main,
+          round,
+          [
+            MatchSource::WinnerOf(pair[0]),
+            MatchSource::WinnerOf(pair[1]),
+          ],
+        )
+      })
+      .collect();
+    rounds.push(next);
+  }
+
+  rounds
+}
+
+pub fn single_elimination(players: usize) -> Vec<BracketMatch> {
+  let mut builder = Builder::new(1);
+  build_main_bracket(&mut builder, players);
+  builder.matches
+}
+
+/// Losers of the main bracket round `r` drop into the losers bracket,
+/// the winner of the losers bracket plays the main bracket winner in the grand final.
+pub fn double_elimination(players: usize) -> Vec<BracketMatch> {
+  let mut builder = Builder::new(1);
+  let main = build_main_bracket(&mut builder, players);
+  if main.len() < 2 {
+    return builder.matches;
+  }
+
+  let mut round = 1;
+  let mut prev: Vec<i32> = main[0]
+    .chunks(2)
+    .map(|pair| {
+      builder.push(
+        TournamentBracket::Losers,
+        round,
+        [MatchSource::LoserOf(pair[0]), MatchSource::LoserOf(pair[1])],
+      )
+    })
+    .collect();
+
+  for (j, dropping) in main.iter().enumerate().skip(1) {
+    round += 1;
+    prev = prev
+      .iter()
+      .zip(dropping.iter())
+      .map(|(lb, wb)| {
+        builder.push(
+          TournamentBracket::Losers,
+          round,
+          [MatchSource::WinnerOf(*lb), MatchSource::LoserOf(*wb)],
+        )
+      })
+      .collect();
+
+    if j < main.len() - 1 {
+      round += 1;
+      prev = prev
+        .chunks(2)
+        .map(|pair| {
+          builder.push(
+            TournamentBracket::Losers,
+            round,
+            [
+              MatchSource::WinnerOf(pair[0]),
+              MatchSource::WinnerOf(pair[1]),
+            ],
+          )
+        })
+        .collect();
+    }
+  }
+
+  let main_final = main.last().unwrap()[0];
+  let losers_final = prev[0];
+  builder.push(
+    TournamentBracket::GrandFinal,
+    1,
+    [
+      MatchSource::WinnerOf(main_final),
+      MatchSource::WinnerOf(losers_final),
+    ],
+  );
+
+  builder.matches
+}
+
+/// Circle method, every player plays every other player once
+pub fn round_robin(players: usize) -> Vec<BracketMatch> {
+  let mut builder = Builder::new(1);
+  if players < 2 {
+    return builder.matches;
+  }
+
+  let size = players + players % 2;
+  let mut arr: Vec<usize> = (0..size).collect();
+  for round in 1..size {
+    for i in 0..(size / 2) {
+      let (a, b) = (arr[i], arr[size - 1 - i]);
+      // the extra position of an odd player count is a bye
+      if a < players && b < players {
+        builder.push(
+          TournamentBracket::Main,
+          round as i32,
+          [MatchSource::Seed(a), MatchSource::Seed(b)],
+        );
+      }
+    }
+    arr[1..].rotate_right(1);
+  }
+
+  builder.matches
+}
+
+/// Pairs players with similar scores who haven't played each other.
+/// If the player count is odd, the lowest ranked player without a bye gets one.
+pub fn swiss_round(
+  round: i32,
+  first_match_no: i32,
+  scores: &[f64],
+  played: &BTreeSet<(usize, usize)>,
+  had_bye: &BTreeSet<usize>,
+) -> Vec<BracketMatch> {
+  let mut builder = Builder::new(first_match_no);
+  let mut ranked: Vec<usize> = (0..scores.len()).collect();
+  ranked.sort_by(|a, b| {
+    scores[*b]
+      .partial_cmp(&scores[*a])
+      .unwrap_or(std::cmp::Ordering::Equal)
+      .then_with(|| a.cmp(b))
+  });
+
+  if ranked.len() % 2 == 1 {
+    let idx = ranked
+      .iter()
+      .rposition(|seed| !had_bye.contains(seed))
+      .unwrap_or(ranked.len() - 1);
+    let seed = ranked.remove(idx);
+    builder.push(
+      TournamentBracket::Main,
+      round,
+      [MatchSource::Seed(seed), MatchSource::Empty],
+    );
+  }
+
+  while !ranked.is_empty() {
+    let a = ranked.remove(0);
+    let idx = ranked
+      .iter()
+      .position(|b| !played.contains(&(a, *b)) && !played.contains(&(*b, a)))
+      .unwrap_or(0);
+    let b = ranked.remove(idx);
+    builder.push(
+      TournamentBracket::Main,
+      round,
+      [MatchSource::Seed(a), MatchSource::Seed(b)],
+    );
+  }
+
+  builder.matches
+}
+
+/// Resolves the players and states of all matches.
+/// `results` maps match numbers to their winner, `None` for draws.
+pub fn resolve(
+  matches: &[BracketMatch],
+  seeds: &[i32],
+  results: &BTreeMap<i32, Option<i32>>,
+) -> Vec<ResolvedMatch> {
+  enum Side {
+    Pending,
+    Player(i32),
+    Bye,
+  }
+
+  let mut resolved: BTreeMap<i32, ResolvedMatch> = BTreeMap::new();
+  let mut sorted: Vec<&BracketMatch> = matches.iter().collect();
+  sorted.sort_by_key(|m| m.match_no);
+
+  for m in sorted {
+    let sides: Vec<Side> = m
+      .sources
+      .iter()
+      .map(|source| match *source {
+        MatchSource::Seed(idx) => seeds
+          .get(idx)
+          .cloned()
+          .map(Side::Player)
+          .unwrap_or(Side::Bye),
+        MatchSource::Empty => Side::Bye,
+        MatchSource::WinnerOf(no) | MatchSource::LoserOf(no) => {
+          let winner = match *source {
+            MatchSource::WinnerOf(_) => true,
+            _ => false,
+          };
+          match resolved.get(&no).map(|m| m.state) {
+            Some(MatchState::Decided {
+              winner: w,
+              loser: l,
+            }) => match if winner { w } else { l } {
+              Some(id) => Side::Player(id),
+              None => Side::Bye,
+            },
+            _ => Side::Pending,
+          }
+        }
+      })
+      .collect();
+
+    let players = [
+      match sides[0] {
+        Side::Player(id) => Some(id),
+        _ => None,
+      },
+      match sides[1] {
+        Side::Player(id) => Some(id),
+        _ => None,
+      },
+    ];
+
+    let state = match (&sides[0], &sides[1]) {
+      (Side::Pending, _) | (_, Side::Pending) => MatchState::Waiting,
+      (Side::Player(a), Side::Player(b)) => match results.get(&m.match_no) {
+        Some(Some(winner)) => MatchState::Decided {
+          winner: Some(*winner),
+          loser: Some(if winner == a { *b } else { *a }),
+        },
+        Some(None) => MatchState::Decided {
+          winner: None,
+          loser: None,
+        },
+        None => MatchState::Ready,
+      },
+      (Side::Player(id), Side::Bye) | (Side::Bye, Side::Player(id)) => MatchState::Decided {
+        winner: Some(*id),
+        loser: None,
+      },
+      (Side::Bye, Side::Bye) => MatchState::Decided {
+        winner: None,
+        loser: None,
+      },
+    };
+
+    resolved.insert(
+      m.match_no,
+      ResolvedMatch {
+        match_no: m.match_no,
+        players,
+        state,
+      },
+    );
+  }
+
+  resolved.into_iter().map(|(_, m)| m).collect()
+}
+
+/// Wins count 1, draws count 0.5, byes count as wins
+pub fn scores(resolved: &[ResolvedMatch]) -> BTreeMap<i32, f64> {
+  let mut scores = BTreeMap::new();
+  for m in resolved {
+    if let MatchState::Decided { winner, .. } = m.state {
+      match winner {
+        Some(winner) => *scores.entry(winner).or_default() += 1.0,
+        None => {
+          for player_id in m.players.iter().flatten() {
+            *scores.entry(*player_id).or_default() += 0.5;
+          }
+        }
+      }
+    }
+  }
+  scores
+}
+
+#[test]
+fn test_single_elimination() {
+  let matches = single_elimination(5);
+  assert_eq!(matches.len(), 7);
+  assert_eq!(
+    matches[0].sources,
+    [MatchSource::Seed(0), MatchSource::Seed(7)]
+  );
+
+  let seeds = vec![10, 20, 30, 40, 50];
+  let resolved = resolve(&matches, &seeds, &BTreeMap::new());
+  // top seeds get byes
+  assert_eq!(
+    resolved[0].state,
+    MatchState::Decided {
+      winner: Some(10),
+      loser: None
+    }
+  );
+  let ready: Vec<_> = resolved
+    .iter()
+    .filter(|m| m.state == MatchState::Ready)
+    .map(|m| m.players)
+    .collect();
+  // seeds 2 and 3 advance through byes and can play before the first round ends
+  assert_eq!(ready, vec![[Some(40), Some(50)], [Some(20), Some(30)]]);
+
+  let results = vec![(2, Some(40)), (5, Some(10)), (6, Some(20)), (7, Some(10))]
+    .into_iter()
+    .collect();
+  let resolved = resolve(&matches, &seeds, &results);
+  assert_eq!(
+    resolved.last().unwrap().state,
+    MatchState::Decided {
+      winner: Some(10),
+      loser: Some(20)
+    }
+  );
+}
+
+#[test]
+fn test_double_elimination() {
+  let matches = double_elimination(4);
+  // 3 main, 2 losers, 1 grand final
+  assert_eq!(matches.len(), 6);
+  let seeds = vec![1, 2, 3, 4];
+  // main: 1 vs 4 (1), 2 vs 3 (2), final (3)
+  let results = vec![
+    (1, Some(1)),
+    (2, Some(2)),
+    (3, Some(1)),
+    (4, Some(4)),
+    (5, Some(2)),
+  ]
+  .into_iter()
+  .collect();
+  let resolved = resolve(&matches, &seeds, &results);
+  assert_eq!(resolved[3].players, [Some(4), Some(3)]);
+  assert_eq!(resolved[4].players, [Some(4), Some(2)]);
+  assert_eq!(resolved[5].players, [Some(1), Some(2)]);
+  assert_eq!(resolved[5].state, MatchState::Ready);
+
+  assert_eq!(double_elimination(8).len(), 7 + 6 + 1);
+  assert_eq!(double_elimination(2).len(), 1);
+}
+
+#[test]
+fn test_round_robin() {
+  for n in 2..8 {
+    let matches = round_robin(n);
+    assert_eq!(matches.len(), n * (n - 1) / 2);
+    let mut pairs = BTreeSet::new();
+    for m in &matches {
+      if let [MatchSource::Seed(a), MatchSource::Seed(b)] = m.sources {
+        assert!(pairs.insert((a.min(b), a.max(b))));
+      } else {
+        panic!("unexpected sources");
+      }
+    }
+  }
+}
+
+#[test]
+fn test_swiss_round() {
+  let played: BTreeSet<_> = vec![(0, 1)].into_iter().collect();
+  let had_bye: BTreeSet<_> = vec![4].into_iter().collect();
+  let matches = swiss_round(2, 10, &[1.0, 1.0, 0.0, 0.0, 1.0], &played, &had_bye);
+  assert_eq!(
+    matches.iter().map(|m| m.sources).collect::<Vec<_>>(),
+    vec![
+      [MatchSource::Seed(3), MatchSource::Empty],
+      [MatchSource::Seed(0), MatchSource::Seed(4)],
+      [MatchSource::Seed(1), MatchSource::Seed(2)],
+    ]
+  );
+  assert_eq!(matches[0].match_no, 10);
+
+  let resolved = resolve(
+    &matches,
+    &[1, 2, 3, 4, 5],
+    &vec![(11, None)].into_iter().collect(),
+  );
+  let scores = scores(&resolved);
+  assert_eq!(scores.get(&4), Some(&1.0));
+  assert_eq!(scores.get(&1), Some(&0.5));
+  assert_eq!(scores.get(&5), Some(&0.5));
+}
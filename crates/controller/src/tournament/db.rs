@@ -0,0 +1,511 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::db::DbConn;
+use crate::error::*;
+use crate::game::{GameStatus, PlayerGameResult};
+use crate::player::PlayerRef;
+use crate::schema::{game, player, tournament, tournament_match, tournament_player};
+use crate::tournament::bracket::{self, BracketMatch, MatchState};
+use crate::tournament::{
+  CreateTournamentParams, Tournament, TournamentBracket, TournamentFormat, TournamentMatch,
+  TournamentMatchStatus, TournamentPlayer, TournamentStatus,
+};
+
+const MAX_PLAYERS: usize = 256;
+
+/// Games ended without a result within this duration are replayed
+const RESULT_GRACE_PERIOD_SECS: i64 = 60;
+
+pub fn create(
+  conn: &DbConn,
+  api_client_id: i32,
+  api_player_id: i32,
+  params: CreateTournamentParams,
+) -> Result<Tournament> {
+  if params.name.is_empty() {
+    return Err(Error::TournamentInvalid("name is empty"));
+  }
+
+  let player_count = params.player_ids.len();
+  if player_count < 2 || player_count > MAX_PLAYERS {
+    return Err(Error::TournamentInvalid("player count out of range"));
+  }
+
+  if params.player_ids.iter().collect::<BTreeSet<_>>().len() != player_count {
+    return Err(Error::TournamentInvalid("duplicate player"));
+  }
+
+  if params.game_settings.map.players.len() < 2 {
+    return Err(Error::TournamentInvalid(
+      "map does not have enough player slots",
+    ));
+  }
+
+  let found: i64 = player::table
+    .filter(
+      player::id
+        .eq_any(&params.player_ids)
+        .and(player::api_client_id.eq(api_client_id)),
+    )
+    .count()
+    .get_result(conn)?;
+  if found != player_count as i64 {
+    return Err(Error::PlayerNotFound);
+  }
+
+  let swiss_rounds = match params.format {
+    TournamentFormat::Swiss => {
+      let default = (player_count as f64).log2().ceil() as i32;
+      let rounds = params.swiss_rounds.unwrap_or(default);
+      if rounds < 1 || rounds >= player_count as i32 {
+        return Err(Error::TournamentInvalid("swiss rounds out of range"));
+      }
+      Some(rounds)
+    }
+    _ => None,
+  };
+
+  let mut player_ids = params.player_ids;
+  if let Some(mode) = params.seed_rating_mode.as_ref() {
+    let mut ratings = BTreeMap::new();
+    for player_id in &player_ids {
+      ratings.insert(
+        *player_id,
+        crate::rating::db::get_rating(conn, *player_id, mode)?,
+      );
+    }
+    // stable sort keeps the given order for equal ratings
+    player_ids.sort_by(|a, b| {
+      ratings[b]
+        .partial_cmp(&ratings[a])
+        .unwrap_or(std::cmp::Ordering::Equal)
+    });
+  }
+
+  let matches = match params.format {
+    TournamentFormat::SingleElimination => bracket::single_elimination(player_count),
+    TournamentFormat::DoubleElimination => bracket::double_elimination(player_count),
+    TournamentFormat::RoundRobin => bracket::round_robin(player_count),
+    TournamentFormat::Swiss => bracket::swiss_round(
+      1,
+      1,
+      &vec![0.0; player_count],
+      &BTreeSet::new(),
+      &BTreeSet::new(),
+    ),
+  };
+
+  let id = conn.transaction(|| -> Result<i32> {
+    let id: i32 = diesel::insert_into(tournament::table)
+      .values(&Insert {
+        api_client_id,
+        api_player_id,
+        name: &params.name,
+        format: params.format,
+        status: TournamentStatus::Running,
+        game_settings: serde_json::to_value(&params.game_settings)?,
+        swiss_rounds,
+      })
+      .returning(tournament::id)
+      .get_result(conn)?;
+
+    let players: Vec<PlayerInsert> = player_ids
+      .iter()
+      .enumerate()
+      .map(|(seed, player_id)| PlayerInsert {
+        tournament_id: id,
+        player_id: *player_id,
+        seed: seed as i32,
+      })
+      .collect();
+    diesel::insert_into(tournament_player::table)
+      .values(&players)
+      .execute(conn)?;
+
+    insert_matches(conn, id, &matches)?;
+
+    Ok(id)
+  })?;
+
+  load(conn, id)
+}
+
+pub fn get(conn: &DbConn, api_client_id: i32, id: i32) -> Result<Tournament> {
+  let tournament = load(conn, id)?;
+  if tournament.api_client_id != api_client_id {
+    return Err(Error::TournamentNotFound);
+  }
+  Ok(tournament)
+}
+
+/// Stops creating games, running match games are not affected
+pub fn cancel(conn: &DbConn, api_client_id: i32, id: i32) -> Result<Tournament> {
+  let updated = diesel::update(
+    tournament::table.filter(
+      tournament::id
+        .eq(id)
+        .and(tournament::api_client_id.eq(api_client_id))
+        .and(tournament::status.eq(TournamentStatus::Running)),
+    ),
+  )
+  .set((
+    tournament::status.eq(TournamentStatus::Cancelled),
+    tournament::updated_at.eq(diesel::dsl::now),
+  ))
+  .execute(conn)?;
+  if updated == 0 {
+    let tournament = get(conn, api_client_id, id)?;
+    if tournament.status != TournamentStatus::Cancelled {
+      return Err(Error::TournamentInvalid("tournament is not running"));
+    }
+  }
+  load(conn, id)
+}
+
+pub fn get_running_ids(conn: &DbConn) -> Result<Vec<i32>> {
+  tournament::table
+    .select(tournament::id)
+    .filter(tournament::status.eq(TournamentStatus::Running))
+    .order(tournament::id)
+    .load(conn)
+    .map_err(Into::into)
+}
+
+pub fn update_match_game(conn: &DbConn, match_id: i32, game_id: Option<i32>) -> Result<()> {
+  diesel::update(tournament_match::table.find(match_id))
+    .set((
+      tournament_match::game_id.eq(game_id),
+      tournament_match::updated_at.eq(diesel::dsl::now),
+    ))
+    .execute(conn)?;
+  Ok(())
+}
+
+/// Records results of finished match games, generates the next swiss round
+/// and ends the tournament once it's decided.
+pub fn advance(conn: &DbConn, id: i32) -> Result<Tournament> {
+  let tournament = load(conn, id)?;
+  if tournament.status != TournamentStatus::Running {
+    return Ok(tournament);
+  }
+
+  conn.transaction(|| -> Result<()> {
+    update_match_results(conn, &tournament)?;
+    let tournament = load(conn, id)?;
+    if !tournament.is_decided() {
+      return Ok(());
+    }
+
+    let rounds = tournament
+      .matches
+      .iter()
+      .map(|m| m.round)
+      .max()
+      .unwrap_or(0);
+    if tournament.format == TournamentFormat::Swiss && rounds < tournament.swiss_rounds.unwrap_or(0)
+    {
+      let matches = next_swiss_round(&tournament, rounds + 1);
+      return insert_matches(conn, id, &matches);
+    }
+
+    let winner_player_id = if tournament.is_elimination() {
+      tournament.matches.last().and_then(|m| m.winner_player_id)
+    } else {
+      // players are ordered by seed, so ties go to the higher seed
+      tournament
+        .players
+        .iter()
+        .fold(None, |top: Option<&TournamentPlayer>, p| match top {
+          Some(top) if top.score >= p.score => Some(top),
+          _ => Some(p),
+        })
+        .map(|p| p.player.id)
+    };
+
+    diesel::update(tournament::table.find(id))
+      .set((
+        tournament::status.eq(TournamentStatus::Ended),
+        tournament::winner_player_id.eq(winner_player_id),
+        tournament::updated_at.eq(diesel::dsl::now),
+      ))
+      .execute(conn)?;
+    Ok(())
+  })?;
+
+  load(conn, id)
+}
+
+fn update_match_results(conn: &DbConn, tournament: &Tournament) -> Result<()> {
+  let replay_before = Utc::now() - Duration::seconds(RESULT_GRACE_PERIOD_SECS);
+  for m in &tournament.matches {
+    let game_id = match (m.status, m.game_id) {
+      (TournamentMatchStatus::Playing, Some(game_id)) => game_id,
+      _ => continue,
+    };
+
+    let game: Option<(GameStatus, DateTime<Utc>)> = game::table
+      .find(game_id)
+      .select((game::status, game::updated_at))
+      .first(conn)
+      .optional()?;
+    let (status, updated_at) = if let Some(v) = game {
+      v
+    } else {
+      update_match_game(conn, m.id, None)?;
+      continue;
+    };
+
+    let result = crate::game::db::get_result(conn, game_id)?;
+    let winner = result.as_ref().and_then(|r| {
+      r.players
+        .iter()
+        .find(|p| p.result == PlayerGameResult::Win)
+        .map(|p| p.player_id)
+    });
+    let draw = result
+      .as_ref()
+      .map(|r| r.players.iter().any(|p| p.result == PlayerGameResult::Draw))
+      .unwrap_or_default();
+
+    if winner.is_some() || (draw && !tournament.is_elimination()) {
+      diesel::update(tournament_match::table.find(m.id))
+        .set((
+          tournament_match::ended.eq(true),
+          tournament_match::winner_player_id.eq(winner),
+          tournament_match::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+    } else if result.is_some() || (!status.is_active() && updated_at < replay_before) {
+      // eliminations can't end in a draw, games without a result are replayed
+      tracing::info!(
+        tournament_id = tournament.id,
+        match_no = m.match_no,
+        game_id,
+        "replay match"
+      );
+      update_match_game(conn, m.id, None)?;
+    }
+  }
+  Ok(())
+}
+
+fn next_swiss_round(tournament: &Tournament, round: i32) -> Vec<BracketMatch> {
+  let seed_map: BTreeMap<i32, usize> = tournament
+    .players
+    .iter()
+    .map(|p| (p.player.id, p.seed as usize))
+    .collect();
+  let mut scores = vec![0.0; tournament.players.len()];
+  for p in &tournament.players {
+    scores[p.seed as usize] = p.score;
+  }
+
+  let mut played = BTreeSet::new();
+  let mut had_bye = BTreeSet::new();
+  for m in &tournament.matches {
+    match (m.player_a_id, m.player_b_id) {
+      (Some(a), Some(b)) => {
+        played.insert((seed_map[&a], seed_map[&b]));
+      }
+      (Some(id), None) | (None, Some(id)) => {
+        had_bye.insert(seed_map[&id]);
+      }
+      (None, None) => {}
+    }
+  }
+
+  let first_match_no = tournament
+    .matches
+    .iter()
+    .map(|m| m.match_no)
+    .max()
+    .unwrap_or(0)
+    + 1;
+  bracket::swiss_round(round, first_match_no, &scores, &played, &had_bye)
+}
+
+fn insert_matches(conn: &DbConn, tournament_id: i32, matches: &[BracketMatch]) -> Result<()> {
+  let rows = matches
+    .iter()
+    .map(|m| {
+      Ok(MatchInsert {
+        tournament_id,
+        match_no: m.match_no,
+        bracket: m.bracket,
+        round: m.round,
+        sources: serde_json::to_value(&m.sources)?,
+      })
+    })
+    .collect::<Result<Vec<_>>>()?;
+  diesel::insert_into(tournament_match::table)
+    .values(&rows)
+    .execute(conn)?;
+  Ok(())
+}
+
+fn load(conn: &DbConn, id: i32) -> Result<Tournament> {
+  let row: Row = tournament::table
+    .find(id)
+    .first(conn)
+    .optional()?
+    .ok_or_else(|| Error::TournamentNotFound)?;
+  let players: Vec<(PlayerRef, i32)> = tournament_player::table
+    .inner_join(player::table)
+    .select((PlayerRef::COLUMNS, tournament_player::seed))
+    .filter(tournament_player::tournament_id.eq(id))
+    .order(tournament_player::seed)
+    .load(conn)?;
+  let matches: Vec<MatchRow> = tournament_match::table
+    .select((
+      tournament_match::id,
+      tournament_match::match_no,
+      tournament_match::bracket,
+      tournament_match::round,
+      tournament_match::sources,
+      tournament_match::game_id,
+      tournament_match::ended,
+      tournament_match::winner_player_id,
+    ))
+    .filter(tournament_match::tournament_id.eq(id))
+    .order(tournament_match::match_no)
+    .load(conn)?;
+  row.into_tournament(players, matches)
+}
+
+#[derive(Debug, Queryable)]
+struct Row {
+  id: i32,
+  api_client_id: i32,
+  api_player_id: i32,
+  name: String,
+  format: TournamentFormat,
+  status: TournamentStatus,
+  game_settings: Value,
+  swiss_rounds: Option<i32>,
+  winner_player_id: Option<i32>,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+}
+
+impl Row {
+  fn into_tournament(
+    self,
+    players: Vec<(PlayerRef, i32)>,
+    rows: Vec<MatchRow>,
+  ) -> Result<Tournament> {
+    let seeds: Vec<i32> = players.iter().map(|(p, _)| p.id).collect();
+    let bracket = rows
+      .iter()
+      .map(|row| {
+        Ok(BracketMatch {
+          match_no: row.match_no,
+          bracket: row.bracket,
+          round: row.round,
+          sources: serde_json::from_value(row.sources.clone())?,
+        })
+      })
+      .collect::<Result<Vec<_>>>()?;
+    let results = rows
+      .iter()
+      .filter(|row| row.ended)
+      .map(|row| (row.match_no, row.winner_player_id))
+      .collect();
+    let resolved = bracket::resolve(&bracket, &seeds, &results);
+    let scores = bracket::scores(&resolved);
+
+    let matches = rows
+      .into_iter()
+      .zip(bracket.into_iter())
+      .zip(resolved.into_iter())
+      .map(|((row, m), resolved)| {
+        let (status, winner_player_id) = match resolved.state {
+          MatchState::Waiting => (TournamentMatchStatus::Waiting, None),
+          MatchState::Ready if row.game_id.is_some() => (TournamentMatchStatus::Playing, None),
+          MatchState::Ready => (TournamentMatchStatus::Ready, None),
+          MatchState::Decided { winner, .. } if row.ended => (TournamentMatchStatus::Ended, winner),
+          MatchState::Decided { winner, .. } => (TournamentMatchStatus::Bye, winner),
+        };
+        TournamentMatch {
+          id: row.id,
+          match_no: row.match_no,
+          bracket: row.bracket,
+          round: row.round,
+          status,
+          player_a_id: resolved.players[0],
+          player_b_id: resolved.players[1],
+          game_id: row.game_id,
+          winner_player_id,
+          sources: m.sources,
+        }
+      })
+      .collect();
+
+    Ok(Tournament {
+      id: self.id,
+      api_client_id: self.api_client_id,
+      api_player_id: self.api_player_id,
+      name: self.name,
+      format: self.format,
+      status: self.status,
+      game_settings: serde_json::from_value(self.game_settings)?,
+      swiss_rounds: self.swiss_rounds,
+      winner_player_id: self.winner_player_id,
+      players: players
+        .into_iter()
+        .map(|(player, seed)| TournamentPlayer {
+          score: scores.get(&player.id).cloned().unwrap_or_default(),
+          player,
+          seed,
+        })
+        .collect(),
+      matches,
+      created_at: self.created_at,
+      updated_at: self.updated_at,
+    })
+  }
+}
+
+#[derive(Debug, Queryable)]
+struct MatchRow {
+  id: i32,
+  match_no: i32,
+  bracket: TournamentBracket,
+  round: i32,
+  sources: Value,
+  game_id: Option<i32>,
+  ended: bool,
+  winner_player_id: Option<i32>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "tournament"]
+struct Insert<'a> {
+  api_client_id: i32,
+  api_player_id: i32,
+  name: &'a str,
+  format: TournamentFormat,
+  status: TournamentStatus,
+  game_settings: Value,
+  swiss_rounds: Option<i32>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "tournament_player"]
+struct PlayerInsert {
+  tournament_id: i32,
+  player_id: i32,
+  seed: i32,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "tournament_match"]
+struct MatchInsert {
+  tournament_id: i32,
+  match_no: i32,
+  bracket: TournamentBracket,
+  round: i32,
+  sources: Value,
+}
@@ -0,0 +1,6 @@
+mod bracket;
+pub mod db;
+pub(crate) mod state;
+mod types;
+
+pub use types::*;
@@ -0,0 +1,467 @@
+use crate::error::*;
+use crate::game::db::CreateGameAsBotParams;
+use crate::game::state::cancel::CancelGame;
+use crate::game::state::create::CreateGameAsBot;
+use crate::game::state::node::{is_node_full, list_available_node_ids, GetNodeGames};
+use crate::game::state::registry::Remove;
+use crate::game::state::start::{StartGameCheckAsBot, StartGameCheckAsBotResult};
+use crate::game::state::GameRegistry;
+use crate::game::{Computer, CreateGameSlot, Race, SlotSettings, SlotStatus};
+use crate::node::select::select_node;
+use crate::node::NodeRegistry;
+use crate::player::state::ping::GetPlayersPingSnapshot;
+use crate::player::state::PlayerRegistry;
+use crate::state::{ActorMapExt, Data};
+use crate::tournament::{
+  CreateTournamentParams, Tournament, TournamentMatch, TournamentMatchStatus, TournamentStatus,
+};
+use bs_diesel_utils::ExecutorRef;
+use flo_state::{async_trait, Actor, Addr, Context, Handler, Message, RegistryRef, Service};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::sleep;
+
+const ADVANCE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Creates match games of running tournaments and advances brackets from game results.
+/// The database is the source of truth, so tournaments continue after a restart.
+pub struct TournamentRegistry {
+  db: ExecutorRef,
+  games: Addr<GameRegistry>,
+  players: Addr<PlayerRegistry>,
+  nodes: Addr<NodeRegistry>,
+  /// match id -> player ids, matches with games being created
+  starting: BTreeMap<i32, [i32; 2]>,
+}
+
+impl TournamentRegistry {
+  async fn advance_all(&mut self, ctx: &mut Context<Self>) -> Result<()> {
+    let ids = self
+      .db
+      .exec(|conn| crate::tournament::db::get_running_ids(conn))
+      .await?;
+
+    let mut pending = vec![];
+    for id in ids {
+      let tournament = match self
+        .db
+        .exec(move |conn| crate::tournament::db::advance(conn, id))
+        .await
+      {
+        Ok(tournament) => tournament,
+        Err(err) => {
+          tracing::error!(tournament_id = id, "advance tournament: {}", err);
+          continue;
+        }
+      };
+      if tournament.status == TournamentStatus::Ended {
+        tracing::info!(
+          tournament_id = id,
+          winner_player_id = ?tournament.winner_player_id,
+          "tournament ended"
+        );
+        continue;
+      }
+      pending.push(tournament);
+    }
+
+    // a player plays one match at a time
+    let mut busy: BTreeSet<i32> = self.starting.values().flatten().cloned().collect();
+    for tournament in &pending {
+      for m in &tournament.matches {
+        if m.status == TournamentMatchStatus::Playing {
+          busy.extend(m.player_a_id.iter().chain(m.player_b_id.iter()));
+        }
+      }
+    }
+
+    let mut candidates = vec![];
+    for tournament in &pending {
+      for m in &tournament.matches {
+        if m.status != TournamentMatchStatus::Ready || self.starting.contains_key(&m.id) {
+          continue;
+        }
+        let players = match (m.player_a_id, m.player_b_id) {
+          (Some(a), Some(b)) => [a, b],
+          _ => continue,
+        };
+        if players.iter().any(|id| busy.contains(id)) {
+          continue;
+        }
+        busy.extend(players.iter());
+        candidates.push((tournament, m, players));
+      }
+    }
+
+    if candidates.is_empty() {
+      return Ok(());
+    }
+
+    let ping_map = self
+      .players
+      .send(GetPlayersPingSnapshot {
+        players: candidates
+          .iter()
+          .flat_map(|(_, _, players)| players.iter().cloned())
+          .collect(),
+      })
+      .await?
+      .map;
+    let mut node_games = self.games.send(GetNodeGames).await?;
+    let mut node_ids = list_available_node_ids(&self.nodes, &node_games).await?;
+
+    for (tournament, m, players) in candidates {
+      // players disconnected from the controller can't receive the game
+      if !players.iter().all(|id| ping_map.contains_key(id)) {
+        continue;
+      }
+
+      let in_game = self
+        .db
+        .exec(move |conn| -> Result<bool> {
+          for player_id in &players {
+            if !crate::game::db::get_player_active_slots(conn, *player_id)?.is_empty() {
+              return Ok(true);
+            }
+          }
+          Ok(false)
+        })
+        .await?;
+      if in_game {
+        continue;
+      }
+
+      let node_id = match tournament.game_settings.node_id {
+        Some(node_id) => node_id,
+        None => {
+          let ping_maps = players
+            .iter()
+            .filter_map(|id| Some((*id, ping_map.get(id)?.clone())))
+            .collect();
+          match select_node(&node_ids, &ping_maps) {
+            Some(selection) => selection.node_id,
+            None => {
+              tracing::debug!(
+                tournament_id = tournament.id,
+                match_no = m.match_no,
+                "no node available for players: {:?}",
+                players
+              );
+              continue;
+            }
+          }
+        }
+      };
+
+      // games created in this pass are not in the registry yet
+      *node_games.entry(node_id).or_default() += 1;
+      if is_node_full(&node_games, node_id) {
+        node_ids.retain(|id| *id != node_id);
+      }
+
+      self.starting.insert(m.id, players);
+      let start_ctx = StartMatchContext {
+        db: self.db.clone(),
+        games: self.games.clone(),
+        addr: ctx.addr(),
+      };
+      spawn_start_match(
+        start_ctx,
+        PendingMatch {
+          tournament: tournament.clone(),
+          tournament_match: m.clone(),
+          players,
+          node_id,
+        },
+      );
+    }
+
+    Ok(())
+  }
+}
+
+fn spawn_start_match(ctx: StartMatchContext, item: PendingMatch) {
+  tokio::spawn(async move {
+    let tournament_id = item.tournament.id;
+    let match_id = item.tournament_match.id;
+    if let Err(err) = ctx.start(item).await {
+      tracing::error!(tournament_id, match_id, "start match: {}", err);
+    }
+    ctx.addr.notify(StartMatchDone { match_id }).await.ok();
+  });
+}
+
+struct PendingMatch {
+  tournament: Tournament,
+  tournament_match: TournamentMatch,
+  players: [i32; 2],
+  node_id: i32,
+}
+
+struct StartMatchContext {
+  db: ExecutorRef,
+  games: Addr<GameRegistry>,
+  addr: Addr<TournamentRegistry>,
+}
+
+impl StartMatchContext {
+  async fn start(
+    &self,
+    PendingMatch {
+      tournament,
+      tournament_match,
+      players,
+      node_id,
+    }: PendingMatch,
+  ) -> Result<()> {
+    let settings = tournament.game_settings.clone();
+    let slots = players
+      .iter()
+      .enumerate()
+      .map(|(idx, player_id)| CreateGameSlot {
+        player_id: Some(*player_id),
+        settings: SlotSettings {
+          team: idx as i32,
+          color: idx as i32,
+          computer: Computer::Easy,
+          handicap: 100,
+          status: SlotStatus::Occupied,
+          race: Race::Random,
+        },
+        is_referee: false,
+      })
+      .collect();
+
+    let game = self
+      .games
+      .send(CreateGameAsBot {
+        api_client_id: tournament.api_client_id,
+        api_player_id: tournament.api_player_id,
+        params: CreateGameAsBotParams {
+          name: format!(
+            "{} R{} #{}",
+            tournament.name, tournament_match.round, tournament_match.match_no
+          ),
+          map: settings.map,
+          is_private: true,
+          is_live: false,
+          node_id,
+          slots,
+          mask_player_names: false,
+          enable_ping_equalizer: settings.enable_ping_equalizer,
+          enable_adaptive_step: settings.enable_adaptive_step,
+          observer_chat_policy: settings.observer_chat_policy,
+          flo_tv_delay_override_secs: None,
+          rating_mode: settings.rating_mode,
+        },
+      })
+      .await??;
+    let game_id = game.id;
+
+    if let Err(err) = self
+      .start_game(&tournament, &tournament_match, game_id)
+      .await
+    {
+      // the match is retried on the next tick, the game must not be left behind
+      if let Err(cancel_err) = self
+        .cancel_game(&tournament, tournament_match.id, game_id)
+        .await
+      {
+        tracing::error!(
+          game_id,
+          tournament_id = tournament.id,
+          match_no = tournament_match.match_no,
+          "cancel match: {}",
+          cancel_err
+        );
+      }
+      return Err(err);
+    }
+
+    Ok(())
+  }
+
+  async fn start_game(
+    &self,
+    tournament: &Tournament,
+    tournament_match: &TournamentMatch,
+    game_id: i32,
+  ) -> Result<()> {
+    let match_id = tournament_match.id;
+
+    self
+      .db
+      .exec(move |conn| crate::tournament::db::update_match_game(conn, match_id, Some(game_id)))
+      .await?;
+
+    let (tx, rx) = oneshot::channel();
+    self
+      .games
+      .send_to(game_id, StartGameCheckAsBot { tx })
+      .await?;
+
+    match rx.await.map_err(|_| Error::TaskCancelled)? {
+      StartGameCheckAsBotResult::Started(_) => {
+        tracing::info!(
+          game_id,
+          tournament_id = tournament.id,
+          match_no = tournament_match.match_no,
+          "match started"
+        );
+      }
+      StartGameCheckAsBotResult::Rejected(pkt) => {
+        tracing::warn!(
+          game_id,
+          tournament_id = tournament.id,
+          match_no = tournament_match.match_no,
+          "match start rejected: {}",
+          pkt.message
+        );
+        // retried on the next tick
+        self.cancel_game(tournament, match_id, game_id).await?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Cancels the game of a match and detaches it from the match
+  async fn cancel_game(&self, tournament: &Tournament, match_id: i32, game_id: i32) -> Result<()> {
+    self
+      .games
+      .send_to(
+        game_id,
+        CancelGame {
+          player_id: Some(tournament.api_player_id),
+        },
+      )
+      .await?;
+    self.games.send(Remove { game_id }).await?;
+    self
+      .db
+      .exec(move |conn| crate::tournament::db::update_match_game(conn, match_id, None))
+      .await?;
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl Actor for TournamentRegistry {
+  async fn started(&mut self, ctx: &mut Context<Self>) {
+    let addr = ctx.addr();
+    ctx.spawn(async move {
+      loop {
+        sleep(ADVANCE_INTERVAL).await;
+        if addr.notify(Advance).await.is_err() {
+          break;
+        }
+      }
+    });
+  }
+}
+
+#[async_trait]
+impl Service<Data> for TournamentRegistry {
+  type Error = Error;
+
+  async fn create(registry: &mut RegistryRef<Data>) -> Result<Self, Self::Error> {
+    let db = registry.data().db.clone();
+    let games = registry.resolve::<GameRegistry>().await?;
+    let players = registry.resolve::<PlayerRegistry>().await?;
+    let nodes = registry.resolve::<NodeRegistry>().await?;
+    Ok(Self {
+      db,
+      games,
+      players,
+      nodes,
+      starting: BTreeMap::new(),
+    })
+  }
+}
+
+struct Advance;
+
+impl Message for Advance {
+  type Result = ();
+}
+
+#[async_trait]
+impl Handler<Advance> for TournamentRegistry {
+  async fn handle(&mut self, ctx: &mut Context<Self>, _: Advance) {
+    if let Err(err) = self.advance_all(ctx).await {
+      tracing::error!("advance tournaments: {}", err);
+    }
+  }
+}
+
+struct StartMatchDone {
+  match_id: i32,
+}
+
+impl Message for StartMatchDone {
+  type Result = ();
+}
+
+#[async_trait]
+impl Handler<StartMatchDone> for TournamentRegistry {
+  async fn handle(&mut self, _: &mut Context<Self>, StartMatchDone { match_id }: StartMatchDone) {
+    self.starting.remove(&match_id);
+  }
+}
+
+pub struct CreateTournament {
+  pub api_client_id: i32,
+  pub api_player_id: i32,
+  pub params: CreateTournamentParams,
+}
+
+impl Message for CreateTournament {
+  type Result = Result<Tournament>;
+}
+
+#[async_trait]
+impl Handler<CreateTournament> for TournamentRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    CreateTournament {
+      api_client_id,
+      api_player_id,
+      params,
+    }: CreateTournament,
+  ) -> Result<Tournament> {
+    let tournament = self
+      .db
+      .exec(move |conn| crate::tournament::db::create(conn, api_client_id, api_player_id, params))
+      .await?;
+    Ok(tournament)
+  }
+}
+
+pub struct CancelTournament {
+  pub api_client_id: i32,
+  pub tournament_id: i32,
+}
+
+impl Message for CancelTournament {
+  type Result = Result<Tournament>;
+}
+
+#[async_trait]
+impl Handler<CancelTournament> for TournamentRegistry {
+  async fn handle(
+    &mut self,
+    _: &mut Context<Self>,
+    CancelTournament {
+      api_client_id,
+      tournament_id,
+    }: CancelTournament,
+  ) -> Result<Tournament> {
+    let tournament = self
+      .db
+      .exec(move |conn| crate::tournament::db::cancel(conn, api_client_id, tournament_id))
+      .await?;
+    Ok(tournament)
+  }
+}
@@ -0,0 +1,148 @@
+use bs_diesel_utils::BSDieselEnum;
+use chrono::{DateTime, Utc};
+use s2_grpc_utils::{S2ProtoEnum, S2ProtoPack, S2ProtoUnpack};
+use serde::{Deserialize, Serialize};
+
+use crate::game::ObserverChatPolicy;
+use crate::map::Map;
+use crate::player::PlayerRef;
+use crate::tournament::bracket::MatchSource;
+
+#[derive(Debug, S2ProtoPack, Clone)]
+#[s2_grpc(message_type = "flo_grpc::controller::Tournament")]
+pub struct Tournament {
+  pub id: i32,
+  #[s2_grpc(skip_pack)]
+  pub api_client_id: i32,
+  #[s2_grpc(skip_pack)]
+  pub api_player_id: i32,
+  pub name: String,
+  #[s2_grpc(proto_enum)]
+  pub format: TournamentFormat,
+  #[s2_grpc(proto_enum)]
+  pub status: TournamentStatus,
+  pub game_settings: TournamentGameSettings,
+  pub swiss_rounds: Option<i32>,
+  pub winner_player_id: Option<i32>,
+  /// Ordered by seed
+  pub players: Vec<TournamentPlayer>,
+  /// Ordered by match number
+  pub matches: Vec<TournamentMatch>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+impl Tournament {
+  pub fn is_elimination(&self) -> bool {
+    match self.format {
+      TournamentFormat::SingleElimination | TournamentFormat::DoubleElimination => true,
+      TournamentFormat::RoundRobin | TournamentFormat::Swiss => false,
+    }
+  }
+
+  pub fn is_decided(&self) -> bool {
+    self.matches.iter().all(|m| match m.status {
+      TournamentMatchStatus::Ended | TournamentMatchStatus::Bye => true,
+      _ => false,
+    })
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, BSDieselEnum, S2ProtoEnum)]
+#[repr(i32)]
+#[s2_grpc(proto_enum_type = "flo_grpc::controller::TournamentFormat")]
+pub enum TournamentFormat {
+  SingleElimination = 0,
+  DoubleElimination = 1,
+  RoundRobin = 2,
+  Swiss = 3,
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, BSDieselEnum, S2ProtoEnum)]
+#[repr(i32)]
+#[s2_grpc(proto_enum_type = "flo_grpc::controller::TournamentStatus")]
+pub enum TournamentStatus {
+  Running = 0,
+  Ended = 1,
+  Cancelled = 2,
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, BSDieselEnum, S2ProtoEnum)]
+#[repr(i32)]
+#[s2_grpc(proto_enum_type = "flo_grpc::controller::TournamentBracket")]
+pub enum TournamentBracket {
+  Main = 0,
+  Losers = 1,
+  GrandFinal = 2,
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, S2ProtoEnum)]
+#[repr(i32)]
+#[s2_grpc(proto_enum_type = "flo_grpc::controller::TournamentMatchStatus")]
+pub enum TournamentMatchStatus {
+  /// Waiting for earlier matches
+  Waiting = 0,
+  /// Both players are known, the game will be created once they are online
+  Ready = 1,
+  Playing = 2,
+  Ended = 3,
+  /// Decided without a game
+  Bye = 4,
+}
+
+/// Settings of the games created for tournament matches
+#[derive(Debug, Serialize, Deserialize, S2ProtoPack, S2ProtoUnpack, Clone)]
+#[s2_grpc(message_type = "flo_grpc::controller::TournamentGameSettings")]
+pub struct TournamentGameSettings {
+  pub map: Map,
+  /// Selects the node from player ping maps if not set
+  pub node_id: Option<i32>,
+  pub enable_ping_equalizer: bool,
+  pub enable_adaptive_step: bool,
+  #[s2_grpc(proto_enum)]
+  pub observer_chat_policy: ObserverChatPolicy,
+  pub rating_mode: Option<String>,
+}
+
+#[derive(Debug, S2ProtoPack, Clone)]
+#[s2_grpc(message_type = "flo_grpc::controller::TournamentPlayer")]
+pub struct TournamentPlayer {
+  pub player: PlayerRef,
+  /// 0 is the top seed
+  pub seed: i32,
+  /// Wins count 1, draws count 0.5
+  pub score: f64,
+}
+
+#[derive(Debug, S2ProtoPack, Clone)]
+#[s2_grpc(message_type = "flo_grpc::controller::TournamentMatch")]
+pub struct TournamentMatch {
+  pub id: i32,
+  pub match_no: i32,
+  #[s2_grpc(proto_enum)]
+  pub bracket: TournamentBracket,
+  pub round: i32,
+  #[s2_grpc(proto_enum)]
+  pub status: TournamentMatchStatus,
+  pub player_a_id: Option<i32>,
+  pub player_b_id: Option<i32>,
+  pub game_id: Option<i32>,
+  pub winner_player_id: Option<i32>,
+  #[s2_grpc(skip_pack)]
+  pub sources: [MatchSource; 2],
+}
+
+#[derive(Debug, S2ProtoUnpack)]
+#[s2_grpc(message_type = "flo_grpc::controller::CreateTournamentRequest")]
+pub struct CreateTournamentParams {
+  pub name: String,
+  #[s2_grpc(proto_enum)]
+  pub format: TournamentFormat,
+  /// Ordered by seed, unless `seed_rating_mode` is set
+  pub player_ids: Vec<i32>,
+  pub game_settings: TournamentGameSettings,
+  /// Defaults to enough rounds to decide a single winner
+  pub swiss_rounds: Option<i32>,
+  /// Seeds players by their rating in this mode
+  pub seed_rating_mode: Option<String>,
+}
@@ -35,3 +35,18 @@ packet_type!(RemoveNode, PacketRemoveNode);
 packet_type!(PlayerMuteListUpdate, PacketPlayerMuteListUpdate);
 packet_type!(PlayerMuteAddRequest, PacketPlayerMuteAddRequest);
 packet_type!(PlayerMuteRemoveRequest, PacketPlayerMuteRemoveRequest);
+packet_type!(MatchmakingQueueUpdate, PacketMatchmakingQueueUpdate);
+packet_type!(MatchmakingMatchFound, PacketMatchmakingMatchFound);
+packet_type!(GameInviteRequest, PacketGameInviteRequest);
+packet_type!(GameInvite, PacketGameInvite);
+packet_type!(InviteResponse, PacketInviteResponse);
+packet_type!(InviteUpdate, PacketInviteUpdate);
+packet_type!(PartyInviteRequest, PacketPartyInviteRequest);
+packet_type!(PartyLeaveRequest, PacketPartyLeaveRequest);
+packet_type!(PartyUpdate, PacketPartyUpdate);
+packet_type!(LobbyChatRequest, PacketLobbyChatRequest);
+packet_type!(LobbyChat, PacketLobbyChat);
+packet_type!(GameKickRequest, PacketGameKickRequest);
+packet_type!(GameTransferHostRequest, PacketGameTransferHostRequest);
+packet_type!(GameHostUpdate, PacketGameHostUpdate);
+packet_type!(GameReadyRequest, PacketGameReadyRequest);
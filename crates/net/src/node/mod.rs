@@ -33,3 +33,6 @@ packet_type!(
 );
 packet_type!(NodeGameStatusUpdate, PacketNodeGameStatusUpdate);
 packet_type!(NodeGameStatusUpdateBulk, PacketNodeGameStatusUpdateBulk);
+packet_type!(NodeGameResult, PacketNodeGameResult);
+packet_type!(NodeGameDesync, PacketNodeGameDesync);
+packet_type!(NodeDrainStatus, PacketNodeDrainStatus);
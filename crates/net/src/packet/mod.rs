@@ -236,6 +236,36 @@ pub enum PacketTypeId {
   PlayerMuteAddRequest,
   #[bin(value = 0x1F)]
   PlayerMuteRemoveRequest,
+  #[bin(value = 0x20)]
+  MatchmakingQueueUpdate,
+  #[bin(value = 0x21)]
+  MatchmakingMatchFound,
+  #[bin(value = 0x22)]
+  GameInviteRequest,
+  #[bin(value = 0x23)]
+  GameInvite,
+  #[bin(value = 0x24)]
+  InviteResponse,
+  #[bin(value = 0x25)]
+  InviteUpdate,
+  #[bin(value = 0x26)]
+  PartyInviteRequest,
+  #[bin(value = 0x27)]
+  PartyLeaveRequest,
+  #[bin(value = 0x28)]
+  PartyUpdate,
+  #[bin(value = 0x29)]
+  LobbyChatRequest,
+  #[bin(value = 0x2A)]
+  LobbyChat,
+  #[bin(value = 0x2B)]
+  GameKickRequest,
+  #[bin(value = 0x2C)]
+  GameTransferHostRequest,
+  #[bin(value = 0x2D)]
+  GameHostUpdate,
+  #[bin(value = 0x2E)]
+  GameReadyRequest,
 
   // Lobby <-> Node
   #[bin(value = 0x30)]
@@ -282,6 +312,12 @@ pub enum PacketTypeId {
   NodeGameStatusUpdate,
   #[bin(value = 0x51)]
   NodeGameStatusUpdateBulk,
+  #[bin(value = 0x52)]
+  NodeGameResult,
+  #[bin(value = 0x53)]
+  NodeGameDesync,
+  #[bin(value = 0x54)]
+  NodeDrainStatus,
 
   // Client <-> Observer
   #[bin(value = 0x60)]
@@ -141,6 +141,15 @@ impl W3GSAckQueue {
     self.tx_pending_ack_q.push_back((meta, packet));
   }
 
+  /// Re-applies a frame that was sent after the snapshot this queue was restored from
+  pub fn replay_send(&mut self, meta: W3GSMetadata, packet: W3GSPacket) {
+    self.tx_next_sid = meta.sid;
+    if meta.ack_sid.is_some() {
+      self.rx_ack_sid.take();
+    }
+    self.push_send(meta, packet);
+  }
+
   pub fn ack_sent(&mut self, ack_sid: u32) {
     // tracing::debug!("ack_sent: {}", ack_sid);
     self.tx_ack_sid.replace(ack_sid);
@@ -185,6 +194,13 @@ impl W3GSAckQueue {
     true
   }
 
+  /// Returns true if the peer acked a sid that has never been sent,
+  /// which happens after the queue has been restored from an outdated snapshot
+  pub fn is_unsent(&self, ack_sid: u32) -> bool {
+    let ahead = ack_sid.wrapping_sub(self.tx_next_sid);
+    ahead > 0 && ahead < Self::WINDOW_SIZE
+  }
+
   pub fn take_ack_received(&mut self) -> Option<u32> {
     self.rx_ack_sid.take()
   }
@@ -200,6 +216,36 @@ impl W3GSAckQueue {
   pub fn pending_ack_queue(&self) -> &VecDeque<(W3GSMetadata, W3GSPacket)> {
     &self.tx_pending_ack_q
   }
+
+  pub fn snapshot(&self) -> W3GSAckQueueSnapshot {
+    W3GSAckQueueSnapshot {
+      tx_next_sid: self.tx_next_sid,
+      tx_ack_sid: self.tx_ack_sid,
+      tx_pending_ack_q: self.tx_pending_ack_q.iter().cloned().collect(),
+      rx_ack_sid: self.rx_ack_sid,
+      last_rx_ack_sid: self.last_rx_ack_sid,
+    }
+  }
+
+  pub fn from_snapshot(snapshot: W3GSAckQueueSnapshot) -> Self {
+    Self {
+      tx_next_sid: snapshot.tx_next_sid,
+      tx_ack_sid: snapshot.tx_ack_sid,
+      tx_pending_ack_q: snapshot.tx_pending_ack_q.into_iter().collect(),
+      rx_ack_sid: snapshot.rx_ack_sid,
+      last_rx_ack_sid: snapshot.last_rx_ack_sid,
+    }
+  }
+}
+
+/// State of a `W3GSAckQueue` that can be persisted and restored
+#[derive(Debug, Clone)]
+pub struct W3GSAckQueueSnapshot {
+  pub tx_next_sid: u32,
+  pub tx_ack_sid: Option<u32>,
+  pub tx_pending_ack_q: Vec<(W3GSMetadata, W3GSPacket)>,
+  pub rx_ack_sid: Option<u32>,
+  pub last_rx_ack_sid: Option<u32>,
 }
 
 #[test]
@@ -277,3 +323,52 @@ fn test_frame_to_w3gs() {
     ] as &[_]
   );
 }
+
+#[test]
+fn test_ack_queue_snapshot() {
+  let mut q = W3GSAckQueue::new();
+  for _ in 0..3 {
+    let sid = q.gen_next_send_sid();
+    q.push_send(
+      W3GSMetadata::new(W3GSPacketTypeId::IncomingAction, sid, None),
+      W3GSPacket::simple(flo_w3gs::protocol::leave::LeaveAck).unwrap(),
+    );
+  }
+  q.ack_sent(1);
+  assert!(q.ack_received(5));
+
+  let mut restored = W3GSAckQueue::from_snapshot(q.snapshot());
+  assert_eq!(restored.pending_ack_len(), 2);
+  assert_eq!(restored.pending_ack_queue()[0].0.sid(), 2);
+  assert_eq!(restored.last_ack_received(), Some(5));
+  assert_eq!(restored.take_ack_received(), Some(5));
+  assert!(!restored.is_unsent(3));
+  assert!(restored.is_unsent(4));
+  assert_eq!(restored.gen_next_send_sid(), 4);
+  assert!(!restored.is_unsent(4));
+}
+
+#[test]
+fn test_ack_queue_replay_send() {
+  let mut q = W3GSAckQueue::new();
+  let packet = W3GSPacket::simple(flo_w3gs::protocol::leave::LeaveAck).unwrap();
+  let sid = q.gen_next_send_sid();
+  q.push_send(
+    W3GSMetadata::new(W3GSPacketTypeId::IncomingAction, sid, None),
+    packet.clone(),
+  );
+  assert!(q.ack_received(1));
+  let snapshot = q.snapshot();
+
+  let ack_sid = q.take_ack_received();
+  let sid = q.gen_next_send_sid();
+  let meta = W3GSMetadata::new(W3GSPacketTypeId::IncomingAction, sid, ack_sid);
+
+  let mut restored = W3GSAckQueue::from_snapshot(snapshot);
+  assert!(restored.is_unsent(2));
+  restored.replay_send(meta, packet);
+  assert!(!restored.is_unsent(2));
+  assert_eq!(restored.pending_ack_len(), 2);
+  assert_eq!(restored.take_ack_received(), None);
+  assert_eq!(restored.gen_next_send_sid(), 3);
+}
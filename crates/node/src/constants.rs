@@ -1,5 +1,6 @@
 use flo_observer::record::ObserverRecordSource;
 use once_cell::sync::Lazy;
+use std::path::PathBuf;
 use std::time::Duration;
 
 pub const PEER_CHANNEL_SIZE: usize = 250;
@@ -16,6 +17,58 @@ pub static GAME_DEFAULT_STEP_MS: Lazy<u16> = Lazy::new(|| {
 pub const GAME_PING_INTERVAL: Duration = Duration::from_secs(1);
 pub const GAME_PING_TIMEOUT: Duration = Duration::from_secs(5);
 pub const GAME_CLOCK_MAX_PAUSE: Duration = Duration::from_secs(60 - 3);
+pub const GAME_ADAPTIVE_STEP_INTERVAL: Duration = Duration::from_secs(1);
+pub const GAME_DESYNC_ACTION_HISTORY_LEN: usize = 20;
+pub static GAME_PLAYER_MAX_PAUSES: Lazy<u32> = Lazy::new(|| {
+  std::env::var("FLO_GAME_PLAYER_MAX_PAUSES")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .unwrap_or(3)
+});
+pub static GAME_PLAYER_MAX_PAUSE_DURATION: Lazy<Duration> = Lazy::new(|| {
+  std::env::var("FLO_GAME_PLAYER_MAX_PAUSE_SECS")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .map(Duration::from_secs)
+    .unwrap_or(Duration::from_secs(120))
+});
+pub static GAME_TEAM_MAX_PAUSES: Lazy<u32> = Lazy::new(|| {
+  std::env::var("FLO_GAME_TEAM_MAX_PAUSES")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .unwrap_or(6)
+});
+pub static GAME_TEAM_MAX_PAUSE_DURATION: Lazy<Duration> = Lazy::new(|| {
+  std::env::var("FLO_GAME_TEAM_MAX_PAUSE_SECS")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .map(Duration::from_secs)
+    .unwrap_or(Duration::from_secs(300))
+});
+pub static GAME_VOTE_KICK_RULE: Lazy<String> = Lazy::new(|| {
+  std::env::var("FLO_GAME_VOTE_KICK_RULE")
+    .ok()
+    .unwrap_or_else(|| "majority".to_string())
+});
+pub static GAME_VOTE_KICK_WINDOW: Lazy<Duration> = Lazy::new(|| {
+  std::env::var("FLO_GAME_VOTE_KICK_WINDOW_SECS")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .map(Duration::from_secs)
+    .unwrap_or(Duration::from_secs(30))
+});
+pub static GAME_VOTE_KICK_MIN_VOTERS: Lazy<usize> = Lazy::new(|| {
+  std::env::var("FLO_GAME_VOTE_KICK_MIN_VOTERS")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .unwrap_or(2)
+});
+pub static GAME_RESULT_DISCONNECT_AS_LOSS: Lazy<bool> = Lazy::new(|| {
+  std::env::var("FLO_GAME_RESULT_DISCONNECT_AS_LOSS")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .unwrap_or(true)
+});
 
 #[cfg(not(debug_assertions))]
 pub const GAME_DELAY_RANGE: [Duration; 2] = [Duration::from_millis(25), Duration::from_millis(100)];
@@ -33,5 +86,20 @@ pub static OBS_SOURCE: Lazy<ObserverRecordSource> = Lazy::new(|| {
     .unwrap_or(ObserverRecordSource::Test)
 });
 
+pub static NODE_SNAPSHOT_DIR: Lazy<Option<PathBuf>> = Lazy::new(|| {
+  std::env::var("FLO_NODE_SNAPSHOT_DIR")
+    .ok()
+    .filter(|v| !v.is_empty())
+    .map(PathBuf::from)
+});
+pub const NODE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+pub static NODE_SNAPSHOT_MAX_AGE: Lazy<Duration> = Lazy::new(|| {
+  std::env::var("FLO_NODE_SNAPSHOT_MAX_AGE_SECS")
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .map(Duration::from_secs)
+    .unwrap_or(Duration::from_secs(60))
+});
+
 pub const RTT_STATS_REPORT_DELAY: Duration = std::time::Duration::from_secs(5);
 pub const RTT_STATS_REPORT_INTERVAL: Duration = std::time::Duration::from_secs(15);
@@ -80,6 +80,7 @@ impl ControllerServer {
     stream
       .send(PacketControllerConnectAccept {
         version: Some(crate::version::FLO_NODE_VERSION.into()),
+        draining: self.state.g_state.is_draining(),
       })
       .await?;
 
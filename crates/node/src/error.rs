@@ -29,6 +29,10 @@ pub enum Error {
   InvalidSecret,
   #[error("invalid token")]
   InvalidToken,
+  #[error("snapshot expired")]
+  SnapshotExpired,
+  #[error("snapshot is behind the client")]
+  SnapshotBehind,
   #[error("invalid client status transition: {0:?} => {1:?}")]
   InvalidClientStatusTransition(SlotClientStatus, SlotClientStatus),
   #[error("observer put record: {0}")]
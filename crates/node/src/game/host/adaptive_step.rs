@@ -0,0 +1,126 @@
+//! Picks the game step from the players' latency, so that games with high-latency players
+//! get longer, less frequent ticks instead of lagging
+
+use super::clock::ActionTickStream;
+
+/// Share of the worst rtt that should be covered by one step
+const RTT_STEP_RATIO: f32 = 0.5;
+
+/// Pending acks a player can have before the step is increased
+const PENDING_ACK_THRESHOLD: usize = 10;
+
+/// Step increase for each pending ack above the threshold
+const PENDING_ACK_STEP_MS: f32 = 2.0;
+
+/// Weight of the latest sample in the smoothed step
+const SMOOTHING_FACTOR: f32 = 0.2;
+
+/// Only apply changes that are at least this large
+const MIN_STEP_CHANGE_MS: u16 = 5;
+
+#[derive(Debug)]
+pub struct AdaptiveStep {
+  base: u16,
+  smoothed: f32,
+  current: u16,
+}
+
+#[derive(Debug, Default)]
+pub struct StepSample {
+  /// Highest average rtt of the connected players
+  pub max_rtt_avg: f32,
+  /// Longest ack queue of the connected players
+  pub max_pending_ack: usize,
+}
+
+impl AdaptiveStep {
+  pub fn new(base: u16) -> Self {
+    let base = clamp(base as f32);
+    Self {
+      base,
+      smoothed: base as f32,
+      current: base,
+    }
+  }
+
+  /// Updates the smoothed step with the latest sample, returns the new step if it should be applied
+  pub fn update(&mut self, sample: &StepSample) -> Option<u16> {
+    let target = self.target(sample);
+    self.smoothed += (target - self.smoothed) * SMOOTHING_FACTOR;
+    let next = clamp(self.smoothed);
+    if (next as i32 - self.current as i32).abs() >= MIN_STEP_CHANGE_MS as i32 {
+      self.current = next;
+      Some(next)
+    } else {
+      None
+    }
+  }
+
+  fn target(&self, sample: &StepSample) -> f32 {
+    let rtt_step = sample.max_rtt_avg * RTT_STEP_RATIO;
+    let ack_step =
+      sample.max_pending_ack.saturating_sub(PENDING_ACK_THRESHOLD) as f32 * PENDING_ACK_STEP_MS;
+    (self.base as f32).max(rtt_step) + ack_step
+  }
+}
+
+fn clamp(value: f32) -> u16 {
+  value
+    .round()
+    .max(ActionTickStream::MIN_STEP as f32)
+    .min(ActionTickStream::MAX_STEP as f32) as u16
+}
+
+#[test]
+fn test_adaptive_step() {
+  let mut s = AdaptiveStep::new(30);
+
+  // low latency keeps the base step
+  for _ in 0..10 {
+    assert_eq!(
+      s.update(&StepSample {
+        max_rtt_avg: 40.0,
+        max_pending_ack: 0,
+      }),
+      None
+    );
+  }
+
+  // high latency increases the step gradually
+  let mut steps = vec![];
+  for _ in 0..30 {
+    if let Some(step) = s.update(&StepSample {
+      max_rtt_avg: 200.0,
+      max_pending_ack: 0,
+    }) {
+      steps.push(step);
+    }
+  }
+  assert!(steps.len() > 1);
+  assert!(steps.windows(2).all(|w| w[0] < w[1]));
+  assert!(*steps.last().unwrap() <= 100);
+  assert!(*steps.last().unwrap() >= 95);
+
+  // a growing ack queue increases the step further
+  let mut last = None;
+  for _ in 0..30 {
+    if let Some(step) = s.update(&StepSample {
+      max_rtt_avg: 200.0,
+      max_pending_ack: 60,
+    }) {
+      last = Some(step);
+    }
+  }
+  assert!(last.unwrap() > 150);
+
+  // the step never leaves the allowed range
+  for _ in 0..100 {
+    s.update(&StepSample {
+      max_rtt_avg: 10000.0,
+      max_pending_ack: 1000,
+    });
+  }
+  assert_eq!(s.current, ActionTickStream::MAX_STEP);
+
+  assert_eq!(AdaptiveStep::new(1).current, ActionTickStream::MIN_STEP);
+}
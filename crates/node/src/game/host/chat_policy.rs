@@ -0,0 +1,65 @@
+//! Decides who receives in-game chat messages.
+//! The recipients requested by the client are filtered on the node,
+//! so a modified client cannot send observer chat to the players.
+
+use std::collections::BTreeSet;
+
+use s2_grpc_utils::S2ProtoEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, S2ProtoEnum)]
+#[s2_grpc(proto_enum_type(flo_net::proto::flo_node::ObserverChatPolicy))]
+#[repr(i32)]
+pub enum ObserverChatPolicy {
+  /// Observers can talk to everyone
+  Open = 0,
+  /// Observers can only talk to other observers
+  Isolated = 1,
+}
+
+#[derive(Debug)]
+pub struct ChatPolicy {
+  observer_policy: ObserverChatPolicy,
+  observers: BTreeSet<i32>,
+  referees: BTreeSet<i32>,
+}
+
+impl ChatPolicy {
+  pub fn new<O, R>(observer_policy: ObserverChatPolicy, observers: O, referees: R) -> Self
+  where
+    O: IntoIterator<Item = i32>,
+    R: IntoIterator<Item = i32>,
+  {
+    Self {
+      observer_policy,
+      observers: observers.into_iter().collect(),
+      referees: referees.into_iter().collect(),
+    }
+  }
+
+  /// Referees can talk to everyone, players can talk to everyone,
+  /// observers are restricted by the observer policy
+  pub fn allows(&self, sender: i32, recipient: i32) -> bool {
+    if self.referees.contains(&sender) || !self.observers.contains(&sender) {
+      return true;
+    }
+    match self.observer_policy {
+      ObserverChatPolicy::Isolated => self.observers.contains(&recipient),
+      ObserverChatPolicy::Open => true,
+    }
+  }
+}
+
+#[test]
+fn test_chat_policy() {
+  // 1, 2: players, 3, 4: observers, 4: referee
+  let p = ChatPolicy::new(ObserverChatPolicy::Isolated, vec![3, 4], vec![4]);
+  assert!(p.allows(1, 2));
+  assert!(p.allows(1, 3));
+  assert!(p.allows(3, 4));
+  assert!(!p.allows(3, 1));
+  assert!(p.allows(4, 1));
+
+  let p = ChatPolicy::new(ObserverChatPolicy::Open, vec![3, 4], vec![]);
+  assert!(p.allows(3, 1));
+  assert!(p.allows(4, 3));
+}
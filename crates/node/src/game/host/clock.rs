@@ -39,7 +39,7 @@ impl ActionTickStream {
 
   pub fn set_step(&mut self, value: u16) {
     self.step = std::cmp::min(Self::MAX_STEP, std::cmp::max(Self::MIN_STEP, value));
-    self.step_duration = Duration::from_millis(value as u64);
+    self.step_duration = Duration::from_millis(self.step as u64);
     self
       .delay
       .as_mut()
@@ -82,6 +82,8 @@ impl ActionTickStream {
 #[derive(Debug)]
 pub struct Tick {
   pub time_increment_ms: u16,
+  /// How late this tick fired behind its schedule
+  pub late_ms: u16,
   pub actions: Vec<PlayerAction>,
   pub actions_bytes_len: usize,
 }
@@ -111,6 +113,7 @@ impl Stream for ActionTickStream {
     let actions_bytes_len = actions.iter().map(|a| a.byte_len()).sum();
     let tick = Tick {
       time_increment_ms: self.step + delay,
+      late_ms: delay,
       actions,
       actions_bytes_len,
     };
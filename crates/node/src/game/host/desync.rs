@@ -0,0 +1,185 @@
+//! Collects what is needed to triage a desync without the players' replays:
+//! the checksum of every player at the desynced tick, which players agreed with each other
+//! and the last actions of every player.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use bytes::Bytes;
+
+use flo_net::proto::flo_node as proto;
+use flo_observer::record::{DesyncReport as DesyncRecord, DesyncReportItem};
+use flo_w3gs::protocol::action::PlayerAction;
+
+use super::sync::DesyncTick;
+
+#[derive(Debug)]
+pub struct ActionHistory {
+  len: usize,
+  players: BTreeMap<u8, VecDeque<RecentAction>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecentAction {
+  pub tick: u32,
+  pub data: Bytes,
+}
+
+impl ActionHistory {
+  /// Keeps the last `len` actions of each player
+  pub fn new(len: usize) -> Self {
+    Self {
+      len,
+      players: BTreeMap::new(),
+    }
+  }
+
+  pub fn push(&mut self, tick: u32, actions: &[PlayerAction]) {
+    for action in actions {
+      let items = self.players.entry(action.player_id).or_default();
+      if items.len() >= self.len {
+        items.pop_front();
+      }
+      items.push_back(RecentAction {
+        tick,
+        data: action.data.clone(),
+      });
+    }
+  }
+
+  pub fn get(&self, slot_player_id: u8) -> Vec<RecentAction> {
+    self
+      .players
+      .get(&slot_player_id)
+      .map(|items| items.iter().cloned().collect())
+      .unwrap_or_default()
+  }
+}
+
+#[derive(Debug)]
+pub struct DesyncReport {
+  pub tick: u32,
+  pub time: u32,
+  pub players: Vec<DesyncPlayer>,
+}
+
+#[derive(Debug)]
+pub struct DesyncPlayer {
+  pub player_id: i32,
+  /// `None` if the player has not acked the tick
+  pub checksum: Option<u32>,
+  pub desynced: bool,
+  pub recent_actions: Vec<RecentAction>,
+}
+
+impl DesyncReport {
+  /// `players` are the player ids and slot player ids of all players in the game
+  pub fn new<I>(
+    tick: &DesyncTick,
+    desynced: &BTreeSet<i32>,
+    players: I,
+    history: &ActionHistory,
+  ) -> Self
+  where
+    I: IntoIterator<Item = (i32, u8)>,
+  {
+    Self {
+      tick: tick.tick,
+      time: tick.time,
+      players: players
+        .into_iter()
+        .map(|(player_id, slot_player_id)| DesyncPlayer {
+          player_id,
+          checksum: tick.checksums.get(&player_id).cloned(),
+          desynced: desynced.contains(&player_id),
+          recent_actions: history.get(slot_player_id),
+        })
+        .collect(),
+    }
+  }
+
+  pub fn to_record(&self) -> DesyncRecord {
+    DesyncRecord::new(
+      self.tick,
+      self.time,
+      self.players.iter().filter_map(|p| {
+        Some(DesyncReportItem {
+          player_id: p.player_id,
+          checksum: p.checksum?,
+          desynced: p.desynced,
+        })
+      }),
+    )
+  }
+
+  pub fn to_packet(&self, game_id: i32, game_version: &str) -> proto::PacketNodeGameDesync {
+    proto::PacketNodeGameDesync {
+      game_id,
+      tick: self.tick,
+      time: self.time,
+      game_version: game_version.to_string(),
+      players: self
+        .players
+        .iter()
+        .map(|p| proto::GameDesyncPlayer {
+          player_id: p.player_id,
+          checksum: p.checksum,
+          desynced: p.desynced,
+          recent_actions: p
+            .recent_actions
+            .iter()
+            .map(|a| proto::GameDesyncAction {
+              tick: a.tick,
+              data: a.data.to_vec(),
+            })
+            .collect(),
+        })
+        .collect(),
+    }
+  }
+}
+
+#[test]
+fn test_desync_report() {
+  let mut history = ActionHistory::new(2);
+  for tick in 1..=3 {
+    history.push(
+      tick,
+      &[
+        PlayerAction {
+          player_id: 1,
+          data: Bytes::from(vec![tick as u8]),
+        },
+        PlayerAction {
+          player_id: 2,
+          data: Bytes::from(vec![0x10 + tick as u8]),
+        },
+      ],
+    );
+  }
+  let actions = history.get(1);
+  assert_eq!(actions.len(), 2);
+  assert_eq!(actions[0].tick, 2);
+  assert_eq!(actions[1].data.as_ref(), &[3]);
+  assert!(history.get(3).is_empty());
+
+  let tick = DesyncTick {
+    tick: 3,
+    time: 90,
+    checksums: vec![(10, 1), (20, 2)].into_iter().collect(),
+  };
+  let report = DesyncReport::new(
+    &tick,
+    &std::iter::once(20).collect(),
+    vec![(10, 1), (20, 2), (30, 3)],
+    &history,
+  );
+  assert_eq!(report.players.len(), 3);
+  assert!(!report.players[0].desynced);
+  assert!(report.players[1].desynced);
+  assert_eq!(report.players[2].checksum, None);
+  assert_eq!(report.players[1].recent_actions[1].data.as_ref(), &[0x13]);
+
+  let record = report.to_record();
+  assert_eq!(record.items.len(), 2);
+  assert_eq!(record.items[1].checksum, 2);
+}
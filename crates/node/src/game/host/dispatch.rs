@@ -1,24 +1,33 @@
+use super::adaptive_step::{AdaptiveStep, StepSample};
+use super::chat_policy::ChatPolicy;
 use super::clock::ActionTickStream;
 use super::delay::{DelayedFrame, DelayedFrameStream};
 use super::delay_equalizer::DelayEqualizer;
+use super::desync::{ActionHistory, DesyncReport};
+use super::journal::{GameJournal, JournalEntry};
+use super::pause::{PauseBudget, PauseLimits, PauseResult};
 use super::player::{PlayerDispatchInfo, PlayerSendError};
+use super::snapshot::HostSnapshot;
 use super::sync::SyncMap;
+use super::vote_kick::{VoteKick, VoteKickResult};
 use super::{broadcast, GameHostOptions};
 use crate::error::*;
 use crate::game::host::clock::Tick;
 use crate::game::host::stream::{PlayerStream, PlayerStreamCmd, PlayerStreamHandle};
-use crate::game::host::sync::{ClockResult, PlayerDesync};
+use crate::game::host::sync::{ClockResult, DesyncTick, PlayerDesync};
 use crate::game::{
-  AckError, GameEvent, GameEventSender, PlayerBanType, PlayerSlot, SlotClientStatus,
-  SlotClientStatusUpdateSource,
+  AckError, GameEvent, GameEventSender, NodeGameStatus, PlayerBanType, PlayerSlot,
+  SlotClientStatus, SlotClientStatusUpdateSource,
 };
 use crate::observer::ObserverPublisherHandle;
+use bytes::Bytes;
 use flo_net::packet::{Frame, PacketTypeId};
 use flo_net::ping::{PingMsg, PingStream};
 use flo_net::w3gs::{W3GSFrameExt, W3GSMetadata, W3GSPacket, W3GSPacketTypeId};
-use flo_observer::record::{RTTStats, RTTStatsItem};
+use flo_observer::record::{PlayerRemovedReason, RTTStats, RTTStatsItem};
 use flo_util::chat::{parse_chat_command, ChatCommand};
 use flo_w3gs::action::{IncomingAction, IncomingAction2, OutgoingKeepAlive};
+use flo_w3gs::actions::ActionTypeId;
 use flo_w3gs::protocol::action::{OutgoingAction, PlayerAction, TimeSlot};
 use flo_w3gs::protocol::chat::ChatToHost;
 use flo_w3gs::protocol::constants::LeaveReason;
@@ -30,6 +39,7 @@ use futures::stream::StreamExt;
 use parking_lot::Mutex;
 use s2_grpc_utils::S2ProtoEnum;
 use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::future::Future;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc::error::TrySendError;
@@ -53,6 +63,9 @@ pub enum Cmd {
     player_id: i32,
     leave_reason: Option<LeaveReason>,
   },
+  Snapshot {
+    tx: oneshot::Sender<HostSnapshot>,
+  },
 }
 
 enum PeerMsg {
@@ -106,6 +119,7 @@ impl Dispatcher {
     slots: &[PlayerSlot],
     obs: ObserverPublisherHandle,
     out_tx: GameEventSender,
+    restore: Option<HostSnapshot>,
   ) -> Self {
     let ct = CancellationToken::new();
     let start_notify = Arc::new(Notify::new());
@@ -113,6 +127,7 @@ impl Dispatcher {
     let (cmd_tx, cmd_rx) = channel(10);
     let (action_tx, action_rx) = channel(32);
     let enabled_ping_equalizer = opts.enabled_ping_equalizer;
+    let enabled_adaptive_step = opts.enabled_adaptive_step;
 
     let state = State::new(
       game_id,
@@ -121,6 +136,8 @@ impl Dispatcher {
       obs.clone(),
       status_rx,
       action_tx.clone(),
+      out_tx.clone(),
+      restore,
       ct.clone(),
     );
 
@@ -130,6 +147,10 @@ impl Dispatcher {
       start_messages.push("Ping equalizer is enabled.".to_string());
     }
 
+    if enabled_adaptive_step {
+      start_messages.push("Adaptive game step is enabled.".to_string());
+    }
+
     let chat_banned_player_names: Vec<String> = state
       .chat_banned_player_ids
       .iter()
@@ -143,11 +164,22 @@ impl Dispatcher {
       ));
     }
 
+    let referee_names: Vec<String> = state
+      .referee_player_ids
+      .iter()
+      .flat_map(|id| state._player_name_lookup.get(&id).cloned())
+      .collect();
+
+    if !referee_names.is_empty() {
+      start_messages.push(format!("Referees: {}", referee_names.join(", ")));
+    }
+
     tokio::spawn(
       Self::tick(
         game_id,
         state.shared.clone(),
         start_messages,
+        enabled_adaptive_step,
         start_notify.clone(),
         status_tx,
         action_rx,
@@ -184,6 +216,18 @@ impl Dispatcher {
     rx.await.map_err(|_| Error::Cancelled)?
   }
 
+  pub fn snapshot(&self) -> impl Future<Output = Result<HostSnapshot>> + Send + 'static {
+    let cmd_tx = self.cmd_tx.clone();
+    async move {
+      let (tx, rx) = oneshot::channel();
+      cmd_tx
+        .send(Cmd::Snapshot { tx })
+        .await
+        .map_err(|_| Error::Cancelled)?;
+      rx.await.map_err(|_| Error::Cancelled)
+    }
+  }
+
   pub async fn notify_player_shutdown(
     &self,
     player_id: i32,
@@ -244,6 +288,7 @@ impl Dispatcher {
     game_id: i32,
     shared: Arc<Mutex<Shared>>,
     start_messages: Vec<String>,
+    enabled_adaptive_step: bool,
     start_notify: Arc<Notify>,
     status_tx: watch::Sender<DispatchStatus>,
     mut rx: Receiver<ActionMsg>,
@@ -270,6 +315,20 @@ impl Dispatcher {
       let mut tick_stream = ActionTickStream::new(*crate::constants::GAME_DEFAULT_STEP_MS);
       let pause_timeout = sleep(Duration::from_secs(0));
       tokio::pin!(pause_timeout);
+      let game_pause_timeout = sleep(Duration::from_secs(0));
+      tokio::pin!(game_pause_timeout);
+      let mut game_paused = false;
+      let mut adaptive_step = if enabled_adaptive_step {
+        Some(AdaptiveStep::new(tick_stream.step()))
+      } else {
+        None
+      };
+      let mut adaptive_step_interval = interval_at(
+        tokio::time::Instant::now() + crate::constants::GAME_ADAPTIVE_STEP_INTERVAL,
+        crate::constants::GAME_ADAPTIVE_STEP_INTERVAL,
+      );
+      adaptive_step_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+      let mut lag_start: Option<Instant> = None;
 
       {
         let ct = ct.clone();
@@ -307,8 +366,11 @@ impl Dispatcher {
               }
               ActionMsg::SetStep(step) => {
                 tick_stream.set_step(step);
+                let mut shared = shared.lock();
+                if adaptive_step.take().is_some() {
+                  shared.broadcast_message("Adaptive game step has been disabled.");
+                }
                 shared
-                  .lock()
                   .broadcast_message(format!("Game step has been set to {}ms.", tick_stream.step()));
               },
               ActionMsg::CheckStopLag => {
@@ -316,6 +378,7 @@ impl Dispatcher {
                   match shared.lock().check_stop_lag() {
                     Ok(true) => {
                       tick_stream.resume();
+                      observe_lag_end(&mut lag_start);
                       status_tx.send(DispatchStatus::Running).ok();
                       tracing::info!(
                         game_id,
@@ -335,8 +398,16 @@ impl Dispatcher {
                   "resume clock"
                 );
                 tick_stream.resume();
+                observe_lag_end(&mut lag_start);
                 status_tx.send(DispatchStatus::Running).ok();
               }
+              ActionMsg::StartPauseTimer(timeout) => {
+                game_pause_timeout.as_mut().reset((Instant::now() + timeout).into());
+                game_paused = true;
+              }
+              ActionMsg::StopPauseTimer => {
+                game_paused = false;
+              }
             }
           }
           Some(tick) = tick_stream.next() => {
@@ -346,6 +417,10 @@ impl Dispatcher {
                 tick_stream.replace_actions(tick.actions);
                 pause_timeout.as_mut().reset((Instant::now() + crate::constants::GAME_CLOCK_MAX_PAUSE).into());
                 tick_stream.pause();
+                crate::metrics::GAME_LAG_STARTS
+                  .with_label_values(&[crate::metrics::game_status_label(NodeGameStatus::Running)])
+                  .inc();
+                lag_start.replace(Instant::now());
                 status_tx.send(DispatchStatus::Paused).ok();
               }
               Err(err) => {
@@ -366,6 +441,29 @@ impl Dispatcher {
               break;
             }
             tick_stream.resume();
+            observe_lag_end(&mut lag_start);
+          }
+          _ = adaptive_step_interval.tick(), if adaptive_step.is_some() && !tick_stream.is_paused() => {
+            let sample = shared.lock().step_sample();
+            if let Some(step) = adaptive_step.as_mut().and_then(|v| v.update(&sample)) {
+              tracing::debug!(
+                game_id,
+                "adaptive step: {}ms, rtt = {}, pending ack = {}",
+                step, sample.max_rtt_avg, sample.max_pending_ack
+              );
+              tick_stream.set_step(step);
+            }
+          }
+          _ = &mut game_pause_timeout, if game_paused => {
+            game_paused = false;
+            let action = shared.lock().auto_resume_game();
+            if let Some(action) = action {
+              tracing::info!(
+                game_id,
+                "auto resume game"
+              );
+              tick_stream.add_action(action);
+            }
           }
         }
       }
@@ -373,12 +471,22 @@ impl Dispatcher {
   }
 }
 
+fn observe_lag_end(lag_start: &mut Option<Instant>) {
+  if let Some(start) = lag_start.take() {
+    crate::metrics::GAME_LAG_DURATION
+      .with_label_values(&[crate::metrics::game_status_label(NodeGameStatus::Running)])
+      .observe(start.elapsed().as_secs_f64());
+  }
+}
+
 #[derive(Debug)]
 enum ActionMsg {
   PlayerAction(PlayerAction),
   SetStep(u16),
   CheckStopLag,
   ResumeClock,
+  StartPauseTimer(Duration),
+  StopPauseTimer,
 }
 
 #[derive(Debug)]
@@ -390,6 +498,9 @@ struct State {
   game_player_id_lookup: BTreeMap<u8, i32>,
   _player_name_lookup: BTreeMap<i32, String>,
   chat_banned_player_ids: Vec<i32>,
+  referee_player_ids: BTreeSet<i32>,
+  chat_policy: ChatPolicy,
+  chat_muted: bool,
   left_players: BTreeSet<i32>,
 }
 
@@ -400,9 +511,24 @@ impl State {
     slots: &[PlayerSlot],
     obs: ObserverPublisherHandle,
     status_rx: watch::Receiver<DispatchStatus>,
-    _action_tx: Sender<ActionMsg>,
+    action_tx: Sender<ActionMsg>,
+    out_tx: GameEventSender,
+    restore: Option<HostSnapshot>,
     ct: CancellationToken,
   ) -> Self {
+    let referee_player_ids: BTreeSet<i32> = slots
+      .into_iter()
+      .filter(|v| v.player.is_referee)
+      .map(|v| v.player.player_id)
+      .collect();
+    let chat_policy = ChatPolicy::new(
+      opts.observer_chat_policy,
+      slots
+        .into_iter()
+        .filter(|v| v.settings.team == 24)
+        .map(|v| v.player.player_id),
+      referee_player_ids.iter().cloned(),
+    );
     let delay_equalizer = if opts.enabled_ping_equalizer {
       Some(DelayEqualizer::new(
         slots.iter().filter(|s| s.settings.team != 24).count(),
@@ -410,7 +536,14 @@ impl State {
     } else {
       None
     };
-    State {
+    let journal = GameJournal::open(
+      game_id,
+      restore
+        .as_ref()
+        .map(|snapshot| snapshot.journal_seq)
+        .unwrap_or_default(),
+    );
+    let mut state = State {
       game_id,
       ct,
       shared: Arc::new(Mutex::new(Shared::new(
@@ -418,6 +551,8 @@ impl State {
         slots,
         obs,
         delay_equalizer,
+        out_tx,
+        journal,
       ))),
       status_rx,
       game_player_id_lookup: slots
@@ -438,7 +573,54 @@ impl State {
           }
         })
         .collect(),
+      referee_player_ids,
+      chat_policy,
+      chat_muted: false,
       left_players: BTreeSet::new(),
+    };
+
+    if let Some(snapshot) = restore {
+      state.chat_muted = snapshot.chat_muted;
+      let pause_timeout = {
+        let mut shared = state.shared.lock();
+        let pause_timeout = shared.restore(snapshot);
+        state.left_players = slots
+          .iter()
+          .map(|slot| slot.player.player_id)
+          .filter(|player_id| !shared.map.contains_key(player_id))
+          .collect();
+        pause_timeout
+      };
+      // the clock only starts after the game started, the timer is queued until then
+      if let Some(timeout) = pause_timeout {
+        action_tx.try_send(ActionMsg::StartPauseTimer(timeout)).ok();
+      }
+    }
+
+    state
+  }
+
+  fn snapshot(&self) -> HostSnapshot {
+    let now = Instant::now();
+    let shared = self.shared.lock();
+    HostSnapshot {
+      sync: shared.sync.snapshot(),
+      players: shared
+        .map
+        .iter()
+        .map(|(player_id, info)| (*player_id, info.ack_queue().snapshot()))
+        .collect(),
+      delays: shared
+        .map
+        .iter()
+        .filter_map(|(player_id, info)| info.delay().map(|delay| (*player_id, *delay)))
+        .collect(),
+      pause: shared.pause.snapshot(now),
+      vote_kick: shared.vote_kick.snapshot(now),
+      chat_muted: self.chat_muted,
+      // entries written from now on are replayed on top of this snapshot
+      journal_seq: shared.journal.rotate(),
+      journal: vec![],
     }
   }
 
@@ -472,6 +654,9 @@ impl State {
           tracing::error!(game_id = self.game_id, player_id, "send shutdown: {}", err);
         }
       }
+      Cmd::Snapshot { tx } => {
+        tx.send(self.snapshot()).ok();
+      }
     }
 
     Ok(())
@@ -498,6 +683,7 @@ impl State {
 
     let (status, delay, reconnected, resend_frames) = {
       let mut guard = self.shared.lock();
+      let status_label = guard.status_label();
       let player = guard
         .get_player(player_id)
         .ok_or_else(|| Error::PlayerAlreadyLeft)?;
@@ -505,6 +691,9 @@ impl State {
       let delay = player.delay().cloned();
       player.register_sender(sender.clone());
       if reconnected {
+        crate::metrics::GAME_RECONNECTS
+          .with_label_values(&[status_label])
+          .inc();
         let resend_frames = player.get_resend_frames();
         let msg = format!("Reconnected to the server: {}", player.player_name());
         player.update_lag_ms_after_reconnect();
@@ -688,10 +877,14 @@ impl State {
     } else {
       None
     };
+    let status_label = shared.status_label();
     shared.get_player(player_id).map(|info| {
       info.push_rtt(rtt);
       if let Some(delay) = delay {
         tracing::debug!(player_id, "auto set delay: {}", delay);
+        crate::metrics::DELAY_EQUALIZER_ADJUSTMENTS
+          .with_label_values(&[status_label])
+          .inc();
         if delay > 0 {
           info
             .set_delay(Duration::from_millis(delay as _).into())
@@ -718,7 +911,7 @@ impl State {
       let player = shared
         .get_player(player_id)
         .ok_or_else(|| Error::PlayerNotFoundInGame)?;
-      if !player.update_ack(meta.clone()) {
+      if !player.update_ack(meta.clone())? {
         tracing::warn!(
           player_id,
           "discard resend: {}, {:?}, {:?}",
@@ -734,11 +927,44 @@ impl State {
     match packet.type_id() {
       PacketTypeId::OutgoingAction => {
         let payload: OutgoingAction = packet.decode_payload()?;
+        let action = PlayerAction {
+          player_id: slot_player_id,
+          data: payload.data,
+        };
+        match action.peek_action_id().map(ActionTypeId::from) {
+          Some(ActionTypeId::PauseGame) => {
+            let res = self.shared.lock().pause_game(player_id);
+            match res {
+              PauseGameResult::Paused(timeout) => {
+                action_tx
+                  .send(ActionMsg::StartPauseTimer(timeout))
+                  .await
+                  .map_err(|_| Error::Cancelled)?;
+              }
+              PauseGameResult::Continue => {}
+              PauseGameResult::Rejected => return Ok(()),
+            }
+          }
+          Some(ActionTypeId::ResumeGame) => {
+            if !self.referee_player_ids.contains(&player_id) && self.shared.lock().is_force_paused()
+            {
+              self
+                .shared
+                .lock()
+                .private_message(player_id, "The game has been paused by a referee.");
+              return Ok(());
+            }
+            if self.shared.lock().resume_game() {
+              action_tx
+                .send(ActionMsg::StopPauseTimer)
+                .await
+                .map_err(|_| Error::Cancelled)?;
+            }
+          }
+          _ => {}
+        }
         action_tx
-          .send(ActionMsg::PlayerAction(PlayerAction {
-            player_id: slot_player_id,
-            data: payload.data,
-          }))
+          .send(ActionMsg::PlayerAction(action))
           .await
           .map_err(|_| Error::Cancelled)?;
       }
@@ -756,7 +982,9 @@ impl State {
         }
       }
       PacketTypeId::ChatToHost => {
-        self.dispatch_chat(player_id, packet, action_tx).await?;
+        self
+          .dispatch_chat(player_id, packet, action_tx, out_tx)
+          .await?;
       }
       PacketTypeId::OutgoingKeepAlive => {
         let payload: OutgoingKeepAlive = packet.decode_simple()?;
@@ -814,6 +1042,42 @@ impl State {
         .map_err(|_err| Error::Cancelled)?;
     }
 
+    out_tx
+      .send(GameEvent::PlayerLeft(player_id, reason))
+      .await
+      .map_err(|_| Error::Cancelled)?;
+    out_tx
+      .send(GameEvent::PlayerStatusChange(
+        player_id,
+        SlotClientStatus::Left,
+        SlotClientStatusUpdateSource::Node,
+      ))
+      .await
+      .map_err(|_| Error::Cancelled)?;
+    Ok(())
+  }
+
+  async fn handle_player_removed(
+    &mut self,
+    removed: RemovedPlayer,
+    reason: PlayerRemovedReason,
+    action_tx: &mut Sender<ActionMsg>,
+    out_tx: &mut GameEventSender,
+  ) -> Result<()> {
+    let player_id = removed.player_id;
+    self.left_players.insert(player_id);
+
+    if removed.lagging {
+      action_tx
+        .send(ActionMsg::CheckStopLag)
+        .await
+        .map_err(|_err| Error::Cancelled)?;
+    }
+
+    out_tx
+      .send(GameEvent::PlayerRemoved(player_id, reason))
+      .await
+      .map_err(|_| Error::Cancelled)?;
     out_tx
       .send(GameEvent::PlayerStatusChange(
         player_id,
@@ -854,12 +1118,16 @@ impl State {
     player_id: i32,
     mut packet: Packet,
     action_tx: &mut Sender<ActionMsg>,
+    out_tx: &mut GameEventSender,
   ) -> Result<()> {
     use flo_w3gs::protocol::constants::PacketTypeId;
 
     let chat: ChatToHost = packet.decode_simple()?;
     if let Some(cmd) = chat.chat_message().and_then(parse_chat_command) {
-      if self.handle_command(action_tx, player_id, cmd).await? {
+      if self
+        .handle_command(action_tx, out_tx, player_id, cmd)
+        .await?
+      {
         return Ok(());
       }
     }
@@ -868,6 +1136,10 @@ impl State {
       return Ok(());
     }
 
+    if self.chat_muted && !self.referee_player_ids.contains(&player_id) && chat.is_in_game_chat() {
+      return Ok(());
+    }
+
     packet.header.type_id = PacketTypeId::ChatFromHost;
     {
       let mut guard = self.shared.lock();
@@ -880,7 +1152,7 @@ impl State {
             .into_iter()
             .filter_map(|id| {
               if let Some(id) = self.game_player_id_lookup.get(&id).cloned() {
-                if id != player_id {
+                if id != player_id && self.chat_policy.allows(player_id, id) {
                   Some(id)
                 } else {
                   None
@@ -897,12 +1169,14 @@ impl State {
   }
 
   async fn handle_command(
-    &self,
+    &mut self,
     action_tx: &mut Sender<ActionMsg>,
+    out_tx: &mut GameEventSender,
     player_id: i32,
     cmd: ChatCommand<'_>,
   ) -> Result<bool> {
     let debug = cfg!(debug_assertions);
+    let referee = self.referee_player_ids.contains(&player_id);
     match cmd.name() {
       "drop" if debug => {
         let shared = self.shared.clone();
@@ -927,6 +1201,12 @@ impl State {
         }
       }
       "delay" => {
+        if referee {
+          if let Ok((slot, ms)) = cmd.parse_arguments::<(u8, u16)>() {
+            self.shared.lock().set_delay_by_slot(player_id, slot, ms);
+            return Ok(true);
+          }
+        }
         if let Some(Some((ms,))) = cmd.parse_arguments::<Option<(u16,)>>().ok() {
           let [min, max] = crate::constants::GAME_DELAY_RANGE;
 
@@ -1011,6 +1291,95 @@ impl State {
           }
         }
       }
+      "votekick" => match cmd.parse_arguments::<(u8,)>().ok() {
+        Some((slot,)) => {
+          let removed = self.shared.lock().vote_kick(player_id, slot)?;
+          if let Some(removed) = removed {
+            self
+              .handle_player_removed(removed, PlayerRemovedReason::VoteKick, action_tx, out_tx)
+              .await?;
+          }
+        }
+        None => {
+          self
+            .shared
+            .lock()
+            .private_message(player_id, "Invalid syntax, usage: !votekick 2");
+        }
+      },
+      "pause" if referee => {
+        let action = self.shared.lock().force_pause_game(player_id);
+        if let Some(action) = action {
+          action_tx
+            .send(ActionMsg::PlayerAction(action))
+            .await
+            .map_err(|_| Error::Cancelled)?;
+        }
+      }
+      "resume" if referee => {
+        let action = self.shared.lock().force_resume_game(player_id);
+        if let Some(action) = action {
+          action_tx
+            .send(ActionMsg::StopPauseTimer)
+            .await
+            .map_err(|_| Error::Cancelled)?;
+          action_tx
+            .send(ActionMsg::PlayerAction(action))
+            .await
+            .map_err(|_| Error::Cancelled)?;
+        }
+      }
+      "kick" if referee => match cmd.parse_arguments::<(u8,)>().ok() {
+        Some((slot,)) => {
+          let removed = {
+            let mut shared = self.shared.lock();
+            match shared.find_player_by_slot(slot) {
+              Some((target, _)) if self.referee_player_ids.contains(&target) => {
+                shared.private_message(player_id, "Referees cannot be kicked.");
+                None
+              }
+              Some((target, name)) => {
+                tracing::info!(
+                  game_id = self.game_id,
+                  player_id = target,
+                  "kicked by referee"
+                );
+                shared.broadcast_message(format!("{} has been kicked by a referee.", name));
+                Some(shared.remove_player_with_reason(target, PlayerRemovedReason::RefereeKick)?)
+              }
+              None => {
+                shared.private_message(player_id, format!("Player not found: slot {}", slot));
+                None
+              }
+            }
+          };
+          if let Some(removed) = removed {
+            self
+              .handle_player_removed(removed, PlayerRemovedReason::RefereeKick, action_tx, out_tx)
+              .await?;
+          }
+        }
+        None => {
+          self
+            .shared
+            .lock()
+            .private_message(player_id, "Invalid syntax, usage: !kick 2");
+        }
+      },
+      "muteall" if referee => {
+        self.chat_muted = true;
+        self
+          .shared
+          .lock()
+          .broadcast_message("In-game chat has been muted by a referee.");
+      }
+      "unmuteall" if referee => {
+        self.chat_muted = false;
+        self
+          .shared
+          .lock()
+          .broadcast_message("In-game chat has been unmuted.");
+      }
       "desync" if debug => {
         let mut lock = self.shared.lock();
         if let Some(player) = lock.get_player(player_id) {
@@ -1097,6 +1466,11 @@ struct Shared {
   obs: ObserverPublisherHandle,
   active_players: BTreeSet<i32>,
   delay_equalizer: Option<DelayEqualizer>,
+  pause: PauseBudget,
+  vote_kick: VoteKick,
+  action_history: ActionHistory,
+  out_tx: GameEventSender,
+  journal: GameJournal,
 }
 
 impl Shared {
@@ -1105,8 +1479,14 @@ impl Shared {
     slots: &[PlayerSlot],
     obs: ObserverPublisherHandle,
     delay_equalizer: Option<DelayEqualizer>,
+    out_tx: GameEventSender,
+    journal: GameJournal,
   ) -> Self {
     let sync = SyncMap::new(slots.iter().map(|s| s.player.player_id).collect());
+    let pause = PauseBudget::new(
+      PauseLimits::from_env(),
+      slots.iter().map(|s| (s.player.player_id, s.settings.team)),
+    );
     let mut slot_id_lookup = BTreeMap::new();
     let mut active_players = BTreeSet::new();
     Self {
@@ -1115,7 +1495,7 @@ impl Shared {
       map: slots
         .into_iter()
         .map(|slot| {
-          let p = PlayerDispatchInfo::new(slot);
+          let p = PlayerDispatchInfo::new(slot, journal.clone());
           slot_id_lookup.insert(slot.player.player_id, p.slot_player_id());
           if !p.is_observer() {
             active_players.insert(slot.player.player_id);
@@ -1130,6 +1510,92 @@ impl Shared {
       obs,
       active_players,
       delay_equalizer,
+      pause,
+      vote_kick: VoteKick::from_env(),
+      action_history: ActionHistory::new(crate::constants::GAME_DESYNC_ACTION_HISTORY_LEN),
+      out_tx,
+      journal,
+    }
+  }
+
+  /// Returns the remaining time of the current pause
+  fn restore(&mut self, snapshot: HostSnapshot) -> Option<Duration> {
+    let now = Instant::now();
+    let removed: Vec<i32> = self
+      .map
+      .keys()
+      .filter(|player_id| !snapshot.players.contains_key(player_id))
+      .cloned()
+      .collect();
+    for player_id in removed {
+      self.map.remove(&player_id);
+      self
+        .delay_equalizer
+        .as_mut()
+        .map(|de| de.remove_player(player_id));
+    }
+    for (player_id, q) in snapshot.players {
+      if let Some(info) = self.map.get_mut(&player_id) {
+        info.restore_ack_queue(q);
+        let delay = snapshot.delays.get(&player_id).cloned();
+        if let Err(err) = info.set_delay(delay) {
+          tracing::error!(game_id = self.game_id, player_id, "restore delay: {}", err);
+        }
+      }
+    }
+    self.sync.restore(snapshot.sync);
+    self.vote_kick.restore(snapshot.vote_kick, now);
+    for entry in snapshot.journal {
+      self.replay(entry);
+    }
+    self.pause.restore(snapshot.pause, now)
+  }
+
+  /// Applies a journal entry written after the snapshot,
+  /// so the players resume from the frames they have already received
+  fn replay(&mut self, entry: JournalEntry) {
+    match entry {
+      JournalEntry::Tick { time_increment_ms } => {
+        self.sync.replay_clock(time_increment_ms);
+      }
+      JournalEntry::Send {
+        player_id,
+        meta,
+        packet,
+      } => {
+        if let Some(info) = self.map.get_mut(&player_id) {
+          info.replay_send(meta, packet);
+        }
+      }
+      JournalEntry::Receive {
+        player_id,
+        sid,
+        ack_sid,
+      } => {
+        if let Some(info) = self.map.get_mut(&player_id) {
+          info.replay_receive(sid, ack_sid);
+        }
+      }
+      JournalEntry::Checksum {
+        player_id,
+        checksum,
+      } => {
+        self.sync.ack(player_id, checksum).ok();
+      }
+      JournalEntry::RemovePlayer { player_id } => {
+        self
+          .delay_equalizer
+          .as_mut()
+          .map(|de| de.remove_player(player_id));
+        if let Some(player) = self.map.remove(&player_id) {
+          self.vote_kick.remove_player(player_id);
+          for p in self.map.values_mut() {
+            p.remove_lag_slot(player.slot_player_id());
+          }
+          // desyncs have been reported before the restart
+          let _ = self.sync.remove_player(player_id);
+        }
+      }
     }
   }
 
@@ -1137,6 +1603,14 @@ impl Shared {
     self.started = true;
   }
 
+  fn status_label(&self) -> &'static str {
+    crate::metrics::game_status_label(if self.started {
+      NodeGameStatus::Running
+    } else {
+      NodeGameStatus::Loading
+    })
+  }
+
   fn get_player(&mut self, player_id: i32) -> Option<&mut PlayerDispatchInfo> {
     self.map.get_mut(&player_id)
   }
@@ -1144,13 +1618,28 @@ impl Shared {
   #[must_use]
   pub fn dispatch_action_tick(&mut self, mut tick: Tick) -> Result<DispatchResult> {
     let time_increment_ms = tick.time_increment_ms;
-    if let ClockResult::Lag(timeouts) = self.sync.clock(time_increment_ms) {
-      let player_ids: Vec<_> = timeouts.into_iter().map(|t| t.player_id).collect();
-      if self.handle_lag(player_ids)? {
-        return Ok(DispatchResult::Lag(tick));
+    match self.sync.clock(time_increment_ms) {
+      ClockResult::Lag(timeouts) => {
+        let player_ids: Vec<_> = timeouts.into_iter().map(|t| t.player_id).collect();
+        if self.handle_lag(player_ids)? {
+          return Ok(DispatchResult::Lag(tick));
+        }
+      }
+      ClockResult::Tick => {
+        self.journal.tick(time_increment_ms);
       }
     }
 
+    self.action_history.push(self.sync.tick(), &tick.actions);
+
+    let status_label = self.status_label();
+    crate::metrics::GAME_TICK_LATENESS
+      .with_label_values(&[status_label])
+      .observe(tick.late_ms as f64);
+    crate::metrics::GAME_ACTION_BYTES
+      .with_label_values(&[status_label])
+      .inc_by(tick.actions_bytes_len as u64);
+
     if tick.actions_bytes_len > DISPATCH_ACTIONS_MTU {
       tracing::debug!(
         "over-sized actions: tick = {}, size = {}, len = {}",
@@ -1211,8 +1700,12 @@ impl Shared {
   }
 
   fn push_rtt_stats(&mut self, time: u32) {
+    let rtt_histogram = crate::metrics::PLAYER_RTT.with_label_values(&[self.status_label()]);
     let items = self.map.iter_mut().map(|(id, info)| {
       let stats = info.take_rtt();
+      if stats.ticks > 0 {
+        rtt_histogram.observe(stats.avg as f64);
+      }
       RTTStatsItem {
         player_id: *id,
         ticks: stats.ticks,
@@ -1227,6 +1720,22 @@ impl Shared {
       .push_rtt_stat(self.game_id, RTTStats::new(time, items))
   }
 
+  fn step_sample(&self) -> StepSample {
+    self
+      .map
+      .iter()
+      .filter(|(id, _)| self.active_players.contains(id))
+      .fold(StepSample::default(), |mut sample, (_, info)| {
+        if let Some(rtt) = info.rtt() {
+          sample.max_rtt_avg = sample.max_rtt_avg.max(rtt.avg);
+        }
+        sample.max_pending_ack = sample
+          .max_pending_ack
+          .max(info.ack_queue().pending_ack_len());
+        sample
+      })
+  }
+
   fn handle_lag(&mut self, add_player_ids: Vec<i32>) -> Result<bool> {
     self.lagging_player_ids.extend(add_player_ids);
     self.obs.push_start_lag(
@@ -1372,6 +1881,7 @@ impl Shared {
           player_id,
           "player dropped before game start"
         );
+        self.observe_dropped_player("disconnect");
         self.remove_player_and_broadcast(player_id, None)?;
         if self.lagging_player_ids.contains(&player_id) {
           Ok(ClosePlayerStreamResult::ClosedLagging)
@@ -1401,6 +1911,10 @@ impl Shared {
 
     tracing::info!(game_id = self.game_id, player_id, "remove player");
 
+    self.journal.remove_player(player_id);
+
+    self.vote_kick.remove_player(player_id);
+
     for p in self.map.values_mut() {
       p.remove_lag_slot(player.slot_player_id());
     }
@@ -1417,7 +1931,8 @@ impl Shared {
         "desync detected after disconnecting player: {:?}",
         desync
       );
-      tracing::warn!("{}", self.sync.debug_pending())
+      tracing::warn!("{}", self.sync.debug_pending());
+      self.report_desync(&desync);
     }
     player.close_stream();
     Ok(())
@@ -1474,6 +1989,7 @@ impl Shared {
         }
         PlayerSendError::AckQueueFull => {
           tracing::warn!(game_id = self.game_id, player_id, "ack queue full");
+          self.observe_dropped_player("ack_queue_full");
           self.remove_player_and_broadcast(player_id, None)?;
         }
         _ => {}
@@ -1517,6 +2033,281 @@ impl Shared {
     }
   }
 
+  fn vote_kick(&mut self, player_id: i32, slot_player_id: u8) -> Result<Option<RemovedPlayer>> {
+    let name = match self.map.get(&player_id) {
+      Some(v) if !v.is_observer() => v.player_name().to_string(),
+      _ => return Ok(None),
+    };
+    let (target, target_name) = if let Some(v) = self.find_player_by_slot(slot_player_id) {
+      v
+    } else {
+      self.private_message(
+        player_id,
+        format!("Player not found: slot {}", slot_player_id),
+      );
+      return Ok(None);
+    };
+    let team = self.map.get(&target).map(|v| v.team());
+    let voters: BTreeSet<i32> = self
+      .map
+      .iter()
+      .filter(|(id, v)| **id != target && !v.is_observer() && Some(v.team()) == team)
+      .map(|(id, _)| *id)
+      .collect();
+
+    match self
+      .vote_kick
+      .vote(player_id, target, &voters, Instant::now())
+    {
+      VoteKickResult::Started { required } => {
+        let window = self.vote_kick.window();
+        self.broadcast_message(format!(
+          "{} started a vote to kick {} (1/{}), type !votekick {} within {}s to vote",
+          name,
+          target_name,
+          required,
+          slot_player_id,
+          window.as_secs()
+        ));
+      }
+      VoteKickResult::Voted { votes, required } => {
+        self.broadcast_message(format!(
+          "Vote to kick {}: {}/{}",
+          target_name, votes, required
+        ));
+      }
+      VoteKickResult::AlreadyVoted => {
+        self.private_message(player_id, "You have already voted.");
+      }
+      VoteKickResult::Rejected(reason) => {
+        self.private_message(player_id, reason.message());
+      }
+      VoteKickResult::Passed => {
+        tracing::info!(
+          game_id = self.game_id,
+          player_id = target,
+          "vote kick passed"
+        );
+        self.broadcast_message(format!("{} has been kicked by vote.", target_name));
+        return self
+          .remove_player_with_reason(target, PlayerRemovedReason::VoteKick)
+          .map(Some);
+      }
+    }
+    Ok(None)
+  }
+
+  /// Finds a non-observer player by slot player id, returns the player id and name
+  fn find_player_by_slot(&self, slot_player_id: u8) -> Option<(i32, String)> {
+    self
+      .map
+      .iter()
+      .find(|(_, v)| v.slot_player_id() == slot_player_id && !v.is_observer())
+      .map(|(id, v)| (*id, v.player_name().to_string()))
+  }
+
+  /// Sets the delay of another player, requested by a referee with `!delay <slot> <ms>`
+  fn set_delay_by_slot(&mut self, player_id: i32, slot_player_id: u8, ms: u16) {
+    let [min, max] = crate::constants::GAME_DELAY_RANGE;
+    let target = if let Some((target, _)) = self.find_player_by_slot(slot_player_id) {
+      target
+    } else {
+      self.private_message(
+        player_id,
+        format!("Player not found: slot {}", slot_player_id),
+      );
+      return;
+    };
+
+    if self.delay_equalizer.is_some() {
+      self.private_message(
+        player_id,
+        "Cannot set delay because ping equalizer is enabled",
+      );
+      return;
+    }
+
+    let delay = if ms == 0 {
+      None
+    } else {
+      let duration = Duration::from_millis(ms as _);
+      if duration < min || duration > max {
+        self.private_message(
+          player_id,
+          format!(
+            "Invalid value, range {} - {}",
+            min.as_millis(),
+            max.as_millis()
+          ),
+        );
+        return;
+      }
+      Some(duration)
+    };
+
+    let name = self
+      .get_player(target)
+      .and_then(|player| -> Option<String> {
+        player.set_delay(delay).ok()?;
+        Some(player.player_name().to_string())
+      });
+    if let Some(name) = name {
+      if delay.is_some() {
+        self.broadcast_message(format!("Set delay for {}: {}ms", name, ms));
+      } else {
+        self.broadcast_message(format!("Removed delay for {}", name));
+      }
+    }
+  }
+
+  /// Removes a player for a reason other than leaving or lagging,
+  /// the reason is recorded for the observers
+  fn remove_player_with_reason(
+    &mut self,
+    player_id: i32,
+    reason: PlayerRemovedReason,
+  ) -> Result<RemovedPlayer> {
+    self
+      .obs
+      .push_player_removed(self.game_id, player_id, reason);
+    self.remove_player_and_broadcast(player_id, None)?;
+    Ok(RemovedPlayer {
+      player_id,
+      lagging: self.lagging_player_ids.contains(&player_id),
+    })
+  }
+
+  fn pause_game(&mut self, player_id: i32) -> PauseGameResult {
+    let name = if let Some(name) = self
+      .map
+      .get(&player_id)
+      .map(|v| v.player_name().to_string())
+    {
+      name
+    } else {
+      return PauseGameResult::Rejected;
+    };
+    match self.pause.pause(player_id, Instant::now()) {
+      PauseResult::Paused {
+        timeout,
+        remaining_count,
+      } => {
+        tracing::info!(
+          game_id = self.game_id,
+          player_id,
+          "game paused: timeout = {:?}",
+          timeout
+        );
+        self.broadcast_message(format!(
+          "{} paused the game ({} pauses left, resuming in {}s)",
+          name,
+          remaining_count,
+          timeout.as_secs()
+        ));
+        PauseGameResult::Paused(timeout)
+      }
+      PauseResult::AlreadyPaused => PauseGameResult::Continue,
+      PauseResult::Rejected(reason) => {
+        tracing::info!(
+          game_id = self.game_id,
+          player_id,
+          "pause rejected: {:?}",
+          reason
+        );
+        self.private_message(player_id, reason.message());
+        PauseGameResult::Rejected
+      }
+    }
+  }
+
+  fn is_force_paused(&self) -> bool {
+    self.pause.current().map(|v| v.forced).unwrap_or(false)
+  }
+
+  /// Pauses the game for a referee, returns the pause action that should be sent to all players
+  fn force_pause_game(&mut self, player_id: i32) -> Option<PlayerAction> {
+    let (name, slot_player_id) = self
+      .map
+      .get(&player_id)
+      .map(|v| (v.player_name().to_string(), v.slot_player_id()))?;
+    if !self.pause.force_pause(player_id, Instant::now()) {
+      self.private_message(player_id, "The game is already paused.");
+      return None;
+    }
+    tracing::info!(game_id = self.game_id, player_id, "game paused by referee");
+    self.broadcast_message(format!("{} (referee) paused the game.", name));
+    Some(PlayerAction {
+      player_id: slot_player_id,
+      data: Bytes::from(vec![u8::from(ActionTypeId::PauseGame)]),
+    })
+  }
+
+  /// Resumes the game for a referee, returns the resume action that should be sent to all players
+  fn force_resume_game(&mut self, player_id: i32) -> Option<PlayerAction> {
+    let slot_player_id = self.map.get(&player_id).map(|v| v.slot_player_id())?;
+    if !self.resume_game() {
+      self.private_message(player_id, "The game is not paused.");
+      return None;
+    }
+    Some(PlayerAction {
+      player_id: slot_player_id,
+      data: Bytes::from(vec![u8::from(ActionTypeId::ResumeGame)]),
+    })
+  }
+
+  fn resume_game(&mut self) -> bool {
+    let resumed = if let Some(v) = self.pause.resume(Instant::now()) {
+      v
+    } else {
+      return false;
+    };
+    tracing::info!(
+      game_id = self.game_id,
+      player_id = resumed.player_id,
+      "game resumed: duration = {:?}",
+      resumed.duration
+    );
+    if resumed.forced {
+      self.broadcast_message("Game resumed.");
+    } else if let Some(name) = self
+      .map
+      .get(&resumed.player_id)
+      .map(|v| v.player_name().to_string())
+    {
+      let remaining_count = self.pause.remaining_count(resumed.player_id);
+      let remaining_duration = self.pause.remaining_duration(resumed.player_id);
+      self.broadcast_message(format!(
+        "Game resumed, {} has {} pauses and {}s pause time left",
+        name,
+        remaining_count,
+        remaining_duration.as_secs()
+      ));
+    }
+    true
+  }
+
+  /// Ends the current pause after the pause budget has been exhausted,
+  /// returns the resume action that should be sent to all players
+  fn auto_resume_game(&mut self) -> Option<PlayerAction> {
+    let current = self.pause.current().cloned()?;
+    let slot_player_id = self
+      .map
+      .get(&current.player_id)
+      .or_else(|| {
+        self
+          .active_players
+          .iter()
+          .find_map(|player_id| self.map.get(player_id))
+      })
+      .map(|v| v.slot_player_id())?;
+    self.broadcast_message("Pause time exhausted, resuming the game.");
+    self.resume_game();
+    Some(PlayerAction {
+      player_id: slot_player_id,
+      data: Bytes::from(vec![u8::from(ActionTypeId::ResumeGame)]),
+    })
+  }
+
   pub fn drop_all_lag_players(&mut self) -> Result<()> {
     let drop_player_ids: Vec<_> = self.lagging_player_ids.iter().cloned().collect();
     for drop_player_id in &drop_player_ids {
@@ -1525,6 +2316,9 @@ impl Shared {
         player_id = *drop_player_id,
         "lagging player dropped."
       );
+      if self.map.contains_key(drop_player_id) {
+        self.observe_dropped_player("lag");
+      }
       self.remove_player_and_broadcast(*drop_player_id, None)?;
     }
     self.lagging_player_ids.clear();
@@ -1532,6 +2326,7 @@ impl Shared {
   }
 
   pub fn ack(&mut self, player_id: i32, checksum: u32) -> Result<AckAction> {
+    self.journal.checksum(player_id, checksum);
     let res = match self.sync.ack(player_id, checksum) {
       Ok(res) => {
         if let Some(checksum) = res.agreed_checksum.clone() {
@@ -1576,6 +2371,8 @@ impl Shared {
   }
 
   fn handle_desync(&mut self, desync: Vec<PlayerDesync>) -> Result<()> {
+    self.report_desync(&desync);
+
     let mut handled = BTreeSet::new();
     let mut targets = vec![];
     for item in desync {
@@ -1605,17 +2402,95 @@ impl Shared {
 
     for (player_id, message) in targets {
       self.broadcast_message(message);
+      self.observe_dropped_player("desync");
       self.remove_player_and_broadcast(player_id, None)?;
     }
     Ok(())
   }
 }
 
+impl Shared {
+  /// Sends a desync report for each desynced tick to the observers and the controller
+  fn report_desync(&mut self, desync: &[PlayerDesync]) {
+    let player_ids: BTreeSet<i32> = desync.iter().map(|item| item.player_id).collect();
+    crate::metrics::GAME_DESYNCS
+      .with_label_values(&[self.status_label()])
+      .inc_by(player_ids.len() as u64);
+
+    let mut ticks = self.sync.take_desync_ticks();
+    if ticks.is_empty() {
+      // the player acked a tick that does not exist
+      ticks = desync
+        .iter()
+        .map(|item| DesyncTick {
+          tick: item.tick,
+          time: item.time,
+          checksums: std::iter::once((item.player_id, item.checksum)).collect(),
+        })
+        .collect();
+    }
+
+    for tick in ticks {
+      let desynced: BTreeSet<i32> = desync
+        .iter()
+        .filter(|item| item.tick == tick.tick)
+        .map(|item| item.player_id)
+        .collect();
+      let report = DesyncReport::new(
+        &tick,
+        &desynced,
+        self
+          .map
+          .iter()
+          .map(|(player_id, info)| (*player_id, info.slot_player_id())),
+        &self.action_history,
+      );
+      self.obs.push_desync(self.game_id, report.to_record());
+      match self.out_tx.try_send(GameEvent::Desync(report)) {
+        Ok(_) => {}
+        Err(TrySendError::Full(event)) => {
+          // don't block the dispatcher, but don't drop the report either
+          let game_id = self.game_id;
+          let tx = self.out_tx.clone();
+          tokio::spawn(async move {
+            if tx.send(event).await.is_err() {
+              tracing::error!(game_id, "send desync report: channel closed");
+            }
+          });
+        }
+        Err(TrySendError::Closed(_)) => {
+          tracing::error!(game_id = self.game_id, "send desync report: channel closed");
+        }
+      }
+    }
+  }
+
+  fn observe_dropped_player(&self, reason: &str) {
+    crate::metrics::GAME_DROPPED_PLAYERS
+      .with_label_values(&[self.status_label(), reason])
+      .inc();
+  }
+}
+
 enum AckAction {
   Continue,
   CheckStopLag,
 }
 
+enum PauseGameResult {
+  /// Forward the pause action, the game should be resumed after the timeout
+  Paused(Duration),
+  /// Forward the action without charging the player
+  Continue,
+  /// Discard the pause action
+  Rejected,
+}
+
+struct RemovedPlayer {
+  player_id: i32,
+  lagging: bool,
+}
+
 enum RequestDropResult {
   NoLaggingPlayer,
   Voting,
@@ -0,0 +1,350 @@
+//! Write-ahead journal of a running game host.
+//! Frames are appended before they are sent to the players, together with the ticks,
+//! checksums and acks that change the host state, so a game restored from the last snapshot
+//! catches up with everything the clients have already received.
+//!
+//! Each snapshot starts a new segment, `<game_id>.<seq>.journal`,
+//! the segments before the one recorded in the last snapshot file are removed by the node.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use flo_net::packet::Message;
+use flo_net::proto::flo_node as proto;
+use flo_net::w3gs::{W3GSMetadata, W3GSPacket};
+use parking_lot::Mutex;
+
+use super::snapshot::{pack_packet, unpack_packet};
+use crate::error::*;
+
+const JOURNAL_EXT: &str = "journal";
+
+#[derive(Debug, Clone)]
+pub enum JournalEntry {
+  Tick {
+    time_increment_ms: u16,
+  },
+  Send {
+    player_id: i32,
+    meta: W3GSMetadata,
+    packet: W3GSPacket,
+  },
+  Receive {
+    player_id: i32,
+    sid: u32,
+    ack_sid: Option<u32>,
+  },
+  Checksum {
+    player_id: i32,
+    checksum: u32,
+  },
+  RemovePlayer {
+    player_id: i32,
+  },
+}
+
+impl JournalEntry {
+  fn pack(&self) -> proto::GameJournalEntry {
+    use proto::game_journal_entry::Entry;
+    let entry = match *self {
+      JournalEntry::Tick { time_increment_ms } => Entry::Tick(proto::GameJournalTick {
+        time_increment_ms: time_increment_ms as u32,
+      }),
+      JournalEntry::Send {
+        player_id,
+        ref meta,
+        ref packet,
+      } => Entry::Send(proto::GameJournalSend {
+        player_id,
+        packet: Some(pack_packet(meta, packet)),
+      }),
+      JournalEntry::Receive {
+        player_id,
+        sid,
+        ack_sid,
+      } => Entry::Receive(proto::GameJournalReceive {
+        player_id,
+        sid,
+        ack_sid,
+      }),
+      JournalEntry::Checksum {
+        player_id,
+        checksum,
+      } => Entry::Checksum(proto::GameJournalChecksum {
+        player_id,
+        checksum,
+      }),
+      JournalEntry::RemovePlayer { player_id } => {
+        Entry::RemovePlayer(proto::GameJournalRemovePlayer { player_id })
+      }
+    };
+    proto::GameJournalEntry { entry: Some(entry) }
+  }
+
+  fn unpack(value: proto::GameJournalEntry) -> Option<Self> {
+    use proto::game_journal_entry::Entry;
+    let entry = match value.entry? {
+      Entry::Tick(v) => JournalEntry::Tick {
+        time_increment_ms: v.time_increment_ms as u16,
+      },
+      Entry::Send(v) => {
+        let (meta, packet) = unpack_packet(v.packet?);
+        JournalEntry::Send {
+          player_id: v.player_id,
+          meta,
+          packet,
+        }
+      }
+      Entry::Receive(v) => JournalEntry::Receive {
+        player_id: v.player_id,
+        sid: v.sid,
+        ack_sid: v.ack_sid,
+      },
+      Entry::Checksum(v) => JournalEntry::Checksum {
+        player_id: v.player_id,
+        checksum: v.checksum,
+      },
+      Entry::RemovePlayer(v) => JournalEntry::RemovePlayer {
+        player_id: v.player_id,
+      },
+    };
+    Some(entry)
+  }
+}
+
+/// Appends the entries of a game to its current journal segment,
+/// disabled if `FLO_NODE_SNAPSHOT_DIR` is not set
+#[derive(Debug, Clone, Default)]
+pub struct GameJournal(Option<Arc<Mutex<JournalFile>>>);
+
+#[derive(Debug)]
+struct JournalFile {
+  dir: PathBuf,
+  game_id: i32,
+  seq: u32,
+  file: Option<File>,
+}
+
+impl GameJournal {
+  /// Starts a segment numbered at least `seq` and after all existing segments of the game
+  pub fn open(game_id: i32, seq: u32) -> Self {
+    if let Some(dir) = crate::constants::NODE_SNAPSHOT_DIR.clone() {
+      Self::open_dir(dir, game_id, seq)
+    } else {
+      Self::default()
+    }
+  }
+
+  fn open_dir(dir: PathBuf, game_id: i32, seq: u32) -> Self {
+    let seq = list_journals(&dir)
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|(id, _, _)| *id == game_id)
+      .map(|(_, seq, _)| seq + 1)
+      .fold(seq, std::cmp::max);
+    let mut file = JournalFile {
+      dir,
+      game_id,
+      seq,
+      file: None,
+    };
+    file.open();
+    Self(Some(Arc::new(Mutex::new(file))))
+  }
+
+  /// Starts a new segment and returns its sequence number,
+  /// called when the state of the game is snapshotted
+  pub fn rotate(&self) -> u32 {
+    if let Some(inner) = self.0.as_ref() {
+      let mut file = inner.lock();
+      file.seq += 1;
+      file.open();
+      file.seq
+    } else {
+      0
+    }
+  }
+
+  pub fn tick(&self, time_increment_ms: u16) {
+    self.append(|| JournalEntry::Tick { time_increment_ms })
+  }
+
+  pub fn send(&self, player_id: i32, meta: &W3GSMetadata, packet: &W3GSPacket) {
+    self.append(|| JournalEntry::Send {
+      player_id,
+      meta: meta.clone(),
+      packet: packet.clone(),
+    })
+  }
+
+  pub fn receive(&self, player_id: i32, meta: &W3GSMetadata) {
+    self.append(|| JournalEntry::Receive {
+      player_id,
+      sid: meta.sid(),
+      ack_sid: meta.ack_sid(),
+    })
+  }
+
+  pub fn checksum(&self, player_id: i32, checksum: u32) {
+    self.append(|| JournalEntry::Checksum {
+      player_id,
+      checksum,
+    })
+  }
+
+  pub fn remove_player(&self, player_id: i32) {
+    self.append(|| JournalEntry::RemovePlayer { player_id })
+  }
+
+  fn append<F>(&self, f: F)
+  where
+    F: FnOnce() -> JournalEntry,
+  {
+    let mut guard = if let Some(inner) = self.0.as_ref() {
+      inner.lock()
+    } else {
+      return;
+    };
+    let res = if let Some(file) = guard.file.as_mut() {
+      file.write_all(&f().pack().encode_length_delimited_to_vec())
+    } else {
+      return;
+    };
+    if let Err(err) = res {
+      tracing::error!(game_id = guard.game_id, "write journal: {}", err);
+      // the segment is incomplete, stop writing until the next snapshot starts a new one
+      guard.file.take();
+    }
+  }
+}
+
+impl JournalFile {
+  fn open(&mut self) {
+    let path = journal_path(&self.dir, self.game_id, self.seq);
+    let res = std::fs::create_dir_all(&self.dir)
+      .and_then(|_| OpenOptions::new().create(true).append(true).open(&path));
+    self.file = match res {
+      Ok(file) => Some(file),
+      Err(err) => {
+        tracing::error!(game_id = self.game_id, "open journal: {}", err);
+        None
+      }
+    };
+  }
+}
+
+/// Reads the entries of the segments numbered `seq` and after.
+/// An entry truncated at the end of a segment has never been sent and is ignored.
+pub fn read_journal(dir: &Path, game_id: i32, seq: u32) -> Result<Vec<JournalEntry>> {
+  let mut segments: Vec<_> = list_journals(dir)?
+    .into_iter()
+    .filter(|(id, segment_seq, _)| *id == game_id && *segment_seq >= seq)
+    .collect();
+  segments.sort_by_key(|(_, seq, _)| *seq);
+
+  let mut entries = vec![];
+  for (_, _, path) in segments {
+    let bytes = std::fs::read(&path)?;
+    let mut buf = bytes.as_slice();
+    while !buf.is_empty() {
+      match proto::GameJournalEntry::decode_length_delimited(&mut buf) {
+        Ok(entry) => entries.extend(JournalEntry::unpack(entry)),
+        Err(err) => {
+          tracing::warn!(game_id, "truncated journal entry: {}", err);
+          break;
+        }
+      }
+    }
+  }
+  Ok(entries)
+}
+
+/// Returns the game id, the sequence number and the path of each segment
+pub fn list_journals(dir: &Path) -> Result<Vec<(i32, u32, PathBuf)>> {
+  if !dir.exists() {
+    return Ok(vec![]);
+  }
+
+  let mut paths = vec![];
+  for entry in std::fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.extension().and_then(|v| v.to_str()) != Some(JOURNAL_EXT) {
+      continue;
+    }
+    let ids = path.file_stem().and_then(|v| v.to_str()).and_then(|v| {
+      let mut parts = v.splitn(2, '.');
+      let game_id = parts.next()?.parse::<i32>().ok()?;
+      let seq = parts.next()?.parse::<u32>().ok()?;
+      Some((game_id, seq))
+    });
+    if let Some((game_id, seq)) = ids {
+      paths.push((game_id, seq, path));
+    }
+  }
+  Ok(paths)
+}
+
+fn journal_path(dir: &Path, game_id: i32, seq: u32) -> PathBuf {
+  dir.join(format!("{}.{}.{}", game_id, seq, JOURNAL_EXT))
+}
+
+#[test]
+fn test_journal() {
+  use flo_w3gs::protocol::leave::LeaveAck;
+  use std::time::SystemTime;
+
+  let dir = std::env::temp_dir().join(format!(
+    "flo-node-journal-{}",
+    SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .unwrap()
+      .as_nanos()
+  ));
+  let packet = W3GSPacket::simple(LeaveAck).unwrap();
+
+  let journal = GameJournal::open_dir(dir.clone(), 1, 0);
+  journal.tick(100);
+  let seq = journal.rotate();
+  assert_eq!(seq, 1);
+  journal.send(2, &W3GSMetadata::new(packet.type_id(), 5, Some(3)), &packet);
+  journal.checksum(2, 42);
+  // an entry that was being written when the process exited
+  OpenOptions::new()
+    .append(true)
+    .open(journal_path(&dir, 1, seq))
+    .unwrap()
+    .write_all(&[10, 1])
+    .unwrap();
+
+  let entries = read_journal(&dir, 1, seq).unwrap();
+  assert_eq!(entries.len(), 2);
+  match entries[0] {
+    JournalEntry::Send {
+      player_id,
+      ref meta,
+      packet: ref restored,
+    } => {
+      assert_eq!(player_id, 2);
+      assert_eq!(meta.sid(), 5);
+      assert_eq!(meta.ack_sid(), Some(3));
+      assert_eq!(restored.payload, packet.payload);
+    }
+    ref other => panic!("unexpected entry: {:?}", other),
+  }
+  assert!(matches!(
+    entries[1],
+    JournalEntry::Checksum {
+      player_id: 2,
+      checksum: 42
+    }
+  ));
+  assert_eq!(read_journal(&dir, 1, 0).unwrap().len(), 3);
+
+  // a restored game appends to a new segment
+  let journal = GameJournal::open_dir(dir.clone(), 1, seq);
+  assert_eq!(journal.rotate(), 3);
+
+  std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,7 +1,12 @@
 use s2_grpc_utils::S2ProtoEnum;
+use std::future::Future;
 
+pub use chat_policy::ObserverChatPolicy;
+pub use desync::DesyncReport;
 use dispatch::Dispatcher;
 use flo_net::packet::*;
+pub use journal::{list_journals, read_journal, JournalEntry};
+pub use snapshot::HostSnapshot;
 pub use sync::AckError;
 
 use crate::error::*;
@@ -10,14 +15,21 @@ use crate::game::{GameEventSender, NodeGameStatusSnapshot, PlayerSlot};
 use crate::observer::ObserverPublisherHandle;
 use flo_w3gs::constants::LeaveReason;
 
+mod adaptive_step;
 mod broadcast;
+mod chat_policy;
 mod clock;
 mod delay;
 mod delay_equalizer;
+mod desync;
 mod dispatch;
+mod journal;
+mod pause;
 mod player;
+mod snapshot;
 pub mod stream;
 mod sync;
+mod vote_kick;
 
 #[derive(Debug)]
 pub struct GameHost {
@@ -28,6 +40,8 @@ pub struct GameHost {
 #[derive(Debug)]
 pub struct GameHostOptions {
   pub enabled_ping_equalizer: bool,
+  pub enabled_adaptive_step: bool,
+  pub observer_chat_policy: ObserverChatPolicy,
 }
 
 impl GameHost {
@@ -37,8 +51,9 @@ impl GameHost {
     slots: &[PlayerSlot],
     obs: ObserverPublisherHandle,
     event_sender: GameEventSender,
+    restore: Option<HostSnapshot>,
   ) -> Self {
-    let dispatcher = Dispatcher::new(game_id, opts, slots, obs, event_sender);
+    let dispatcher = Dispatcher::new(game_id, opts, slots, obs, event_sender, restore);
     Self {
       game_id,
       dispatcher,
@@ -76,6 +91,11 @@ impl GameHost {
     self.dispatcher.register_player_stream(stream).await
   }
 
+  /// The returned future does not borrow the host
+  pub fn snapshot(&self) -> impl Future<Output = Result<HostSnapshot>> + Send + 'static {
+    self.dispatcher.snapshot()
+  }
+
   pub async fn notify_player_shutdown(
     &mut self,
     player_id: i32,
@@ -0,0 +1,366 @@
+//! Limits how many times and for how long each player and each team can pause the game.
+//! The pausing player and their team are charged for the pause until someone resumes the game.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PauseLimits {
+  pub player_max_count: u32,
+  pub player_max_duration: Duration,
+  pub team_max_count: u32,
+  pub team_max_duration: Duration,
+}
+
+impl PauseLimits {
+  pub fn from_env() -> Self {
+    use crate::constants::*;
+    Self {
+      player_max_count: *GAME_PLAYER_MAX_PAUSES,
+      player_max_duration: *GAME_PLAYER_MAX_PAUSE_DURATION,
+      team_max_count: *GAME_TEAM_MAX_PAUSES,
+      team_max_duration: *GAME_TEAM_MAX_PAUSE_DURATION,
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct PauseBudget {
+  limits: PauseLimits,
+  player_teams: BTreeMap<i32, i32>,
+  players: BTreeMap<i32, Usage>,
+  teams: BTreeMap<i32, Usage>,
+  current: Option<CurrentPause>,
+}
+
+#[derive(Debug)]
+pub enum PauseResult {
+  /// The game has been paused, it should be resumed automatically after `timeout`
+  Paused {
+    timeout: Duration,
+    remaining_count: u32,
+  },
+  /// The game is already paused, nothing has been charged
+  AlreadyPaused,
+  Rejected(PauseRejectReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseRejectReason {
+  PlayerCountExhausted,
+  PlayerDurationExhausted,
+  TeamCountExhausted,
+  TeamDurationExhausted,
+}
+
+impl PauseRejectReason {
+  pub fn message(&self) -> &'static str {
+    match *self {
+      PauseRejectReason::PlayerCountExhausted => "You have no pauses left.",
+      PauseRejectReason::PlayerDurationExhausted => "You have no pause time left.",
+      PauseRejectReason::TeamCountExhausted => "Your team has no pauses left.",
+      PauseRejectReason::TeamDurationExhausted => "Your team has no pause time left.",
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct CurrentPause {
+  pub player_id: i32,
+  pub started_at: Instant,
+  /// Paused by a referee, not charged to any budget
+  pub forced: bool,
+}
+
+#[derive(Debug)]
+pub struct ResumedPause {
+  pub player_id: i32,
+  pub duration: Duration,
+  pub forced: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Usage {
+  count: u32,
+  duration: Duration,
+}
+
+/// State of a `PauseBudget` that can be persisted and restored,
+/// the current pause is stored as the time elapsed since it started
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PauseSnapshot {
+  /// Player id -> (count, duration)
+  pub players: BTreeMap<i32, (u32, Duration)>,
+  /// Team -> (count, duration)
+  pub teams: BTreeMap<i32, (u32, Duration)>,
+  pub current: Option<CurrentPauseSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrentPauseSnapshot {
+  pub player_id: i32,
+  pub elapsed: Duration,
+  pub forced: bool,
+}
+
+impl PauseBudget {
+  pub fn new<I>(limits: PauseLimits, player_teams: I) -> Self
+  where
+    I: IntoIterator<Item = (i32, i32)>,
+  {
+    Self {
+      limits,
+      player_teams: player_teams.into_iter().collect(),
+      players: BTreeMap::new(),
+      teams: BTreeMap::new(),
+      current: None,
+    }
+  }
+
+  pub fn current(&self) -> Option<&CurrentPause> {
+    self.current.as_ref()
+  }
+
+  /// Charges a pause to the player and the player's team, if both have budget left
+  pub fn pause(&mut self, player_id: i32, now: Instant) -> PauseResult {
+    if self.current.is_some() {
+      return PauseResult::AlreadyPaused;
+    }
+
+    let player = self.players.get(&player_id).cloned().unwrap_or_default();
+    let team = self.team_usage(player_id);
+
+    if player.count >= self.limits.player_max_count {
+      return PauseResult::Rejected(PauseRejectReason::PlayerCountExhausted);
+    }
+    if player.duration >= self.limits.player_max_duration {
+      return PauseResult::Rejected(PauseRejectReason::PlayerDurationExhausted);
+    }
+    if team.count >= self.limits.team_max_count {
+      return PauseResult::Rejected(PauseRejectReason::TeamCountExhausted);
+    }
+    if team.duration >= self.limits.team_max_duration {
+      return PauseResult::Rejected(PauseRejectReason::TeamDurationExhausted);
+    }
+
+    self.players.entry(player_id).or_default().count += 1;
+    if let Some(team) = self.player_teams.get(&player_id).cloned() {
+      self.teams.entry(team).or_default().count += 1;
+    }
+    self.current = Some(CurrentPause {
+      player_id,
+      started_at: now,
+      forced: false,
+    });
+
+    PauseResult::Paused {
+      timeout: self.remaining_duration(player_id),
+      remaining_count: self.remaining_count(player_id),
+    }
+  }
+
+  /// Pauses the game without charging anyone, returns false if the game is already paused
+  pub fn force_pause(&mut self, player_id: i32, now: Instant) -> bool {
+    if self.current.is_some() {
+      return false;
+    }
+    self.current = Some(CurrentPause {
+      player_id,
+      started_at: now,
+      forced: true,
+    });
+    true
+  }
+
+  /// Ends the current pause and charges its duration to the player who paused the game
+  pub fn resume(&mut self, now: Instant) -> Option<ResumedPause> {
+    let current = self.current.take()?;
+    let duration = now.saturating_duration_since(current.started_at);
+    let player_id = current.player_id;
+    if !current.forced {
+      self.players.entry(player_id).or_default().duration += duration;
+      if let Some(team) = self.player_teams.get(&player_id).cloned() {
+        self.teams.entry(team).or_default().duration += duration;
+      }
+    }
+    Some(ResumedPause {
+      player_id,
+      duration,
+      forced: current.forced,
+    })
+  }
+
+  pub fn remaining_count(&self, player_id: i32) -> u32 {
+    let player = self
+      .players
+      .get(&player_id)
+      .map(|v| v.count)
+      .unwrap_or_default();
+    let team = self.team_usage(player_id).count;
+    std::cmp::min(
+      self.limits.player_max_count.saturating_sub(player),
+      self.limits.team_max_count.saturating_sub(team),
+    )
+  }
+
+  pub fn remaining_duration(&self, player_id: i32) -> Duration {
+    let player = self
+      .players
+      .get(&player_id)
+      .map(|v| v.duration)
+      .unwrap_or_default();
+    let team = self.team_usage(player_id).duration;
+    std::cmp::min(
+      self.limits.player_max_duration.saturating_sub(player),
+      self.limits.team_max_duration.saturating_sub(team),
+    )
+  }
+
+  pub fn snapshot(&self, now: Instant) -> PauseSnapshot {
+    fn pack(map: &BTreeMap<i32, Usage>) -> BTreeMap<i32, (u32, Duration)> {
+      map
+        .iter()
+        .map(|(id, usage)| (*id, (usage.count, usage.duration)))
+        .collect()
+    }
+    PauseSnapshot {
+      players: pack(&self.players),
+      teams: pack(&self.teams),
+      current: self.current.as_ref().map(|current| CurrentPauseSnapshot {
+        player_id: current.player_id,
+        elapsed: now.saturating_duration_since(current.started_at),
+        forced: current.forced,
+      }),
+    }
+  }
+
+  /// Restores the usage and the current pause,
+  /// returns the remaining time before the current pause should be resumed automatically
+  pub fn restore(&mut self, snapshot: PauseSnapshot, now: Instant) -> Option<Duration> {
+    fn unpack(map: BTreeMap<i32, (u32, Duration)>) -> BTreeMap<i32, Usage> {
+      map
+        .into_iter()
+        .map(|(id, (count, duration))| (id, Usage { count, duration }))
+        .collect()
+    }
+    self.players = unpack(snapshot.players);
+    self.teams = unpack(snapshot.teams);
+    self.current = snapshot.current.map(|current| CurrentPause {
+      player_id: current.player_id,
+      started_at: now.checked_sub(current.elapsed).unwrap_or(now),
+      forced: current.forced,
+    });
+    let current = self.current.as_ref().filter(|current| !current.forced)?;
+    Some(
+      self
+        .remaining_duration(current.player_id)
+        .saturating_sub(now.saturating_duration_since(current.started_at)),
+    )
+  }
+
+  fn team_usage(&self, player_id: i32) -> Usage {
+    self
+      .player_teams
+      .get(&player_id)
+      .and_then(|team| self.teams.get(team))
+      .cloned()
+      .unwrap_or_default()
+  }
+}
+
+#[test]
+fn test_pause_budget() {
+  let limits = PauseLimits {
+    player_max_count: 2,
+    player_max_duration: Duration::from_secs(60),
+    team_max_count: 3,
+    team_max_duration: Duration::from_secs(100),
+  };
+  let mut b = PauseBudget::new(limits, vec![(1, 0), (2, 0), (3, 1)]);
+  let t = Instant::now();
+
+  match b.pause(1, t) {
+    PauseResult::Paused {
+      timeout,
+      remaining_count,
+    } => {
+      assert_eq!(timeout, Duration::from_secs(60));
+      assert_eq!(remaining_count, 1);
+    }
+    other => panic!("unexpected: {:?}", other),
+  }
+  assert!(matches!(b.pause(2, t), PauseResult::AlreadyPaused));
+
+  let resumed = b.resume(t + Duration::from_secs(50)).unwrap();
+  assert_eq!(resumed.player_id, 1);
+  assert_eq!(resumed.duration, Duration::from_secs(50));
+  assert!(b.resume(t + Duration::from_secs(50)).is_none());
+
+  // player 1 has 10s left
+  match b.pause(1, t) {
+    PauseResult::Paused { timeout, .. } => assert_eq!(timeout, Duration::from_secs(10)),
+    other => panic!("unexpected: {:?}", other),
+  }
+  b.resume(t + Duration::from_secs(10));
+  assert!(matches!(
+    b.pause(1, t),
+    PauseResult::Rejected(PauseRejectReason::PlayerCountExhausted)
+  ));
+
+  // player 2 is limited by the team budget
+  match b.pause(2, t) {
+    PauseResult::Paused {
+      timeout,
+      remaining_count,
+    } => {
+      assert_eq!(timeout, Duration::from_secs(40));
+      assert_eq!(remaining_count, 0);
+    }
+    other => panic!("unexpected: {:?}", other),
+  }
+  b.resume(t + Duration::from_secs(40));
+  assert!(matches!(
+    b.pause(2, t),
+    PauseResult::Rejected(PauseRejectReason::TeamCountExhausted)
+  ));
+
+  // the other team is not affected
+  assert_eq!(b.remaining_count(3), 2);
+  assert_eq!(b.remaining_duration(3), Duration::from_secs(60));
+
+  // forced pauses are not charged
+  assert!(b.force_pause(3, t));
+  assert!(!b.force_pause(3, t));
+  assert!(matches!(b.pause(3, t), PauseResult::AlreadyPaused));
+  let resumed = b.resume(t + Duration::from_secs(30)).unwrap();
+  assert!(resumed.forced);
+  assert_eq!(b.remaining_count(3), 2);
+  assert_eq!(b.remaining_duration(3), Duration::from_secs(60));
+}
+
+#[test]
+fn test_pause_budget_snapshot() {
+  let limits = PauseLimits {
+    player_max_count: 2,
+    player_max_duration: Duration::from_secs(60),
+    team_max_count: 3,
+    team_max_duration: Duration::from_secs(100),
+  };
+  let t = Instant::now();
+  let mut b = PauseBudget::new(limits, vec![(1, 0), (2, 0)]);
+  b.pause(1, t);
+  b.resume(t + Duration::from_secs(20));
+  b.pause(2, t);
+
+  let snapshot = b.snapshot(t + Duration::from_secs(10));
+  let mut restored = PauseBudget::new(limits, vec![(1, 0), (2, 0)]);
+  let timeout = restored.restore(snapshot.clone(), t + Duration::from_secs(10));
+
+  // player 2 has 60s, 10s of it already spent by the current pause
+  assert_eq!(timeout, Some(Duration::from_secs(50)));
+  assert_eq!(restored.remaining_count(1), 1);
+  assert_eq!(restored.snapshot(t + Duration::from_secs(10)), snapshot);
+  let resumed = restored.resume(t + Duration::from_secs(30)).unwrap();
+  assert_eq!(resumed.player_id, 2);
+  assert_eq!(resumed.duration, Duration::from_secs(30));
+}
@@ -1,8 +1,9 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::game::host::journal::GameJournal;
 use crate::game::host::stream::PlayerStreamHandle;
 use crate::game::{PlayerBanType, PlayerSlot};
 use flo_net::packet::Frame;
-use flo_net::w3gs::{W3GSAckQueue, W3GSFrameExt, W3GSMetadata, W3GSPacket};
+use flo_net::w3gs::{W3GSAckQueue, W3GSAckQueueSnapshot, W3GSFrameExt, W3GSMetadata, W3GSPacket};
 use flo_w3gs::protocol::chat::ChatFromHost;
 use std::collections::BTreeSet;
 use std::time::{Duration, Instant};
@@ -10,6 +11,7 @@ use tokio::sync::mpsc::error::TrySendError;
 
 #[derive(Debug)]
 pub struct PlayerDispatchInfo {
+  player_id: i32,
   player_name: String,
   last_stream_id: Option<u64>,
   tx: Option<PlayerStreamHandle>,
@@ -24,11 +26,14 @@ pub struct PlayerDispatchInfo {
   rtt_stats: PlayerRTTStats,
   last_rtt_stats: Option<PlayerRTTStats>,
   is_observer: bool,
+  team: i32,
+  journal: GameJournal,
 }
 
 impl PlayerDispatchInfo {
-  pub fn new(slot: &PlayerSlot) -> Self {
+  pub fn new(slot: &PlayerSlot, journal: GameJournal) -> Self {
     Self {
+      player_id: slot.player.player_id,
       player_name: slot.player.name.clone(),
       last_stream_id: None,
       tx: None,
@@ -43,6 +48,8 @@ impl PlayerDispatchInfo {
       rtt_stats: PlayerRTTStats::default(),
       last_rtt_stats: None,
       is_observer: slot.settings.team == 24,
+      team: slot.settings.team,
+      journal,
     }
   }
 
@@ -58,18 +65,53 @@ impl PlayerDispatchInfo {
     self.is_observer
   }
 
+  pub fn team(&self) -> i32 {
+    self.team
+  }
+
   pub fn ack_queue(&self) -> &W3GSAckQueue {
     &self.w3gs_ack_q
   }
 
-  pub fn update_ack(&mut self, meta: W3GSMetadata) -> bool {
+  /// Restores the ack queue of a session that was running before a node restart,
+  /// the first connection will be handled as a reconnect and the pending frames will be resent
+  pub fn restore_ack_queue(&mut self, snapshot: W3GSAckQueueSnapshot) {
+    self.w3gs_ack_q = W3GSAckQueue::from_snapshot(snapshot);
+    self.last_stream_id.get_or_insert(0);
+    self.set_last_disconnect();
+  }
+
+  /// Re-applies a frame sent after the restored snapshot, read from the journal
+  pub fn replay_send(&mut self, meta: W3GSMetadata, pkt: W3GSPacket) {
+    self.w3gs_ack_q.replay_send(meta, pkt);
+  }
+
+  /// Re-applies a frame received after the restored snapshot, read from the journal
+  pub fn replay_receive(&mut self, sid: u32, ack_sid: Option<u32>) {
+    if self.w3gs_ack_q.ack_received(sid) {
+      if let Some(ack_sid) = ack_sid {
+        self.w3gs_ack_q.ack_sent(ack_sid);
+      }
+    }
+  }
+
+  /// Returns false if the packet is a resend and should be discarded
+  pub fn update_ack(&mut self, meta: W3GSMetadata) -> Result<bool> {
+    if let Some(ack_sid) = meta.ack_sid() {
+      // the client has received packets neither the restored snapshot nor the journal know about,
+      // which only happens if the journal could not be written
+      if self.w3gs_ack_q.is_unsent(ack_sid) {
+        return Err(Error::SnapshotBehind);
+      }
+    }
     if !self.w3gs_ack_q.ack_received(meta.sid()) {
-      return false;
+      return Ok(false);
     }
+    self.journal.receive(self.player_id, &meta);
     if let Some(ack_sid) = meta.ack_sid() {
       self.w3gs_ack_q.ack_sent(ack_sid);
     }
-    true
+    Ok(true)
   }
 
   pub fn register_sender(&mut self, tx: PlayerStreamHandle) {
@@ -118,6 +160,8 @@ impl PlayerDispatchInfo {
     let sid = self.w3gs_ack_q.gen_next_send_sid();
     let ack_sid = self.w3gs_ack_q.take_ack_received();
     let meta = W3GSMetadata::new(pkt.type_id(), sid, ack_sid.clone());
+    // written before the frame is sent, so a restored game knows about it
+    self.journal.send(self.player_id, &meta, &pkt);
     self.w3gs_ack_q.push_send(meta.clone(), pkt);
     meta
   }
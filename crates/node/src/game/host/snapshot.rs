@@ -0,0 +1,273 @@
+//! State of a running game host that is persisted by the node,
+//! so the game can be resumed after the node restarts.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use flo_net::proto::flo_node as proto;
+use flo_net::w3gs::{W3GSAckQueueSnapshot, W3GSHeader, W3GSMetadata, W3GSPacket, W3GSPacketTypeId};
+
+use super::journal::JournalEntry;
+use super::pause::{CurrentPauseSnapshot, PauseSnapshot};
+use super::sync::{DesyncTick, SyncSnapshot};
+use super::vote_kick::VoteKickSnapshot;
+
+#[derive(Debug, Clone)]
+pub struct HostSnapshot {
+  pub sync: SyncSnapshot,
+  /// Ack queues of the players that have not left the game
+  pub players: BTreeMap<i32, W3GSAckQueueSnapshot>,
+  /// Delays set by the players or by a referee
+  pub delays: BTreeMap<i32, Duration>,
+  pub pause: PauseSnapshot,
+  pub vote_kick: Option<VoteKickSnapshot>,
+  pub chat_muted: bool,
+  /// First journal segment written after the snapshot
+  pub journal_seq: u32,
+  /// Journal entries written after the snapshot, read from the journal files on restore
+  pub journal: Vec<JournalEntry>,
+}
+
+impl HostSnapshot {
+  pub fn pack(&self) -> proto::GameHostSnapshot {
+    proto::GameHostSnapshot {
+      tick: self.sync.tick,
+      time: self.sync.time,
+      players: self
+        .players
+        .iter()
+        .map(|(player_id, q)| {
+          let (tick, time) = self
+            .sync
+            .players
+            .get(player_id)
+            .cloned()
+            .unwrap_or_default();
+          proto::GameHostPlayerSnapshot {
+            player_id: *player_id,
+            tick,
+            time,
+            tx_next_sid: q.tx_next_sid,
+            tx_ack_sid: q.tx_ack_sid,
+            rx_ack_sid: q.rx_ack_sid,
+            last_rx_ack_sid: q.last_rx_ack_sid,
+            delay_ms: self
+              .delays
+              .get(player_id)
+              .map(|delay| delay.as_millis() as u32),
+            pending_ack_packets: q
+              .tx_pending_ack_q
+              .iter()
+              .map(|(meta, packet)| pack_packet(meta, packet))
+              .collect(),
+          }
+        })
+        .collect(),
+      pending_ticks: self
+        .sync
+        .pending_ticks
+        .iter()
+        .map(|item| proto::GamePendingTick {
+          tick: item.tick,
+          time: item.time,
+          checksums: item.checksums.clone().into_iter().collect(),
+        })
+        .collect(),
+      pause: Some(pack_pause(&self.pause)),
+      vote_kick: self
+        .vote_kick
+        .as_ref()
+        .map(|vote| proto::GameVoteKickSnapshot {
+          target_player_id: vote.target,
+          elapsed_ms: vote.elapsed.as_millis() as u32,
+          voter_player_ids: vote.voters.iter().cloned().collect(),
+        }),
+      chat_muted: self.chat_muted,
+      journal_seq: self.journal_seq,
+    }
+  }
+
+  pub fn unpack(value: proto::GameHostSnapshot) -> Self {
+    let mut sync = SyncSnapshot {
+      tick: value.tick,
+      time: value.time,
+      players: BTreeMap::new(),
+      pending_ticks: value
+        .pending_ticks
+        .into_iter()
+        .map(|item| DesyncTick {
+          tick: item.tick,
+          time: item.time,
+          checksums: item.checksums.into_iter().collect(),
+        })
+        .collect(),
+    };
+    let mut players = BTreeMap::new();
+    let mut delays = BTreeMap::new();
+    for player in value.players {
+      sync
+        .players
+        .insert(player.player_id, (player.tick, player.time));
+      if let Some(ms) = player.delay_ms {
+        delays.insert(player.player_id, Duration::from_millis(ms as u64));
+      }
+      players.insert(
+        player.player_id,
+        W3GSAckQueueSnapshot {
+          tx_next_sid: player.tx_next_sid,
+          tx_ack_sid: player.tx_ack_sid,
+          tx_pending_ack_q: player
+            .pending_ack_packets
+            .into_iter()
+            .map(unpack_packet)
+            .collect(),
+          rx_ack_sid: player.rx_ack_sid,
+          last_rx_ack_sid: player.last_rx_ack_sid,
+        },
+      );
+    }
+    Self {
+      sync,
+      players,
+      delays,
+      pause: value.pause.map(unpack_pause).unwrap_or_default(),
+      vote_kick: value.vote_kick.map(|vote| VoteKickSnapshot {
+        target: vote.target_player_id,
+        elapsed: Duration::from_millis(vote.elapsed_ms as u64),
+        voters: vote.voter_player_ids.into_iter().collect(),
+      }),
+      chat_muted: value.chat_muted,
+      journal_seq: value.journal_seq,
+      journal: vec![],
+    }
+  }
+}
+
+pub(super) fn pack_packet(meta: &W3GSMetadata, packet: &W3GSPacket) -> proto::PendingAckPacket {
+  proto::PendingAckPacket {
+    type_id: u8::from(meta.type_id()) as u32,
+    sid: meta.sid(),
+    ack_sid: meta.ack_sid(),
+    payload: packet.payload.to_vec(),
+  }
+}
+
+pub(super) fn unpack_packet(item: proto::PendingAckPacket) -> (W3GSMetadata, W3GSPacket) {
+  let type_id = W3GSPacketTypeId::from(item.type_id as u8);
+  (
+    W3GSMetadata::new(type_id, item.sid, item.ack_sid),
+    W3GSPacket {
+      header: W3GSHeader::new(type_id, (item.payload.len() + 4) as u16),
+      payload: item.payload.into(),
+    },
+  )
+}
+
+fn pack_pause(pause: &PauseSnapshot) -> proto::GamePauseSnapshot {
+  fn pack_usage(map: &BTreeMap<i32, (u32, Duration)>) -> Vec<proto::GamePauseUsage> {
+    map
+      .iter()
+      .map(|(id, (count, duration))| proto::GamePauseUsage {
+        id: *id,
+        count: *count,
+        duration_ms: duration.as_millis() as u32,
+      })
+      .collect()
+  }
+  proto::GamePauseSnapshot {
+    players: pack_usage(&pause.players),
+    teams: pack_usage(&pause.teams),
+    current: pause
+      .current
+      .as_ref()
+      .map(|current| proto::GameCurrentPause {
+        player_id: current.player_id,
+        elapsed_ms: current.elapsed.as_millis() as u32,
+        forced: current.forced,
+      }),
+  }
+}
+
+fn unpack_pause(value: proto::GamePauseSnapshot) -> PauseSnapshot {
+  fn unpack_usage(items: Vec<proto::GamePauseUsage>) -> BTreeMap<i32, (u32, Duration)> {
+    items
+      .into_iter()
+      .map(|item| {
+        (
+          item.id,
+          (item.count, Duration::from_millis(item.duration_ms as u64)),
+        )
+      })
+      .collect()
+  }
+  PauseSnapshot {
+    players: unpack_usage(value.players),
+    teams: unpack_usage(value.teams),
+    current: value.current.map(|current| CurrentPauseSnapshot {
+      player_id: current.player_id,
+      elapsed: Duration::from_millis(current.elapsed_ms as u64),
+      forced: current.forced,
+    }),
+  }
+}
+
+#[test]
+fn test_host_snapshot() {
+  use flo_net::w3gs::W3GSAckQueue;
+  use flo_w3gs::protocol::leave::LeaveAck;
+
+  let mut q = W3GSAckQueue::new();
+  let sid = q.gen_next_send_sid();
+  let packet = W3GSPacket::simple(LeaveAck).unwrap();
+  q.push_send(
+    W3GSMetadata::new(packet.type_id(), sid, Some(7)),
+    packet.clone(),
+  );
+
+  let snapshot = HostSnapshot {
+    sync: SyncSnapshot {
+      tick: 10,
+      time: 300,
+      players: vec![(1, (9, 270))].into_iter().collect(),
+      pending_ticks: vec![DesyncTick {
+        tick: 10,
+        time: 300,
+        checksums: vec![(1, 42)].into_iter().collect(),
+      }],
+    },
+    players: vec![(1, q.snapshot())].into_iter().collect(),
+    delays: vec![(1, Duration::from_millis(50))].into_iter().collect(),
+    pause: PauseSnapshot {
+      players: vec![(1, (1, Duration::from_secs(5)))].into_iter().collect(),
+      teams: vec![(0, (1, Duration::from_secs(5)))].into_iter().collect(),
+      current: Some(CurrentPauseSnapshot {
+        player_id: 1,
+        elapsed: Duration::from_secs(3),
+        forced: true,
+      }),
+    },
+    vote_kick: Some(VoteKickSnapshot {
+      target: 2,
+      elapsed: Duration::from_secs(4),
+      voters: vec![1].into_iter().collect(),
+    }),
+    chat_muted: true,
+    journal_seq: 3,
+    journal: vec![],
+  };
+
+  let restored = HostSnapshot::unpack(snapshot.pack());
+  assert_eq!(restored.sync.tick, 10);
+  assert_eq!(restored.sync.players.get(&1), Some(&(9, 270)));
+  assert_eq!(restored.sync.pending_ticks[0].checksums.get(&1), Some(&42));
+  let (meta, restored_packet) = &restored.players[&1].tx_pending_ack_q[0];
+  assert_eq!(meta.sid(), sid);
+  assert_eq!(meta.ack_sid(), Some(7));
+  assert_eq!(restored_packet.type_id(), packet.type_id());
+  assert_eq!(restored_packet.payload, packet.payload);
+  assert_eq!(restored.delays, snapshot.delays);
+  assert_eq!(restored.pause, snapshot.pause);
+  assert_eq!(restored.vote_kick, snapshot.vote_kick);
+  assert!(restored.chat_muted);
+  assert_eq!(restored.journal_seq, 3);
+}
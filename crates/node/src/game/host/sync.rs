@@ -11,6 +11,7 @@ pub struct SyncMap {
   pending_tick: BTreeMap<u32, usize>,
   pending_slab: Slab<Pending>,
   desync_buf: Vec<PlayerDesync>,
+  desync_ticks: Vec<DesyncTick>,
 }
 
 impl SyncMap {
@@ -25,6 +26,7 @@ impl SyncMap {
       pending_tick: BTreeMap::new(),
       pending_slab: Slab::new(),
       desync_buf: vec![],
+      desync_ticks: vec![],
     }
   }
 
@@ -41,14 +43,20 @@ impl SyncMap {
     if let Some(v) = timeout_players {
       ClockResult::Lag(v)
     } else {
-      self.tick += 1;
-      self.time += time_increment as u32;
-      let id = self.pending_slab.insert(Pending::new(self.tick, self.time));
-      self.pending_tick.insert(self.tick, id);
+      self.replay_clock(time_increment);
       ClockResult::Tick
     }
   }
 
+  /// Advances the clock without checking for lagging players,
+  /// used to replay the ticks journaled after a snapshot
+  pub fn replay_clock(&mut self, time_increment: u16) {
+    self.tick += 1;
+    self.time += time_increment as u32;
+    let id = self.pending_slab.insert(Pending::new(self.tick, self.time));
+    self.pending_tick.insert(self.tick, id);
+  }
+
   fn check_timeout(&mut self, time_increment: u16) -> Option<Vec<PlayerTimeout>> {
     for id in self.pending_tick.values() {
       let item = &mut self.pending_slab[*id];
@@ -81,13 +89,16 @@ impl SyncMap {
   pub fn remove_player(&mut self, player_id: i32) -> Option<Vec<PlayerDesync>> {
     self.players.remove(&player_id);
     self.desync_buf.clear();
+    self.desync_ticks.clear();
     let mut finished = None;
     for (tick, id) in &self.pending_tick {
       let item = &mut self.pending_slab[*id];
       item.checksums.remove(&player_id);
       if let Some(token) = item.should_check_desync(self.players.len()) {
         finished.get_or_insert_with(|| vec![]).push((*tick, *id));
-        item.check_desync(token, &mut self.desync_buf);
+        if item.check_desync(token, &mut self.desync_buf) {
+          self.desync_ticks.push(item.to_desync_tick());
+        }
       }
     }
     if let Some(finished) = finished {
@@ -128,7 +139,10 @@ impl SyncMap {
     let rtt = Instant::now().saturating_duration_since(pending.t);
     if let Some(token) = pending.should_check_desync(self.players.len()) {
       self.desync_buf.clear();
-      pending.check_desync(token, &mut self.desync_buf);
+      self.desync_ticks.clear();
+      if pending.check_desync(token, &mut self.desync_buf) {
+        self.desync_ticks.push(pending.to_desync_tick());
+      }
       self.pending_tick.remove(&tick);
       let pending = self.pending_slab.remove(id);
       if self.desync_buf.is_empty() {
@@ -176,6 +190,49 @@ impl SyncMap {
     "debug_pending\n".to_string() + &values.join("\n")
   }
 
+  /// Checksums of all players at the ticks where the last `ack` or `remove_player` call detected a desync
+  pub fn take_desync_ticks(&mut self) -> Vec<DesyncTick> {
+    std::mem::replace(&mut self.desync_ticks, vec![])
+  }
+
+  pub fn snapshot(&self) -> SyncSnapshot {
+    SyncSnapshot {
+      tick: self.tick,
+      time: self.time,
+      players: self
+        .players
+        .iter()
+        .map(|(player_id, state)| (*player_id, (state.tick, state.time)))
+        .collect(),
+      pending_ticks: self
+        .pending_tick
+        .values()
+        .map(|id| self.pending_slab[*id].to_desync_tick())
+        .collect(),
+    }
+  }
+
+  /// Restores the clock and the acked ticks of the players in the snapshot,
+  /// players not in the snapshot are removed
+  pub fn restore(&mut self, snapshot: SyncSnapshot) {
+    self.tick = snapshot.tick;
+    self.time = snapshot.time;
+    self.players = snapshot
+      .players
+      .into_iter()
+      .filter(|(player_id, _)| self.players.contains_key(player_id))
+      .map(|(player_id, (tick, time))| (player_id, PlayerState { tick, time }))
+      .collect();
+    self.pending_tick.clear();
+    self.pending_slab.clear();
+    for item in snapshot.pending_ticks {
+      let mut pending = Pending::new(item.tick, item.time);
+      pending.checksums = item.checksums;
+      let id = self.pending_slab.insert(pending);
+      self.pending_tick.insert(item.tick, id);
+    }
+  }
+
   fn take_desync(&mut self) -> Option<Vec<PlayerDesync>> {
     if self.desync_buf.is_empty() {
       return None;
@@ -235,7 +292,15 @@ impl Pending {
     }
   }
 
-  fn check_desync(&self, _token: CheckDesyncToken, out: &mut Vec<PlayerDesync>) {
+  fn to_desync_tick(&self) -> DesyncTick {
+    DesyncTick {
+      tick: self.tick,
+      time: self.time,
+      checksums: self.checksums.clone(),
+    }
+  }
+
+  fn check_desync(&self, _token: CheckDesyncToken, out: &mut Vec<PlayerDesync>) -> bool {
     let mut last_checksum = None;
     let mut desync_detected = false;
     for checksum in self.checksums.values() {
@@ -320,6 +385,7 @@ impl Pending {
         }
       }
     }
+    desync_detected
   }
 }
 
@@ -351,6 +417,22 @@ pub struct PlayerDesync {
   pub checksum: u32,
 }
 
+#[derive(Debug, Clone)]
+pub struct SyncSnapshot {
+  pub tick: u32,
+  pub time: u32,
+  /// player id => (acked tick, acked time)
+  pub players: BTreeMap<i32, (u32, u32)>,
+  pub pending_ticks: Vec<DesyncTick>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DesyncTick {
+  pub tick: u32,
+  pub time: u32,
+  pub checksums: BTreeMap<i32, u32>,
+}
+
 #[test]
 fn test_sync_map() {
   use rand::seq::SliceRandom;
@@ -457,3 +539,47 @@ fn test_sync_map() {
   assert!(map.pending_tick.is_empty());
   dbg!(&map.pending_slab.capacity());
 }
+
+#[test]
+fn test_desync_ticks() {
+  let mut map = SyncMap::new(vec![1, 2, 3]);
+  assert!(matches!(map.clock(10), ClockResult::Tick));
+  assert!(map.ack(1, 100).unwrap().desync.is_none());
+  assert!(map.ack(2, 100).unwrap().desync.is_none());
+  let res = map.ack(3, 200).unwrap();
+  assert_eq!(res.agreed_checksum, Some(100));
+  assert_eq!(res.desync.unwrap().len(), 1);
+
+  let ticks = map.take_desync_ticks();
+  assert_eq!(ticks.len(), 1);
+  assert_eq!(ticks[0].tick, 1);
+  assert_eq!(ticks[0].time, 10);
+  assert_eq!(ticks[0].checksums.get(&3), Some(&200));
+  assert!(map.take_desync_ticks().is_empty());
+}
+
+#[test]
+fn test_sync_snapshot() {
+  let mut map = SyncMap::new(vec![1, 2]);
+  for _ in 0..3 {
+    assert!(matches!(map.clock(10), ClockResult::Tick));
+  }
+  map.ack(1, 100).unwrap();
+  map.ack(2, 100).unwrap();
+  map.ack(1, 200).unwrap();
+
+  let snapshot = map.snapshot();
+  assert_eq!(snapshot.pending_ticks.len(), 2);
+
+  let mut restored = SyncMap::new(vec![1, 2]);
+  restored.restore(snapshot);
+  assert_eq!(restored.tick(), 3);
+  assert_eq!(restored.time(), 30);
+  assert_eq!(restored.player_pending_ticks(1), Some(1));
+  assert_eq!(restored.player_pending_ticks(2), Some(2));
+  let res = restored.ack(2, 200).unwrap();
+  assert_eq!(res.player_tick, 2);
+  assert_eq!(res.agreed_checksum, Some(200));
+  assert!(matches!(restored.clock(10), ClockResult::Tick));
+  assert_eq!(restored.tick(), 4);
+}
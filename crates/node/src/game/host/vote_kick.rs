@@ -0,0 +1,312 @@
+//! Lets players remove another player with `!votekick <slot>`.
+//! Only the teammates of the target can vote, so a team can't remove its opponents.
+//! A vote stays open for a limited window and passes once enough of them have voted for it.
+
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoteKickRule {
+  /// More than half of the voters
+  Majority,
+  /// At least two thirds of the voters
+  TwoThirds,
+  /// Every voter
+  Unanimous,
+}
+
+impl VoteKickRule {
+  pub fn required(&self, voters: usize) -> usize {
+    match *self {
+      VoteKickRule::Majority => voters / 2 + 1,
+      VoteKickRule::TwoThirds => (voters * 2 + 2) / 3,
+      VoteKickRule::Unanimous => voters,
+    }
+  }
+}
+
+impl FromStr for VoteKickRule {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "majority" => Ok(VoteKickRule::Majority),
+      "two_thirds" => Ok(VoteKickRule::TwoThirds),
+      "unanimous" => Ok(VoteKickRule::Unanimous),
+      other => Err(other.to_string()),
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct VoteKick {
+  rule: VoteKickRule,
+  window: Duration,
+  min_voters: usize,
+  current: Option<Vote>,
+}
+
+#[derive(Debug)]
+struct Vote {
+  target: i32,
+  started_at: Instant,
+  voters: BTreeSet<i32>,
+}
+
+/// The vote in progress, persisted with the game
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoteKickSnapshot {
+  pub target: i32,
+  pub elapsed: Duration,
+  pub voters: BTreeSet<i32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum VoteKickResult {
+  Started { required: usize },
+  Voted { votes: usize, required: usize },
+  AlreadyVoted,
+  Passed,
+  Rejected(VoteKickRejectReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoteKickRejectReason {
+  SelfTarget,
+  NotTeammate,
+  AnotherVoteInProgress,
+  NotEnoughVoters,
+}
+
+impl VoteKickRejectReason {
+  pub fn message(&self) -> &'static str {
+    match *self {
+      VoteKickRejectReason::SelfTarget => "You cannot vote to kick yourself.",
+      VoteKickRejectReason::NotTeammate => "Only teammates can vote to kick a player.",
+      VoteKickRejectReason::AnotherVoteInProgress => "Another kick vote is in progress.",
+      VoteKickRejectReason::NotEnoughVoters => "Not enough players to start a kick vote.",
+    }
+  }
+}
+
+impl VoteKick {
+  pub fn new(rule: VoteKickRule, window: Duration, min_voters: usize) -> Self {
+    Self {
+      rule,
+      window,
+      min_voters,
+      current: None,
+    }
+  }
+
+  pub fn from_env() -> Self {
+    use crate::constants::*;
+    let rule = GAME_VOTE_KICK_RULE.parse().unwrap_or_else(|value| {
+      tracing::warn!("invalid vote kick rule: {}", value);
+      VoteKickRule::Majority
+    });
+    Self::new(rule, *GAME_VOTE_KICK_WINDOW, *GAME_VOTE_KICK_MIN_VOTERS)
+  }
+
+  /// Records a vote from `voter` to kick `target`.
+  /// `voters` are the players allowed to vote, the teammates of the target
+  pub fn vote(
+    &mut self,
+    voter: i32,
+    target: i32,
+    voters: &BTreeSet<i32>,
+    now: Instant,
+  ) -> VoteKickResult {
+    if voter == target {
+      return VoteKickResult::Rejected(VoteKickRejectReason::SelfTarget);
+    }
+
+    if !voters.contains(&voter) {
+      return VoteKickResult::Rejected(VoteKickRejectReason::NotTeammate);
+    }
+
+    if let Some(vote) = self.current.as_ref() {
+      if now.saturating_duration_since(vote.started_at) > self.window {
+        self.current.take();
+      }
+    }
+
+    let required = self.rule.required(voters.len());
+    let vote = match self.current.as_mut() {
+      Some(vote) => {
+        if vote.target != target {
+          return VoteKickResult::Rejected(VoteKickRejectReason::AnotherVoteInProgress);
+        }
+        if !vote.voters.insert(voter) {
+          return VoteKickResult::AlreadyVoted;
+        }
+        vote
+      }
+      None => {
+        if voters.len() < self.min_voters {
+          return VoteKickResult::Rejected(VoteKickRejectReason::NotEnoughVoters);
+        }
+        self.current.get_or_insert(Vote {
+          target,
+          started_at: now,
+          voters: std::iter::once(voter).collect(),
+        })
+      }
+    };
+
+    let votes = vote.voters.len();
+    if votes >= required {
+      self.current.take();
+      VoteKickResult::Passed
+    } else if votes == 1 {
+      VoteKickResult::Started { required }
+    } else {
+      VoteKickResult::Voted { votes, required }
+    }
+  }
+
+  /// Cancels the vote if the target left, or discards the vote of a leaving player
+  pub fn remove_player(&mut self, player_id: i32) {
+    if let Some(vote) = self.current.as_mut() {
+      if vote.target == player_id {
+        self.current.take();
+      } else {
+        vote.voters.remove(&player_id);
+      }
+    }
+  }
+
+  pub fn window(&self) -> Duration {
+    self.window
+  }
+
+  pub fn snapshot(&self, now: Instant) -> Option<VoteKickSnapshot> {
+    self.current.as_ref().map(|vote| VoteKickSnapshot {
+      target: vote.target,
+      elapsed: now.saturating_duration_since(vote.started_at),
+      voters: vote.voters.clone(),
+    })
+  }
+
+  pub fn restore(&mut self, snapshot: Option<VoteKickSnapshot>, now: Instant) {
+    self.current = snapshot.map(|vote| Vote {
+      target: vote.target,
+      started_at: now.checked_sub(vote.elapsed).unwrap_or(now),
+      voters: vote.voters,
+    });
+  }
+}
+
+#[cfg(test)]
+fn team(ids: &[i32]) -> BTreeSet<i32> {
+  ids.iter().cloned().collect()
+}
+
+#[test]
+fn test_vote_kick() {
+  assert_eq!(VoteKickRule::Majority.required(3), 2);
+  assert_eq!(VoteKickRule::Majority.required(4), 3);
+  assert_eq!(VoteKickRule::TwoThirds.required(3), 2);
+  assert_eq!(VoteKickRule::TwoThirds.required(5), 4);
+  assert_eq!(VoteKickRule::Unanimous.required(5), 5);
+
+  let window = Duration::from_secs(30);
+  let mut v = VoteKick::new(VoteKickRule::Majority, window, 2);
+  let t = Instant::now();
+
+  assert_eq!(
+    v.vote(1, 1, &team(&[1, 3, 4]), t),
+    VoteKickResult::Rejected(VoteKickRejectReason::SelfTarget)
+  );
+  assert_eq!(
+    v.vote(1, 2, &team(&[1]), t),
+    VoteKickResult::Rejected(VoteKickRejectReason::NotEnoughVoters)
+  );
+  assert_eq!(
+    v.vote(1, 2, &team(&[1, 3, 4]), t),
+    VoteKickResult::Started { required: 2 }
+  );
+  assert_eq!(
+    v.vote(1, 2, &team(&[1, 3, 4]), t),
+    VoteKickResult::AlreadyVoted
+  );
+  assert_eq!(
+    v.vote(3, 4, &team(&[1, 2, 3]), t),
+    VoteKickResult::Rejected(VoteKickRejectReason::AnotherVoteInProgress)
+  );
+  assert_eq!(v.vote(3, 2, &team(&[1, 3, 4]), t), VoteKickResult::Passed);
+
+  // expired votes are discarded
+  let voters = team(&[1, 3, 4, 5]);
+  assert_eq!(
+    v.vote(1, 2, &voters, t),
+    VoteKickResult::Started { required: 3 }
+  );
+  assert_eq!(
+    v.vote(3, 2, &voters, t),
+    VoteKickResult::Voted {
+      votes: 2,
+      required: 3
+    }
+  );
+  assert_eq!(
+    v.vote(4, 2, &voters, t + window + Duration::from_secs(1)),
+    VoteKickResult::Started { required: 3 }
+  );
+
+  // the vote is cancelled if the target leaves
+  v.remove_player(2);
+  assert_eq!(
+    v.vote(1, 4, &team(&[1, 3, 5, 6]), t),
+    VoteKickResult::Started { required: 3 }
+  );
+}
+
+#[test]
+fn test_vote_kick_opponents() {
+  // 3v3, players 1-3 against 4-6
+  let window = Duration::from_secs(30);
+  let mut v = VoteKick::new(VoteKickRule::Majority, window, 2);
+  let t = Instant::now();
+
+  // the opposing team alone can't start or pass a vote
+  let teammates = team(&[2, 3]);
+  for opponent in 4..=6 {
+    assert_eq!(
+      v.vote(opponent, 1, &teammates, t),
+      VoteKickResult::Rejected(VoteKickRejectReason::NotTeammate)
+    );
+  }
+
+  assert_eq!(
+    v.vote(2, 1, &teammates, t),
+    VoteKickResult::Started { required: 2 }
+  );
+  assert_eq!(
+    v.vote(4, 1, &teammates, t),
+    VoteKickResult::Rejected(VoteKickRejectReason::NotTeammate)
+  );
+  assert_eq!(v.vote(3, 1, &teammates, t), VoteKickResult::Passed);
+}
+
+#[test]
+fn test_vote_kick_snapshot() {
+  let window = Duration::from_secs(30);
+  let mut v = VoteKick::new(VoteKickRule::Majority, window, 2);
+  let t = Instant::now();
+  let voters = team(&[1, 3, 4]);
+  assert_eq!(
+    v.vote(1, 2, &voters, t),
+    VoteKickResult::Started { required: 2 }
+  );
+
+  let snapshot = v.snapshot(t + Duration::from_secs(10));
+  let mut restored = VoteKick::new(VoteKickRule::Majority, window, 2);
+  restored.restore(snapshot, t + Duration::from_secs(10));
+  assert_eq!(
+    restored.vote(1, 2, &voters, t),
+    VoteKickResult::AlreadyVoted
+  );
+  assert_eq!(restored.vote(3, 2, &voters, t), VoteKickResult::Passed);
+}
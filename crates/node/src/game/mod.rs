@@ -14,24 +14,30 @@ use flo_net::stream::FloStream;
 use flo_task::SpawnScope;
 pub use flo_types::node::*;
 use host::stream::PlayerStreamHandle;
-pub use host::AckError;
 use host::GameHost;
+pub use host::{list_journals, read_journal, AckError, HostSnapshot, JournalEntry};
 
 use crate::controller::ControllerServerHandle;
 use crate::error::*;
 use crate::observer::ObserverPublisherHandle;
 use crate::state::event::GlobalEventSender;
 use crate::state::GlobalEvent;
+use flo_observer::record::PlayerRemovedReason;
 use flo_w3gs::constants::LeaveReason;
 
-use self::host::GameHostOptions;
+use self::host::{DesyncReport, GameHostOptions, ObserverChatPolicy};
+use self::result::GameResultTracker;
 
 mod host;
+mod result;
 
 #[derive(Debug)]
 pub enum GameEvent {
   GameStatusChange(NodeGameStatus),
   PlayerStatusChange(i32, SlotClientStatus, SlotClientStatusUpdateSource),
+  PlayerLeft(i32, Option<LeaveReason>),
+  PlayerRemoved(i32, PlayerRemovedReason),
+  Desync(DesyncReport),
 }
 
 pub type GameEventSender = Sender<GameEvent>;
@@ -53,29 +59,84 @@ impl GameSession {
     ctrl: ControllerServerHandle,
     obs: ObserverPublisherHandle,
     g_event_sender: GlobalEventSender,
+  ) -> Result<Self> {
+    Self::create(game, None, ctrl, obs, g_event_sender)
+  }
+
+  /// Resumes a running game from the snapshot persisted before the node restarted.
+  /// All players are disconnected until they reconnect.
+  pub fn restore(
+    game: proto::Game,
+    host: HostSnapshot,
+    ctrl: ControllerServerHandle,
+    obs: ObserverPublisherHandle,
+    g_event_sender: GlobalEventSender,
+  ) -> Result<Self> {
+    Self::create(game, Some(host), ctrl, obs, g_event_sender)
+  }
+
+  fn create(
+    game: proto::Game,
+    restore: Option<HostSnapshot>,
+    ctrl: ControllerServerHandle,
+    obs: ObserverPublisherHandle,
+    g_event_sender: GlobalEventSender,
   ) -> Result<Self> {
     let scope = SpawnScope::new();
     let game_id = game.id;
+    let snapshot_game = game.clone();
+    let observer_chat_policy = ObserverChatPolicy::unpack_enum(game.observer_chat_policy());
+    let game_version = game.game_version.clone();
     let (tx, mut rx) = GameEvent::channel(32);
-    let slots: Vec<_> = Vec::<GameSlot>::unpack(game.slots)?
+    let mut slots: Vec<_> = Vec::<GameSlot>::unpack(game.slots)?
       .into_iter()
       .filter_map(PlayerSlot::from_game_slot)
       .collect();
 
+    let restored = restore.is_some();
+    if restored {
+      for slot in &mut slots {
+        if slot.client_status != SlotClientStatus::Left {
+          slot.client_status = SlotClientStatus::Disconnected;
+        }
+      }
+    }
+
+    let mut host = GameHost::new(
+      game_id,
+      GameHostOptions {
+        enabled_ping_equalizer: game.enable_ping_equalizer,
+        enabled_adaptive_step: game.enable_adaptive_step,
+        observer_chat_policy,
+      },
+      &slots,
+      obs.clone(),
+      tx.clone(),
+      restore,
+    );
+    if restored {
+      host.start();
+    }
+
     let mut scope_handle = scope.handle();
     let state = Arc::new(Mutex::new(State {
       game_id,
+      game: snapshot_game,
+      game_version,
       g_event_sender,
-      host: GameHost::new(
-        game_id,
-        GameHostOptions {
-          enabled_ping_equalizer: game.enable_ping_equalizer,
-        },
-        &slots,
-        obs.clone(),
-        tx.clone(),
+      host,
+      status: if restored {
+        NodeGameStatus::Running
+      } else {
+        NodeGameStatus::Created
+      },
+      result: GameResultTracker::new(
+        *crate::constants::GAME_RESULT_DISCONNECT_AS_LOSS,
+        slots
+          .iter()
+          .filter(|slot| slot.settings.team != 24)
+          .map(|slot| (slot.player.player_id, slot.settings.team)),
       ),
-      status: NodeGameStatus::Created,
       player_slots: slots
         .into_iter()
         .map(|slot| (slot.player.player_id, slot))
@@ -124,6 +185,20 @@ impl GameSession {
 
   async fn handle_event(handle: &GameSessionHandle, event: GameEvent) -> Result<()> {
     match event {
+      GameEvent::PlayerLeft(player_id, reason) => {
+        handle.0.lock().await.result.record_leave(player_id, reason);
+      }
+      GameEvent::PlayerRemoved(player_id, reason) => {
+        handle
+          .0
+          .lock()
+          .await
+          .result
+          .record_removed(player_id, reason);
+      }
+      GameEvent::Desync(report) => {
+        handle.0.lock().await.report_desync(report).await;
+      }
       GameEvent::PlayerStatusChange(player_id, status, source) => {
         handle
           .update_player_client_status(source, player_id, status)
@@ -200,6 +275,34 @@ impl GameSessionHandle {
     Ok(())
   }
 
+  /// Returns `None` if the game is not running
+  pub async fn snapshot(&self) -> Result<Option<proto::GameSessionSnapshot>> {
+    let (mut game, host) = {
+      let guard = self.0.lock().await;
+      if guard.status != NodeGameStatus::Running {
+        return Ok(None);
+      }
+      let mut game = guard.game.clone();
+      for slot in &mut game.slots {
+        let status = slot
+          .player
+          .as_ref()
+          .and_then(|player| guard.player_slots.get(&player.player_id))
+          .map(|slot| slot.client_status);
+        if let Some(status) = status {
+          slot.set_client_status(status.into_proto_enum());
+        }
+      }
+      (game, guard.host.snapshot())
+    };
+    game.set_status(NodeGameStatus::Running.into_proto_enum());
+    Ok(Some(proto::GameSessionSnapshot {
+      game: Some(game),
+      player_tokens: vec![],
+      host: Some(host.await?.pack()),
+    }))
+  }
+
   pub async fn retry_shutdown(
     &self,
     player_id: i32,
@@ -308,6 +411,11 @@ impl GameSessionHandle {
 
     slot.client_status = next_status;
 
+    // disconnected players can reconnect, only a final leave counts
+    if next_status == SlotClientStatus::Left {
+      guard.result.record_leave(player_id, None);
+    }
+
     match next_status {
       SlotClientStatus::Left => {
         if !guard.check_game_end().await {
@@ -396,9 +504,12 @@ pub enum SlotClientStatusUpdateSource {
 #[derive(Debug)]
 struct State {
   game_id: i32,
+  game: proto::Game,
+  game_version: String,
   g_event_sender: GlobalEventSender,
   host: GameHost,
   status: NodeGameStatus,
+  result: GameResultTracker,
   player_slots: BTreeMap<i32, PlayerSlot>,
   ctrl: ControllerServerHandle,
   tx: GameEventSender,
@@ -532,9 +643,13 @@ impl State {
         || slot.client_status == SlotClientStatus::Disconnected)
         || slot.settings.team == 24
     }) {
+      let running = self.status == NodeGameStatus::Running;
       self.status = NodeGameStatus::Ended;
       tracing::debug!("all player left, end game");
       self.obs.push_game_end(self.game_id);
+      if running {
+        self.report_result().await;
+      }
       self
         .g_event_sender
         .send(GlobalEvent::GameEnded(self.game_id))
@@ -546,6 +661,38 @@ impl State {
     }
   }
 
+  async fn report_result(&mut self) {
+    let result = self.result.resolve();
+    tracing::info!(game_id = self.game_id, "game result: {:?}", result);
+    match result.to_packet(self.game_id).encode_as_frame() {
+      Ok(frame) => {
+        self.ctrl.send(frame).await.ok();
+      }
+      Err(err) => {
+        tracing::error!(game_id = self.game_id, "encode game result: {}", err);
+      }
+    }
+  }
+
+  async fn report_desync(&mut self, report: DesyncReport) {
+    match report
+      .to_packet(self.game_id, &self.game_version)
+      .encode_as_frame()
+    {
+      Ok(frame) => {
+        if self.ctrl.send(frame).await.is_err() {
+          tracing::error!(
+            game_id = self.game_id,
+            "send game desync: controller channel closed"
+          );
+        }
+      }
+      Err(err) => {
+        tracing::error!(game_id = self.game_id, "encode game desync: {}", err);
+      }
+    }
+  }
+
   async fn check_game_all_joined(&mut self) {
     if self
       .player_slots
@@ -630,6 +777,7 @@ pub struct GamePlayer {
   pub player_id: i32,
   pub name: String,
   pub ban_list: Vec<PlayerBanType>,
+  pub is_referee: bool,
 }
 
 impl<'a> From<&'a State> for NodeGameStatusSnapshot {
@@ -0,0 +1,270 @@
+//! Infers per-player game results from the leave reasons reported by the clients,
+//! the order in which players left and the team composition.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use s2_grpc_utils::S2ProtoEnum;
+
+use flo_net::proto::flo_node as proto;
+use flo_observer::record::PlayerRemovedReason;
+use flo_w3gs::constants::LeaveReason;
+
+#[derive(Debug, Clone, Copy, PartialEq, S2ProtoEnum)]
+#[s2_grpc(proto_enum_type(flo_net::proto::flo_node::PlayerGameResult))]
+#[repr(i32)]
+pub enum PlayerResult {
+  Unknown = 0,
+  Win = 1,
+  Loss = 2,
+  Draw = 3,
+}
+
+#[derive(Debug)]
+pub struct GameResultTracker {
+  disconnect_as_loss: bool,
+  player_teams: BTreeMap<i32, i32>,
+  leaves: Vec<(i32, Option<LeaveReason>)>,
+  removed: BTreeMap<i32, PlayerRemovedReason>,
+}
+
+#[derive(Debug)]
+enum Outcome {
+  Winners(BTreeSet<i32>),
+  Draw,
+  Unknown,
+}
+
+#[derive(Debug)]
+pub struct GameResult {
+  pub winning_team: Option<i32>,
+  pub players: Vec<PlayerGameResult>,
+}
+
+#[derive(Debug)]
+pub struct PlayerGameResult {
+  pub player_id: i32,
+  pub result: PlayerResult,
+  pub leave_reason: Option<LeaveReason>,
+  pub removed_reason: Option<PlayerRemovedReason>,
+}
+
+impl GameResultTracker {
+  /// `player_teams` should not contain observers
+  pub fn new<I>(disconnect_as_loss: bool, player_teams: I) -> Self
+  where
+    I: IntoIterator<Item = (i32, i32)>,
+  {
+    Self {
+      disconnect_as_loss,
+      player_teams: player_teams.into_iter().collect(),
+      leaves: vec![],
+      removed: BTreeMap::new(),
+    }
+  }
+
+  /// Records the first leave of a player, a reported reason replaces a leave recorded without one,
+  /// other later calls for the same player are ignored
+  pub fn record_leave(&mut self, player_id: i32, reason: Option<LeaveReason>) {
+    if !self.player_teams.contains_key(&player_id) {
+      return;
+    }
+    if let Some(entry) = self.leaves.iter_mut().find(|(id, _)| *id == player_id) {
+      if entry.1.is_none() {
+        entry.1 = reason;
+      }
+      return;
+    }
+    self.leaves.push((player_id, reason));
+  }
+
+  /// Records that the node removed the player, e.g. after a kick vote
+  pub fn record_removed(&mut self, player_id: i32, reason: PlayerRemovedReason) {
+    if self.player_teams.contains_key(&player_id) {
+      self.removed.insert(player_id, reason);
+    }
+  }
+
+  pub fn resolve(&self) -> GameResult {
+    let reasons: BTreeMap<i32, Option<LeaveReason>> = self.leaves.iter().cloned().collect();
+    let outcome = self.outcome();
+    GameResult {
+      winning_team: match outcome {
+        Outcome::Winners(ref teams) if teams.len() == 1 => teams.iter().next().cloned(),
+        _ => None,
+      },
+      players: self
+        .player_teams
+        .iter()
+        .map(|(player_id, team)| PlayerGameResult {
+          player_id: *player_id,
+          result: match outcome {
+            Outcome::Winners(ref teams) => {
+              if teams.contains(team) {
+                PlayerResult::Win
+              } else {
+                PlayerResult::Loss
+              }
+            }
+            Outcome::Draw => PlayerResult::Draw,
+            Outcome::Unknown => PlayerResult::Unknown,
+          },
+          leave_reason: reasons.get(player_id).cloned().flatten(),
+          removed_reason: self.removed.get(player_id).cloned(),
+        })
+        .collect(),
+    }
+  }
+
+  fn outcome(&self) -> Outcome {
+    let won_teams: BTreeSet<i32> = self
+      .leaves
+      .iter()
+      .filter(|(_, reason)| *reason == Some(LeaveReason::LeaveWon))
+      .filter_map(|(player_id, _)| self.player_teams.get(player_id).cloned())
+      .collect();
+    if !won_teams.is_empty() {
+      return Outcome::Winners(won_teams);
+    }
+
+    if self
+      .leaves
+      .iter()
+      .any(|(_, reason)| *reason == Some(LeaveReason::LeaveDraw))
+    {
+      return Outcome::Draw;
+    }
+
+    if let Some(team) = self.last_team_standing() {
+      return Outcome::Winners(std::iter::once(team).collect());
+    }
+
+    Outcome::Unknown
+  }
+
+  /// The team of the last leaving player wins if every player of the other teams left with a loss
+  fn last_team_standing(&self) -> Option<i32> {
+    let teams: BTreeSet<i32> = self.player_teams.values().cloned().collect();
+    if teams.len() < 2 || self.leaves.len() != self.player_teams.len() {
+      return None;
+    }
+
+    let (last_player_id, last_reason) = self.leaves.last()?;
+    if matches!(
+      last_reason,
+      Some(LeaveReason::LeaveLost) | Some(LeaveReason::LeaveLostBuildings)
+    ) {
+      return None;
+    }
+    let team = self.player_teams.get(last_player_id).cloned()?;
+
+    let others_lost = self.leaves.iter().all(|(player_id, reason)| {
+      self.player_teams.get(player_id) == Some(&team) || self.is_loss(*reason)
+    });
+
+    if others_lost {
+      Some(team)
+    } else {
+      None
+    }
+  }
+
+  fn is_loss(&self, reason: Option<LeaveReason>) -> bool {
+    match reason {
+      Some(LeaveReason::LeaveLost) | Some(LeaveReason::LeaveLostBuildings) => true,
+      Some(LeaveReason::LeaveDisconnect) | None => self.disconnect_as_loss,
+      _ => false,
+    }
+  }
+}
+
+impl GameResult {
+  pub fn to_packet(&self, game_id: i32) -> proto::PacketNodeGameResult {
+    proto::PacketNodeGameResult {
+      game_id,
+      winning_team: self.winning_team,
+      players: self
+        .players
+        .iter()
+        .map(|p| {
+          let mut item = proto::GamePlayerResult {
+            player_id: p.player_id,
+            leave_reason: p.leave_reason.map(Into::into),
+            removed_reason: p.removed_reason.map(|v| v as u32),
+            ..Default::default()
+          };
+          item.set_result(p.result.into_proto_enum());
+          item
+        })
+        .collect(),
+    }
+  }
+}
+
+#[test]
+fn test_game_result() {
+  // 2v2, team 1 surrenders
+  let mut t = GameResultTracker::new(true, vec![(1, 0), (2, 0), (3, 1), (4, 1)]);
+  t.record_leave(3, Some(LeaveReason::LeaveLost));
+  t.record_leave(4, Some(LeaveReason::LeaveLost));
+  t.record_leave(3, Some(LeaveReason::LeaveDisconnect));
+  t.record_leave(1, Some(LeaveReason::LeaveWon));
+  t.record_leave(2, Some(LeaveReason::LeaveDisconnect));
+  let r = t.resolve();
+  assert_eq!(r.winning_team, Some(0));
+  let results: Vec<_> = r.players.iter().map(|p| p.result).collect();
+  assert_eq!(
+    results,
+    vec![
+      PlayerResult::Win,
+      PlayerResult::Win,
+      PlayerResult::Loss,
+      PlayerResult::Loss
+    ]
+  );
+  assert_eq!(r.players[2].leave_reason, Some(LeaveReason::LeaveLost));
+
+  // kicked players are reported with the removal reason
+  let mut t = GameResultTracker::new(true, vec![(1, 0), (2, 1), (3, 1)]);
+  t.record_removed(3, PlayerRemovedReason::VoteKick);
+  t.record_leave(3, None);
+  t.record_leave(2, Some(LeaveReason::LeaveLost));
+  t.record_leave(1, Some(LeaveReason::LeaveWon));
+  let r = t.resolve();
+  assert_eq!(r.winning_team, Some(0));
+  assert_eq!(
+    r.players[2].removed_reason,
+    Some(PlayerRemovedReason::VoteKick)
+  );
+  assert_eq!(r.players[1].removed_reason, None);
+
+  // 1v1, loser disconnects, winner leaves without reporting a win
+  let mut t = GameResultTracker::new(true, vec![(1, 0), (2, 1)]);
+  t.record_leave(2, None);
+  t.record_leave(1, Some(LeaveReason::LeaveDisconnect));
+  assert_eq!(t.resolve().winning_team, Some(0));
+
+  // the reason reported after the client status change is kept
+  let mut t = GameResultTracker::new(true, vec![(1, 0), (2, 1)]);
+  t.record_leave(2, None);
+  t.record_leave(2, Some(LeaveReason::LeaveLost));
+  t.record_leave(1, Some(LeaveReason::LeaveWon));
+  let r = t.resolve();
+  assert_eq!(r.winning_team, Some(0));
+  assert_eq!(r.players[1].leave_reason, Some(LeaveReason::LeaveLost));
+
+  // same game, but disconnects are not treated as losses
+  let mut t = GameResultTracker::new(false, vec![(1, 0), (2, 1)]);
+  t.record_leave(2, None);
+  t.record_leave(1, Some(LeaveReason::LeaveDisconnect));
+  let r = t.resolve();
+  assert_eq!(r.winning_team, None);
+  assert!(r.players.iter().all(|p| p.result == PlayerResult::Unknown));
+
+  // draw
+  let mut t = GameResultTracker::new(true, vec![(1, 0), (2, 1)]);
+  t.record_leave(1, Some(LeaveReason::LeaveDraw));
+  t.record_leave(2, Some(LeaveReason::LeaveDraw));
+  let r = t.resolve();
+  assert_eq!(r.winning_team, None);
+  assert!(r.players.iter().all(|p| p.result == PlayerResult::Draw));
+}
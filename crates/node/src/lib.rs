@@ -4,6 +4,7 @@ mod echo;
 mod env;
 mod game;
 mod metrics;
+mod snapshot;
 mod state;
 mod version;
 
@@ -11,6 +12,8 @@ mod constants;
 pub mod error;
 mod observer;
 
+use std::future::Future;
+
 use error::Result;
 
 use flo_event::*;
@@ -18,27 +21,57 @@ use flo_event::*;
 use self::client::serve_client;
 use self::echo::serve_echo;
 use self::metrics::serve_metrics;
+use self::snapshot::serve_snapshot;
 use crate::state::GlobalState;
 use state::event::{handle_global_events, FloNodeEventContext, GlobalEvent};
 
 pub async fn serve() -> Result<()> {
+  serve_with_drain_signal(std::future::pending()).await
+}
+
+/// Starts draining when `drain_signal` resolves or on `POST /drain` with the node secret
+/// in the `x-flo-node-secret` header: new games are rejected,
+/// and this function returns after all running games have ended.
+pub async fn serve_with_drain_signal<F>(drain_signal: F) -> Result<()>
+where
+  F: Future<Output = ()>,
+{
   let (event_sender, event_receiver) = GlobalEvent::channel(30);
-  let state = GlobalState::new(event_sender).into_ref();
+  let state = GlobalState::new(event_sender.clone()).into_ref();
   let mut ctrl = controller::ControllerServer::new(state.clone());
   let ctrl_handle = ctrl.handle();
 
-  tokio::try_join!(
-    ctrl.serve(),
-    serve_client(state.clone()),
-    serve_metrics(),
-    serve_echo(),
-    handle_global_events(
-      FloNodeEventContext {
-        state,
-        ctrl: ctrl_handle,
-      },
-      event_receiver
+  snapshot::restore_snapshots(&state, ctrl_handle.clone());
+
+  let serve = async {
+    tokio::try_join!(
+      ctrl.serve(),
+      serve_client(state.clone()),
+      serve_metrics(event_sender.clone()),
+      serve_echo(),
+      serve_snapshot(state.clone()),
+      handle_global_events(
+        FloNodeEventContext {
+          state: state.clone(),
+          ctrl: ctrl_handle,
+        },
+        event_receiver
+      ),
+      async {
+        drain_signal.await;
+        event_sender.send(GlobalEvent::Drain).await.ok();
+        Ok::<_, error::Error>(())
+      }
     )
-  )
-  .map(|_| ())
+    .map(|_| ())
+  };
+
+  tokio::select! {
+    res = serve => res,
+    _ = state.drained() => {
+      tracing::info!("all games ended, exiting.");
+      snapshot::clear_snapshots();
+      Ok(())
+    }
+  }
 }
@@ -1,7 +1,13 @@
 use once_cell::sync::Lazy;
-use prometheus::{register_int_gauge, Encoder, IntGauge, TextEncoder};
+use prometheus::{
+  register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramOpts,
+  HistogramVec, IntCounterVec, IntGauge, Opts, TextEncoder,
+};
+
+use flo_types::node::NodeGameStatus;
 
 use crate::error::*;
+use crate::state::{GlobalEvent, GlobalEventSender};
 use hyper::header::CONTENT_TYPE;
 
 pub static GAME_SESSIONS: Lazy<IntGauge> =
@@ -21,14 +27,161 @@ pub static PLAYER_TOKENS: Lazy<IntGauge> = Lazy::new(|| {
   .unwrap()
 });
 
-pub async fn serve_metrics() -> Result<()> {
+/// Value of the `node` label, read from `FLO_NODE_NAME`
+static NODE_NAME: Lazy<String> = Lazy::new(|| {
+  std::env::var("FLO_NODE_NAME")
+    .or_else(|_| std::env::var("HOSTNAME"))
+    .ok()
+    .filter(|v| !v.is_empty())
+    .unwrap_or_else(|| "unknown".to_string())
+});
+
+const GAME_LABELS: &[&str] = &["status"];
+
+fn game_opts(name: &str, help: &str) -> Opts {
+  Opts::new(name, help).const_label("node", NODE_NAME.as_str())
+}
+
+fn game_histogram_opts(name: &str, help: &str, buckets: Vec<f64>) -> HistogramOpts {
+  HistogramOpts::new(name, help)
+    .const_label("node", NODE_NAME.as_str())
+    .buckets(buckets)
+}
+
+pub static GAME_TICK_LATENESS: Lazy<HistogramVec> = Lazy::new(|| {
+  register_histogram_vec!(
+    game_histogram_opts(
+      "flonode_game_tick_lateness_ms",
+      "Delay of the action ticks behind their schedule",
+      vec![1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 250.0]
+    ),
+    GAME_LABELS
+  )
+  .unwrap()
+});
+pub static GAME_LAG_STARTS: Lazy<IntCounterVec> = Lazy::new(|| {
+  register_int_counter_vec!(
+    game_opts(
+      "flonode_game_lag_starts_total",
+      "Number of times the lag screen was shown"
+    ),
+    GAME_LABELS
+  )
+  .unwrap()
+});
+pub static GAME_LAG_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+  register_histogram_vec!(
+    game_histogram_opts(
+      "flonode_game_lag_duration_seconds",
+      "Duration of the lag screens",
+      vec![0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0]
+    ),
+    GAME_LABELS
+  )
+  .unwrap()
+});
+pub static GAME_DESYNCS: Lazy<IntCounterVec> = Lazy::new(|| {
+  register_int_counter_vec!(
+    game_opts("flonode_game_desyncs_total", "Number of desynced players"),
+    GAME_LABELS
+  )
+  .unwrap()
+});
+pub static GAME_RECONNECTS: Lazy<IntCounterVec> = Lazy::new(|| {
+  register_int_counter_vec!(
+    game_opts(
+      "flonode_game_reconnects_total",
+      "Number of player reconnections"
+    ),
+    GAME_LABELS
+  )
+  .unwrap()
+});
+pub static GAME_DROPPED_PLAYERS: Lazy<IntCounterVec> = Lazy::new(|| {
+  register_int_counter_vec!(
+    game_opts(
+      "flonode_game_dropped_players_total",
+      "Number of players removed from games by the node"
+    ),
+    &["status", "reason"]
+  )
+  .unwrap()
+});
+pub static GAME_ACTION_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+  register_int_counter_vec!(
+    game_opts(
+      "flonode_game_action_bytes_total",
+      "Size of the player actions dispatched"
+    ),
+    GAME_LABELS
+  )
+  .unwrap()
+});
+pub static PLAYER_RTT: Lazy<HistogramVec> = Lazy::new(|| {
+  register_histogram_vec!(
+    game_histogram_opts(
+      "flonode_player_rtt_ms",
+      "Average player RTT of each RTT stats report",
+      vec![10.0, 25.0, 50.0, 75.0, 100.0, 150.0, 200.0, 300.0, 500.0, 1000.0]
+    ),
+    GAME_LABELS
+  )
+  .unwrap()
+});
+pub static DELAY_EQUALIZER_ADJUSTMENTS: Lazy<IntCounterVec> = Lazy::new(|| {
+  register_int_counter_vec!(
+    game_opts(
+      "flonode_delay_equalizer_adjustments_total",
+      "Number of delay changes made by the ping equalizer"
+    ),
+    GAME_LABELS
+  )
+  .unwrap()
+});
+
+/// Value of the `status` label
+pub fn game_status_label(status: NodeGameStatus) -> &'static str {
+  match status {
+    NodeGameStatus::Created => "created",
+    NodeGameStatus::Waiting => "waiting",
+    NodeGameStatus::Loading => "loading",
+    NodeGameStatus::Running => "running",
+    NodeGameStatus::Ended => "ended",
+  }
+}
+
+/// Header carrying the node secret, required by `/drain`
+const NODE_SECRET_HEADER: &str = "x-flo-node-secret";
+
+pub async fn serve_metrics(event_sender: GlobalEventSender) -> Result<()> {
   use bytes::Bytes;
   use http_body_util::Full;
   use hyper::{body, service::service_fn, Request, Response};
   use hyper_util::rt::TokioExecutor;
   use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 
-  async fn serve_req(req: Request<body::Incoming>) -> Result<Response<Full<Bytes>>, hyper::Error> {
+  async fn serve_req(
+    req: Request<body::Incoming>,
+    event_sender: GlobalEventSender,
+  ) -> Result<Response<Full<Bytes>>, hyper::Error> {
+    if req.uri().path() == "/drain" {
+      let status = if req.method() != hyper::Method::POST {
+        405
+      } else if !is_authorized(&req) {
+        401
+      } else if event_sender.send(GlobalEvent::Drain).await.is_ok() {
+        200
+      } else {
+        500
+      };
+      let response = Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::new()))
+        .unwrap();
+
+      return Ok(response);
+    }
+
     if req.uri().path() == "/version" {
       let response = Response::builder()
         .status(200)
@@ -64,11 +217,15 @@ pub async fn serve_metrics() -> Result<()> {
     let (stream, _) = listener.accept().await?;
 
     let io = hyper_util::rt::TokioIo::new(stream);
+    let event_sender = event_sender.clone();
 
     tokio::spawn(async move {
       // use `auto::Builder` is for supporting both HTTP/1 and HTTP/2 at the same time.
       if let Err(err) = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
-        .serve_connection(io, service_fn(serve_req))
+        .serve_connection(
+          io,
+          service_fn(move |req| serve_req(req, event_sender.clone())),
+        )
         .await
       {
         tracing::error!("Error serving connection: {}", err);
@@ -76,3 +233,16 @@ pub async fn serve_metrics() -> Result<()> {
     });
   }
 }
+
+/// The request must carry the node secret, an unset secret rejects every request
+fn is_authorized<B>(req: &hyper::Request<B>) -> bool {
+  let secret = crate::env::Env::get().secret_key.as_bytes();
+  if secret.is_empty() {
+    return false;
+  }
+  req
+    .headers()
+    .get(NODE_SECRET_HEADER)
+    .map(|value| value.as_bytes() == secret)
+    .unwrap_or(false)
+}
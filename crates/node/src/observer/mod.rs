@@ -1,7 +1,10 @@
 use crate::error::Result;
 use backoff::backoff::Backoff;
 use bytes::{BufMut, Bytes, BytesMut};
-use flo_observer::{record::GameRecord, record::RTTStats, KINESIS_CLIENT};
+use flo_observer::{
+  record::DesyncReport, record::GameRecord, record::PlayerRemovedReason, record::RTTStats,
+  KINESIS_CLIENT,
+};
 use flo_w3gs::packet::Packet;
 use parking_lot::Mutex;
 use std::cell::Cell;
@@ -78,6 +81,14 @@ impl ObserverPublisherHandle {
     self.push_record(GameRecord::new_rtt_stats(game_id, stats))
   }
 
+  pub fn push_player_removed(&self, game_id: i32, player_id: i32, reason: PlayerRemovedReason) {
+    self.push_record(GameRecord::new_player_removed(game_id, player_id, reason))
+  }
+
+  pub fn push_desync(&self, game_id: i32, report: DesyncReport) {
+    self.push_record(GameRecord::new_desync(game_id, report))
+  }
+
   fn push_record(&self, record: GameRecord) {
     if self.broken.get() {
       return;
@@ -0,0 +1,213 @@
+//! Persists running games to the local disk periodically,
+//! so they can be resumed after the node restarts.
+//! Frames sent after the last snapshot are written ahead to the journal of the game,
+//! the journal is replayed on top of the snapshot when the game is restored.
+//!
+//! Enabled by setting `FLO_NODE_SNAPSHOT_DIR`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use flo_net::packet::Message;
+use flo_net::proto::flo_node::GameSessionSnapshot;
+
+use crate::controller::ControllerServerHandle;
+use crate::error::*;
+use crate::game::{list_journals, read_journal};
+use crate::state::{GlobalState, GlobalStateRef};
+
+const SNAPSHOT_EXT: &str = "snapshot";
+
+pub async fn serve_snapshot(state: GlobalStateRef) -> Result<()> {
+  let dir = if let Some(dir) = crate::constants::NODE_SNAPSHOT_DIR.clone() {
+    dir
+  } else {
+    return Ok(());
+  };
+
+  let mut interval = tokio::time::interval(crate::constants::NODE_SNAPSHOT_INTERVAL);
+  interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+  loop {
+    interval.tick().await;
+    let snapshots = state.snapshot_games().await;
+    let dir = dir.clone();
+    match tokio::task::spawn_blocking(move || write_snapshots(&dir, snapshots)).await {
+      Ok(Ok(())) => {}
+      Ok(Err(err)) => {
+        tracing::error!("write snapshots: {}", err);
+      }
+      Err(err) => {
+        tracing::error!("write snapshots: {}", err);
+      }
+    }
+  }
+}
+
+/// Resumes games from the snapshot files written by the previous process.
+/// Files that are too old or fail to restore are removed.
+pub fn restore_snapshots(state: &GlobalState, ctrl: ControllerServerHandle) {
+  let dir = if let Some(dir) = crate::constants::NODE_SNAPSHOT_DIR.as_ref() {
+    dir
+  } else {
+    return;
+  };
+
+  let paths = match list_snapshots(dir) {
+    Ok(paths) => paths,
+    Err(err) => {
+      tracing::error!("list snapshots: {}", err);
+      return;
+    }
+  };
+
+  for (game_id, path) in paths {
+    match restore_snapshot(state, ctrl.clone(), dir, game_id, &path) {
+      Ok(()) => {
+        tracing::info!(game_id, "game restored");
+      }
+      Err(err) => {
+        tracing::warn!(game_id, "restore game: {}", err);
+        std::fs::remove_file(&path).ok();
+      }
+    }
+  }
+}
+
+/// Removes all snapshot and journal files, called after all games have ended.
+pub fn clear_snapshots() {
+  if let Some(dir) = crate::constants::NODE_SNAPSHOT_DIR.as_ref() {
+    if let Ok(paths) = list_snapshots(dir) {
+      for (_, path) in paths {
+        std::fs::remove_file(&path).ok();
+      }
+    }
+    if let Ok(paths) = list_journals(dir) {
+      for (_, _, path) in paths {
+        std::fs::remove_file(&path).ok();
+      }
+    }
+  }
+}
+
+fn restore_snapshot(
+  state: &GlobalState,
+  ctrl: ControllerServerHandle,
+  dir: &Path,
+  game_id: i32,
+  path: &Path,
+) -> Result<()> {
+  let age = std::fs::metadata(path)?
+    .modified()?
+    .elapsed()
+    .unwrap_or_default();
+  if age > *crate::constants::NODE_SNAPSHOT_MAX_AGE {
+    return Err(Error::SnapshotExpired);
+  }
+  let bytes = std::fs::read(path)?;
+  let snapshot =
+    GameSessionSnapshot::decode(bytes.as_slice()).map_err(flo_net::error::Error::from)?;
+  let journal_seq = snapshot
+    .host
+    .as_ref()
+    .map(|host| host.journal_seq)
+    .unwrap_or_default();
+  let journal = read_journal(dir, game_id, journal_seq)?;
+  state.restore_game(ctrl, snapshot, journal)
+}
+
+fn write_snapshots(dir: &Path, snapshots: Vec<GameSessionSnapshot>) -> Result<()> {
+  std::fs::create_dir_all(dir)?;
+
+  // first journal segment needed by the snapshot of each game
+  let mut journal_seqs = BTreeMap::new();
+  for snapshot in snapshots {
+    let game_id = if let Some(game) = snapshot.game.as_ref() {
+      game.id
+    } else {
+      continue;
+    };
+    let path = snapshot_path(dir, game_id);
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, snapshot.encode_to_vec())?;
+    std::fs::rename(&tmp_path, &path)?;
+    journal_seqs.insert(
+      game_id,
+      snapshot
+        .host
+        .as_ref()
+        .map(|host| host.journal_seq)
+        .unwrap_or_default(),
+    );
+  }
+
+  // remove snapshots of the games that are no longer running
+  for (game_id, path) in list_snapshots(dir)? {
+    if !journal_seqs.contains_key(&game_id) {
+      std::fs::remove_file(&path)?;
+    }
+  }
+
+  // remove journal segments already included in the snapshots
+  for (game_id, seq, path) in list_journals(dir)? {
+    if journal_seqs
+      .get(&game_id)
+      .map(|journal_seq| seq < *journal_seq)
+      .unwrap_or(true)
+    {
+      std::fs::remove_file(&path)?;
+    }
+  }
+
+  Ok(())
+}
+
+fn list_snapshots(dir: &Path) -> Result<Vec<(i32, PathBuf)>> {
+  if !dir.exists() {
+    return Ok(vec![]);
+  }
+
+  let mut paths = vec![];
+  for entry in std::fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.extension().and_then(|v| v.to_str()) != Some(SNAPSHOT_EXT) {
+      continue;
+    }
+    let game_id = path
+      .file_stem()
+      .and_then(|v| v.to_str())
+      .and_then(|v| v.parse::<i32>().ok());
+    if let Some(game_id) = game_id {
+      paths.push((game_id, path));
+    }
+  }
+  Ok(paths)
+}
+
+fn snapshot_path(dir: &Path, game_id: i32) -> PathBuf {
+  dir.join(format!("{}.{}", game_id, SNAPSHOT_EXT))
+}
+
+#[test]
+fn test_list_snapshots() {
+  use std::time::SystemTime;
+
+  let dir = std::env::temp_dir().join(format!(
+    "flo-node-snapshot-{}",
+    SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .unwrap()
+      .as_nanos()
+  ));
+  std::fs::create_dir_all(&dir).unwrap();
+  std::fs::write(snapshot_path(&dir, 1), b"").unwrap();
+  std::fs::write(snapshot_path(&dir, 2), b"").unwrap();
+  std::fs::write(dir.join("3.tmp"), b"").unwrap();
+  std::fs::write(dir.join("x.snapshot"), b"").unwrap();
+  std::fs::write(dir.join("1.0.journal"), b"").unwrap();
+
+  write_snapshots(&dir, vec![]).unwrap();
+  assert!(list_snapshots(&dir).unwrap().is_empty());
+  assert!(list_journals(&dir).unwrap().is_empty());
+
+  std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -2,6 +2,9 @@ use tokio::sync::mpsc::{Receiver, Sender};
 
 use flo_event::*;
 
+use flo_net::packet::FloPacket;
+use flo_net::proto::flo_node::PacketNodeDrainStatus;
+
 use crate::controller::ControllerServerHandle;
 use crate::error::*;
 use crate::state::GlobalStateRef;
@@ -13,6 +16,8 @@ pub type GlobalEventSender = Sender<GlobalEvent>;
 pub enum GlobalEvent {
   // A game has ended, remove the session from global state
   GameEnded(i32),
+  // Stop accepting new games and exit after all running games have ended
+  Drain,
 }
 
 impl FloEvent for GlobalEvent {
@@ -35,6 +40,19 @@ pub async fn handle_global_events(
         tracing::debug!(game_id, "game ended: {}", game_id);
         ctx.state.end_game(game_id);
       }
+      GlobalEvent::Drain => {
+        if ctx.state.start_drain() {
+          tracing::info!("draining");
+          match (PacketNodeDrainStatus { draining: true }).encode_as_frame() {
+            Ok(frame) => {
+              ctx.ctrl.send(frame).await.ok();
+            }
+            Err(err) => {
+              tracing::error!("encode drain status: {}", err);
+            }
+          }
+        }
+      }
     }
   }
   Ok(())
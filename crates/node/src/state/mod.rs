@@ -8,11 +8,13 @@ use dashmap::DashMap;
 use parking_lot::RwLock;
 use s2_grpc_utils::S2ProtoEnum;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use tokio::sync::Notify;
 
 use flo_net::packet::{FloPacket, Frame, OptionalFieldExt};
 use flo_net::proto::flo_node::{
-  ControllerCreateGameRejectReason, Game, PacketControllerCreateGame,
+  ControllerCreateGameRejectReason, Game, GameSessionSnapshot, PacketControllerCreateGame,
   PacketControllerCreateGameAccept, PacketControllerCreateGameReject,
   PacketControllerUpdateSlotStatus, PacketControllerUpdateSlotStatusAccept,
   PacketControllerUpdateSlotStatusReject,
@@ -20,7 +22,9 @@ use flo_net::proto::flo_node::{
 
 use crate::controller::ControllerServerHandle;
 use crate::error::*;
-use crate::game::{GameSession, GameSessionHandle, SlotClientStatusUpdateSource};
+use crate::game::{
+  GameSession, GameSessionHandle, HostSnapshot, JournalEntry, SlotClientStatusUpdateSource,
+};
 use crate::metrics;
 use crate::observer::{ObserverPublisher, ObserverPublisherHandle};
 
@@ -30,6 +34,8 @@ pub struct GlobalState {
   players: PlayerRegistry,
   games: GameRegistry,
   obs: ObserverPublisher,
+  draining: AtomicBool,
+  drained: Notify,
 }
 
 pub type GlobalStateRef = Arc<GlobalState>;
@@ -41,6 +47,8 @@ impl GlobalState {
       players: PlayerRegistry::new(),
       games: GameRegistry::new(),
       obs: ObserverPublisher::new(),
+      draining: AtomicBool::new(false),
+      drained: Notify::new(),
     }
   }
 
@@ -59,6 +67,96 @@ impl GlobalState {
   pub fn end_game(&self, id: i32) {
     self.players.remove_game(id);
     self.games.remove(id);
+    self.check_drained();
+  }
+
+  /// Snapshots of all running games, including the player tokens
+  pub async fn snapshot_games(&self) -> Vec<GameSessionSnapshot> {
+    let mut snapshots = vec![];
+    for (game_id, handle) in self.games.handles() {
+      match handle.snapshot().await {
+        Ok(Some(mut snapshot)) => {
+          snapshot.player_tokens = self
+            .players
+            .get_game_tokens(game_id)
+            .into_iter()
+            .map(|(player_id, token)| flo_net::proto::flo_node::PlayerToken {
+              player_id,
+              token: token.to_vec(),
+            })
+            .collect();
+          snapshots.push(snapshot);
+        }
+        Ok(None) => {}
+        Err(err) => {
+          tracing::error!(game_id, "snapshot game: {}", err);
+        }
+      }
+    }
+    snapshots
+  }
+
+  /// Re-registers a game that was running before the node restarted,
+  /// `journal` contains the entries written after the snapshot
+  pub fn restore_game(
+    &self,
+    ctrl: ControllerServerHandle,
+    snapshot: GameSessionSnapshot,
+    journal: Vec<JournalEntry>,
+  ) -> Result<()> {
+    let game = snapshot.game.extract()?;
+    let mut host = HostSnapshot::unpack(snapshot.host.extract()?);
+    host.journal = journal;
+    let game_id = game.id;
+
+    let pairs = snapshot
+      .player_tokens
+      .into_iter()
+      .filter_map(|item| {
+        Some((
+          PlayerToken::from_vec(item.token)?,
+          RegisteredPlayer {
+            player_id: item.player_id,
+            game_id,
+          },
+        ))
+      })
+      .collect();
+
+    self.games.restore(
+      game,
+      host,
+      ctrl,
+      self.obs.handle(),
+      self.event_sender.clone().into(),
+    )?;
+    self.players.register(GamePlayerTokens { game_id, pairs });
+    Ok(())
+  }
+
+  /// Stops accepting new games.
+  /// Returns `false` if the node is already draining.
+  pub fn start_drain(&self) -> bool {
+    let started = !self.draining.swap(true, Ordering::SeqCst);
+    if started {
+      self.check_drained();
+    }
+    started
+  }
+
+  pub fn is_draining(&self) -> bool {
+    self.draining.load(Ordering::SeqCst)
+  }
+
+  /// Resolves after the node started draining and all game sessions have ended
+  pub async fn drained(&self) {
+    self.drained.notified().await
+  }
+
+  fn check_drained(&self) {
+    if self.is_draining() && metrics::GAME_SESSIONS.get() == 0 {
+      self.drained.notify_one();
+    }
   }
 
   pub fn handle_controller_create_game(
@@ -79,6 +177,16 @@ impl GlobalState {
       return Err(Error::NoPlayer);
     }
 
+    if self.is_draining() {
+      return Ok(
+        PacketControllerCreateGameReject {
+          game_id,
+          reason: ControllerCreateGameRejectReason::Maintenance.into(),
+        }
+        .encode_as_frame()?,
+      );
+    }
+
     let pending: Vec<(PlayerToken, RegisteredPlayer)> = {
       let players: Vec<_> = game
         .slots
@@ -306,6 +414,16 @@ impl PlayerRegistry {
     }
   }
 
+  fn get_game_tokens(&self, game_id: i32) -> Vec<(i32, PlayerToken)> {
+    self
+      .state
+      .read()
+      .game_tokens
+      .get(&game_id)
+      .cloned()
+      .unwrap_or_default()
+  }
+
   pub fn get_by_token(&self, token: &PlayerToken) -> Option<RegisteredPlayer> {
     self.state.read().map.get(&token).cloned()
   }
@@ -350,6 +468,35 @@ impl GameRegistry {
     Ok(())
   }
 
+  fn restore(
+    &self,
+    game: Game,
+    host: HostSnapshot,
+    ctrl: ControllerServerHandle,
+    obs: ObserverPublisherHandle,
+    g_event_sender: GlobalEventSender,
+  ) -> Result<()> {
+    use dashmap::mapref::entry::Entry;
+    let game_id = game.id;
+
+    match self.map.entry(game_id) {
+      Entry::Vacant(entry) => {
+        entry.insert(GameSession::restore(game, host, ctrl, obs, g_event_sender)?);
+        metrics::GAME_SESSIONS.inc();
+        Ok(())
+      }
+      Entry::Occupied(_) => Err(Error::GameExists),
+    }
+  }
+
+  fn handles(&self) -> Vec<(i32, GameSessionHandle)> {
+    self
+      .map
+      .iter()
+      .map(|r| (*r.key(), r.value().handle()))
+      .collect()
+  }
+
   fn get(&self, game_id: i32) -> Option<GameSessionHandle> {
     self.map.get(&game_id).map(|r| r.value().handle())
   }
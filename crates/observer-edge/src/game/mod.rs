@@ -332,6 +332,16 @@ impl GameHandler {
           // }
         }
         GameRecordData::TickChecksum { .. } => {}
+        GameRecordData::PlayerRemoved(player_id, reason) => {
+          self.span.in_scope(|| {
+            tracing::info!(player_id, "removed: {:?}", reason);
+          });
+        }
+        GameRecordData::Desync(ref report) => {
+          self.span.in_scope(|| {
+            tracing::warn!("desync: tick = {}, time = {}", report.tick, report.time);
+          });
+        }
         GameRecordData::RTTStats(stats) => {
           self.game.put_rtt(self.meta.id, stats, snapshot_map)?;
           continue;
@@ -22,8 +22,12 @@ pub enum RecordError {
   DecodeW3GSHeader(flo_util::error::BinDecodeError),
   #[error("decode rtt stats record: {0}")]
   DecodeRTTStatsRecord(flo_util::error::BinDecodeError),
+  #[error("decode desync record: {0}")]
+  DecodeDesyncRecord(flo_util::error::BinDecodeError),
   #[error("decode w3gs: {0}")]
   DecodeW3GS(flo_w3gs::error::Error),
+  #[error("unknown player removed reason: {0}")]
+  UnknownPlayerRemovedReason(u8),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -107,6 +111,28 @@ pub enum GameRecordData {
   GameEnd,
   TickChecksum { tick: u32, checksum: u32 },
   RTTStats(RTTStats),
+  PlayerRemoved(i32, PlayerRemovedReason),
+  Desync(DesyncReport),
+}
+
+/// Why the node removed a player from the game
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum PlayerRemovedReason {
+  VoteKick = 1,
+  RefereeKick = 2,
+}
+
+impl TryFrom<u8> for PlayerRemovedReason {
+  type Error = RecordError;
+
+  fn try_from(value: u8) -> Result<Self, Self::Error> {
+    Ok(match value {
+      1 => Self::VoteKick,
+      2 => Self::RefereeKick,
+      other => return Err(RecordError::UnknownPlayerRemovedReason(other)),
+    })
+  }
 }
 
 #[derive(Debug, Clone, BinEncode, BinDecode)]
@@ -137,6 +163,35 @@ pub struct RTTStatsItem {
   pub avg: f32,
 }
 
+#[derive(Debug, Clone, BinEncode, BinDecode)]
+pub struct DesyncReport {
+  pub tick: u32,
+  pub time: u32,
+  items_len: u8,
+  #[bin(repeat = "items_len")]
+  pub items: Vec<DesyncReportItem>,
+}
+
+impl DesyncReport {
+  pub fn new(tick: u32, time: u32, items: impl Iterator<Item = DesyncReportItem>) -> Self {
+    let items: Vec<_> = items.into_iter().take(u8::MAX as usize).collect();
+    Self {
+      tick,
+      time,
+      items_len: items.len() as _,
+      items,
+    }
+  }
+}
+
+#[derive(Debug, Clone, BinEncode, BinDecode)]
+pub struct DesyncReportItem {
+  pub player_id: i32,
+  pub checksum: u32,
+  /// The player's checksum differs from the checksum of the majority
+  pub desynced: bool,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum DataTypeId {
@@ -146,6 +201,8 @@ pub enum DataTypeId {
   GameEnd = 4,
   TickChecksum = 5,
   RTTStat = 6,
+  PlayerRemoved = 7,
+  Desync = 8,
 }
 
 impl GameRecordData {
@@ -157,6 +214,8 @@ impl GameRecordData {
       GameRecordData::GameEnd => DataTypeId::GameEnd,
       GameRecordData::TickChecksum { .. } => DataTypeId::TickChecksum,
       GameRecordData::RTTStats { .. } => DataTypeId::RTTStat,
+      GameRecordData::PlayerRemoved(..) => DataTypeId::PlayerRemoved,
+      GameRecordData::Desync(_) => DataTypeId::Desync,
     }
   }
 
@@ -168,6 +227,10 @@ impl GameRecordData {
       GameRecordData::GameEnd => 0,
       GameRecordData::TickChecksum { .. } => 4 + 4,
       GameRecordData::RTTStats(ref data) => 4 + 1 + (data.items.len() * RTTStatsItem::MIN_SIZE),
+      GameRecordData::PlayerRemoved(..) => 4 + 1,
+      GameRecordData::Desync(ref data) => {
+        4 + 4 + 1 + (data.items.len() * DesyncReportItem::MIN_SIZE)
+      }
     }
   }
 
@@ -200,6 +263,13 @@ impl GameRecordData {
       GameRecordData::RTTStats(ref data) => {
         data.encode(&mut buf);
       }
+      GameRecordData::PlayerRemoved(player_id, reason) => {
+        buf.put_i32(player_id);
+        buf.put_u8(reason as u8);
+      }
+      GameRecordData::Desync(ref data) => {
+        data.encode(&mut buf);
+      }
     }
   }
 
@@ -214,6 +284,8 @@ impl GameRecordData {
       4 => DataTypeId::GameEnd,
       5 => DataTypeId::TickChecksum,
       6 => DataTypeId::RTTStat,
+      7 => DataTypeId::PlayerRemoved,
+      8 => DataTypeId::Desync,
       other => return Err(RecordError::UnknownDataTypeId(other)),
     };
     Ok(match data_type {
@@ -262,6 +334,16 @@ impl GameRecordData {
       DataTypeId::RTTStat => {
         Self::RTTStats(RTTStats::decode(&mut buf).map_err(RecordError::DecodeRTTStatsRecord)?)
       }
+      DataTypeId::PlayerRemoved => {
+        if buf.remaining() < 4 + 1 {
+          return Err(RecordError::UnexpectedEndOfBuffer);
+        }
+        let player_id = buf.get_i32();
+        Self::PlayerRemoved(player_id, PlayerRemovedReason::try_from(buf.get_u8())?)
+      }
+      DataTypeId::Desync => {
+        Self::Desync(DesyncReport::decode(&mut buf).map_err(RecordError::DecodeDesyncRecord)?)
+      }
     })
   }
 }
@@ -309,6 +391,20 @@ impl GameRecord {
     }
   }
 
+  pub fn new_player_removed(game_id: i32, player_id: i32, reason: PlayerRemovedReason) -> Self {
+    Self {
+      game_id,
+      data: GameRecordData::PlayerRemoved(player_id, reason),
+    }
+  }
+
+  pub fn new_desync(game_id: i32, report: DesyncReport) -> Self {
+    Self {
+      game_id,
+      data: GameRecordData::Desync(report),
+    }
+  }
+
   pub fn encode_len(&self) -> usize {
     4 + self.data.encode_len()
   }
@@ -440,4 +536,52 @@ fn test_encode_decode() {
     assert_eq!(max, i as u16);
     assert_eq!(avg, i as f32);
   }
+
+  let record = encode_then_decode(&GameRecord::new_player_removed(
+    1234,
+    5678,
+    PlayerRemovedReason::VoteKick,
+  ));
+  assert_eq!(record.game_id, 1234);
+  assert_eq!(record.data.type_id(), DataTypeId::PlayerRemoved);
+  match record.data {
+    GameRecordData::PlayerRemoved(player_id, reason) => {
+      assert_eq!(player_id, 5678);
+      assert_eq!(reason, PlayerRemovedReason::VoteKick);
+    }
+    _ => unreachable!(),
+  }
+  let record = encode_then_decode(&GameRecord::new_desync(
+    1234,
+    DesyncReport::new(
+      100,
+      5000,
+      vec![
+        DesyncReportItem {
+          player_id: 1,
+          checksum: 11,
+          desynced: false,
+        },
+        DesyncReportItem {
+          player_id: 2,
+          checksum: 22,
+          desynced: true,
+        },
+      ]
+      .into_iter(),
+    ),
+  ));
+  assert_eq!(record.game_id, 1234);
+  assert_eq!(record.data.type_id(), DataTypeId::Desync);
+  match record.data {
+    GameRecordData::Desync(report) => {
+      assert_eq!(report.tick, 100);
+      assert_eq!(report.time, 5000);
+      assert_eq!(report.items.len(), 2);
+      assert_eq!(report.items[1].player_id, 2);
+      assert_eq!(report.items[1].checksum, 22);
+      assert!(report.items[1].desynced);
+    }
+    _ => unreachable!(),
+  }
 }
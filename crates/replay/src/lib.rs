@@ -425,6 +425,8 @@ where
         records.push(Record::TimeSlotAck(TimeSlotAck::new(checksum)))
       }
       GameRecordData::RTTStats(_) => {}
+      GameRecordData::PlayerRemoved(..) => {}
+      GameRecordData::Desync(_) => {}
     }
   }
 
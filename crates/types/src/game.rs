@@ -39,6 +39,19 @@ pub struct PlayerInfo {
   pub id: i32,
   pub name: String,
   pub source: PlayerSource,
+  #[serde(default)]
+  pub ratings: Vec<PlayerRating>,
+}
+
+#[derive(Debug, S2ProtoUnpack, Serialize, Deserialize, Clone)]
+#[s2_grpc(message_type = "flo_net::proto::flo_connect::PlayerRating")]
+pub struct PlayerRating {
+  pub mode: String,
+  pub rating: f64,
+  pub games: i32,
+  pub wins: i32,
+  pub losses: i32,
+  pub draws: i32,
 }
 
 #[derive(Debug, S2ProtoEnum, PartialEq, Copy, Clone, Serialize, Deserialize)]
@@ -124,6 +137,7 @@ pub struct Slot {
   pub settings: SlotSettings,
   #[s2_grpc(proto_enum)]
   pub client_status: SlotClientStatus,
+  pub ready: bool,
 }
 
 impl Default for Slot {
@@ -132,6 +146,7 @@ impl Default for Slot {
       player: None,
       settings: SlotSettings::default(),
       client_status: SlotClientStatus::Pending,
+      ready: false,
     }
   }
 }
@@ -0,0 +1,7 @@
+alter table "game_used_slot"
+    drop column leave_reason,
+    drop column result;
+
+alter table "game"
+    drop column result_reported_at,
+    drop column result_winning_team;
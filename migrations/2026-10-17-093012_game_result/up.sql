@@ -0,0 +1,7 @@
+alter table "game"
+    add column result_winning_team integer,
+    add column result_reported_at timestamp with time zone;
+
+alter table "game_used_slot"
+    add column result integer,
+    add column leave_reason integer;
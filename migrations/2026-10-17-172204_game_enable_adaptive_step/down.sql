@@ -0,0 +1,2 @@
+alter table "game"
+    drop column enable_adaptive_step;
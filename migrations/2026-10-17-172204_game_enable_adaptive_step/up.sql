@@ -0,0 +1,2 @@
+alter table "game"
+    add column enable_adaptive_step boolean default false not null;
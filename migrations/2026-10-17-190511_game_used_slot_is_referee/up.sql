@@ -0,0 +1,2 @@
+alter table "game_used_slot"
+    add column is_referee boolean default false not null;
@@ -0,0 +1,2 @@
+alter table "game"
+    drop column observer_chat_policy;
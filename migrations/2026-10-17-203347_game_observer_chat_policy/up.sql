@@ -0,0 +1,2 @@
+alter table "game"
+    add column observer_chat_policy integer default 0 not null;
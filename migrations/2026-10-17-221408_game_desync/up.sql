@@ -0,0 +1,11 @@
+create table game_desync (
+    id serial not null primary key,
+    game_id integer not null references game(id),
+    tick integer not null,
+    time integer not null,
+    game_version text,
+    players jsonb not null,
+    created_at timestamp with time zone default now() not null
+);
+
+create index game_desync_game_id on game_desync(game_id);
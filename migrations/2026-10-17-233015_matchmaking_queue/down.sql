@@ -0,0 +1 @@
+drop table matchmaking_queue;
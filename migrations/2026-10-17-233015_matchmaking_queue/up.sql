@@ -0,0 +1,13 @@
+create table matchmaking_queue (
+    id serial not null primary key,
+    api_client_id integer not null references api_client(id),
+    name text not null,
+    mode text not null,
+    team_size integer not null,
+    maps jsonb not null,
+    enable_ping_equalizer boolean not null default false,
+    created_at timestamp with time zone default now() not null,
+    updated_at timestamp with time zone default now() not null
+);
+
+create unique index matchmaking_queue_api_client_id_name on matchmaking_queue(api_client_id, name);
@@ -0,0 +1,5 @@
+drop table player_rating_change;
+drop table player_rating;
+
+alter table "game"
+    drop column rating_mode;
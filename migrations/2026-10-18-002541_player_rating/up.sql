@@ -0,0 +1,29 @@
+alter table "game"
+    add column rating_mode text;
+
+create table player_rating (
+    player_id integer not null references player(id) on delete cascade,
+    mode text not null,
+    rating double precision not null,
+    games integer not null default 0,
+    wins integer not null default 0,
+    losses integer not null default 0,
+    draws integer not null default 0,
+    created_at timestamp with time zone default now() not null,
+    updated_at timestamp with time zone default now() not null,
+    primary key (player_id, mode)
+);
+
+create index player_rating_mode_rating on player_rating(mode, rating desc, player_id);
+
+create table player_rating_change (
+    id serial not null primary key,
+    game_id integer not null references game(id) on delete cascade,
+    player_id integer not null references player(id) on delete cascade,
+    mode text not null,
+    rating_before double precision not null,
+    rating_after double precision not null,
+    created_at timestamp with time zone default now() not null
+);
+
+create unique index player_rating_change_game_id_player_id on player_rating_change(game_id, player_id);
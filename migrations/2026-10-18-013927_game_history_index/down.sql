@@ -0,0 +1,4 @@
+drop index game_started_at;
+
+drop index game_used_slot_player_id_game_id;
+create index game_used_slot_player_id on game_used_slot(player_id);
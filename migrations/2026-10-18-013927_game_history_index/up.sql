@@ -0,0 +1,4 @@
+drop index if exists game_used_slot_player_id;
+create index game_used_slot_player_id_game_id on game_used_slot(player_id, game_id) where player_id is not null;
+
+create index game_started_at on game(started_at desc, id desc) where started_at is not null;
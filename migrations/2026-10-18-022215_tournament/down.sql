@@ -0,0 +1,3 @@
+drop table tournament_match;
+drop table tournament_player;
+drop table tournament;
@@ -0,0 +1,40 @@
+create table tournament (
+    id serial not null primary key,
+    api_client_id integer not null references api_client(id),
+    api_player_id integer not null references player(id),
+    name text not null,
+    format integer not null,
+    status integer not null default 0,
+    game_settings jsonb not null,
+    swiss_rounds integer,
+    winner_player_id integer references player(id),
+    created_at timestamp with time zone default now() not null,
+    updated_at timestamp with time zone default now() not null
+);
+
+create index tournament_api_client_id on tournament(api_client_id);
+create index tournament_status on tournament(status);
+
+create table tournament_player (
+    tournament_id integer not null references tournament(id) on delete cascade,
+    player_id integer not null references player(id) on delete cascade,
+    seed integer not null,
+    primary key (tournament_id, player_id)
+);
+
+create table tournament_match (
+    id serial not null primary key,
+    tournament_id integer not null references tournament(id) on delete cascade,
+    match_no integer not null,
+    bracket integer not null,
+    round integer not null,
+    sources jsonb not null,
+    game_id integer references game(id) on delete set null,
+    ended boolean not null default false,
+    winner_player_id integer references player(id),
+    created_at timestamp with time zone default now() not null,
+    updated_at timestamp with time zone default now() not null
+);
+
+create unique index tournament_match_tournament_id_match_no on tournament_match(tournament_id, match_no);
+create index tournament_match_game_id on tournament_match(game_id) where game_id is not null;
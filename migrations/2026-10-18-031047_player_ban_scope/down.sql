@@ -0,0 +1,14 @@
+drop table player_ban_audit;
+
+delete from player_ban where map_sha1 <> '';
+
+alter table player_ban
+    drop constraint player_ban_player_id_ban_type_map_sha1_key;
+
+alter table player_ban
+    add constraint player_ban_player_id_ban_type_key unique (player_id, ban_type);
+
+alter table player_ban
+    drop column reason,
+    drop column map_sha1,
+    drop column api_client_id;
@@ -0,0 +1,24 @@
+alter table player_ban
+    add column reason text,
+    add column map_sha1 text not null default '',
+    add column api_client_id integer references api_client(id);
+
+alter table player_ban
+    drop constraint player_ban_player_id_ban_type_key;
+
+alter table player_ban
+    add constraint player_ban_player_id_ban_type_map_sha1_key unique (player_id, ban_type, map_sha1);
+
+create table player_ban_audit (
+    id serial not null primary key,
+    player_id integer not null references player(id) on delete cascade,
+    action integer not null,
+    ban_type integer not null,
+    map_sha1 text not null default '',
+    ban_expires_at timestamp with time zone,
+    reason text,
+    api_client_id integer references api_client(id),
+    created_at timestamp with time zone default now() not null
+);
+
+create index player_ban_audit_player_id on player_ban_audit(player_id, id desc);
@@ -0,0 +1,12 @@
+create table audit_log (
+    id serial not null primary key,
+    api_client_id integer references api_client(id),
+    action text not null,
+    target_type text,
+    target_id text,
+    params jsonb not null,
+    created_at timestamp with time zone default now() not null
+);
+
+create index audit_log_api_client_id on audit_log(api_client_id, id desc);
+create index audit_log_target on audit_log(target_type, target_id, id desc);
@@ -0,0 +1,19 @@
+alter table api_client
+    add column secret_key text not null default '';
+
+update api_client c
+set secret_key = k.secret_key
+from (
+    select distinct on (api_client_id) api_client_id, secret_key
+    from api_client_key
+    order by api_client_id, id
+) k
+where k.api_client_id = c.id;
+
+alter table api_client
+    alter column secret_key drop default;
+
+drop table api_client_key;
+
+alter table api_client
+    drop column scopes;
@@ -0,0 +1,22 @@
+alter table api_client
+    add column scopes jsonb not null default '[]';
+
+-- existing clients keep everything but nodes, queues and api clients,
+-- the operator client is granted `Admin` by hand
+update api_client set scopes = '["Read", "GameManagement", "Moderation"]';
+
+create table api_client_key (
+    id serial not null primary key,
+    api_client_id integer not null references api_client(id),
+    secret_key text not null unique,
+    expires_at timestamp with time zone,
+    created_at timestamp with time zone default now() not null
+);
+
+create index api_client_key_api_client_id on api_client_key(api_client_id);
+
+insert into api_client_key (api_client_id, secret_key)
+select id, secret_key from api_client;
+
+alter table api_client
+    drop column secret_key;
@@ -0,0 +1,2 @@
+alter table game_used_slot
+    add column ready boolean not null default false;
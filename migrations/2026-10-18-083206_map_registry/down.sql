@@ -0,0 +1,8 @@
+alter table matchmaking_queue
+    drop column map_pool_id;
+
+drop table map_pool_map;
+
+drop table map_pool;
+
+drop table map_info;
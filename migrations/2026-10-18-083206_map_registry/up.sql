@@ -0,0 +1,38 @@
+create table map_info (
+    id serial not null primary key,
+    sha1 text not null,
+    checksum bigint not null,
+    name text not null,
+    description text not null,
+    author text not null,
+    path text not null,
+    width integer not null,
+    height integer not null,
+    suggested_players text not null,
+    players jsonb not null,
+    forces jsonb not null,
+    preview_jpeg bytea,
+    created_at timestamp with time zone default now() not null,
+    updated_at timestamp with time zone default now() not null
+);
+
+create unique index map_info_sha1 on map_info(sha1);
+
+create table map_pool (
+    id serial not null primary key,
+    api_client_id integer not null references api_client(id),
+    name text not null,
+    created_at timestamp with time zone default now() not null,
+    updated_at timestamp with time zone default now() not null
+);
+
+create unique index map_pool_api_client_id_name on map_pool(api_client_id, name);
+
+create table map_pool_map (
+    map_pool_id integer not null references map_pool(id) on delete cascade,
+    map_info_id integer not null references map_info(id) on delete cascade,
+    primary key (map_pool_id, map_info_id)
+);
+
+alter table matchmaking_queue
+    add column map_pool_id integer references map_pool(id) on delete set null;
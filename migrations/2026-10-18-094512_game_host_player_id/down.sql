@@ -0,0 +1,2 @@
+alter table game
+    drop column host_player_id;
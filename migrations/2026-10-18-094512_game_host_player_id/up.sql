@@ -0,0 +1,5 @@
+alter table game
+    add column host_player_id integer references player(id);
+update game set host_player_id = created_by;
+alter table game
+    alter column host_player_id set not null;
@@ -0,0 +1,2 @@
+alter table matchmaking_queue
+    drop column observer_chat_policy;
@@ -0,0 +1,3 @@
+-- existing queues keep the isolated policy their games were created with
+alter table matchmaking_queue
+    add column observer_chat_policy integer default 1 not null;